@@ -0,0 +1,68 @@
+//! Interpreter throughput on the synthetic workloads in `oxid8::chip8::synth`.
+//!
+//! Each benchmark runs a fixed number of instructions, so criterion reports
+//! instructions per second as its throughput and the time per instruction
+//! is the mean time divided by the count. Benchmark names are stable, for
+//! comparing changes against `cargo bench --bench exec -- --save-baseline
+//! before` with `--baseline before`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use oxid8::chip8::consts::PROGRAM_START;
+use oxid8::chip8::cpu::Chip8;
+use oxid8::chip8::emulator::{Emulator, EmulatorConfig};
+use oxid8::chip8::synth::Workload;
+
+/// Instructions per frame, as in the emulator's default configuration.
+const CYCLES_PER_FRAME: u64 = 12;
+/// Frames per measured iteration.
+const FRAMES: u64 = 100;
+const INSTRUCTIONS: u64 = CYCLES_PER_FRAME * FRAMES;
+
+fn chip(workload: Workload) -> Chip8 {
+    let rom = workload.rom();
+    let mut chip = Chip8::new();
+    chip.seed_rng(0);
+    chip.load_font();
+    chip.memory[PROGRAM_START..PROGRAM_START + rom.len()].copy_from_slice(&rom);
+    chip
+}
+
+fn emulator(workload: Workload) -> Emulator {
+    let mut emu = Emulator::new(EmulatorConfig {
+        cycles_per_frame: CYCLES_PER_FRAME,
+        seed: Some(0),
+        ..EmulatorConfig::default()
+    });
+    emu.load_rom(&workload.rom()).unwrap();
+    emu
+}
+
+fn exec(c: &mut Criterion) {
+    for workload in Workload::ALL {
+        let mut group = c.benchmark_group(workload.name());
+        group.throughput(Throughput::Elements(INSTRUCTIONS));
+        group.bench_function("run_step", |b| {
+            b.iter_batched_ref(
+                || chip(workload),
+                |chip| chip.run_step(INSTRUCTIONS),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function("run_frame", |b| {
+            b.iter_batched_ref(
+                || emulator(workload),
+                |emu| {
+                    for _ in 0..FRAMES {
+                        emu.run_frame();
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, exec);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! The bit-packed `Framebuffer` against the flat byte-per-8-pixels
+//! `FlatScreen` on the same mix of sprite rows and scrolls. Needs the
+//! `flat-screen` feature:
+//!
+//! ```bash
+//! cargo bench --features flat-screen --bench screen
+//! ```
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use oxid8::chip8::framebuffer::Framebuffer;
+use oxid8::chip8::screen::{exercise, FlatScreen, ScreenOps};
+
+/// Screen operations per measured iteration.
+const OPS: u64 = 1024;
+
+fn bench_backend<S: ScreenOps + Default>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group("screen");
+    group.throughput(Throughput::Elements(OPS));
+    group.bench_function(name, |b| {
+        b.iter_batched_ref(
+            S::default,
+            |screen| exercise(screen, OPS),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn screen(c: &mut Criterion) {
+    bench_backend::<Framebuffer>(c, "bits");
+    bench_backend::<FlatScreen>(c, "flat");
+}
+
+criterion_group!(benches, screen);
+criterion_main!(benches);
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxid8::chip8::decode::decode;
+
+// Every word decodes to something, and encoding that gives back a word
+// with the same meaning.
+fuzz_target!(|word: u16| {
+    let op = decode(word);
+    assert_eq!(decode(op.encode()), op, "{word:#06X}");
+});
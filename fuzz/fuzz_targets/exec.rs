@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxid8::chip8::consts::PROGRAM_START;
+use oxid8::chip8::cpu::Chip8;
+
+/// Instructions to run per input; enough to reach calls, loops and stores.
+const MAX_STEPS: usize = 1000;
+
+// Any ROM runs until it faults or the step budget is spent, without a
+// panic, and PC stays in RAM while it runs.
+fuzz_target!(|rom: &[u8]| {
+    let mut chip = Chip8::new();
+    chip.seed_rng(0);
+    chip.load_font();
+    let rom = &rom[..rom.len().min(chip.memory.len() - PROGRAM_START)];
+    chip.memory[PROGRAM_START..PROGRAM_START + rom.len()].copy_from_slice(rom);
+    for _ in 0..MAX_STEPS {
+        if chip.exit || chip.try_step().is_err() {
+            break;
+        }
+        assert!(chip.pc < chip.memory.len(), "PC {:#05X}", chip.pc);
+    }
+});
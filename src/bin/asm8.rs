@@ -1,15 +1,22 @@
 use clap::Parser;
 
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{self, Read},
+    path::Path,
 };
 
+use oxid8::compiler::asm;
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// Source files; each is assembled to a .ch8 ROM next to it
     #[arg(short, long)]
     files: Vec<String>,
+    /// Also write a .sym symbol map beside each ROM, for oxid8 --symbols
+    #[arg(long)]
+    symbols: bool,
 }
 
 fn read_file(filename: &str) -> Result<String, io::Error> {
@@ -19,9 +26,36 @@ fn read_file(filename: &str) -> Result<String, io::Error> {
     Ok(contents)
 }
 
+/// Assemble `filename`, returning the path of the ROM written.
+fn assemble_file(filename: &str, symbols: bool) -> Result<String, String> {
+    if Path::new(filename)
+        .extension()
+        .is_some_and(|ext| ext == "ch8")
+    {
+        return Err("source files can't have the .ch8 extension of the ROM".into());
+    }
+    let source = read_file(filename).map_err(|e| e.to_string())?;
+    let assembled = asm::assemble(&source)?;
+    let rom_path = Path::new(filename).with_extension("ch8");
+    fs::write(&rom_path, &assembled.rom).map_err(|e| e.to_string())?;
+    if symbols {
+        fs::write(
+            rom_path.with_extension("sym"),
+            assembled.symbols.to_string(),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(rom_path.display().to_string())
+}
+
 fn main() {
     let args = Args::parse();
-    args.files.iter().for_each(|asm| print!("{asm:}"));
-
     println!("CHIP-8 ASM Compiler");
+
+    for filename in &args.files {
+        match assemble_file(filename, args.symbols) {
+            Ok(rom) => println!("{filename} -> {rom}"),
+            Err(e) => eprintln!("{filename}: {e}"),
+        }
+    }
 }
@@ -0,0 +1,267 @@
+//! Measure emulation throughput headless.
+//!
+//! Runs whole frames (timers ticked once per frame, no pacing) for exactly
+//! the requested instruction count, then reports speed and how much the
+//! run allocated.
+
+use clap::Parser;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+#[cfg(feature = "flat-screen")]
+use std::time::Instant;
+
+use oxid8::chip8::benchmark;
+use oxid8::chip8::emulator::{Emulator, EmulatorConfig};
+#[cfg(feature = "flat-screen")]
+use oxid8::chip8::framebuffer::Framebuffer;
+use oxid8::chip8::rom;
+#[cfg(feature = "flat-screen")]
+use oxid8::chip8::screen::{self, FlatScreen, ScreenBackend, ScreenOps};
+use oxid8::chip8::synth::Workload;
+
+/// The system allocator, counting every allocation.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// ROM to run [default: the synthetic --workload]
+    rom: Option<String>,
+    /// Synthetic instruction mix to run without a ROM: alu, draw, memory or
+    /// scroll
+    #[arg(long, default_value = "draw", conflicts_with = "rom")]
+    workload: Workload,
+    /// Time screen draws and scrolls on a screen backend, bits or flat,
+    /// instead of running instructions. Each operation counts as one
+    /// instruction in the report
+    #[cfg(feature = "flat-screen")]
+    #[arg(long, conflicts_with = "rom")]
+    screen: Option<ScreenBackend>,
+    /// Millions of instructions to execute
+    #[arg(short, long, default_value_t = 10)]
+    millions: u64,
+    /// Instructions per frame
+    #[arg(short, long, default_value_t = 12)]
+    cpu_cycles: u64,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, PartialEq)]
+struct Report {
+    instructions: u64,
+    frames: u64,
+    elapsed: Duration,
+    allocations: u64,
+    allocated_bytes: u64,
+}
+
+impl Report {
+    fn instructions_per_sec(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64()
+    }
+
+    fn ns_per_frame(&self) -> f64 {
+        self.elapsed.as_nanos() as f64 / self.frames.max(1) as f64
+    }
+
+    fn ns_per_instruction(&self) -> f64 {
+        self.elapsed.as_nanos() as f64 / self.instructions.max(1) as f64
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            concat!(
+                "{{\"instructions\":{},\"frames\":{},\"elapsed_s\":{:.6},",
+                "\"instructions_per_sec\":{:.0},\"ns_per_frame\":{:.1},",
+                "\"ns_per_instruction\":{:.2},",
+                "\"allocations\":{},\"allocated_bytes\":{}}}"
+            ),
+            self.instructions,
+            self.frames,
+            self.elapsed.as_secs_f64(),
+            self.instructions_per_sec(),
+            self.ns_per_frame(),
+            self.ns_per_instruction(),
+            self.allocations,
+            self.allocated_bytes,
+        )
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            concat!(
+                "instructions:   {}\n",
+                "frames:         {}\n",
+                "elapsed:        {:.3} s\n",
+                "instructions/s: {:.0}\n",
+                "time/frame:     {:.1} ns\n",
+                "time/instr:     {:.2} ns\n",
+                "allocations:    {} ({} bytes)"
+            ),
+            self.instructions,
+            self.frames,
+            self.elapsed.as_secs_f64(),
+            self.instructions_per_sec(),
+            self.ns_per_frame(),
+            self.ns_per_instruction(),
+            self.allocations,
+            self.allocated_bytes,
+        )
+    }
+}
+
+fn bench(rom: &[u8], instructions: u64, cpu_cycles: u64) -> color_eyre::Result<Report> {
+    let mut emu = Emulator::new(EmulatorConfig {
+        cycles_per_frame: cpu_cycles,
+        seed: Some(0),
+        ..EmulatorConfig::default()
+    });
+    emu.load_rom(rom)?;
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let run = benchmark::run(&mut emu, instructions);
+
+    Ok(Report {
+        instructions: run.instructions,
+        frames: run.frames,
+        elapsed: run.elapsed,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+    })
+}
+
+#[cfg(feature = "flat-screen")]
+fn bench_screen(backend: ScreenBackend, ops: u64) -> Report {
+    match backend {
+        ScreenBackend::Bits => bench_screen_ops(&mut Framebuffer::default(), ops),
+        ScreenBackend::Flat => bench_screen_ops(&mut FlatScreen::default(), ops),
+    }
+}
+
+#[cfg(feature = "flat-screen")]
+fn bench_screen_ops<S: ScreenOps>(screen: &mut S, ops: u64) -> Report {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    screen::exercise(screen, ops);
+    let elapsed = start.elapsed();
+
+    Report {
+        instructions: ops,
+        frames: 0,
+        elapsed,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+    }
+}
+
+fn run(args: &Args) -> color_eyre::Result<Report> {
+    let instructions = args.millions * 1_000_000;
+    #[cfg(feature = "flat-screen")]
+    if let Some(backend) = args.screen {
+        return Ok(bench_screen(backend, instructions));
+    }
+    let rom = match &args.rom {
+        Some(path) => rom::read_rom(path)?,
+        None => args.workload.rom(),
+    };
+    bench(&rom, instructions, args.cpu_cycles)
+}
+
+fn main() -> color_eyre::Result<()> {
+    let args = Args::parse();
+    let report = run(&args)?;
+    if args.json {
+        println!("{}", report.to_json());
+    } else {
+        println!("{}", report.to_text());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxid8::chip8::synth;
+
+    #[test]
+    fn test_bench_synthetic() {
+        let report = bench(&synth::drw_loop(), 1200, 12).unwrap();
+        assert_eq!(report.frames, 100);
+        assert_eq!(report.instructions, 1200);
+        let report = bench(&synth::drw_loop(), 1000, 12).unwrap();
+        assert_eq!((report.instructions, report.frames), (1000, 84));
+    }
+
+    #[test]
+    fn test_bench_workloads() {
+        for workload in Workload::ALL {
+            let report = bench(&workload.rom(), 1200, 12).unwrap();
+            assert_eq!(report.frames, 100, "{workload:?}");
+        }
+        let args = Args::try_parse_from(["oxid8-bench", "--workload", "alu"]).unwrap();
+        assert_eq!(args.workload, Workload::Alu);
+        assert!(Args::try_parse_from(["oxid8-bench", "--workload", "alu", "game.ch8"]).is_err());
+    }
+
+    #[cfg(feature = "flat-screen")]
+    #[test]
+    fn test_bench_screen() {
+        for backend in ScreenBackend::ALL {
+            let report = bench_screen(backend, 1000);
+            assert_eq!((report.instructions, report.allocations), (1000, 0));
+        }
+        let args = Args::try_parse_from(["oxid8-bench", "--screen", "flat"]).unwrap();
+        assert_eq!(args.screen, Some(ScreenBackend::Flat));
+        assert!(Args::try_parse_from(["oxid8-bench", "--screen", "flat", "game.ch8"]).is_err());
+    }
+
+    #[test]
+    fn test_bench_stops_on_exit() {
+        let report = bench(&[0x00, 0xFD], 1200, 12).unwrap();
+        assert_eq!(report.frames, 0);
+    }
+
+    #[test]
+    fn test_report_json() {
+        let report = Report {
+            instructions: 2_000_000,
+            frames: 1000,
+            elapsed: Duration::from_millis(500),
+            allocations: 3,
+            allocated_bytes: 96,
+        };
+        assert_eq!(
+            report.to_json(),
+            concat!(
+                "{\"instructions\":2000000,\"frames\":1000,\"elapsed_s\":0.500000,",
+                "\"instructions_per_sec\":4000000,\"ns_per_frame\":500000.0,",
+                "\"ns_per_instruction\":250.00,",
+                "\"allocations\":3,\"allocated_bytes\":96}"
+            )
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Windowed frontend built on SDL2. Build with `--features sdl`.
+
+use clap::{ArgGroup, Parser};
+use color_eyre::eyre::{eyre, Result};
+
+use sdl2::event::Event;
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use oxid8::chip8::audio::Beeper;
+use oxid8::chip8::consts::{PROGRAM_START, RAM_SIZE, STACK_DEPTH};
+use oxid8::chip8::cpu;
+use oxid8::chip8::emulator::{
+    self, BeepStyle, Emulator, EmulatorConfig, KeyEvent, RenderSnapshot, MIN_KEY_HOLD,
+};
+use oxid8::chip8::frontend::{self, AudioCommand, Frontend, InputEvent, Recording};
+use oxid8::chip8::gfx::{self, Palette, FRAMEBUFFER_HEIGHT, FRAMEBUFFER_WIDTH};
+use oxid8::chip8::keymap::KeyMap;
+use oxid8::chip8::mem::{self, MemoryFill};
+use oxid8::chip8::quirks::Variant;
+use oxid8::chip8::rom::{self, STDIN_PATH};
+use oxid8::chip8::romdb::{self, Overrides};
+use oxid8::utils::config;
+#[cfg(feature = "tracing")]
+use oxid8::utils::logging;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None, args_override_self = true)]
+#[command(group(ArgGroup::new("rom_path").args(["rom", "rom_flag"]).required(true)))]
+struct Args {
+    /// ROM file to run, or `-` to read it from stdin
+    rom: Option<String>,
+    /// Same as the positional ROM argument
+    #[arg(short = 'r', long = "rom", value_name = "ROM")]
+    rom_flag: Option<String>,
+    /// Instructions per frame [default: 12, or the ROM database's setting]
+    #[arg(short, long)]
+    cpu_cycles: Option<u64>,
+    /// Interpreter to emulate: chip8 or schip [default: chip8, or the ROM
+    /// database's setting]
+    #[arg(long)]
+    variant: Option<Variant>,
+    /// Don't apply settings from the built-in ROM database
+    #[arg(long)]
+    ignore_rom_db: bool,
+    /// Instructions per second, instead of --cpu-cycles per frame
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u64).range(1..))]
+    cpu_hz: Option<u64>,
+    /// Emulation speed, e.g. 0.25 for slow motion
+    #[arg(long, default_value_t = 1.0, value_parser = emulator::parse_speed)]
+    speed: f64,
+    /// Beeper behaviour: "sustained" tone while ST runs, or a "click" per ST load
+    #[arg(long, value_name = "STYLE", default_value = "sustained")]
+    beep: BeepStyle,
+    /// Keyboard keys for CHIP-8 keys 0-F, e.g. x123qweasdzc4rfv, by their
+    /// position on a US layout
+    #[arg(long, value_name = "KEYS")]
+    keymap: Option<KeyMap>,
+    /// Fill RAM with one hex byte (0xAA) or an alternating pair (0xDEAD)
+    /// instead of zeros, to expose ROMs that read uninitialized memory
+    #[arg(long, value_name = "PATTERN")]
+    fill: Option<MemoryFill>,
+    /// Bytes of RAM, up to 65536 for XO-CHIP ROMs larger than the usual
+    /// 3584-byte limit
+    #[arg(long, value_name = "BYTES", default_value_t = RAM_SIZE, value_parser = mem::parse_ram_size)]
+    ram_size: usize,
+    /// Address to load the hex font at, e.g. 0x50
+    #[arg(long, value_name = "ADDR", default_value_t = 0, value_parser = mem::parse_font_base)]
+    font_base: usize,
+    /// Deepest CALL nesting, up to 255 for ROMs that need more than 16
+    #[arg(long, value_name = "N", default_value_t = STACK_DEPTH, value_parser = cpu::parse_stack_depth)]
+    stack_depth: usize,
+    /// Instructions a key stays down before its release counts, so quick
+    /// taps still register
+    #[arg(long, value_name = "N", default_value_t = MIN_KEY_HOLD)]
+    min_key_hold: u64,
+    /// Power on in high-res, for SUPER-CHIP ROMs that never execute HIGH
+    #[arg(long)]
+    start_high_res: bool,
+    /// Colour of lit pixels, as #rrggbb
+    #[arg(long, value_name = "COLOR", value_parser = gfx::parse_hex_color)]
+    fg: Option<[u8; 3]>,
+    /// Colour of unlit pixels, as #rrggbb
+    #[arg(long, value_name = "COLOR", value_parser = gfx::parse_hex_color)]
+    bg: Option<[u8; 3]>,
+    /// Swap the colours, drawing lit pixels in the --bg colour on the --fg
+    /// colour
+    #[arg(long)]
+    invert: bool,
+    /// Write logs to FILE
+    #[cfg(feature = "tracing")]
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<String>,
+    /// Minimum level written to --log-file: trace, debug, info, warn or error
+    #[cfg(feature = "tracing")]
+    #[arg(long, value_name = "LEVEL", default_value = "info", value_parser = logging::parse_level)]
+    log_level: tracing::Level,
+    /// Read options from a `key = value` file; flags given here override it
+    #[arg(long, value_name = "FILE")]
+    config: Option<String>,
+    /// Seed RND so runs are reproducible
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Record key input to a replay file
+    #[arg(long, value_name = "FILE")]
+    record: Option<String>,
+    /// Window pixels per high-res pixel
+    #[arg(long, default_value_t = 5)]
+    scale: u32,
+}
+
+impl Args {
+    /// Parse the command line, applying `--config` if given.
+    fn parse_with_config() -> color_eyre::Result<Self> {
+        let argv: Vec<String> = env::args().collect();
+        Ok(match config::config_path(&argv) {
+            Some(path) => Self::parse_from(config::merge_args(&argv, &fs::read_to_string(path)?)?),
+            None => Self::parse_from(argv),
+        })
+    }
+
+    fn palette(&self) -> Palette {
+        let default = Palette::default();
+        Palette {
+            on: self.fg.unwrap_or(default.on),
+            off: self.bg.unwrap_or(default.off),
+            invert: self.invert,
+            ..default
+        }
+    }
+
+    fn rom_path(&self) -> &str {
+        self.rom
+            .as_deref()
+            .or(self.rom_flag.as_deref())
+            .unwrap_or(STDIN_PATH)
+    }
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let args = Args::parse_with_config()?;
+    #[cfg(feature = "tracing")]
+    if let Some(path) = &args.log_file {
+        logging::init(path, args.log_level)?;
+    }
+
+    let rom = rom::read_rom_limited(args.rom_path(), args.ram_size - PROGRAM_START)?;
+    let rom_info = if args.ignore_rom_db {
+        None
+    } else {
+        romdb::lookup(&rom)
+    };
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        rom_hash = format!("{:#018x}", romdb::rom_hash(&rom)),
+        title = rom_info.map(|info| info.title),
+        "loaded rom"
+    );
+    let mut config = EmulatorConfig {
+        cpu_hz: args.cpu_hz,
+        seed: args.seed,
+        speed: args.speed,
+        beep_style: args.beep,
+        fill: args.fill.unwrap_or_default(),
+        ram_size: args.ram_size,
+        font_base: args.font_base,
+        stack_depth: args.stack_depth,
+        min_key_hold: args.min_key_hold,
+        start_high_res: args.start_high_res,
+        ..EmulatorConfig::default()
+    };
+    let overrides = Overrides {
+        variant: args.variant,
+        cycles_per_frame: args.cpu_cycles,
+    };
+    romdb::apply(&mut config, rom_info, &overrides);
+    let mut emu = Emulator::new(config);
+    emu.load_rom(&rom)?;
+
+    let sdl = sdl2::init().map_err(|e| eyre!(e))?;
+    let video = sdl.video().map_err(|e| eyre!(e))?;
+    let rom_name = match rom_info {
+        Some(info) => info.title.to_string(),
+        None => Path::new(args.rom_path())
+            .file_name()
+            .map_or("stdin".to_string(), |name| {
+                name.to_string_lossy().into_owned()
+            }),
+    };
+    let window = video
+        .window(
+            &format!("oxid8 - {rom_name} ({}x)", args.speed),
+            FRAMEBUFFER_WIDTH as u32 * args.scale,
+            FRAMEBUFFER_HEIGHT as u32 * args.scale,
+        )
+        .position_centered()
+        .resizable()
+        .build()?;
+    let mut canvas = window.into_canvas().build()?;
+    canvas.set_logical_size(FRAMEBUFFER_WIDTH as u32, FRAMEBUFFER_HEIGHT as u32)?;
+    canvas.set_integer_scale(true).map_err(|e| eyre!(e))?;
+    let texture_creator = canvas.texture_creator();
+    let texture = texture_creator.create_texture_streaming(
+        PixelFormatEnum::RGB24,
+        FRAMEBUFFER_WIDTH as u32,
+        FRAMEBUFFER_HEIGHT as u32,
+    )?;
+
+    let sdl_frontend = SdlFrontend {
+        canvas,
+        texture,
+        event_pump: sdl.event_pump().map_err(|e| eyre!(e))?,
+        beeper: Beeper::new()?,
+        palette: args.palette(),
+        keymap: args.keymap.clone().unwrap_or_default(),
+        draw_failed: false,
+    };
+
+    if let Some(path) = &args.record {
+        let mut recording = Recording::new(sdl_frontend);
+        frontend::run(&mut emu, &mut recording);
+        fs::write(path, recording.replay.to_string())?;
+    } else {
+        let mut sdl_frontend = sdl_frontend;
+        frontend::run(&mut emu, &mut sdl_frontend);
+    }
+    match emu.fault() {
+        Some(message) => Err(eyre!("Fault: {message}\n{}", emu.chip().dump_summary())),
+        None => Ok(()),
+    }
+}
+
+/// Window frontend: the display is streamed into a 128x64 texture and
+/// scaled by whole multiples to the window.
+struct SdlFrontend<'a> {
+    canvas: Canvas<Window>,
+    texture: Texture<'a>,
+    event_pump: EventPump,
+    beeper: Beeper,
+    palette: Palette,
+    keymap: KeyMap,
+    /// Whether a frame has failed to draw, so the error is shown once.
+    draw_failed: bool,
+}
+
+impl SdlFrontend<'_> {
+    fn draw(&mut self, snapshot: &RenderSnapshot) -> Result<(), String> {
+        self.texture.with_lock(None, |buf, pitch| {
+            gfx::write_rgb24(&snapshot.chip, buf, pitch, &self.palette)
+        })?;
+        self.canvas.clear();
+        self.canvas.copy(&self.texture, None, None)?;
+        self.canvas.present();
+        Ok(())
+    }
+}
+
+impl Frontend for SdlFrontend<'_> {
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        for event in self.event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    scancode: Some(Scancode::Escape),
+                    ..
+                } => events.push(InputEvent::Quit),
+                Event::KeyDown {
+                    scancode: Some(code),
+                    repeat: false,
+                    ..
+                } => events.extend(
+                    chip8_key_of_scancode(&self.keymap, code)
+                        .map(|k| InputEvent::Key(KeyEvent::Down(k))),
+                ),
+                Event::KeyUp {
+                    scancode: Some(code),
+                    ..
+                } => events.extend(
+                    chip8_key_of_scancode(&self.keymap, code)
+                        .map(|k| InputEvent::Key(KeyEvent::Up(k))),
+                ),
+                _ => {}
+            }
+        }
+        events
+    }
+
+    fn present(&mut self, snapshot: &RenderSnapshot) {
+        if let Err(e) = self.draw(snapshot) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %e, frame = snapshot.frame, "couldn't draw frame");
+            if !self.draw_failed {
+                self.draw_failed = true;
+                eprintln!("warning: couldn't draw frame {}: {e}", snapshot.frame);
+            }
+        }
+    }
+
+    fn set_audio(&mut self, command: AudioCommand) {
+        self.beeper.set(command == AudioCommand::Start);
+    }
+}
+
+/// The CHIP-8 key `keymap` puts on `code`, going by the key's name on a US
+/// layout.
+fn chip8_key_of_scancode(keymap: &KeyMap, code: Scancode) -> Option<u8> {
+    let mut name = code.name().chars();
+    match (name.next(), name.next()) {
+        (Some(c), None) => keymap.key_of_char(c),
+        _ => None,
+    }
+}
@@ -1,44 +1,363 @@
-use clap::Parser;
+use clap::{ArgAction, ArgGroup, Parser};
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use color_eyre::Section;
 
-use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 
 use atomic_enum::atomic_enum;
 
-use std::fs::File;
-use std::io::{self, Read};
-use std::sync::atomic::Ordering;
-use std::sync::{mpsc, Arc};
+use std::any::Any;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::Range;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use oxid8::chip8::audio::Beeper;
-use oxid8::chip8::consts::{PROGRAM_START, RAM_SIZE};
-use oxid8::chip8::cpu::Chip8;
-use oxid8::chip8::{gfx, timers};
-use oxid8::utils::triple_buffer;
+use oxid8::chip8::benchmark;
+use oxid8::chip8::broadcast::{self, Broadcasting, Server};
+use oxid8::chip8::capabilities;
+use oxid8::chip8::consts::{PROGRAM_START, RAM_SIZE, STACK_DEPTH};
+use oxid8::chip8::cpu::{self, Chip8, ProtectPolicy};
+use oxid8::chip8::debugger::Debugger;
+use oxid8::chip8::decode::decode;
+use oxid8::chip8::disasm;
+use oxid8::chip8::emulator::{
+    self, panic_message, BeepStyle, Emulator, EmulatorConfig, Halt, KeyEvent, RenderSnapshot,
+    SpeedChange, SpeedToggles, MIN_KEY_HOLD,
+};
+use oxid8::chip8::frame_export::{DirSink, FrameExport};
+use oxid8::chip8::frame_hashes::{FrameHashes, FrameHashing};
+use oxid8::chip8::frontend::{
+    self, AudioCommand, Frontend, HeadlessFrontend, InputEvent, Recording, StopReason,
+};
+use oxid8::chip8::gdb::GdbStub;
+use oxid8::chip8::gfx::{self, AnsiMode, ColorDepth, FreshPixels, Palette, StatusBar};
+use oxid8::chip8::key_hold::KeyHold;
+use oxid8::chip8::keymap::KeyMap;
+use oxid8::chip8::mem::{self, MemoryFill};
+use oxid8::chip8::playlist::{self, Playlist};
+use oxid8::chip8::quirks::Variant;
+use oxid8::chip8::repl::Monitor;
+use oxid8::chip8::replay::Replay;
+use oxid8::chip8::rom::{self, STDIN_PATH};
+use oxid8::chip8::romdb::{self, Overrides, RomInfo};
+use oxid8::chip8::state;
+use oxid8::chip8::summary::StateSummary;
+use oxid8::chip8::symbols::Symbols;
+use oxid8::chip8::timer_history::{TimerHistory, TIMER_SAMPLES};
+use oxid8::compiler::asm::{self, Assembled};
+use oxid8::utils::config;
+#[cfg(feature = "tracing")]
+use oxid8::utils::logging;
+use oxid8::utils::triple_buffer::{self, TripleBufferWriter};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_override_self = true)]
+#[command(group(ArgGroup::new("rom_path").args(["rom", "rom_flag", "rom_dir"])))]
+#[command(group(ArgGroup::new("headless_run").args(["headless", "replay"]).multiple(true)))]
 struct Args {
-    #[arg(short, long)]
-    rom: String,
+    /// ROM file to run, or `-` to read it from stdin
+    #[arg(required_unless_present_any = ["rom_flag", "rom_dir", "decode", "capabilities", "instructions_json", "view"])]
+    rom: Option<String>,
+    /// Same as the positional ROM argument. Give it more than once to switch
+    /// between the ROMs with PageUp/PageDown (or F9/F10)
+    #[arg(short = 'r', long = "rom", value_name = "ROM", action = ArgAction::Append)]
+    rom_flag: Vec<String>,
+    /// Play the ROMs in a directory, switching with PageUp/PageDown (or F9/F10)
+    #[arg(long, value_name = "DIR")]
+    rom_dir: Option<String>,
     #[arg(short, long)]
     debug: bool,
-    #[arg(short, long, default_value_t = 12)]
-    cpu_cycles: u64,
-    #[arg(short, long, default_value_t = 60)]
-    cpu_hz: u64,
+    /// CALLs and RETs to list in the --debug view
+    #[arg(long, value_name = "N", default_value_t = 32)]
+    call_history: usize,
+    /// Instructions per frame [default: 12, or the ROM database's setting]
+    #[arg(short, long)]
+    cpu_cycles: Option<u64>,
+    /// Interpreter to emulate: chip8 or schip [default: chip8, or the ROM
+    /// database's setting]
+    #[arg(long)]
+    variant: Option<Variant>,
+    /// Don't apply settings from the built-in ROM database
+    #[arg(long)]
+    ignore_rom_db: bool,
+    /// Instructions per second, instead of --cpu-cycles per frame
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u64).range(1..))]
+    cpu_hz: Option<u64>,
+    /// Pace instructions by approximate COSMAC VIP cycle costs instead of
+    /// a fixed count per frame
+    #[arg(long, conflicts_with_all = ["cpu_cycles", "cpu_hz"])]
+    cosmac_timing: bool,
+    /// Terminal redraws per second, independent of the emulation rate
+    #[arg(long, value_name = "FPS", default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    render_fps: u64,
+    /// Emulation speed, e.g. 0.25 for slow motion
+    #[arg(long, default_value_t = 1.0, value_parser = emulator::parse_speed)]
+    speed: f64,
+    /// Beeper behaviour: "sustained" tone while ST runs, or a "click" per ST load
+    #[arg(long, value_name = "STYLE", default_value = "sustained")]
+    beep: BeepStyle,
+    /// Keyboard keys for CHIP-8 keys 0-F, e.g. x123qweasdzc4rfv
+    #[arg(long, value_name = "KEYS")]
+    keymap: Option<KeyMap>,
+    /// Hold every key for N frames per press, ignoring releases, for
+    /// terminals where auto-repeat or latency garbles them (0 to follow
+    /// releases). Overrides the keymap's hold=N
+    #[arg(long, value_name = "N")]
+    hold_frames: Option<u32>,
+    /// Fill RAM with one hex byte (0xAA) or an alternating pair (0xDEAD)
+    /// instead of zeros, to expose ROMs that read uninitialized memory
+    #[arg(long, value_name = "PATTERN")]
+    fill: Option<MemoryFill>,
+    /// Bytes of RAM, up to 65536 for XO-CHIP ROMs larger than the usual
+    /// 3584-byte limit
+    #[arg(long, value_name = "BYTES", default_value_t = RAM_SIZE, value_parser = mem::parse_ram_size)]
+    ram_size: usize,
+    /// Address to load the hex font at, e.g. 0x50 for ROMs that expect it
+    /// there
+    #[arg(long, value_name = "ADDR", default_value_t = 0, value_parser = mem::parse_font_base)]
+    font_base: usize,
+    /// Deepest CALL nesting, up to 255 for ROMs that need more than 16
+    #[arg(long, value_name = "N", default_value_t = STACK_DEPTH, value_parser = cpu::parse_stack_depth)]
+    stack_depth: usize,
+    /// Instructions a key stays down before its release counts, so quick
+    /// taps still register
+    #[arg(long, value_name = "N", default_value_t = MIN_KEY_HOLD)]
+    min_key_hold: u64,
+    /// Power on in high-res, for SUPER-CHIP ROMs that never execute HIGH
+    #[arg(long)]
+    start_high_res: bool,
+    /// On exit, print a histogram of how long key events took to reach the
+    /// keypad once the emulator received them
+    #[arg(long)]
+    input_latency: bool,
+    /// Display colours as bg,fg, or bg,fg,plane2,both for XO-CHIP's second
+    /// plane and pixels lit in both, each #rgb or #rrggbb
+    #[arg(long = "palette", value_name = "COLORS", value_parser = gfx::parse_palette)]
+    colors: Option<Palette>,
+    /// Colour of lit pixels, as #rrggbb, overriding --palette
+    #[arg(long, value_name = "COLOR", value_parser = gfx::parse_hex_color)]
+    fg: Option<[u8; 3]>,
+    /// Colour of unlit pixels, as #rrggbb, overriding --palette
+    #[arg(long, value_name = "COLOR", value_parser = gfx::parse_hex_color)]
+    bg: Option<[u8; 3]>,
+    /// Swap the colours, drawing lit pixels in the --bg colour on the --fg
+    /// colour
+    #[arg(long)]
+    invert: bool,
+    /// Tint the pixels each frame draws, to show which sprites are moving
+    #[arg(long)]
+    highlight_drawn: bool,
+    /// Write logs to FILE
+    #[cfg(feature = "tracing")]
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<String>,
+    /// Minimum level written to --log-file: trace, debug, info, warn or error
+    #[cfg(feature = "tracing")]
+    #[arg(long, value_name = "LEVEL", default_value = "info", value_parser = logging::parse_level)]
+    log_level: tracing::Level,
+    /// Decode one opcode (e.g. 0xD12F), print it and exit
+    #[arg(long, value_name = "OPCODE", value_parser = parse_opcode, exclusive = true)]
+    decode: Option<u16>,
+    /// Print the supported instructions, variants and features, and exit
+    #[arg(long, exclusive = true)]
+    capabilities: bool,
+    /// Print every instruction as JSON (pattern, mnemonic, syntax and
+    /// operands) and exit, optionally only those `=VARIANT` runs
+    #[arg(
+        long,
+        value_name = "VARIANT",
+        num_args = 0..=1,
+        require_equals = true,
+        exclusive = true
+    )]
+    instructions_json: Option<Option<Variant>>,
+    /// Print the ROM as assembly and exit, optionally only the addresses in
+    /// `range:0x200-0x400`
+    #[arg(
+        long,
+        alias = "print",
+        value_name = "RANGE",
+        num_args = 0..=1,
+        require_equals = true,
+        value_parser = disasm::parse_range
+    )]
+    disassemble: Option<Option<Range<usize>>>,
+    /// Read options from a `key = value` file; flags given here override it
+    #[arg(long, value_name = "FILE")]
+    config: Option<String>,
+    /// Seed RND so runs are reproducible
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Record key input to a replay file. Replays don't record ROM
+    /// switches, so this takes a single ROM
+    #[arg(long, value_name = "FILE", conflicts_with = "rom_dir")]
+    record: Option<String>,
+    /// Play a recorded replay headless and print the final state hash
+    #[arg(long, value_name = "FILE")]
+    replay: Option<String>,
+    /// Frames to run headless (default: until the last --replay event, or 600)
+    #[arg(long)]
+    frames: Option<u64>,
+    /// Run without a terminal and print the final state hash
+    #[arg(long)]
+    headless: bool,
+    /// Stop when the program jumps to itself, as many games do when they end
+    #[arg(long)]
+    halt_on_infinite: bool,
+    /// Treat unknown opcodes as 2-byte no-ops instead of faulting
+    #[arg(long)]
+    skip_unknown: bool,
+    /// Fault on stores below 0x200, where the font lives, or with
+    /// =ignore drop them with a warning
+    #[arg(
+        long,
+        value_name = "POLICY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "fault"
+    )]
+    protect_low_mem: Option<ProtectPolicy>,
+    /// Fault on SYS (0NNN) machine code calls instead of skipping them
+    #[arg(long)]
+    strict_sys: bool,
+    /// Debug from stdin without a terminal; type help for the commands
+    #[arg(long, alias = "monitor", conflicts_with_all = ["headless", "replay", "record"])]
+    repl: bool,
+    /// Wait for GDB to attach on localhost PORT and run under its control
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["headless", "replay", "record", "repl"])]
+    gdb_port: Option<u16>,
+    /// Symbol map written by `asm8 --symbols`, shown in the instructions pane
+    #[arg(long, value_name = "PATH", conflicts_with = "rom_dir")]
+    symbols: Option<String>,
+    /// Don't warn about ROMs that look like the wrong kind of file
+    #[arg(long)]
+    force: bool,
+    /// Assemble the ROM file before running it, whatever its extension
+    /// (`.asm` files always are)
+    #[arg(long, conflicts_with = "rom_dir")]
+    asm: bool,
+    /// On a fault, show it in the TUI and wait for a key before exiting
+    #[arg(long, conflicts_with_all = ["headless", "replay", "repl", "gdb_port"])]
+    pause_on_fault: bool,
+    /// Stream the display to viewers connecting on ADDR (e.g. 0.0.0.0:8080)
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["headless", "replay", "repl", "gdb_port"])]
+    serve: Option<String>,
+    /// Watch a session shared with --serve at ADDR instead of running a ROM
+    #[arg(
+        long,
+        value_name = "ADDR",
+        conflicts_with_all = ["rom", "rom_flag", "rom_dir", "headless", "replay", "record", "repl", "gdb_port", "serve"]
+    )]
+    view: Option<String>,
+    /// Save each frame as a numbered PPM image in DIR, for making GIFs or
+    /// videos
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["headless", "replay", "repl", "gdb_port"])]
+    record_frames: Option<String>,
+    /// Stop saving frames after this many (3600 is a minute at 60 Hz)
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 3600,
+        requires = "record_frames"
+    )]
+    record_frames_limit: u64,
+    /// On exit, write registers, timers and a screen hash to PATH as JSON
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["repl", "gdb_port", "view"])]
+    dump_state: Option<String>,
+    /// Run the ROM for N instructions (default 10 million) with nothing
+    /// drawn or played, print instructions per second and exit
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "10000000",
+        conflicts_with_all = ["headless", "replay", "record", "repl", "gdb_port", "serve", "record_frames", "disassemble"]
+    )]
+    benchmark: Option<u64>,
+    /// After the headless run, print the screen as half-block text
+    #[arg(long, requires = "headless_run")]
+    print_screen: bool,
+    /// Print the screen without colour escapes
+    #[arg(long, requires = "print_screen")]
+    no_color: bool,
+    /// Exit with an error unless the headless run's state hash matches
+    #[arg(long, value_name = "HEX", value_parser = parse_hash, requires = "headless_run")]
+    expect_hash: Option<u64>,
+    /// Write each frame's number and screen hash to FILE, a line per frame
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["repl", "gdb_port", "view", "benchmark"])]
+    frame_hashes: Option<String>,
+    /// Check every frame of the headless run against a --frame-hashes
+    /// trace, stopping with a diff at the first that differs
+    #[arg(long, value_name = "FILE", requires = "headless_run")]
+    verify_frame_hashes: Option<String>,
 }
 
-fn load_rom(filename: &str, memory: &mut [u8]) -> io::Result<()> {
-    let mut file = File::open(filename)?;
-    let mut contents = Vec::new();
-    file.read_to_end(&mut contents)?;
-    if contents.len() > RAM_SIZE - PROGRAM_START {
-        panic!("Rom too large");
+impl Args {
+    /// Parse the command line, applying `--config` if given.
+    fn parse_with_config() -> color_eyre::Result<Self> {
+        let argv: Vec<String> = env::args().collect();
+        Ok(match config::config_path(&argv) {
+            Some(path) => Self::parse_from(config::merge_args(&argv, &fs::read_to_string(path)?)?),
+            None => Self::parse_from(argv),
+        })
+    }
+
+    fn palette(&self) -> Palette {
+        let colors = self.colors.unwrap_or_default();
+        Palette {
+            on: self.fg.unwrap_or(colors.on),
+            off: self.bg.unwrap_or(colors.off),
+            invert: self.invert,
+            depth: ColorDepth::detect(),
+            ..colors
+        }
+    }
+
+    fn rom_path(&self) -> &str {
+        self.rom
+            .as_deref()
+            .or(self.rom_flag.first().map(String::as_str))
+            .unwrap_or(STDIN_PATH)
+    }
+}
+
+fn parse_opcode(s: &str) -> Result<u16, String> {
+    let s = s.trim();
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid opcode {s:?}: expected up to 4 hex digits"));
+    }
+    u16::from_str_radix(digits, 16).map_err(|e| format!("invalid opcode {s:?}: {e}"))
+}
+
+/// The `--decode` report for `word`.
+fn describe_opcode(word: u16) -> String {
+    let op = decode(word);
+    format!("word:    {word:#06X}\ndisplay: {op}\ndebug:   {op:?}")
+}
+
+fn parse_hash(s: &str) -> Result<u64, String> {
+    let digits = s.trim_start_matches("0x");
+    u64::from_str_radix(digits, 16).map_err(|e| format!("invalid hash {s:?}: {e}"))
+}
+
+fn overrides_of_args(args: &Args) -> Overrides {
+    Overrides {
+        variant: args.variant,
+        cycles_per_frame: args.cpu_cycles,
     }
-    memory[0..contents.len()].copy_from_slice(&contents[0..]);
-    Ok(())
 }
 
 #[atomic_enum]
@@ -51,149 +370,1009 @@ enum RunningState {
 #[derive(Debug)]
 struct Model {
     running_state: Arc<AtomicRunningState>,
+    status: Arc<Mutex<StatusBar>>,
+    playlist: Option<Playlist>,
+    use_rom_db: bool,
+    keymap: KeyMap,
+    show_help: Arc<AtomicBool>,
+    /// Set when the terminal is resized, until the render thread redraws
+    /// from scratch.
+    resized: Arc<AtomicBool>,
+    fault: Arc<Mutex<FaultState>>,
+    /// Set by the dump key, until the next frame is written out.
+    dump_requested: bool,
+    /// Mirrors the emulator's, for the status bar.
+    speed: SpeedToggles,
+    /// The [`gfx::THEMES`] entry drawn with, 0 for the configured colours.
+    theme: Arc<AtomicUsize>,
+    /// Whether the debug view's instructions pane stays where it was
+    /// instead of following PC.
+    lock_instructions: Arc<AtomicBool>,
+}
+
+impl Model {
+    fn new(
+        status: StatusBar,
+        playlist: Option<Playlist>,
+        use_rom_db: bool,
+        keymap: KeyMap,
+    ) -> Self {
+        Self {
+            running_state: Arc::new(AtomicRunningState::new(RunningState::Running)),
+            status: Arc::new(Mutex::new(status)),
+            playlist,
+            use_rom_db,
+            keymap,
+            show_help: Arc::new(AtomicBool::new(false)),
+            resized: Arc::new(AtomicBool::new(false)),
+            fault: Arc::default(),
+            dump_requested: false,
+            speed: SpeedToggles::default(),
+            theme: Arc::default(),
+            lock_instructions: Arc::default(),
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Message {
     KeyDown(u8), // 0x0..=0xF
     KeyUp(u8),
+    NextRom,
+    PrevRom,
+    ToggleHelp,
+    Dump,
+    SaveState,
+    CycleTheme,
+    ToggleInstructionLock,
+    Speed(SpeedChange),
+    Resize { width: u16, height: u16 },
     Quit,
 }
 
-fn main() -> color_eyre::Result<()> {
-    let mut model = Model {
-        running_state: Arc::new(AtomicRunningState::new(RunningState::Running)),
+fn rom_name(path: &Path) -> String {
+    path.file_name().map_or("stdin".to_string(), |name| {
+        name.to_string_lossy().into_owned()
+    })
+}
+
+fn lookup_rom(use_rom_db: bool, rom: &[u8]) -> Option<&'static RomInfo> {
+    if use_rom_db {
+        romdb::lookup(rom)
+    } else {
+        None
+    }
+}
+
+/// Read and validate the ROM at `path`, so that any problem is reported
+/// before the terminal is taken over.
+fn read_rom(path: &str, limit: usize) -> color_eyre::Result<Vec<u8>> {
+    let rom =
+        rom::read_rom_limited(path, limit).wrap_err_with(|| format!("Failed to load ROM {path}"));
+    let rom = match rom_hint(path, &[]) {
+        Some(hint) => rom.suggestion(hint),
+        None => rom,
+    }?;
+    validate_rom(path, &rom, limit)?;
+    Ok(rom)
+}
+
+/// [`read_rom`], or [`assemble_rom`] for assembly source, as for each ROM
+/// of a playlist.
+fn load_playlist_rom(path: &Path, limit: usize) -> Result<Vec<u8>, String> {
+    let path = path.to_string_lossy();
+    let rom = match is_source(&path) {
+        true => assemble_rom(&path, limit).map(|assembled| assembled.rom),
+        false => read_rom(&path, limit),
     };
+    rom.map_err(|err| format!("{err:#}"))
+}
 
-    let args = Args::parse();
+/// Whether `path` is assembly source, to be assembled rather than loaded.
+fn is_source(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("asm"))
+}
 
-    let mut chip = Chip8::new();
-    chip.load_font();
+/// Assemble the source at `path`, or stdin for `-`, into a ROM and its
+/// symbol map, reporting any error before the terminal is taken over.
+fn assemble_rom(path: &str, limit: usize) -> color_eyre::Result<Assembled> {
+    let source = match path {
+        STDIN_PATH => io::read_to_string(io::stdin()),
+        _ => fs::read_to_string(path),
+    }
+    .wrap_err_with(|| format!("Can't read {path}"))?;
+    let assembled = asm::assemble(&source)
+        .map_err(|err| eyre!("{err}"))
+        .wrap_err_with(|| format!("Failed to assemble {path}"))?;
+    validate_rom(path, &assembled.rom, limit)?;
+    Ok(assembled)
+}
 
-    let res = load_rom(&args.rom, &mut chip.memory[PROGRAM_START..]);
-    if res.is_err() {
-        panic!("Failed to load rom");
+fn validate_rom(path: &str, rom: &[u8], limit: usize) -> color_eyre::Result<()> {
+    if rom.len() > limit {
+        bail!(
+            "ROM {path} is too large: {} bytes (limit {limit})",
+            rom.len()
+        );
+    }
+    if let Some(hint) = rom_hint(path, rom) {
+        return Err(eyre!("{path} is not a CHIP-8 ROM")).suggestion(hint);
     }
+    Ok(())
+}
 
-    tui::install_panic_hook();
-    let mut terminal = tui::init_terminal()?;
+/// A suggestion for files that are commonly passed by mistake.
+fn rom_hint(path: &str, rom: &[u8]) -> Option<&'static str> {
+    let extension = Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    if extension.as_deref() == Some("8o") {
+        Some("this is Octo source; did you mean to assemble it with asm8?")
+    } else if extension.as_deref() == Some("zip") || rom.starts_with(b"PK\x03\x04") {
+        Some("this is a zip archive; extract the .ch8 ROM from it first")
+    } else {
+        None
+    }
+}
 
-    let beeper = Beeper::new().unwrap();
-    let timer_rx = timers::spawn_timers(chip.dt.clone(), chip.st.clone());
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+    let args = Args::parse_with_config()?;
+    if let Some(word) = args.decode {
+        println!("{}", describe_opcode(word));
+        return Ok(());
+    }
+    if args.capabilities {
+        print!("{}", capabilities::report());
+        return Ok(());
+    }
+    if let Some(variant) = args.instructions_json {
+        print!("{}", capabilities::instructions_json(variant));
+        return Ok(());
+    }
+    #[cfg(feature = "tracing")]
+    if let Some(path) = &args.log_file {
+        logging::init(path, args.log_level)?;
+    }
+    if let Some(addr) = &args.view {
+        return run_viewer(addr, args.palette());
+    }
 
-    // Setup async rendering thread using a BufChannel for communication.
-    let (mut buf_tx, buf_rx) = triple_buffer::triple_buffer::<Chip8>(Chip8::new());
-    let running_state = model.running_state.clone();
-    let render_join_handle = thread::spawn(move || {
-        while running_state.load(Ordering::Acquire) != RunningState::Done {
-            {
-                let read_handle = buf_rx.read();
-                // Render the current view
-                terminal
-                    .draw(|f| gfx::view(&read_handle, f, args.debug))
-                    .unwrap();
+    if args.asm && args.rom_flag.len() > 1 {
+        bail!("--asm assembles a single source file, not a list of ROMs");
+    }
+    if args.record.is_some() && args.rom_flag.len() > 1 {
+        bail!("--record can't replay switching between ROMs, so give it a single ROM");
+    }
+    let limit = args.ram_size - PROGRAM_START;
+    let playlist = match &args.rom_dir {
+        Some(dir) => Some(Playlist::from_dir(Path::new(dir))?),
+        None if args.rom_flag.len() > 1 => Some(Playlist::new(
+            args.rom_flag.iter().map(PathBuf::from).collect(),
+        )),
+        None => None,
+    };
+    let mut playlist =
+        playlist.map(|playlist| playlist.with_loader(move |path| load_playlist_rom(path, limit)));
+    let (name, rom, source_symbols) = match playlist.as_mut() {
+        Some(playlist) => {
+            let Some((path, rom)) = playlist.load_current() else {
+                let failures = playlist
+                    .take_failures()
+                    .into_iter()
+                    .map(|(_, err)| err)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                match &args.rom_dir {
+                    Some(dir) => bail!("No readable ROMs in {dir}\n{failures}"),
+                    None => bail!("None of the --rom files could be read\n{failures}"),
+                }
+            };
+            (rom_name(&path), rom, None)
+        }
+        None => {
+            if args.repl && args.rom_path() == STDIN_PATH {
+                bail!("--repl reads commands from stdin, so the ROM can't come from there too");
             }
-            thread::sleep(Duration::from_nanos(16_666_667)); // ~60 Hz
+            let (rom, symbols) = match args.asm || is_source(args.rom_path()) {
+                true => assemble_rom(args.rom_path(), limit).map(|a| (a.rom, Some(a.symbols)))?,
+                false => (read_rom(args.rom_path(), limit)?, None),
+            };
+            (rom_name(Path::new(args.rom_path())), rom, symbols)
         }
-    });
+    };
+    if let Some(range) = &args.disassemble {
+        print!("{}", disasm::disassemble(&rom, range.clone()));
+        return Ok(());
+    }
+    let rom_info = lookup_rom(!args.ignore_rom_db, &rom);
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        rom = %name,
+        rom_hash = format!("{:#018x}", romdb::rom_hash(&rom)),
+        title = rom_info.map(|info| info.title),
+        "loaded rom"
+    );
+    let warnings = match args.force {
+        true => Vec::new(),
+        false => {
+            let variant = args.variant.or(rom_info.map(|info| info.variant));
+            rom::validate_rom(&rom, variant.unwrap_or_default())
+        }
+    };
+    for warning in &warnings {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(rom = %name, %warning, "suspicious rom");
+        if args.headless
+            || args.replay.is_some()
+            || args.repl
+            || args.gdb_port.is_some()
+            || args.benchmark.is_some()
+        {
+            eprintln!("warning: {name}: {warning}");
+        }
+    }
 
-    let (input_tx, input_rx) = mpsc::channel::<Message>();
-    let running_state = model.running_state.clone();
-    let input_join_handle = thread::spawn(move || {
-        while running_state.load(Ordering::Acquire) != RunningState::Done {
-            // Handle events and map to a Message
-            let message = if let Event::Key(key) = event::read().unwrap() {
-                handle_key(key)
-            } else {
-                None
-            };
-            if let Some(message) = message {
-                input_tx.send(message).unwrap();
-            }
+    let mut config = EmulatorConfig {
+        cpu_hz: args.cpu_hz,
+        seed: args.seed,
+        speed: args.speed,
+        beep_style: args.beep,
+        fill: args.fill.unwrap_or_default(),
+        ram_size: args.ram_size,
+        font_base: args.font_base,
+        stack_depth: args.stack_depth,
+        min_key_hold: args.min_key_hold,
+        halt_on_infinite_loop: args.halt_on_infinite,
+        skip_unknown: args.skip_unknown,
+        protect_low_mem: args.protect_low_mem,
+        strict_sys: args.strict_sys,
+        cosmac_timing: args.cosmac_timing,
+        start_high_res: args.start_high_res,
+        input_latency: args.input_latency,
+        call_history: if args.debug { args.call_history } else { 0 },
+        ..EmulatorConfig::default()
+    };
+    romdb::apply(&mut config, rom_info, &overrides_of_args(&args));
+    let mut emu = Emulator::new(config);
+    emu.load_rom(&rom)?;
 
-            thread::sleep(Duration::from_nanos(16_666_667)); // ~60 Hz
+    if let Some(instructions) = args.benchmark {
+        println!("{name}: {}", benchmark::run(&mut emu, instructions));
+        return Ok(());
+    }
+    if args.headless || args.replay.is_some() {
+        return run_headless(&mut emu, &args);
+    }
+    if args.repl {
+        return run_repl(emu);
+    }
+    if let Some(port) = args.gdb_port {
+        return run_gdb(emu, port);
+    }
+
+    let server = match &args.serve {
+        Some(addr) => {
+            Some(Server::bind(addr.as_str()).wrap_err_with(|| format!("Can't serve on {addr}"))?)
+        }
+        None => None,
+    };
+    tui::install_panic_hook();
+    let mut status = StatusBar::for_rom(&name, rom_info);
+    if !warnings.is_empty() {
+        let warnings: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+        status.hint = Some(format!(
+            "warning: {} (--force hides this)",
+            warnings.join(", ")
+        ));
+    }
+    let mut keymap = args.keymap.clone().unwrap_or_default();
+    if let Some(frames) = args.hold_frames {
+        keymap.set_hold_frames(frames);
+    }
+    // Without releases a key would stay down until the game resets it, so
+    // each press holds for a few frames unless a hold is configured.
+    if !tui::reports_releases() && keymap.hold_frames() == [0; 16] {
+        keymap.set_hold_frames(FALLBACK_HOLD_FRAMES);
+    }
+    let hold_frames = keymap.hold_frames();
+    let model = Model::new(status, playlist, !args.ignore_rom_db, keymap);
+    let symbols = match &args.symbols {
+        Some(path) => load_symbols(path)?,
+        None => source_symbols.unwrap_or_default(),
+    };
+    let tui = TuiFrontend::new(
+        args.debug,
+        model,
+        args.palette(),
+        symbols,
+        render_interval(args.render_fps),
+        args.highlight_drawn,
+    )?;
+    let frame_sink = match &args.record_frames {
+        Some(dir) => Some(
+            DirSink::create(Path::new(dir))
+                .wrap_err_with(|| format!("Can't write frames to {dir}"))?,
+        ),
+        None => None,
+    };
+    let tui = FrameExport::new(tui, frame_sink, args.palette(), args.record_frames_limit);
+    // Inside the recording, so replays get the synthesized releases.
+    let tui = KeyHold::new(tui, hold_frames);
+    let tui = FrameHashing::new(tui, args.frame_hashes.is_some(), None);
+
+    let record = args.record.is_some();
+    let (hashing, replay, stopped) = match server {
+        Some(server) => {
+            let (broadcasting, replay, stopped) =
+                run_frontend(&mut emu, Broadcasting { inner: tui, server }, record);
+            (broadcasting.inner, replay, stopped)
         }
+        None => run_frontend(&mut emu, tui, record),
+    };
+    // Dropping the rest of the exporter finishes writing its frames.
+    let mut tui = hashing.inner.inner.inner;
+    let halt_reason = stopped.as_ref().map_or("fault", |stop| stop.name());
+    dump_state(args.dump_state.as_deref(), &emu, halt_reason)?;
+    write_frame_hashes(args.frame_hashes.as_deref(), hashing.hashes.as_ref())?;
+    if let Err(payload) = stopped {
+        // A panic outside the machine, so the TUI itself may be what broke:
+        // give the terminal back before reporting it.
+        tui.shutdown()?;
+        eprintln!("{}", panic_message(payload.as_ref()));
+        report_crash(&emu);
+        panic::resume_unwind(payload)
+    }
+    if let Some(message) = emu.fault().filter(|_| args.pause_on_fault) {
+        tui.wait_on_fault(emu.chip(), fault_details(message, &emu));
+    }
+    tui.shutdown()?;
+    if emu.fault().is_some() {
+        report_crash(&emu);
+    }
+    report_end_of_rom(&emu);
+    report_input_latency(&emu);
+    if let (Some(path), Some(replay)) = (&args.record, replay) {
+        fs::write(path, replay.to_string())?;
+    }
+    match emu.fault() {
+        Some(message) => Err(eyre!("Fault: {message}")),
+        None => Ok(()),
+    }
+}
+
+fn load_symbols(path: &str) -> color_eyre::Result<Symbols> {
+    let text = fs::read_to_string(path).wrap_err_with(|| format!("Can't read {path}"))?;
+    text.parse()
+        .map_err(|err| eyre!("{err}"))
+        .wrap_err_with(|| format!("Invalid symbol map {path}"))
+}
+
+/// The payload of a panic caught from a frontend.
+type Fault = Box<dyn Any + Send>;
+
+/// Run `frontend` to the end, recording its input if asked, and hand it
+/// back so the caller can shut it down. The emulator's own panics halt it
+/// with a fault (see [`Emulator::run_frame_caught`]); a panic in the
+/// frontend ends the run and is returned rather than unwinding further.
+fn run_frontend<F: Frontend>(
+    emu: &mut Emulator,
+    mut frontend: F,
+    record: bool,
+) -> (F, Option<Replay>, Result<StopReason, Fault>) {
+    if record {
+        let mut recording = Recording::new(frontend);
+        let stopped = tui::catch_quietly(|| frontend::run(emu, &mut recording));
+        (recording.inner, Some(recording.replay), stopped)
+    } else {
+        let stopped = tui::catch_quietly(|| frontend::run(emu, &mut frontend));
+        (frontend, None, stopped)
+    }
+}
+
+/// Print the --input-latency histogram, if one was collected.
+fn report_input_latency(emu: &Emulator) {
+    if let Some(histogram) = emu.input_latency() {
+        eprint!("Input latency, received to applied:\n{histogram}");
+    }
+}
+
+/// Explain a halt that isn't the program's own EXIT, which would otherwise
+/// look like the emulator quitting for no reason.
+fn report_end_of_rom(emu: &Emulator) {
+    if let Some(halt @ (Halt::EndOfRom { .. } | Halt::Unprogrammed { .. })) = emu.halt() {
+        eprintln!("warning: {halt}");
+    }
+}
+
+/// Write the machine summary for --dump-state, if it was asked for.
+fn dump_state(
+    path: Option<&str>,
+    emu: &Emulator,
+    halt_reason: &'static str,
+) -> color_eyre::Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let summary = StateSummary {
+        halt_reason: Some(halt_reason),
+        ..emu.state_summary()
+    };
+    fs::write(path, summary.to_json()).wrap_err_with(|| format!("Can't write {path}"))
+}
+
+/// The panic message and machine state to show for a fault.
+fn fault_details(message: &str, emu: &Emulator) -> String {
+    format!("{message}\n\n{}", emu.chip().dump_summary())
+}
+
+/// `oxid8-<kind>-<unix ms>.<extension>`, for files saved from the TUI.
+fn timestamped_path(kind: &str, extension: &str) -> String {
+    let ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("oxid8-{kind}-{ms}.{extension}")
+}
+
+/// Write `chip`'s [`Chip8::dump`] to `oxid8-dump-<unix ms>.txt` in the
+/// working directory, returning the file name.
+fn save_dump(chip: &Chip8) -> io::Result<String> {
+    let path = timestamped_path("dump", "txt");
+    fs::write(&path, chip.dump())?;
+    Ok(path)
+}
+
+/// Save `chip` to `oxid8-state-<unix ms>.o8s` in the working directory,
+/// for the REPL's `load`, returning the file name.
+fn save_state(chip: &Chip8) -> io::Result<String> {
+    let path = timestamped_path("state", "o8s");
+    fs::write(&path, state::save(chip))?;
+    Ok(path)
+}
+
+/// Print the machine state after a crash, and save it for the bug report.
+fn report_crash(emu: &Emulator) {
+    eprint!("Machine state:\n{}", emu.chip().dump());
+    match save_dump(emu.chip()) {
+        Ok(path) => eprintln!("Saved to {path}"),
+        Err(err) => eprintln!("Can't save machine state: {err}"),
+    }
+}
+
+/// Whether the TUI is running the program or holding a fault on screen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+enum FaultState {
+    #[default]
+    Running,
+    /// Showing the fault details until Esc is pressed.
+    Faulted(String),
+    Dismissed,
+}
+
+impl FaultState {
+    fn fault(&mut self, details: String) {
+        if *self == FaultState::Running {
+            *self = FaultState::Faulted(details);
+        }
+    }
+
+    /// Leave the faulted state on Esc. Other keys are left for saving the
+    /// machine, or ignored, so a held game key can't dismiss the fault
+    /// before it's been read.
+    fn update(&mut self, message: Message) {
+        if matches!(self, FaultState::Faulted(_)) && matches!(message, Message::Quit) {
+            *self = FaultState::Dismissed;
+        }
+    }
+
+    fn waiting(&self) -> bool {
+        matches!(self, FaultState::Faulted(_))
+    }
+}
+
+/// Frames run by --headless when neither --frames nor --replay say otherwise.
+const DEFAULT_HEADLESS_FRAMES: u64 = 600;
+/// Frames a press holds its key for on terminals that don't report
+/// releases.
+const FALLBACK_HOLD_FRAMES: u32 = 4;
+
+fn run_headless(emu: &mut Emulator, args: &Args) -> color_eyre::Result<()> {
+    let replay = match &args.replay {
+        Some(path) => Replay::parse(&fs::read_to_string(path)?)?,
+        None => Replay::new(),
+    };
+    let trace = match &args.verify_frame_hashes {
+        Some(path) => Some(FrameHashes::parse(&fs::read_to_string(path)?)?),
+        None => None,
+    };
+    let frames = args.frames.unwrap_or(match (&trace, &args.replay) {
+        (Some(trace), _) => trace.frames(),
+        (None, Some(_)) => replay.frames(),
+        (None, None) => DEFAULT_HEADLESS_FRAMES,
     });
+    let headless = HeadlessFrontend::new(frames).with_replay(&replay);
+    let mut hashing = FrameHashing::new(headless, args.frame_hashes.is_some(), trace);
+    let stop = frontend::run(emu, &mut hashing);
+    report_end_of_rom(emu);
+    report_input_latency(emu);
+    dump_state(args.dump_state.as_deref(), emu, stop.name())?;
+    write_frame_hashes(args.frame_hashes.as_deref(), hashing.hashes.as_ref())?;
+    if let Some(message) = emu.fault() {
+        bail!("Fault: {}", fault_details(message, emu));
+    }
+    if args.print_screen {
+        let mode = match args.no_color {
+            true => AnsiMode::Plain,
+            false => AnsiMode::Color,
+        };
+        let screen = gfx::screen_to_ansi(emu.chip(), &args.palette(), mode);
+        io::stdout().lock().write_all(screen.as_bytes())?;
+    }
 
-    let cpu_millis = 1000 / args.cpu_hz;
-    while model.running_state.load(Ordering::Acquire) != RunningState::Done {
-        chip.run_step(args.cpu_cycles);
-        if chip.exit {
-            break;
+    let hash = emu.snapshot().state_hash();
+    println!("{hash:016x}");
+    if let Some(expected) = args.expect_hash {
+        if expected != hash {
+            bail!("State hash mismatch: expected {expected:016x}, got {hash:016x}");
         }
+    }
+    if let Some(mismatch) = &hashing.mismatch {
+        eprint!("{mismatch}");
+        bail!("Frame hash mismatch at frame {}", mismatch.frame);
+    }
+    if !hashing.verified() {
+        bail!(
+            "The run ended after {} of the trace's frames",
+            hashing.checked()
+        );
+    }
+    Ok(())
+}
 
-        {
-            let mut send_handle = buf_tx.write();
-            if args.debug {
-                *send_handle = chip.clone(); // must clone here as screen is causal
-            } else {
-                send_handle.screen = chip.screen.clone(); // must clone here as screen is causal
-                send_handle.resolution = chip.resolution;
+fn write_frame_hashes(path: Option<&str>, hashes: Option<&FrameHashes>) -> color_eyre::Result<()> {
+    if let (Some(path), Some(hashes)) = (path, hashes) {
+        fs::write(path, hashes.to_string()).wrap_err_with(|| format!("Can't write {path}"))?;
+    }
+    Ok(())
+}
+
+/// Read debugger commands from stdin until `quit` or end of input.
+fn run_repl(emu: Emulator) -> color_eyre::Result<()> {
+    let mut monitor = Monitor::new(Debugger::new(emu));
+    for line in io::stdin().lock().lines() {
+        match monitor.handle(&line?) {
+            None => break,
+            Some(Ok(out)) if out.is_empty() => {}
+            Some(Ok(out)) => println!("{out}"),
+            Some(Err(err)) => eprintln!("error: {err}"),
+        }
+    }
+    Ok(())
+}
+
+/// Serve one GDB session on `port`, exiting when it detaches.
+fn run_gdb(emu: Emulator, port: u16) -> color_eyre::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    eprintln!("Waiting for GDB on 127.0.0.1:{port}");
+    let (stream, _) = listener.accept()?;
+    GdbStub::new(stream, Debugger::new(emu)).serve()?;
+    Ok(())
+}
+
+/// Show frames from a --serve session at `addr` until Esc or the session
+/// ends.
+fn run_viewer(addr: &str, palette: Palette) -> color_eyre::Result<()> {
+    let mut stream =
+        TcpStream::connect(addr).wrap_err_with(|| format!("Can't connect to {addr}"))?;
+    broadcast::Header::read_from(&mut stream)
+        .wrap_err_with(|| format!("{addr} isn't an oxid8 --serve session"))?;
+    let (frame_tx, frame_rx) = mpsc::channel();
+    thread::Builder::new().name("view".into()).spawn(move || {
+        while let Ok(frame) = broadcast::Frame::read_from(&mut stream) {
+            if frame_tx.send(frame).is_err() {
+                break;
             }
         }
+    })?;
 
-        // Run input
-        while let Ok(message) = input_rx.try_recv() {
-            match message {
-                Message::KeyDown(key) => chip.press_key(key),
-                Message::KeyUp(key) => chip.release_key(key),
-                _ => {}
+    tui::install_panic_hook();
+    let mut terminal = tui::init_terminal()?;
+    let status = StatusBar {
+        title: Some(format!("viewing {addr}")),
+        hint: Some("Esc to quit".into()),
+        speed: None,
+    };
+    let mut chip = Chip8::new();
+    let ended = loop {
+        match frame_rx.try_iter().last() {
+            Some(frame) => frame.apply(&mut chip),
+            None if frame_rx.recv_timeout(Duration::ZERO)
+                == Err(mpsc::RecvTimeoutError::Disconnected) =>
+            {
+                break true;
             }
-            update(&mut model, message);
+            None => {}
+        }
+        terminal.draw(|f| gfx::view(&chip, f, None, &status, &palette, None))?;
+        if event::poll(Duration::from_nanos(16_666_667))? {
+            if let Event::Key(key) = event::read()? {
+                if key.code == KeyCode::Esc {
+                    break false;
+                }
+            }
+        }
+    };
+    tui::restore_terminal()?;
+    if ended {
+        println!("The session at {addr} ended");
+    }
+    Ok(())
+}
+
+/// Time between terminal redraws at `fps`.
+fn render_interval(fps: u64) -> Duration {
+    Duration::from_nanos(1_000_000_000 / fps.max(1))
+}
+
+/// Terminal frontend: ratatui rendering and crossterm input each on their
+/// own thread, with cpal for the beeper.
+struct TuiFrontend {
+    model: Model,
+    buf_tx: TripleBufferWriter<RenderSnapshot>,
+    input_rx: mpsc::Receiver<Message>,
+    beeper: Beeper,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl TuiFrontend {
+    fn new(
+        debug: bool,
+        model: Model,
+        palette: Palette,
+        symbols: Symbols,
+        render_interval: Duration,
+        highlight_drawn: bool,
+    ) -> color_eyre::Result<Self> {
+        let mut terminal = tui::init_terminal()?;
+        let beeper = Beeper::new().unwrap();
+
+        // Setup async rendering thread using a BufChannel for communication.
+        let (buf_tx, buf_rx) = triple_buffer::triple_buffer(RenderSnapshot::default());
+        let running_state = model.running_state.clone();
+        let status = model.status.clone();
+        let show_help = model.show_help.clone();
+        let resized = model.resized.clone();
+        let fault = model.fault.clone();
+        let keymap = model.keymap.clone();
+        let theme = model.theme.clone();
+        let lock_instructions = model.lock_instructions.clone();
+        let render_join_handle = thread::Builder::new()
+            .name("render".into())
+            .spawn(move || {
+                let mut timers = TimerHistory::new(if debug { TIMER_SAMPLES } else { 0 });
+                let mut lock = None;
+                let mut fresh = highlight_drawn.then(FreshPixels::default);
+                while running_state.load(Ordering::Acquire) != RunningState::Done {
+                    if resized.swap(false, Ordering::AcqRel) {
+                        terminal.clear().unwrap();
+                    }
+                    {
+                        let read_handle = buf_rx.read();
+                        let status = status.lock().unwrap().clone();
+                        timers.sample(&read_handle.chip);
+                        if let Some(fresh) = &mut fresh {
+                            fresh.update(read_handle.screen_version, &read_handle.chip.screen);
+                        }
+                        // Lock on whatever PC is when the key is pressed.
+                        lock = match lock_instructions.load(Ordering::Acquire) {
+                            true => lock.or(Some(read_handle.chip.pc)),
+                            false => None,
+                        };
+                        // Render the current view
+                        terminal
+                            .draw(|f| {
+                                let pane = debug.then_some(gfx::DebugPane {
+                                    calls: &read_handle.call_history,
+                                    timers: &timers,
+                                    symbols: &symbols,
+                                    lock,
+                                });
+                                let palette = palette.with_theme(theme.load(Ordering::Acquire));
+                                let fresh = fresh.as_ref().map(FreshPixels::pixels);
+                                gfx::view(&read_handle.chip, f, pane, &status, &palette, fresh);
+                                if show_help.load(Ordering::Acquire) {
+                                    gfx::render_help(f, f.area(), &keymap);
+                                }
+                                if let FaultState::Faulted(details) = &*fault.lock().unwrap() {
+                                    gfx::render_fault(f, f.area(), details);
+                                }
+                            })
+                            .unwrap();
+                    }
+                    thread::sleep(render_interval);
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!("render thread stopped");
+            })?;
+
+        let (input_tx, input_rx) = mpsc::channel::<Message>();
+        let running_state = model.running_state.clone();
+        let keymap = model.keymap.clone();
+        let releases = tui::reports_releases();
+        let input_join_handle = thread::Builder::new().name("input".into()).spawn(move || {
+            input_loop(
+                &mut Crossterm,
+                &running_state,
+                &keymap,
+                releases,
+                &input_tx,
+                INPUT_POLL,
+            );
+            #[cfg(feature = "tracing")]
+            tracing::debug!("input thread stopped");
+        })?;
+
+        Ok(Self {
+            model,
+            buf_tx,
+            input_rx,
+            beeper,
+            threads: vec![render_join_handle, input_join_handle],
+        })
+    }
+
+    /// Show `details` over the last frame until Esc is pressed, saving
+    /// the faulted `chip` as a dump or a state on request.
+    fn wait_on_fault(&mut self, chip: &Chip8, details: String) {
+        self.beeper.set(false);
+        self.model.fault.lock().unwrap().fault(details);
+        while self.model.fault.lock().unwrap().waiting() {
+            let saved = match self.input_rx.recv() {
+                Ok(message @ Message::Resize { .. }) => {
+                    update(&mut self.model, message);
+                    continue;
+                }
+                Ok(Message::Dump) => save_dump(chip),
+                Ok(Message::SaveState) => save_state(chip),
+                Ok(message) => {
+                    self.model.fault.lock().unwrap().update(message);
+                    continue;
+                }
+                Err(_) => break,
+            };
+            self.model.status.lock().unwrap().hint = Some(match saved {
+                Ok(path) => format!("Saved {path}"),
+                Err(err) => format!("Can't save: {err}"),
+            });
+        }
+    }
+
+    /// Stop the render and input threads and give the terminal back.
+    fn shutdown(mut self) -> color_eyre::Result<()> {
+        update(&mut self.model, Message::Quit);
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
         }
+        tui::restore_terminal()
+    }
+}
 
-        // Play sounds
-        while let Ok(on) = timer_rx.try_recv() {
-            beeper.set(on);
+impl Frontend for TuiFrontend {
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        while let Ok(message) = self.input_rx.try_recv() {
+            let event = match message {
+                Message::NextRom => switch_rom(&mut self.model, Playlist::next_rom),
+                Message::PrevRom => switch_rom(&mut self.model, Playlist::prev_rom),
+                _ => input_event_of_message(message),
+            };
+            events.extend(event);
+            update(&mut self.model, message);
         }
+        events
+    }
 
-        thread::sleep(Duration::from_millis(cpu_millis));
+    fn present(&mut self, snapshot: &RenderSnapshot) {
+        if std::mem::take(&mut self.model.dump_requested) {
+            let hint = match save_dump(&snapshot.chip) {
+                Ok(path) => format!("Saved {path}"),
+                Err(err) => format!("Can't save dump: {err}"),
+            };
+            self.model.status.lock().unwrap().hint = Some(hint);
+        }
+        *self.buf_tx.write() = snapshot.clone();
     }
 
-    let _ = render_join_handle.join();
-    let _ = input_join_handle.join();
+    fn set_audio(&mut self, command: AudioCommand) {
+        self.beeper.set(command == AudioCommand::Start);
+    }
+}
 
-    tui::restore_terminal()?;
-    Ok(())
+/// How long the input thread waits for an event before checking whether
+/// it should stop.
+const INPUT_POLL: Duration = Duration::from_nanos(16_666_667); // ~60 Hz
+
+/// Where the input thread reads terminal events from.
+trait EventSource {
+    /// Wait up to `timeout` for an event, returning whether one is ready.
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool>;
+    fn read(&mut self) -> io::Result<Event>;
+}
+
+/// The real terminal, through crossterm.
+struct Crossterm;
+
+impl EventSource for Crossterm {
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        event::poll(timeout)
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        event::read()
+    }
+}
+
+/// Send terminal events to `tx` as messages until `running_state` is
+/// `Done`, waiting at most `timeout` between checks. Stops early if the
+/// terminal can't be read or nothing is receiving. Without `releases`
+/// the terminal never says a key was let go, so Tab toggles turbo.
+fn input_loop(
+    source: &mut impl EventSource,
+    running_state: &AtomicRunningState,
+    keymap: &KeyMap,
+    releases: bool,
+    tx: &mpsc::Sender<Message>,
+    timeout: Duration,
+) {
+    while running_state.load(Ordering::Acquire) != RunningState::Done {
+        match source.poll(timeout) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(_) => return,
+        }
+        // Drain everything already queued so keys pressed together reach
+        // the chip together.
+        loop {
+            let Ok(event) = source.read() else { return };
+            if let Some(message) = message_of_event(event, keymap, releases) {
+                if tx.send(message).is_err() {
+                    return;
+                }
+            }
+            if !source.poll(Duration::ZERO).unwrap_or(false) {
+                break;
+            }
+        }
+    }
 }
 
-fn chip8_key_of_char(c: char) -> Option<u8> {
-    match c {
-        '1' => Some(0x1),
-        '2' => Some(0x2),
-        '3' => Some(0x3),
-        '4' => Some(0xC),
-        'q' | 'Q' => Some(0x4),
-        'w' | 'W' => Some(0x5),
-        'e' | 'E' => Some(0x6),
-        'r' | 'R' => Some(0xD),
-        'a' | 'A' => Some(0x7),
-        's' | 'S' => Some(0x8),
-        'd' | 'D' => Some(0x9),
-        'f' | 'F' => Some(0xE),
-        'z' | 'Z' => Some(0xA),
-        'x' | 'X' => Some(0x0),
-        'c' | 'C' => Some(0xB),
-        'v' | 'V' => Some(0xF),
+fn message_of_event(event: Event, keymap: &KeyMap, releases: bool) -> Option<Message> {
+    match event {
+        Event::Key(key)
+            if !releases && key.code == KeyCode::Tab && key.modifiers == KeyModifiers::NONE =>
+        {
+            Some(Message::Speed(SpeedChange::ToggleTurbo))
+        }
+        Event::Key(key) => handle_key(key, keymap),
+        Event::Resize(width, height) => Some(Message::Resize { width, height }),
         _ => None,
     }
 }
 
-fn handle_key(key: event::KeyEvent) -> Option<Message> {
+fn handle_key(key: event::KeyEvent, keymap: &KeyMap) -> Option<Message> {
     match key.code {
+        KeyCode::Char('?') if key.kind == KeyEventKind::Press => Some(Message::ToggleHelp),
         KeyCode::Char(c) => {
-            let k = chip8_key_of_char(c)?;
+            let k = keymap.key_of_char(c)?;
             match key.kind {
                 KeyEventKind::Press | KeyEventKind::Repeat => Some(Message::KeyDown(k)),
                 KeyEventKind::Release => Some(Message::KeyUp(k)),
             }
         }
+        KeyCode::PageDown | KeyCode::F(10) if key.kind == KeyEventKind::Press => {
+            Some(Message::NextRom)
+        }
+        KeyCode::PageUp | KeyCode::F(9) if key.kind == KeyEventKind::Press => {
+            Some(Message::PrevRom)
+        }
+        KeyCode::BackTab if key.kind == KeyEventKind::Press => {
+            Some(Message::Speed(SpeedChange::ToggleSlowMotion))
+        }
+        KeyCode::Tab if key.modifiers.contains(KeyModifiers::SHIFT) => (key.kind
+            == KeyEventKind::Press)
+            .then_some(Message::Speed(SpeedChange::ToggleSlowMotion)),
+        KeyCode::Tab => match key.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => {
+                Some(Message::Speed(SpeedChange::TurboOn))
+            }
+            KeyEventKind::Release => Some(Message::Speed(SpeedChange::TurboOff)),
+        },
+        KeyCode::F(2) if key.kind == KeyEventKind::Press => Some(Message::CycleTheme),
+        KeyCode::F(3) if key.kind == KeyEventKind::Press => Some(Message::ToggleInstructionLock),
+        KeyCode::F(5) if key.kind == KeyEventKind::Press => Some(Message::SaveState),
+        KeyCode::F(12) if key.kind == KeyEventKind::Press => Some(Message::Dump),
         KeyCode::Esc => Some(Message::Quit),
         _ => None,
     }
 }
 
+/// Translate a terminal message for the emulator. Each key is tracked
+/// independently so several keys can be held at once (e.g. diagonal
+/// movement).
+fn input_event_of_message(message: Message) -> Option<InputEvent> {
+    match message {
+        Message::KeyDown(key) => Some(InputEvent::Key(KeyEvent::Down(key))),
+        Message::KeyUp(key) => Some(InputEvent::Key(KeyEvent::Up(key))),
+        Message::NextRom
+        | Message::PrevRom
+        | Message::ToggleHelp
+        | Message::Dump
+        | Message::SaveState
+        | Message::CycleTheme
+        | Message::ToggleInstructionLock
+        | Message::Resize { .. } => None,
+        Message::Speed(change) => Some(InputEvent::Speed(change)),
+        Message::Quit => Some(InputEvent::Quit),
+    }
+}
+
+/// Move through the playlist with `step` and load the ROM it lands on,
+/// updating the status bar to match.
+fn switch_rom(
+    model: &mut Model,
+    step: fn(&mut Playlist) -> Option<playlist::Entry>,
+) -> Option<InputEvent> {
+    let playlist = model.playlist.as_mut()?;
+    let entry = step(playlist);
+    let skipped: Vec<String> = playlist
+        .take_failures()
+        .into_iter()
+        .map(|(path, err)| format!("skipped {}: {err}", rom_name(&path)))
+        .collect();
+    let (path, rom) = entry?;
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        path = %path.display(),
+        rom_hash = format!("{:#018x}", romdb::rom_hash(&rom)),
+        "playlist switched rom"
+    );
+    let info = lookup_rom(model.use_rom_db, &rom);
+    let mut status = model.status.lock().unwrap();
+    *status = StatusBar {
+        speed: status.speed.take(),
+        ..StatusBar::for_rom(&rom_name(&path), info)
+    };
+    if !skipped.is_empty() {
+        status.hint = Some(skipped.join("; "));
+    }
+    drop(status);
+    Some(InputEvent::LoadRom(rom))
+}
+
 fn update(model: &mut Model, msg: Message) -> Option<Message> {
+    if let Message::ToggleHelp = msg {
+        model.show_help.fetch_xor(true, Ordering::AcqRel);
+    }
+    if let Message::Dump = msg {
+        model.dump_requested = true;
+    }
+    if let Message::CycleTheme = msg {
+        let theme = model.theme.load(Ordering::Acquire);
+        model.theme.store(gfx::next_theme(theme), Ordering::Release);
+    }
+    if let Message::ToggleInstructionLock = msg {
+        model.lock_instructions.fetch_xor(true, Ordering::AcqRel);
+    }
+    if let Message::Speed(change) = msg {
+        model.speed.apply(change);
+        model.status.lock().unwrap().speed = model.speed.label();
+    }
+    if let Message::Resize { .. } = msg {
+        model.resized.store(true, Ordering::Release);
+    }
     if let Message::Quit = msg {
         model
             .running_state
@@ -217,43 +1396,746 @@ mod tui {
         },
         Terminal,
     };
-    use std::{io::stdout, panic};
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::{io::stdout, thread};
+
+    thread_local! {
+        /// Set while a panic on this thread is caught by [`catch_quietly`].
+        static CATCHING: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Whether [`init_terminal`] pushed keyboard enhancement flags, which
+    /// restoring the terminal pops.
+    static ENHANCED: AtomicBool = AtomicBool::new(false);
 
     pub fn init_terminal() -> color_eyre::Result<Terminal<impl Backend>> {
         enable_raw_mode()?;
         stdout().execute(EnterAlternateScreen)?;
 
-        // Check if kitty keyboard protocol is supported before enabling
-        if supports_keyboard_enhancement()? {
-            // Then set enhancement flags
+        // The kitty keyboard protocol reports releases. Without it the
+        // input loop falls back to presses alone, see `reports_releases`.
+        if reports_releases() {
             stdout().execute(PushKeyboardEnhancementFlags(
                 KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
                     | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
                     | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES,
             ))?;
-        } else {
-            panic!("Terminal must support kitty keyboard enhancements");
+            ENHANCED.store(true, Ordering::Release);
         }
 
         let terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
         Ok(terminal)
     }
 
+    /// Whether the terminal reports keys being let go, which takes the
+    /// keyboard enhancement protocol.
+    pub fn reports_releases() -> bool {
+        supports_keyboard_enhancement().unwrap_or(false)
+    }
+
     pub fn restore_terminal() -> color_eyre::Result<()> {
-        // Disable kitty keyboard protocol with CSI < u
-        stdout().execute(PopKeyboardEnhancementFlags)?;
+        if ENHANCED.swap(false, Ordering::AcqRel) {
+            stdout().execute(PopKeyboardEnhancementFlags)?;
+        }
         stdout().execute(LeaveAlternateScreen)?;
         disable_raw_mode()?;
         Ok(())
     }
 
+    /// Restore the terminal before reporting a panic. Panics inside
+    /// [`catch_quietly`] are left to its caller, which shows them in the
+    /// TUI first.
     pub fn install_panic_hook() {
         let original_hook = panic::take_hook();
         panic::set_hook(Box::new(move |panic_info| {
-            stdout().execute(PopKeyboardEnhancementFlags).unwrap();
+            if CATCHING.get() {
+                return;
+            }
+            if ENHANCED.swap(false, Ordering::AcqRel) {
+                stdout().execute(PopKeyboardEnhancementFlags).unwrap();
+            }
             stdout().execute(LeaveAlternateScreen).unwrap();
             disable_raw_mode().unwrap();
             original_hook(panic_info);
         }));
     }
+
+    /// Run `f`, catching a panic from it without the panic hook reporting
+    /// it or touching the terminal.
+    pub fn catch_quietly<R>(f: impl FnOnce() -> R) -> thread::Result<R> {
+        let outer = CATCHING.replace(true);
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        CATCHING.set(outer);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+    use oxid8::chip8::consts::MAX_ROM_SIZE;
+    use std::path::PathBuf;
+
+    fn idle_emulator() -> Emulator {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[0x12, 0x00]).unwrap(); // JP 0x200
+        emu
+    }
+
+    fn apply_input(emu: &mut Emulator, message: Message) {
+        if let Some(InputEvent::Key(event)) = input_event_of_message(message) {
+            emu.queue_key_event(event);
+        }
+    }
+
+    #[test]
+    fn test_apply_input_multiple_keys_held() {
+        let mut emu = idle_emulator();
+        for message in [
+            Message::KeyDown(0x5),
+            Message::KeyDown(0x8),
+            Message::KeyUp(0x5),
+        ] {
+            apply_input(&mut emu, message);
+        }
+        emu.run_frame();
+
+        let chip = emu.chip();
+        let pressed: Vec<usize> = (0..16).filter(|&k| chip.keys[k]).collect();
+        assert_eq!(pressed, vec![0x8]);
+    }
+
+    #[test]
+    fn test_switch_rom() {
+        let playlist = Playlist::new(vec![
+            PathBuf::from("roms/keypad.ch8"),
+            PathBuf::from("roms/missing.ch8"),
+        ])
+        .with_loader(|path| load_playlist_rom(path, MAX_ROM_SIZE));
+        let mut model = Model::new(
+            StatusBar::default(),
+            Some(playlist),
+            true,
+            KeyMap::default(),
+        );
+
+        let event = switch_rom(&mut model, Playlist::next_rom);
+        assert_eq!(
+            event,
+            Some(InputEvent::LoadRom(
+                include_bytes!("../../roms/keypad.ch8").to_vec()
+            ))
+        );
+        let status = model.status.lock().unwrap().clone();
+        assert_eq!(status.title.as_deref(), Some("Keypad test"));
+        let hint = status.hint.unwrap();
+        assert!(
+            hint.starts_with("skipped missing.ch8: Failed to load ROM"),
+            "{hint}"
+        );
+
+        let mut model = Model::new(StatusBar::default(), None, true, KeyMap::default());
+        assert_eq!(switch_rom(&mut model, Playlist::prev_rom), None);
+    }
+
+    /// Events handed out in order, each poll waiting out its timeout when
+    /// none are left. Flips `running_state` to `Done` on poll `stop_at`, as
+    /// the main thread would while the input thread waits.
+    struct MockEvents {
+        events: std::collections::VecDeque<io::Result<Event>>,
+        running_state: Arc<AtomicRunningState>,
+        stop_at: usize,
+        polls: usize,
+    }
+
+    impl EventSource for MockEvents {
+        fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+            self.polls += 1;
+            if self.polls == self.stop_at {
+                self.running_state
+                    .store(RunningState::Done, Ordering::Release);
+            }
+            if self.events.is_empty() {
+                thread::sleep(timeout);
+            }
+            Ok(!self.events.is_empty())
+        }
+
+        fn read(&mut self) -> io::Result<Event> {
+            self.events.pop_front().unwrap()
+        }
+    }
+
+    fn run_input_loop(events: Vec<io::Result<Event>>, stop_at: usize) -> (usize, Vec<Message>) {
+        let running_state = Arc::new(AtomicRunningState::new(RunningState::Running));
+        let mut source = MockEvents {
+            events: events.into(),
+            running_state: running_state.clone(),
+            stop_at,
+            polls: 0,
+        };
+        let (tx, rx) = mpsc::channel();
+        let timeout = Duration::from_millis(5);
+        input_loop(
+            &mut source,
+            &running_state,
+            &KeyMap::default(),
+            true,
+            &tx,
+            timeout,
+        );
+        (source.polls, rx.try_iter().collect())
+    }
+
+    #[test]
+    fn test_input_loop_stops_within_one_poll() {
+        // Idle: the flag flips during the third wait, and that wait is the
+        // last.
+        let (polls, messages) = run_input_loop(Vec::new(), 3);
+        assert_eq!((polls, messages.len()), (3, 0));
+
+        let start = std::time::Instant::now();
+        run_input_loop(Vec::new(), 1);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_input_loop_forwards_keys_and_resizes() {
+        use ratatui::crossterm::event::KeyModifiers;
+        let key = |c| {
+            Ok(Event::Key(event::KeyEvent::new(
+                KeyCode::Char(c),
+                KeyModifiers::NONE,
+            )))
+        };
+        let events = vec![
+            key('x'),
+            Ok(Event::Resize(80, 24)),
+            Ok(Event::FocusLost),
+            key('1'),
+        ];
+        let (_, messages) = run_input_loop(events, 6);
+        assert!(matches!(
+            messages[..],
+            [
+                Message::KeyDown(0x0),
+                Message::Resize {
+                    width: 80,
+                    height: 24
+                },
+                Message::KeyDown(0x1)
+            ]
+        ));
+
+        let mut model = Model::new(StatusBar::default(), None, true, KeyMap::default());
+        update(&mut model, messages[1]);
+        assert!(model.resized.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_input_loop_stops_when_the_terminal_fails() {
+        let events = vec![Err(io::Error::other("terminal gone"))];
+        let (polls, messages) = run_input_loop(events, usize::MAX);
+        assert_eq!((polls, messages.len()), (1, 0));
+    }
+
+    #[test]
+    fn test_handle_key_keymap_and_help() {
+        use ratatui::crossterm::event::KeyModifiers;
+        let press = |c| event::KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let keymap: KeyMap = "0123456789abcdef".parse().unwrap();
+
+        assert!(matches!(
+            handle_key(press('b'), &keymap),
+            Some(Message::KeyDown(0xB))
+        ));
+        assert!(handle_key(press('v'), &keymap).is_none());
+        assert!(matches!(
+            handle_key(press('?'), &keymap),
+            Some(Message::ToggleHelp)
+        ));
+
+        let mut model = Model::new(StatusBar::default(), None, true, keymap.clone());
+        update(&mut model, Message::ToggleHelp);
+        assert!(model.show_help.load(Ordering::Acquire));
+        update(&mut model, Message::ToggleHelp);
+        assert!(!model.show_help.load(Ordering::Acquire));
+
+        let f12 = event::KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE);
+        assert!(matches!(handle_key(f12, &keymap), Some(Message::Dump)));
+        update(&mut model, Message::Dump);
+        assert!(model.dump_requested);
+
+        let f2 = event::KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE);
+        assert!(matches!(handle_key(f2, &keymap), Some(Message::CycleTheme)));
+        for expected in (1..=gfx::THEMES.len()).chain([0]) {
+            update(&mut model, Message::CycleTheme);
+            assert_eq!(model.theme.load(Ordering::Acquire), expected);
+        }
+
+        let f3 = event::KeyEvent::new(KeyCode::F(3), KeyModifiers::NONE);
+        assert!(matches!(
+            handle_key(f3, &keymap),
+            Some(Message::ToggleInstructionLock)
+        ));
+        update(&mut model, Message::ToggleInstructionLock);
+        assert!(model.lock_instructions.load(Ordering::Acquire));
+        update(&mut model, Message::ToggleInstructionLock);
+        assert!(!model.lock_instructions.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_handle_key_speed() {
+        let keymap = KeyMap::default();
+        let key = |code, modifiers, kind| {
+            handle_key(
+                event::KeyEvent::new_with_kind(code, modifiers, kind),
+                &keymap,
+            )
+        };
+        let speed = |change| Some(Message::Speed(change));
+        let none = KeyModifiers::NONE;
+        use KeyEventKind::{Press, Release, Repeat};
+        assert!(key(KeyCode::Tab, none, Press) == speed(SpeedChange::TurboOn));
+        assert!(key(KeyCode::Tab, none, Repeat) == speed(SpeedChange::TurboOn));
+        assert!(key(KeyCode::Tab, none, Release) == speed(SpeedChange::TurboOff));
+        let slow = speed(SpeedChange::ToggleSlowMotion);
+        assert!(key(KeyCode::BackTab, KeyModifiers::SHIFT, Press) == slow);
+        assert!(key(KeyCode::Tab, KeyModifiers::SHIFT, Press) == slow);
+        assert!(key(KeyCode::Tab, KeyModifiers::SHIFT, Release).is_none());
+
+        // Without release events every key arrives as a press.
+        let tab = Event::Key(event::KeyEvent::new(KeyCode::Tab, none));
+        let toggle = speed(SpeedChange::ToggleTurbo);
+        assert!(message_of_event(tab.clone(), &keymap, false) == toggle);
+        assert!(message_of_event(tab, &keymap, true) == speed(SpeedChange::TurboOn));
+        let shift_tab = Event::Key(event::KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT));
+        assert!(message_of_event(shift_tab, &keymap, false) == slow);
+
+        let mut model = Model::new(StatusBar::default(), None, true, keymap);
+        update(&mut model, Message::Speed(SpeedChange::ToggleSlowMotion));
+        update(&mut model, Message::Speed(SpeedChange::TurboOn));
+        let status = model.status.clone();
+        let label = || status.lock().unwrap().speed.clone();
+        assert_eq!(label().as_deref(), Some("turbo 8x"));
+        update(&mut model, Message::Speed(SpeedChange::TurboOff));
+        assert_eq!(label().as_deref(), Some("slow 0.25x"));
+        update(&mut model, Message::Speed(SpeedChange::ToggleSlowMotion));
+        assert_eq!(label(), None);
+    }
+
+    #[test]
+    fn test_args() {
+        use clap::CommandFactory;
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn test_args_rom_path() {
+        let rom_path = |argv: &[&str]| {
+            Args::try_parse_from(argv)
+                .map(|args| args.rom_path().to_string())
+                .map_err(|e| e.kind())
+        };
+        assert_eq!(rom_path(&["oxid8", "game.ch8"]), Ok("game.ch8".into()));
+        assert_eq!(rom_path(&["oxid8", "-"]), Ok("-".into()));
+        assert_eq!(
+            rom_path(&["oxid8", "--rom", "game.ch8"]),
+            Ok("game.ch8".into())
+        );
+        assert_eq!(
+            rom_path(&["oxid8", "-r", "game.ch8", "-d"]),
+            Ok("game.ch8".into())
+        );
+        assert_eq!(
+            rom_path(&["oxid8", "a.ch8", "--rom", "b.ch8"]),
+            Err(ErrorKind::ArgumentConflict)
+        );
+        let args = Args::try_parse_from(["oxid8", "-r", "a.ch8", "--rom", "b.ch8"]).unwrap();
+        assert_eq!(args.rom_flag, ["a.ch8", "b.ch8"]);
+        assert_eq!(args.rom_path(), "a.ch8");
+        assert_eq!(
+            rom_path(&["oxid8", "--headless"]),
+            Err(ErrorKind::MissingRequiredArgument)
+        );
+    }
+
+    #[test]
+    fn test_args_with_config() {
+        let config = "cpu-cycles = 20\nfg = #ff0000\nbg = #000010\n";
+        let argv = |args: &[&str]| -> Vec<String> {
+            let argv: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+            config::merge_args(&argv, config).unwrap()
+        };
+
+        let args = Args::parse_from(argv(&["oxid8", "game.ch8"]));
+        assert_eq!(args.cpu_cycles, Some(20));
+        assert_eq!(
+            args.palette(),
+            Palette {
+                on: [0xFF, 0, 0],
+                off: [0, 0, 0x10],
+                depth: ColorDepth::detect(),
+                ..Palette::default()
+            }
+        );
+        let args = Args::parse_from(argv(&["oxid8", "game.ch8", "--invert"]));
+        assert!(args.palette().invert);
+
+        let args = Args::parse_from(argv(&["oxid8", "game.ch8", "-c", "30"]));
+        assert_eq!(args.cpu_cycles, Some(30));
+        assert_eq!(args.palette().on, [0xFF, 0, 0]);
+
+        let config = "palette = 1a1b26,c0caf5,f00,00f\nfg = #ff0000\n";
+        let argv: Vec<String> = ["oxid8", "game.ch8"].map(String::from).to_vec();
+        let args = Args::parse_from(config::merge_args(&argv, config).unwrap());
+        let palette = args.palette();
+        assert_eq!(
+            (palette.off, palette.on),
+            ([0x1A, 0x1B, 0x26], [0xFF, 0, 0])
+        );
+        assert_eq!(palette.planes, Some([[0xFF, 0, 0], [0, 0, 0xFF]]));
+        let config = "palette = 1a1b26,nope\n";
+        assert!(Args::try_parse_from(config::merge_args(&argv, config).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_overrides_of_args() {
+        let args = Args::parse_from(["oxid8", "-r", "x.ch8", "-c", "20", "--variant", "schip"]);
+        let overrides = overrides_of_args(&args);
+        assert_eq!(overrides.cycles_per_frame, Some(20));
+        assert_eq!(overrides.variant, Some(Variant::SuperChip));
+
+        let args = Args::parse_from(["oxid8", "-r", "x.ch8"]);
+        assert_eq!(overrides_of_args(&args), Overrides::default());
+    }
+
+    #[test]
+    fn test_cpu_hz_and_render_fps() {
+        let args = Args::try_parse_from([
+            "oxid8",
+            "--cpu-hz",
+            "1500",
+            "--render-fps",
+            "30",
+            "game.ch8",
+        ])
+        .unwrap();
+        assert_eq!((args.cpu_hz, args.render_fps), (Some(1500), 30));
+        assert_eq!(
+            render_interval(args.render_fps),
+            Duration::from_nanos(33_333_333)
+        );
+        assert_eq!(render_interval(60), Duration::from_nanos(16_666_666));
+        assert!(Args::try_parse_from(["oxid8", "--render-fps", "0", "game.ch8"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_opcode() {
+        assert_eq!(parse_opcode("0xD12F"), Ok(0xD12F));
+        assert_eq!(parse_opcode("0Xd12f"), Ok(0xD12F));
+        assert_eq!(parse_opcode(" 00E0 "), Ok(0x00E0));
+        assert_eq!(parse_opcode("E0"), Ok(0x00E0));
+        assert!(parse_opcode("0x").is_err());
+        assert!(parse_opcode("0x12345").is_err());
+        assert!(parse_opcode("+12").is_err());
+        assert!(parse_opcode("zz").is_err());
+    }
+
+    #[test]
+    fn test_decode_mode() {
+        let args = Args::try_parse_from(["oxid8", "--decode", "0x00E0"]).unwrap();
+        assert_eq!(args.decode, Some(0x00E0));
+        let report = describe_opcode(args.decode.unwrap());
+        assert_eq!(report.lines().nth(1), Some("display: CLS"));
+        assert!(report.starts_with("word:    0x00E0"));
+
+        let conflict = Args::try_parse_from(["oxid8", "game.ch8", "--decode", "00E0"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_validate_rom_too_large() {
+        let err = validate_rom("big.ch8", &vec![0; MAX_ROM_SIZE + 1], MAX_ROM_SIZE).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "ROM big.ch8 is too large: {} bytes (limit {MAX_ROM_SIZE})",
+                MAX_ROM_SIZE + 1
+            )
+        );
+        assert!(validate_rom("ok.ch8", &[0; MAX_ROM_SIZE], MAX_ROM_SIZE).is_ok());
+    }
+
+    #[test]
+    fn test_read_rom_directory() {
+        let dir = std::env::temp_dir().to_string_lossy().into_owned();
+        let err = read_rom(&dir, MAX_ROM_SIZE).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            format!("Failed to load ROM {dir}: is a directory, not a ROM file")
+        );
+    }
+
+    #[test]
+    fn test_rom_hint() {
+        assert!(rom_hint("pong.8o", &[]).unwrap().contains("asm8"));
+        assert!(rom_hint("roms.ZIP", &[]).unwrap().contains("zip"));
+        assert!(rom_hint("pong.ch8", b"PK\x03\x04").unwrap().contains("zip"));
+        assert_eq!(rom_hint("pong.ch8", &[0x12, 0x00]), None);
+        assert!(validate_rom("pong.8o", b": main", MAX_ROM_SIZE).is_err());
+    }
+
+    #[test]
+    fn test_is_source() {
+        assert!(is_source("game.asm") && is_source("GAME.ASM"));
+        assert!(!is_source("game.ch8") && !is_source("game.8o") && !is_source("-"));
+    }
+
+    #[test]
+    fn test_args_disassemble() {
+        let args = Args::try_parse_from(["oxid8", "--disassemble", "game.ch8"]).unwrap();
+        assert_eq!(args.disassemble, Some(None));
+        let args =
+            Args::try_parse_from(["oxid8", "--disassemble=range:0x200-0x400", "game.ch8"]).unwrap();
+        assert_eq!(args.disassemble, Some(Some(0x200..0x400)));
+        assert!(Args::try_parse_from(["oxid8", "--disassemble=0x200", "game.ch8"]).is_err());
+        let args = Args::try_parse_from(["oxid8", "--print", "game.ch8"]).unwrap();
+        assert_eq!(args.disassemble, Some(None));
+    }
+
+    #[test]
+    fn test_args_repl() {
+        let args = Args::try_parse_from(["oxid8", "--repl", "game.ch8"]).unwrap();
+        assert!(args.repl);
+        let args = Args::try_parse_from(["oxid8", "--monitor", "game.ch8"]).unwrap();
+        assert!(args.repl);
+        let conflict = Args::try_parse_from(["oxid8", "--repl", "--headless", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_fault_state() {
+        let mut state = FaultState::default();
+        state.update(Message::Quit);
+        assert_eq!(state, FaultState::Running);
+
+        state.fault("Unkown opcode: 0x5AB1".into());
+        assert!(state.waiting());
+        state.fault("a later fault".into());
+        assert_eq!(state, FaultState::Faulted("Unkown opcode: 0x5AB1".into()));
+        // Only Esc leaves, so a held game key or a save doesn't.
+        for message in [
+            Message::KeyUp(0x5),
+            Message::KeyDown(0x5),
+            Message::SaveState,
+            Message::Dump,
+        ] {
+            state.update(message);
+            assert!(state.waiting());
+        }
+        state.update(Message::Quit);
+        assert_eq!(state, FaultState::Dismissed);
+
+        let mut state = FaultState::default();
+        state.fault("fault".into());
+        state.update(Message::Quit);
+        assert!(!state.waiting());
+    }
+
+    #[test]
+    fn test_run_frontend_catches_faults() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[0x5A, 0xB1]).unwrap();
+        let (frontend, replay, stopped) = run_frontend(&mut emu, HeadlessFrontend::new(10), true);
+        assert_eq!(frontend.presented(), 1);
+        assert!(replay.is_some());
+        assert_eq!(stopped.unwrap(), StopReason::Faulted);
+        let details = fault_details(emu.fault().unwrap(), &emu);
+        assert!(details.starts_with("Unkown opcode: 0x5AB1"), "{details}");
+        assert!(details.contains("PC=0x200"), "{details}");
+        assert!(details.contains("-> 0x200  5AB1"), "{details}");
+    }
+
+    /// A frontend that panics when asked to show a frame.
+    struct PanickingFrontend;
+
+    impl Frontend for PanickingFrontend {
+        fn poll_input(&mut self) -> Vec<InputEvent> {
+            Vec::new()
+        }
+        fn present(&mut self, _: &RenderSnapshot) {
+            panic!("frontend bug");
+        }
+        fn set_audio(&mut self, _: AudioCommand) {}
+    }
+
+    #[test]
+    fn test_run_frontend_catches_frontend_panics() {
+        let mut emu = idle_emulator();
+        let (_, _, stopped) = run_frontend(&mut emu, PanickingFrontend, false);
+        assert_eq!(panic_message(stopped.unwrap_err().as_ref()), "frontend bug");
+        assert_eq!(emu.fault(), None);
+    }
+
+    #[test]
+    fn test_save_keys() {
+        use ratatui::crossterm::event::KeyModifiers;
+        let keymap = KeyMap::default();
+        let f5 = event::KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE);
+        assert!(matches!(handle_key(f5, &keymap), Some(Message::SaveState)));
+        assert_eq!(input_event_of_message(Message::SaveState), None);
+        let path = timestamped_path("state", "o8s");
+        assert!(
+            path.starts_with("oxid8-state-") && path.ends_with(".o8s"),
+            "{path}"
+        );
+    }
+
+    #[test]
+    fn test_args_capabilities() {
+        let args = Args::try_parse_from(["oxid8", "--capabilities"]).unwrap();
+        assert!(args.capabilities);
+        let conflict = Args::try_parse_from(["oxid8", "--capabilities", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_args_benchmark() {
+        use oxid8::chip8::benchmark::BENCHMARK_INSTRUCTIONS;
+
+        let args = Args::try_parse_from(["oxid8", "--benchmark", "game.ch8"]).unwrap();
+        assert_eq!(args.benchmark, Some(BENCHMARK_INSTRUCTIONS));
+        let args = Args::try_parse_from(["oxid8", "--benchmark=5000", "game.ch8"]).unwrap();
+        assert_eq!(args.benchmark, Some(5000));
+        let conflict = Args::try_parse_from(["oxid8", "--benchmark", "--headless", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_args_print_screen() {
+        let args = Args::try_parse_from([
+            "oxid8",
+            "--headless",
+            "--frames",
+            "100",
+            "--print-screen",
+            "--no-color",
+            "game.ch8",
+        ])
+        .unwrap();
+        assert!(args.print_screen && args.no_color);
+        let missing = Args::try_parse_from(["oxid8", "--print-screen", "game.ch8"]);
+        assert_eq!(
+            missing.unwrap_err().kind(),
+            ErrorKind::MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn test_args_instructions_json() {
+        let args = Args::try_parse_from(["oxid8", "--instructions-json"]).unwrap();
+        assert_eq!(args.instructions_json, Some(None));
+        let args = Args::try_parse_from(["oxid8", "--instructions-json=schip"]).unwrap();
+        assert_eq!(args.instructions_json, Some(Some(Variant::SuperChip)));
+        let conflict = Args::try_parse_from(["oxid8", "--instructions-json", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_args_protect_low_mem() {
+        let parse = |flags: &[&str]| {
+            let argv = ["oxid8"].iter().chain(flags).chain(&["game.ch8"]);
+            Args::try_parse_from(argv).map(|args| args.protect_low_mem)
+        };
+        assert_eq!(parse(&[]).unwrap(), None);
+        assert_eq!(
+            parse(&["--protect-low-mem"]).unwrap(),
+            Some(ProtectPolicy::Fault)
+        );
+        assert_eq!(
+            parse(&["--protect-low-mem=ignore"]).unwrap(),
+            Some(ProtectPolicy::Ignore)
+        );
+        assert!(parse(&["--protect-low-mem=maybe"]).is_err());
+    }
+
+    #[test]
+    fn test_args_record_single_rom() {
+        let conflict =
+            Args::try_parse_from(["oxid8", "--record", "out.replay", "--rom-dir", "roms"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_args_pause_on_fault() {
+        assert!(
+            !Args::try_parse_from(["oxid8", "game.ch8"])
+                .unwrap()
+                .pause_on_fault
+        );
+        let args = Args::try_parse_from(["oxid8", "--pause-on-fault", "game.ch8"]).unwrap();
+        assert!(args.pause_on_fault);
+        let conflict =
+            Args::try_parse_from(["oxid8", "--pause-on-fault", "--headless", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_args_serve_and_view() {
+        let args = Args::try_parse_from(["oxid8", "--serve", "0.0.0.0:8080", "game.ch8"]).unwrap();
+        assert_eq!(args.serve.as_deref(), Some("0.0.0.0:8080"));
+        let args = Args::try_parse_from(["oxid8", "--view", "host:8080"]).unwrap();
+        assert_eq!(args.view.as_deref(), Some("host:8080"));
+        let conflict = Args::try_parse_from(["oxid8", "--view", "host:8080", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+        let conflict =
+            Args::try_parse_from(["oxid8", "--serve", ":8080", "--headless", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_args_dump_state() {
+        let args = Args::try_parse_from([
+            "oxid8",
+            "--headless",
+            "--dump-state",
+            "out.json",
+            "game.ch8",
+        ])
+        .unwrap();
+        assert_eq!(args.dump_state.as_deref(), Some("out.json"));
+        let conflict =
+            Args::try_parse_from(["oxid8", "--dump-state", "out.json", "--repl", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_args_gdb_port() {
+        let args = Args::try_parse_from(["oxid8", "--gdb-port", "3333", "game.ch8"]).unwrap();
+        assert_eq!(args.gdb_port, Some(3333));
+        let conflict = Args::try_parse_from(["oxid8", "--gdb-port", "3333", "--repl", "game.ch8"]);
+        assert_eq!(conflict.unwrap_err().kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_parse_hash() {
+        assert_eq!(parse_hash("0x00ff"), Ok(0xFF));
+        assert_eq!(parse_hash("DEADBEEF"), Ok(0xDEADBEEF));
+        assert!(parse_hash("xyz").is_err());
+    }
+
+    #[test]
+    fn test_apply_input_simultaneous_press() {
+        let mut emu = idle_emulator();
+        apply_input(&mut emu, Message::KeyDown(0x4));
+        apply_input(&mut emu, Message::KeyDown(0x6));
+        emu.run_frame();
+
+        assert!(emu.chip().keys[0x4]);
+        assert!(emu.chip().keys[0x6]);
+    }
 }
@@ -5,25 +5,49 @@ use std::f32::consts::PI;
 use color_eyre::eyre::{bail, eyre, Result};
 use cpal::traits::{DeviceTrait, HostTrait};
 
-pub struct Beeper {
-    pub stream: cpal::Stream,
+/// Where a [`Beeper`] sends its tone: the sound device, or anything else
+/// that wants to know when it sounds.
+pub trait AudioSink {
+    /// Let the tone through, or silence it.
+    fn set_gate(&mut self, on: bool);
 }
 
-impl Beeper {
-    pub fn new() -> color_eyre::Result<Self> {
-        Ok(Self {
-            stream: super::audio::setup()?,
-        })
-    }
-    pub fn set(&self, on: bool) {
+impl AudioSink for cpal::Stream {
+    fn set_gate(&mut self, on: bool) {
         if on {
-            let _ = self.stream.play();
+            let _ = self.play();
         } else {
-            let _ = self.stream.pause();
+            let _ = self.pause();
         };
     }
 }
 
+pub struct Beeper<S: AudioSink = cpal::Stream> {
+    pub sink: S,
+    on: bool,
+}
+
+impl Beeper {
+    /// A beeper playing through the default output device.
+    pub fn new() -> color_eyre::Result<Self> {
+        Ok(Self::with_sink(super::audio::setup()?))
+    }
+}
+
+impl<S: AudioSink> Beeper<S> {
+    /// A silent beeper feeding `sink`.
+    pub fn with_sink(sink: S) -> Self {
+        Self { sink, on: false }
+    }
+    /// Sound or silence the tone. Only changes reach the sink.
+    pub fn set(&mut self, on: bool) {
+        if on != self.on {
+            self.on = on;
+            self.sink.set_gate(on);
+        }
+    }
+}
+
 pub fn setup() -> Result<cpal::Stream> {
     let host = cpal::default_host();
     let device = host
@@ -44,7 +68,11 @@ pub fn setup() -> Result<cpal::Stream> {
     let mut phase = 0.0_f32;
     let phase_inc = 2.0 * PI * freq / sample_rate;
 
-    let err_fn = |e| eprintln!("stream error: {e}");
+    let err_fn = |e| {
+        log_event!(error, "audio stream error: {e}");
+        #[cfg(not(feature = "tracing"))]
+        eprintln!("stream error: {e}");
+    };
 
     let stream = match sample_format {
         cpal::SampleFormat::F32 => device.build_output_stream(
@@ -104,3 +132,65 @@ fn write_sine_u16(buf: &mut [u16], ch: usize, amp: f32, phase: &mut f32, inc: f3
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::{Emulator, EmulatorConfig};
+
+    /// Records every gate change instead of playing anything.
+    #[derive(Default)]
+    struct MockSink(Vec<bool>);
+
+    impl AudioSink for MockSink {
+        fn set_gate(&mut self, on: bool) {
+            self.0.push(on);
+        }
+    }
+
+    #[test]
+    fn test_set_only_passes_changes() {
+        let mut beeper = Beeper::with_sink(MockSink::default());
+        beeper.set(false);
+        beeper.set(true);
+        beeper.set(true);
+        beeper.set(false);
+        assert_eq!(beeper.sink.0, [true, false]);
+    }
+
+    #[test]
+    fn test_sound_timer_drives_gate() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[
+            0x60, 0x02, // LD V0, 2
+            0xF0, 0x18, // LD ST, V0
+            0x61, 0x04, // LD V1, 4
+            0xF1, 0x15, // LD DT, V1
+            0xF2, 0x07, // LD V2, DT
+            0x32, 0x00, // SE V2, 0
+            0x12, 0x08, // JP 0x208
+            0x12, 0x00, // JP 0x200
+        ])
+        .unwrap();
+        let mut beeper = Beeper::with_sink(MockSink::default());
+        let mut gates = Vec::new();
+        for frame in 0..12 {
+            let len = beeper.sink.0.len();
+            beeper.set(emu.run_frame().beep);
+            gates.extend(beeper.sink.0[len..].iter().map(|&on| (frame, on)));
+        }
+        // ST = 2 sounds until the next frame's tick, and the loop stores
+        // it again every 4 frames, once DT has run down.
+        assert_eq!(
+            gates,
+            [
+                (0, true),
+                (1, false),
+                (4, true),
+                (5, false),
+                (8, true),
+                (9, false)
+            ]
+        );
+    }
+}
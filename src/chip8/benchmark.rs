@@ -0,0 +1,108 @@
+//! Raw emulation throughput: whole frames run back to back with no
+//! frontend, so nothing is drawn, played or paced. Shared by
+//! `oxid8 --benchmark` and `oxid8-bench`.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::chip8::emulator::Emulator;
+
+/// Instructions `oxid8 --benchmark` runs when not told otherwise.
+pub const BENCHMARK_INSTRUCTIONS: u64 = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    pub instructions: u64,
+    /// Frames run to completion; one the program halted in isn't counted.
+    pub frames: u64,
+    pub elapsed: Duration,
+}
+
+impl Throughput {
+    pub fn instructions_per_sec(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl fmt::Display for Throughput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} instructions in {:.3} s: {:.0} instructions/s",
+            self.instructions,
+            self.elapsed.as_secs_f64(),
+            self.instructions_per_sec()
+        )
+    }
+}
+
+/// Run `emu` for exactly `instructions` instructions, or until the program
+/// halts. The last frame is shortened to fit, running at speed 1.
+pub fn run(emu: &mut Emulator, instructions: u64) -> Throughput {
+    let start_count = emu.executed();
+    let mut frames = 0;
+    let start = Instant::now();
+    loop {
+        let done = emu.executed() - start_count;
+        if done >= instructions {
+            break;
+        }
+        let remaining = instructions - done;
+        let config = emu.config();
+        // A frame's budget rounds up by at most one with the speed carry.
+        if remaining as f64 <= config.instructions_per_frame() * config.speed + 1.0 {
+            emu.set_speed(1.0);
+            emu.set_cycles_per_frame(remaining);
+        }
+        if emu.run_frame().halted {
+            break;
+        }
+        frames += 1;
+    }
+    Throughput {
+        instructions: emu.executed() - start_count,
+        frames,
+        elapsed: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::EmulatorConfig;
+    use crate::chip8::synth::Workload;
+
+    fn emulator(config: EmulatorConfig, rom: &[u8]) -> Emulator {
+        let mut emu = Emulator::new(config);
+        emu.load_rom(rom).unwrap();
+        emu
+    }
+
+    #[test]
+    fn test_runs_exactly_the_requested_count() {
+        for workload in Workload::ALL {
+            let mut emu = emulator(EmulatorConfig::default(), &workload.rom());
+            let run = run(&mut emu, 1000);
+            assert_eq!(run.instructions, 1000, "{workload:?}");
+            assert_eq!(emu.executed(), 1000);
+            // 83 frames of 12 and one of 4.
+            assert_eq!(run.frames, 84);
+        }
+
+        let config = EmulatorConfig {
+            cpu_hz: Some(700),
+            speed: 1.5,
+            ..EmulatorConfig::default()
+        };
+        let mut emu = emulator(config, &Workload::Alu.rom());
+        assert_eq!(run(&mut emu, 12_345).instructions, 12_345);
+    }
+
+    #[test]
+    fn test_stops_when_the_program_halts() {
+        // EXIT
+        let mut emu = emulator(EmulatorConfig::default(), &[0x00, 0xFD]);
+        let run = run(&mut emu, 1000);
+        assert_eq!((run.instructions, run.frames), (1, 0));
+    }
+}
@@ -0,0 +1,337 @@
+//! Streaming the display to remote viewers over TCP.
+//!
+//! A connection starts with a header: the magic `OX8V`, a version byte and
+//! the display width and height in pixels as u16. Then each frame follows
+//! as a u32 payload length and the payload: the frame number as u64, the
+//! resolution (0 low, 1 high) and the display packed one bit per pixel, a
+//! row of `W` bytes at a time, every plane ORed together. All integers are
+//! little-endian.
+//!
+//! Every client has a short queue of its own. A client that falls behind
+//! is dropped, so a slow viewer never stalls the emulator.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chip8::consts::{H, W};
+use crate::chip8::cpu::{Chip8, Resolution};
+use crate::chip8::emulator::RenderSnapshot;
+use crate::chip8::framebuffer::Framebuffer;
+use crate::chip8::frontend::{AudioCommand, Frontend, InputEvent};
+
+const MAGIC: &[u8; 4] = b"OX8V";
+pub const VERSION: u8 = 1;
+
+/// Frames a client may have queued before it counts as too slow.
+const CLIENT_QUEUE: usize = 4;
+/// Shortest time between two frames sent to clients, matching the TUI.
+const FRAME_INTERVAL: Duration = Duration::from_nanos(16_666_667);
+/// Bytes of a frame payload: frame number, resolution and bitmap.
+const PAYLOAD_LEN: usize = 8 + 1 + H * W;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            width: (W * 8) as u16,
+            height: H as u16,
+        }
+    }
+}
+
+impl Header {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(self.version);
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out
+    }
+
+    /// Read a header, failing unless it's one this build can display.
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut bytes = [0; 9];
+        reader.read_exact(&mut bytes)?;
+        if &bytes[..4] != MAGIC {
+            return Err(invalid("not an oxid8 broadcast"));
+        }
+        let header = Self {
+            version: bytes[4],
+            width: u16::from_le_bytes([bytes[5], bytes[6]]),
+            height: u16::from_le_bytes([bytes[7], bytes[8]]),
+        };
+        if header != Self::default() {
+            return Err(invalid(&format!(
+                "unsupported broadcast: version {} at {}x{}",
+                header.version, header.width, header.height
+            )));
+        }
+        Ok(header)
+    }
+}
+
+/// One frame of the display as sent to viewers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub frame: u64,
+    pub resolution: Resolution,
+    /// `H` rows of `W` bytes, one bit per pixel.
+    pub bitmap: Vec<u8>,
+}
+
+impl Frame {
+    pub fn of_chip(chip: &Chip8, frame: u64) -> Self {
+        let bitmap = (0..H)
+            .flat_map(|y| (0..W).map(move |x| (y, x)))
+            .map(|(y, x)| chip.screen_byte(y, x))
+            .collect();
+        Self {
+            frame,
+            resolution: chip.resolution,
+            bitmap,
+        }
+    }
+
+    /// The packet for this frame, length prefix included.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + PAYLOAD_LEN);
+        out.extend_from_slice(&(PAYLOAD_LEN as u32).to_le_bytes());
+        out.extend_from_slice(&self.frame.to_le_bytes());
+        out.push(matches!(self.resolution, Resolution::High) as u8);
+        out.extend_from_slice(&self.bitmap);
+        out
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut len = [0; 4];
+        reader.read_exact(&mut len)?;
+        if u32::from_le_bytes(len) as usize != PAYLOAD_LEN {
+            return Err(invalid("frame has the wrong length"));
+        }
+        let mut payload = vec![0; PAYLOAD_LEN];
+        reader.read_exact(&mut payload)?;
+        Ok(Self {
+            frame: u64::from_le_bytes(payload[..8].try_into().unwrap()),
+            resolution: match payload[8] {
+                0 => Resolution::Low,
+                _ => Resolution::High,
+            },
+            bitmap: payload.split_off(9),
+        })
+    }
+
+    /// Show this frame on `chip`, for rendering with the usual gfx code.
+    pub fn apply(&self, chip: &mut Chip8) {
+        chip.resolution = self.resolution;
+        chip.screen = Framebuffer::from_bytes(&self.bitmap).unwrap();
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+type Clients = Arc<Mutex<Vec<SyncSender<Arc<Vec<u8>>>>>>;
+
+/// Accepts viewers on a TCP port and sends each of them the frames passed
+/// to [`Server::publish`].
+pub struct Server {
+    addr: SocketAddr,
+    clients: Clients,
+    last_sent: Option<(Instant, u64)>,
+}
+
+impl Server {
+    /// Listen on `addr`, accepting viewers on a background thread.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let clients = Clients::default();
+        let accepting = clients.clone();
+        thread::Builder::new()
+            .name("broadcast".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Ok(client) = spawn_client(stream) {
+                        accepting.lock().unwrap().push(client);
+                    }
+                }
+            })?;
+        Ok(Self {
+            addr,
+            clients,
+            last_sent: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Queue `snapshot` for every client if the screen changed, at most
+    /// once per render interval. Clients whose queue is full are dropped.
+    pub fn publish(&mut self, snapshot: &RenderSnapshot) {
+        let now = Instant::now();
+        if let Some((at, version)) = self.last_sent {
+            if version == snapshot.screen_version || now - at < FRAME_INTERVAL {
+                return;
+            }
+        }
+        self.last_sent = Some((now, snapshot.screen_version));
+        let packet = Arc::new(Frame::of_chip(&snapshot.chip, snapshot.frame).encode());
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| match client.try_send(packet.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    log_event!(info, "dropping slow broadcast client");
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+}
+
+/// Start a thread writing the header and then queued packets to `stream`.
+/// The thread ends, closing the connection, when its sender is dropped or
+/// a write fails.
+fn spawn_client(mut stream: TcpStream) -> io::Result<SyncSender<Arc<Vec<u8>>>> {
+    let (tx, rx) = mpsc::sync_channel::<Arc<Vec<u8>>>(CLIENT_QUEUE);
+    stream.set_nodelay(true)?;
+    thread::Builder::new()
+        .name("broadcast-client".into())
+        .spawn(move || {
+            if stream.write_all(&Header::default().encode()).is_err() {
+                return;
+            }
+            for packet in rx {
+                if stream.write_all(&packet).is_err() {
+                    return;
+                }
+            }
+        })?;
+    Ok(tx)
+}
+
+/// Wraps a frontend, broadcasting every frame it presents.
+pub struct Broadcasting<F> {
+    pub inner: F,
+    pub server: Server,
+}
+
+impl<F: Frontend> Frontend for Broadcasting<F> {
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        self.inner.poll_input()
+    }
+
+    fn present(&mut self, snapshot: &RenderSnapshot) {
+        self.server.publish(snapshot);
+        self.inner.present(snapshot);
+    }
+
+    fn set_audio(&mut self, command: AudioCommand) {
+        self.inner.set_audio(command);
+    }
+
+    fn realtime(&self) -> bool {
+        self.inner.realtime()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frame() -> Frame {
+        let mut chip = Chip8::new();
+        chip.resolution = Resolution::High;
+        chip.screen.set_byte(0, 0, 0x80);
+        chip.screen.set_byte(63, 15, 0x01);
+        Frame::of_chip(&chip, 42)
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = Header::default();
+        let bytes = header.encode();
+        assert_eq!(&bytes[..5], b"OX8V\x01");
+        assert_eq!(Header::read_from(&mut Cursor::new(bytes)).unwrap(), header);
+
+        let other = Header {
+            version: 2,
+            ..header
+        };
+        assert!(Header::read_from(&mut Cursor::new(other.encode())).is_err());
+        assert!(Header::read_from(&mut Cursor::new(b"HTTP/1.1")).is_err());
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let frame = frame();
+        let bytes = frame.encode();
+        assert_eq!(bytes.len(), 4 + PAYLOAD_LEN);
+        assert_eq!(Frame::read_from(&mut Cursor::new(&bytes)).unwrap(), frame);
+        assert!(Frame::read_from(&mut Cursor::new(&bytes[..100])).is_err());
+
+        let mut chip = Chip8::new();
+        frame.apply(&mut chip);
+        assert_eq!(chip.screen_byte(0, 0), 0x80);
+        assert_eq!(chip.screen_byte(63, 15), 0x01);
+        assert_eq!(Frame::of_chip(&chip, 42), frame);
+    }
+
+    #[test]
+    fn test_serve_over_localhost() {
+        let mut server = Server::bind("127.0.0.1:0").unwrap();
+        let mut viewer = TcpStream::connect(server.local_addr()).unwrap();
+        Header::read_from(&mut viewer).unwrap();
+        while server.clients() == 0 {
+            thread::yield_now();
+        }
+
+        let frame = frame();
+        let mut snapshot = RenderSnapshot {
+            chip: Chip8::new(),
+            frame: 42,
+            screen_version: 1,
+            ..RenderSnapshot::default()
+        };
+        frame.apply(&mut snapshot.chip);
+        server.publish(&snapshot);
+        // Unchanged screens aren't sent again.
+        server.publish(&snapshot);
+        assert_eq!(Frame::read_from(&mut viewer).unwrap(), frame);
+
+        drop(viewer);
+        thread::sleep(FRAME_INTERVAL);
+        snapshot.screen_version = 2;
+        // The first send to a closed socket can still succeed; later ones
+        // fail and end the client thread, disconnecting its queue.
+        for _ in 0..100 {
+            if server.clients() == 0 {
+                break;
+            }
+            thread::sleep(FRAME_INTERVAL);
+            snapshot.screen_version += 1;
+            server.publish(&snapshot);
+        }
+        assert_eq!(server.clients(), 0);
+    }
+}
@@ -0,0 +1,89 @@
+//! The most recent CALLs and RETs, for the debug view.
+//!
+//! The stack only holds return addresses for subroutines still running;
+//! the history also shows where each call came from and where returns
+//! went after their frame was popped.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::chip8::op::ChipOp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallEvent {
+    /// The CALL at `from` jumped to `to`.
+    Call { from: usize, to: usize },
+    /// The RET at `from` returned to `to`.
+    Ret { from: usize, to: usize },
+}
+
+impl fmt::Display for CallEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallEvent::Call { from, to } => write!(f, "CALL {from:#05x} > {to:#05x}"),
+            CallEvent::Ret { from, to } => write!(f, "RET  {from:#05x} > {to:#05x}"),
+        }
+    }
+}
+
+/// The last `capacity` call events, oldest first. A capacity of zero
+/// records nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallHistory {
+    events: VecDeque<CallEvent>,
+    capacity: usize,
+}
+
+impl CallHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Note that `op`, fetched from `from`, left PC at `to`. Anything but
+    /// CALL and RET is ignored.
+    pub fn observe(&mut self, op: ChipOp, from: usize, to: usize) {
+        let event = match op {
+            ChipOp::CallNnn { .. } => CallEvent::Call { from, to },
+            ChipOp::Ret => CallEvent::Ret { from, to },
+            _ => return,
+        };
+        if !self.enabled() {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub fn events(&self) -> impl DoubleEndedIterator<Item = &CallEvent> {
+        self.events.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_the_last_events() {
+        let mut history = CallHistory::new(2);
+        history.observe(ChipOp::CallNnn { nnn: 0x300 }, 0x200, 0x300);
+        history.observe(ChipOp::Cls, 0x300, 0x302);
+        history.observe(ChipOp::Ret, 0x302, 0x202);
+        history.observe(ChipOp::CallNnn { nnn: 0x400 }, 0x202, 0x400);
+        let events: Vec<String> = history.events().map(ToString::to_string).collect();
+        assert_eq!(events, ["RET  0x302 > 0x202", "CALL 0x202 > 0x400"]);
+
+        let mut off = CallHistory::default();
+        off.observe(ChipOp::Ret, 0x302, 0x202);
+        assert_eq!(off.events().count(), 0);
+    }
+}
@@ -0,0 +1,199 @@
+//! A report of what this build supports, for `--capabilities` and bug
+//! reports. Everything is read from the code itself so it can't go stale.
+
+use std::fmt::Write;
+
+use crate::chip8::consts::PLANES;
+use crate::chip8::decode::decode;
+use crate::chip8::op::{instructions, ChipOp};
+use crate::chip8::quirks::Variant;
+
+/// Cargo features this build was compiled with.
+pub fn features() -> Vec<&'static str> {
+    [
+        ("flat-screen", cfg!(feature = "flat-screen")),
+        ("ndarray", cfg!(feature = "ndarray")),
+        ("python", cfg!(feature = "python")),
+        ("sdl", cfg!(feature = "sdl")),
+        ("tracing", cfg!(feature = "tracing")),
+        ("xo-chip", cfg!(feature = "xo-chip")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+pub fn report() -> String {
+    let mut out = String::new();
+    writeln!(out, "oxid8 {}", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(out, "features: {}", features().join(", ")).unwrap();
+    writeln!(out, "audio: cpal").unwrap();
+    writeln!(out, "display planes: {PLANES}").unwrap();
+
+    writeln!(out, "\nvariants:").unwrap();
+    for variant in Variant::ALL {
+        writeln!(out, "  {}", variant.name()).unwrap();
+        for (name, value) in variant.quirks().entries() {
+            writeln!(out, "    {name}={value}").unwrap();
+        }
+    }
+
+    writeln!(out, "\ninstructions:").unwrap();
+    for op in instructions() {
+        writeln!(
+            out,
+            "  {}  {:<5} {}",
+            op.pattern(),
+            op.mnemonic(),
+            op.instruction_set()
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// The operand fields an opcode pattern can hold, longest first.
+const OPERANDS: [(&str, &str); 5] = [
+    ("NNN", "12-bit address"),
+    ("NN", "8-bit value"),
+    ("N", "4-bit value"),
+    ("X", "register VX"),
+    ("Y", "register VY"),
+];
+
+/// `op`'s assembly form with its operands named as in its pattern, e.g.
+/// `DRW VX, VY, N`.
+pub fn syntax(op: ChipOp) -> String {
+    // Fill each field with a digit that can't appear otherwise, display
+    // that, and swap the digits back for the field names.
+    let word = op.pattern().chars().fold(0u16, |word, c| {
+        let digit = match c {
+            'X' => 0xA,
+            'Y' => 0xB,
+            'N' => 0xC,
+            _ => c.to_digit(16).unwrap_or_default() as u16,
+        };
+        word << 4 | digit
+    });
+    decode(word)
+        .to_string()
+        .replace("0xCCC", "NNN")
+        .replace("0xCC", "NN")
+        .replace("0xC", "N")
+        .replace("VA", "VX")
+        .replace("VB", "VY")
+}
+
+/// The operand fields in `pattern`, in the order they appear.
+fn operands(pattern: &str) -> Vec<(&'static str, &'static str)> {
+    let mut rest = pattern.to_string();
+    let mut found = Vec::new();
+    for (name, description) in OPERANDS {
+        if let Some(at) = rest.find(name) {
+            rest.replace_range(at..at + name.len(), &"_".repeat(name.len()));
+            found.push((at, name, description));
+        }
+    }
+    found.sort_unstable();
+    found.into_iter().map(|(_, name, d)| (name, d)).collect()
+}
+
+/// Every instruction `variant` runs, or all of them, as a JSON array with
+/// one object per line for assemblers, editors and syntax highlighters.
+pub fn instructions_json(variant: Option<Variant>) -> String {
+    let entries: Vec<String> = instructions()
+        .into_iter()
+        .filter(|op| variant.is_none_or(|v| op.instruction_set() <= v.instruction_set()))
+        .map(|op| {
+            let operands: Vec<String> = operands(op.pattern())
+                .into_iter()
+                .map(|(name, description)| {
+                    format!("{{\"name\": \"{name}\", \"description\": \"{description}\"}}")
+                })
+                .collect();
+            format!(
+                concat!(
+                    "  {{\"pattern\": \"{}\", \"mnemonic\": \"{}\", \"syntax\": \"{}\", ",
+                    "\"instruction_set\": \"{}\", \"operands\": [{}]}}"
+                ),
+                op.pattern(),
+                op.mnemonic(),
+                syntax(op),
+                op.instruction_set(),
+                operands.join(", ")
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let report = report();
+        assert!(report.starts_with(&format!("oxid8 {}\n", env!("CARGO_PKG_VERSION"))));
+        for line in [
+            "  00E0  CLS   CHIP-8",
+            "  00FF  HIGH  SUPER-CHIP",
+            "  5XY2  LD    XO-CHIP",
+            "  DXYN  DRW   CHIP-8",
+            "  FX65  LD    CHIP-8",
+        ] {
+            assert!(report.lines().any(|l| l == line), "{line:?} in\n{report}");
+        }
+        assert_eq!(report, super::report());
+
+        // The quirk lines under a variant's name.
+        let quirks = |name: &str| -> Vec<&str> {
+            let mut lines = report.lines().skip_while(|&l| l != format!("  {name}"));
+            lines.next();
+            lines
+                .map_while(|l| l.strip_prefix("    "))
+                .collect::<Vec<_>>()
+        };
+        let chip8 = quirks("chip8");
+        assert_eq!(chip8.len(), 6);
+        assert!(chip8.contains(&"shift_uses_vy=true"));
+        assert!(chip8.contains(&"wrap_memory=false"));
+        let schip = quirks("schip");
+        assert!(schip.contains(&"shift_uses_vy=false"));
+        assert!(schip.contains(&"load_store_increments_i=false"));
+        assert!(schip.contains(&"half_pixel_scroll=true"));
+    }
+
+    #[test]
+    fn test_syntax() {
+        let syntax = |word| syntax(decode(word));
+        assert_eq!(syntax(0xD123), "DRW VX, VY, N");
+        assert_eq!(syntax(0x6123), "LD VX, NN");
+        assert_eq!(syntax(0xB123), "JP V0, NNN");
+        assert_eq!(syntax(0x00C1), "SCD N");
+        assert_eq!(syntax(0x5122), "LD [I],VX-VY");
+        assert_eq!(syntax(0x00E0), "CLS");
+        assert_eq!(syntax(0xF133), "LD B, VX");
+    }
+
+    #[test]
+    fn test_instructions_json() {
+        let json = instructions_json(None);
+        let drw = json.lines().find(|l| l.contains("\"DXYN\"")).unwrap();
+        assert_eq!(
+            drw,
+            concat!(
+                "  {\"pattern\": \"DXYN\", \"mnemonic\": \"DRW\", \"syntax\": \"DRW VX, VY, N\", ",
+                "\"instruction_set\": \"CHIP-8\", \"operands\": [",
+                "{\"name\": \"X\", \"description\": \"register VX\"}, ",
+                "{\"name\": \"Y\", \"description\": \"register VY\"}, ",
+                "{\"name\": \"N\", \"description\": \"4-bit value\"}]},"
+            )
+        );
+        assert!(json.starts_with("[\n") && json.ends_with("}]}\n]\n"));
+        assert_eq!(json.lines().count(), instructions().len() + 2);
+
+        let chip8 = instructions_json(Some(Variant::Chip8));
+        assert!(chip8.contains("\"DXYN\"") && !chip8.contains("\"00CN\""));
+    }
+}
@@ -23,4 +23,26 @@ pub const WINDOW: isize = 8;
 pub const W: usize = 16;
 pub const H: usize = 64;
 
+/// Display planes, each a full `H` x `W` buffer. XO-CHIP uses two; every
+/// other ROM needs one, so the second is only built with `xo-chip`.
+pub const PLANES: usize = if cfg!(feature = "xo-chip") { 2 } else { 1 };
+
+/// CALL nesting levels on the original interpreter.
+pub const STACK_DEPTH: usize = 16;
+/// Deepest configurable stack, so SP still fits in a save state's byte.
+pub const MAX_STACK_DEPTH: usize = 255;
+
+/// Tallest sprite `DXYN` draws, in one-byte rows. N is the opcode's low
+/// nibble, and N = 0 draws nothing.
+pub const MAX_SPRITE_HEIGHT: usize = 15;
+
+/// Machine cycles the COSMAC VIP's 1802 runs in one 60 Hz frame.
+pub const COSMAC_FRAME_CYCLES: u32 = 3668;
+
 pub const RAM_SIZE: usize = 4096;
+/// RAM on XO-CHIP machines, addressable in full with `LD I, long`.
+pub const XO_RAM_SIZE: usize = 0x10000;
+
+/// Largest ROM that fits between the program start and the end of the
+/// standard 4KB of RAM.
+pub const MAX_ROM_SIZE: usize = RAM_SIZE - PROGRAM_START;
@@ -1,17 +1,24 @@
-use ndarray::Array2;
-use random_number::random;
+use std::fmt;
+use std::str::FromStr;
 
 use crate::chip8::mem::Memory;
 use crate::chip8::op::ChipOp;
+use crate::chip8::quirks::Quirks;
+use crate::chip8::rng::Rng;
+use crate::chip8::timers;
 use crate::chip8::{consts::PROGRAM_START, decode::decode};
 use std::sync::{
     atomic::{AtomicU8, Ordering},
     Arc,
 };
 
-use crate::chip8::consts::{CHIP8_FONTSET, H, W};
+use crate::chip8::consts::{
+    CHIP8_FONTSET, MAX_SPRITE_HEIGHT, MAX_STACK_DEPTH, PLANES, STACK_DEPTH,
+};
+use crate::chip8::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+use crate::chip8::key_log::KeyLog;
 
-#[derive(Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub enum Resolution {
     #[default]
     Low,
@@ -25,18 +32,194 @@ impl Resolution {
             Resolution::Low => 1,
         }
     }
+
+    /// The display's width and height in pixels at this resolution.
+    pub fn dims(&self) -> (usize, usize) {
+        (WIDTH / 2 * self.factor(), HEIGHT / 2 * self.factor())
+    }
 }
 
-#[derive(Default, Clone)]
-pub enum KeyState {
+pub type Screen = Framebuffer;
+
+/// Why an instruction couldn't run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecError {
+    UnknownOpcode {
+        word: u16,
+        family: u8,
+    },
+    /// CALL with every stack level in use.
+    StackOverflow,
+    /// RET with an empty stack.
+    StackUnderflow,
+    /// A load, store or sprite read reaching past the end of RAM.
+    MemoryOutOfBounds {
+        addr: usize,
+        len: usize,
+    },
+    /// PC left RAM, so the next instruction can't be fetched.
+    PcOutOfBounds {
+        pc: usize,
+    },
+    /// `5XY2`/`5XY3` with VY before VX.
+    ReversedRange {
+        x: usize,
+        y: usize,
+    },
+    /// `EX9E`/`EXA1` with VX above 0xF, when `mask_skip_key` is off.
+    KeyOutOfRange {
+        key: u8,
+    },
+    /// `0NNN` with `strict_sys` set.
+    SysCall {
+        nnn: usize,
+    },
+    /// A store below the program start with `protect_low_mem` set.
+    ProtectedWrite {
+        addr: usize,
+    },
+    /// PC reached the word 0x0000, which padding and unwritten RAM hold,
+    /// rather than an instruction. The program has ended rather than
+    /// faulted; `Emulator` reports it as a halt.
+    Unprogrammed {
+        pc: usize,
+    },
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ExecError::UnknownOpcode { word, family } => {
+                write!(f, "Unkown opcode: {word:#05X} (family {family:#X})")
+            }
+            ExecError::StackOverflow => write!(f, "Stack overflow: CALL with a full stack"),
+            ExecError::StackUnderflow => write!(f, "Stack underflow: RET with an empty stack"),
+            ExecError::MemoryOutOfBounds { addr, len } => {
+                write!(f, "Memory access out of bounds: {len} bytes at {addr:#05X}")
+            }
+            ExecError::PcOutOfBounds { pc } => write!(f, "PC out of bounds: {pc:#05X}"),
+            ExecError::ReversedRange { x, y } => {
+                write!(
+                    f,
+                    "Register range V{x:X}-V{y:X}: VY must be a higher register than VX"
+                )
+            }
+            ExecError::KeyOutOfRange { key } => {
+                write!(f, "Key out of range: {key:#04X} is not a key from 0 to F")
+            }
+            ExecError::SysCall { nnn } => {
+                write!(f, "Machine code call: SYS {nnn:#05X} can't run here")
+            }
+            ExecError::ProtectedWrite { addr } => {
+                write!(f, "Protected write: {addr:#05X} is below the program start")
+            }
+            ExecError::Unprogrammed { pc } => {
+                write!(f, "Unprogrammed memory: reached 0x0000 at {pc:#05X}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+/// What a store below the program start does under
+/// [`Chip8::protect_low_mem`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProtectPolicy {
+    /// Fault with [`ExecError::ProtectedWrite`], storing nothing.
     #[default]
-    AwaitingPress,
-    AwaitingRelease,
+    Fault,
+    /// Drop the protected bytes with a warning and store the rest.
+    Ignore,
 }
 
-pub type Screen = Array2<u8>;
+impl FromStr for ProtectPolicy {
+    type Err = String;
 
-#[derive(Default, Clone)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fault" => Ok(ProtectPolicy::Fault),
+            "ignore" => Ok(ProtectPolicy::Ignore),
+            _ => Err(format!(
+                "invalid protection policy {s:?}: expected fault or ignore"
+            )),
+        }
+    }
+}
+
+/// CALL's return addresses, one slot per nesting level, so its length is
+/// the deepest nesting allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallStack(pub Vec<usize>);
+impl Default for CallStack {
+    fn default() -> Self {
+        Self::new(STACK_DEPTH)
+    }
+}
+impl std::ops::Deref for CallStack {
+    type Target = [usize];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl std::ops::DerefMut for CallStack {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+impl CallStack {
+    /// Room for `depth` nested CALLs.
+    pub fn new(depth: usize) -> Self {
+        Self(vec![0; depth])
+    }
+}
+
+/// Parse a stack depth from 1 to [`MAX_STACK_DEPTH`].
+pub fn parse_stack_depth(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(depth) if (1..=MAX_STACK_DEPTH).contains(&depth) => Ok(depth),
+        _ => Err(format!(
+            "invalid stack depth {s:?}: expected 1 to {MAX_STACK_DEPTH}"
+        )),
+    }
+}
+
+/// A sprite row with every pixel doubled, for drawing low-res sprites at
+/// physical resolution.
+fn double_bits(byte: u8) -> u16 {
+    (0..8).fold(0, |bits, i| {
+        bits | (u16::from(byte >> i & 1) * 0b11) << (2 * i)
+    })
+}
+
+/// An instruction [`Chip8::try_step`] ran, with the registers around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastStep {
+    /// Where the instruction was fetched from.
+    pub pc: usize,
+    pub op: ChipOp,
+    /// V0 to VF before and after.
+    pub v: ([u8; 16], [u8; 16]),
+    /// I before and after.
+    pub i: (usize, usize),
+}
+
+impl LastStep {
+    /// The V registers the instruction changed, as (index, before, after).
+    pub fn changed_v(&self) -> impl Iterator<Item = (usize, u8, u8)> + '_ {
+        let (before, after) = &self.v;
+        (0..16)
+            .filter(move |&x| before[x] != after[x])
+            .map(move |x| (x, before[x], after[x]))
+    }
+}
+
+/// A CHIP-8 machine.
+///
+/// DT and ST are shared with whatever ticks them, so `clone()` shares them
+/// too; say which copy is meant with [`Chip8::snapshot`] or
+/// [`Chip8::deep_clone`].
+#[derive(Debug, Default, Clone)]
 pub struct Chip8 {
     pub pc: usize,         // Program counter
     pub v: [u8; 16],       // General purpose registers
@@ -45,107 +228,230 @@ pub struct Chip8 {
     pub dt: Arc<AtomicU8>, // Delay timer
     pub st: Arc<AtomicU8>, // Sound timer
     pub keys: [bool; 16],
-    pub stack: [usize; 16],
+    pub stack: CallStack,
+    /// The first display plane, and the only one drawn to.
     pub screen: Screen,
+    /// Planes after the first; empty unless built with `xo-chip`.
+    pub extra_planes: [Screen; PLANES - 1],
     pub memory: Memory,
     pub resolution: Resolution,
-    pub key_state: KeyState,
-    pub last_key: u8,
+    /// Key events for `FX0A`.
+    pub key_log: KeyLog,
     pub exit: bool,
+    pub sound_loads: u32, // Times ST was loaded with a nonzero value
+    pub quirks: Quirks,
+    pub rng: Rng, // RND source, seeded from the clock unless seed_rng is called
+    /// Instructions run by `step_with_timers` since the timers last ticked.
+    pub timer_steps: u64,
+    /// Address of the hex font, for `load_font` and `LD F, Vx`.
+    pub font_base: usize,
+    /// DT as `LD Vx, DT` reads it, when set: the value at the start of the
+    /// frame, plus any `LD DT, Vx` since. `None` reads the shared timer
+    /// live, as threaded loops that tick it between instructions want.
+    pub latched_dt: Option<u8>,
+    /// Run unknown opcodes as two-byte no-ops, with a warning, instead of
+    /// faulting. For exploring ROMs that use instructions we don't have.
+    pub skip_unknown: bool,
+    /// Keep stores off the interpreter and font area below the program
+    /// start, handling such a store as the policy says. Reads are
+    /// unaffected.
+    pub protect_low_mem: Option<ProtectPolicy>,
+    /// Fault on `0NNN` machine code calls instead of stepping over them
+    /// as modern interpreters do.
+    pub strict_sys: bool,
+    last_step: Option<LastStep>,
 }
 
 impl Chip8 {
     pub fn new() -> Self {
         Chip8 {
             pc: PROGRAM_START,
-            screen: Array2::<u8>::zeros((H, W)),
             ..Chip8::default()
         }
     }
+    /// A copy sharing this machine's DT and ST, which keep ticking in both:
+    /// what a renderer showing the live machine wants.
+    pub fn snapshot(&self) -> Chip8 {
+        self.clone()
+    }
+    /// A fully independent copy whose DT and ST start at this machine's
+    /// current values, for save states, rewind and comparing runs.
+    pub fn deep_clone(&self) -> Chip8 {
+        let timer = |t: &AtomicU8| Arc::new(AtomicU8::new(t.load(Ordering::Acquire)));
+        Chip8 {
+            dt: timer(&self.dt),
+            st: timer(&self.st),
+            ..self.clone()
+        }
+    }
+    /// Make RND reproducible by drawing from a generator seeded with `seed`.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+    /// The visible display's width and height in pixels: 64x32 in
+    /// low-res and 128x64 in high-res. With [`Quirks::half_pixel_scroll`]
+    /// the screen holds physical pixels, so it's always 128x64.
+    pub fn display_dimensions(&self) -> (usize, usize) {
+        if self.quirks.half_pixel_scroll {
+            Resolution::High.dims()
+        } else {
+            self.resolution.dims()
+        }
+    }
+    /// Every display plane, first to last.
+    pub fn planes_mut(&mut self) -> impl Iterator<Item = &mut Screen> {
+        std::iter::once(&mut self.screen).chain(self.extra_planes.iter_mut())
+    }
+    /// Byte `x` of row `y` with every plane combined, for display.
+    pub fn screen_byte(&self, y: usize, x: usize) -> u8 {
+        self.extra_planes
+            .iter()
+            .fold(self.screen.byte(y, x), |byte, plane| {
+                byte | plane.byte(y, x)
+            })
+    }
+    /// Whether pixel `(x, y)` of the framebuffer is lit in any plane.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        std::iter::once(&self.screen)
+            .chain(&self.extra_planes)
+            .any(|plane| plane.pixel(x, y))
+    }
     pub fn load_font(&mut self) {
-        let base = 0x0;
+        let base = self.font_base;
         self.memory[base..base + CHIP8_FONTSET.len()].copy_from_slice(&CHIP8_FONTSET);
     }
+    /// Press `key`. Only the low nibble is used, as SKP and SKNP do by
+    /// default, so an out-of-range key presses its alias rather than
+    /// panicking.
     pub fn press_key(&mut self, key: u8) {
-        self.keys[key as usize] = true;
+        self.set_key(key & 0xF, true);
     }
+    /// Release `key`, masked like [`press_key`](Self::press_key).
     pub fn release_key(&mut self, key: u8) {
-        self.keys[key as usize] = false;
+        self.set_key(key & 0xF, false);
+    }
+    /// Set every key at once: bit `k` of `mask` is key `k`.
+    pub fn set_keys(&mut self, mask: u16) {
+        for key in 0..16 {
+            self.set_key(key, mask & (1 << key) != 0);
+        }
     }
+    /// Press or release `key`, logging the change for `FX0A`.
+    fn set_key(&mut self, key: u8, pressed: bool) {
+        let slot = &mut self.keys[usize::from(key)];
+        if *slot != pressed {
+            *slot = pressed;
+            self.key_log.record(key, pressed);
+        }
+    }
+    /// The pressed keys as a word, bit `k` for key `k`.
+    pub fn keys_bitmask(&self) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (key, &pressed)| {
+                mask | (u16::from(pressed) << key)
+            })
+    }
+    /// Fetch, decode and execute `cycles` instructions. Each fetch decodes
+    /// from memory afresh, so self-modifying code needs no invalidation.
+    ///
+    /// # Panics
+    ///
+    /// On any [`ExecError`]; see [`Chip8::try_step`].
     pub fn run_step(&mut self, cycles: u64) {
         for _ in 0..cycles {
-            let b = self.memory[self.pc];
-            let s = self.memory[self.pc + 1];
-            let op = decode(u16::from_be_bytes([b, s]));
-            self.exec(op);
+            if let Err(err) = self.try_step() {
+                panic!("{err}");
+            }
+        }
+    }
+    /// Fetch, decode and execute one instruction, returning an error instead
+    /// of panicking when the program faults or reaches unprogrammed memory.
+    /// PC is in RAM after every successful step.
+    pub fn try_step(&mut self) -> Result<(), ExecError> {
+        let word = match self.memory.slice(self.pc, 2) {
+            Ok(&[b, s]) => u16::from_be_bytes([b, s]),
+            _ => return Err(ExecError::PcOutOfBounds { pc: self.pc }),
+        };
+        if word == 0 {
+            return Err(ExecError::Unprogrammed { pc: self.pc });
+        }
+        let (pc, op, v, i) = (self.pc, decode(word), self.v, self.i);
+        self.try_exec(op)?;
+        self.pc = self.addr(self.pc);
+        if self.pc >= self.memory.len() {
+            return Err(ExecError::PcOutOfBounds { pc: self.pc });
+        }
+        self.last_step = Some(LastStep {
+            pc,
+            op,
+            v: (v, self.v),
+            i: (i, self.i),
+        });
+        Ok(())
+    }
+    /// The instruction the last successful step ran, for visualizers.
+    pub fn last_op(&self) -> Option<ChipOp> {
+        self.last_step.map(|step| step.op)
+    }
+    /// The last successful step in full: its address and the registers
+    /// before and after it.
+    pub fn last_step(&self) -> Option<&LastStep> {
+        self.last_step.as_ref()
+    }
+    /// Run one instruction and tick DT and ST once every `ipf` of them, for
+    /// embedders stepping the machine without a timer thread.
+    pub fn step_with_timers(&mut self, ipf: u64) {
+        self.run_step(1);
+        self.timer_steps += 1;
+        if self.timer_steps >= ipf.max(1) {
+            self.timer_steps = 0;
+            timers::tick(&self.dt);
+            timers::tick(&self.st);
         }
     }
+    /// Execute `op`.
+    ///
+    /// # Panics
+    ///
+    /// On any [`ExecError`]; [`Chip8::try_exec`] returns it instead.
     pub fn exec(&mut self, op: ChipOp) {
+        if let Err(err) = self.try_exec(op) {
+            panic!("{err}");
+        }
+    }
+    pub fn try_exec(&mut self, op: ChipOp) -> Result<(), ExecError> {
         use ChipOp::*;
         match op {
             ScdN { n } => {
-                let screen = self.screen.clone();
-                for (y, mut row) in self.screen.outer_iter_mut().enumerate() {
-                    for (x, elem) in row.iter_mut().enumerate() {
-                        let y_shifted: i16 = (y as i16) - (n as i16);
-                        if y_shifted >= 0 {
-                            *elem = screen[(y_shifted as usize, x)]
-                        } else {
-                            *elem = 0;
-                        }
-                    }
-                }
+                self.planes_mut()
+                    .for_each(|plane| plane.scroll_down(n as usize));
                 self.pc += 2;
             }
             ScuN { n } => {
-                let screen = self.screen.clone();
-                let (nrows, _) = self.screen.dim();
-                for (y, mut row) in self.screen.outer_iter_mut().enumerate() {
-                    for (x, elem) in row.iter_mut().enumerate() {
-                        let y_shifted: usize = y + (n as usize);
-                        if y_shifted < nrows {
-                            *elem = screen[(y_shifted, x)]
-                        } else {
-                            *elem = 0;
-                        }
-                    }
-                }
+                self.planes_mut()
+                    .for_each(|plane| plane.scroll_up(n as usize));
                 self.pc += 2;
             }
             Cls => {
-                self.screen.fill(0);
+                // Clear the whole buffer in either resolution, as SUPER-CHIP
+                // and Octo do, so pixels drawn in high-res can't reappear
+                // after switching back from low-res.
+                self.planes_mut().for_each(Framebuffer::clear);
                 self.pc += 2;
             }
             Ret => {
-                self.pc = self.stack[self.sp - 1];
-                self.sp -= 1;
+                self.sp = self.sp.checked_sub(1).ok_or(ExecError::StackUnderflow)?;
+                self.pc = self.stack[self.sp];
             }
 
             Scr => {
-                let screen = self.screen.clone();
-                for (y, mut row) in self.screen.outer_iter_mut().enumerate() {
-                    for (x, elem) in row.iter_mut().enumerate() {
-                        let mut tmp = screen[(y, x)] >> 4;
-                        if x > 0 {
-                            tmp |= screen[(y, x - 1)] << 4;
-                        }
-                        *elem = tmp
-                    }
-                }
+                self.planes_mut().for_each(|plane| plane.scroll_right(4));
                 self.pc += 2;
             }
             Scl => {
-                let screen = self.screen.clone();
-                let (_, ncols) = self.screen.dim();
-                for (y, mut row) in self.screen.outer_iter_mut().enumerate() {
-                    for (x, elem) in row.iter_mut().enumerate() {
-                        let mut tmp = screen[(y, x)] << 4;
-                        if x < ncols - 1 {
-                            tmp |= screen[(y, x + 1)] >> 4;
-                        }
-                        *elem = tmp
-                    }
-                }
+                self.planes_mut().for_each(|plane| plane.scroll_left(4));
                 self.pc += 2;
             }
             Exit => {
@@ -163,8 +469,11 @@ impl Chip8 {
                 self.pc = nnn;
             }
             CallNnn { nnn } => {
+                if self.sp >= self.stack.len() {
+                    return Err(ExecError::StackOverflow);
+                }
+                self.stack[self.sp] = self.pc + 2;
                 self.sp += 1;
-                self.stack[self.sp - 1] = self.pc + 2;
                 self.pc = nnn;
             }
             SeVxNn { x, nn } => {
@@ -190,19 +499,18 @@ impl Chip8 {
             }
             LdVxVyI { x, y } => {
                 if y < x {
-                    panic!("LdVxVyI: VY must be a higher register than VX");
+                    return Err(ExecError::ReversedRange { x, y });
                 }
-                let range_end = self.i + y - x;
-                let mem_range = self.i..=range_end;
-                self.memory[mem_range].copy_from_slice(&self.v[x..=y]);
+                let regs = self.v;
+                self.write_at_i(&regs[x..=y])?;
             }
             LdIVxVy { x, y } => {
                 if y < x {
-                    panic!("LdVxVyI: VY must be a higher register than VX");
+                    return Err(ExecError::ReversedRange { x, y });
                 }
-                let range_end = self.i + y - x;
-                let mem_range = self.i..=range_end;
-                self.v[x..=y].copy_from_slice(&self.memory[mem_range]);
+                let mut regs = [0; 16];
+                self.read_at_i(&mut regs[x..=y])?;
+                self.v[x..=y].copy_from_slice(&regs[x..=y]);
             }
             LdVxNn { x, nn } => {
                 *self.vx(x) = nn;
@@ -254,7 +562,7 @@ impl Chip8 {
                 self.pc += 2;
             }
             ShrVxVy { x, y } => {
-                let vy = *self.vx(y);
+                let vy = *self.vx(self.shift_src(x, y));
                 *self.vx(x) = vy >> 1;
                 self.v[0xF] = vy & 0x1;
                 self.pc += 2;
@@ -268,7 +576,7 @@ impl Chip8 {
                 self.pc += 2;
             }
             ShlVxVy { x, y } => {
-                let vy = *self.vx(y);
+                let vy = *self.vx(self.shift_src(x, y));
                 *self.vx(x) = vy << 1;
                 self.v[0xF] = vy >> 7;
                 self.pc += 2;
@@ -285,59 +593,66 @@ impl Chip8 {
                 self.pc += 2;
             }
             JpV0Nnn { nnn } => {
-                self.pc = (nnn + (*self.vx(0) as u16)) as usize;
+                // NNN + V0 can reach 0x10FE, past the end of 4K of RAM.
+                let target = usize::from(nnn) + usize::from(*self.vx(0));
+                let target = self.addr(target);
+                self.pc = if self.quirks.wrap_memory {
+                    target % self.memory.len()
+                } else if target < self.memory.len() {
+                    target
+                } else {
+                    return Err(ExecError::PcOutOfBounds { pc: target });
+                };
             }
             RndVxNn { x, nn } => {
-                let n: u8 = random!();
-                *self.vx(x) = n & nn;
+                *self.vx(x) = self.rng.next_u8() & nn;
                 self.pc += 2;
             }
             DrwVxVyN { x, y, n } => {
-                let vx = *self.vx(x) as usize;
-                let vy = *self.vx(y) as usize;
-                let bit_off = vx & 7; // vx % 8
-                let col_byte = vx >> 3; // vx / 8
-                let height = n as usize;
-
-                let (rows, bytes_per_row) = self.screen.dim();
-
-                // collision flag (VF)
-                self.v[0xF] = 0;
-
-                for (row, &byte) in self.memory[self.i..self.i + height].iter().enumerate() {
-                    let y_idx = (vy + row) % rows;
-                    let x0 = col_byte % bytes_per_row;
-                    let x1 = (col_byte + 1) % bytes_per_row; // next byte (wrap horizontally)
-
-                    // Shift the 8-bit sprite line by bit_off across two bytes.
-                    let shifted = (u16::from(byte) << 8) >> bit_off;
-                    let [hi, lo] = shifted.to_be_bytes();
-
-                    // Cache low and hi bytes to check collision flag
-                    let before0 = self.screen[(y_idx, x0)];
-                    let before1 = self.screen[(y_idx, x1)];
-
-                    self.screen[(y_idx, x0)] ^= hi;
-                    self.screen[(y_idx, x1)] ^= lo;
-
-                    // Check and set collision flag (VF)
-                    if (before0 & hi != 0) || (before1 & lo != 0) {
-                        self.v[0xF] = 1;
+                // The origin wraps to the display; the overhang wraps at
+                // the framebuffer's edges.
+                let (width, height) = self.resolution.dims();
+                let vx = *self.vx(x) as usize % width;
+                let vy = *self.vx(y) as usize % height;
+                // N is a nibble; a hand-built op can't ask for more. The
+                // rows are read with the same bounds check as LD Vx, [I],
+                // so a sprite never reads past the end of RAM.
+                let mut rows = [0; MAX_SPRITE_HEIGHT];
+                let sprite = &mut rows[..usize::from(n & 0xF)];
+                self.read_at_i(sprite)?;
+                let mut collided = false;
+                // On a physical screen a low-res pixel is a 2x2 block.
+                let physical = self.quirks.half_pixel_scroll && self.resolution == Resolution::Low;
+                let scale = if physical { 2 } else { 1 };
+                let bits = |byte: u8| {
+                    if physical {
+                        double_bits(byte)
+                    } else {
+                        byte.into()
+                    }
+                };
+                for (row, &byte) in sprite.iter().enumerate() {
+                    for copy in 0..scale {
+                        let y = ((vy + row) * scale + copy) % HEIGHT;
+                        collided |=
+                            self.screen
+                                .draw_sprite_row(vx * scale, y, bits(byte), 8 * scale);
                     }
                 }
+                self.v[0xF] = collided as u8;
                 self.pc += 2;
             }
             SkpVx { x } => {
-                let vx = *self.vx(x);
-                if self.keys[(vx & 0xF) as usize] {
+                let vx = self.skip_key(x)?;
+                if self.keys[vx] {
                     self.pc += 4
                 } else {
                     self.pc += 2
                 }
             }
             SknpVx { x } => {
-                let vx = *self.vx(x);
-                if !self.keys[(vx & 0xF) as usize] {
+                let vx = self.skip_key(x)?;
+                if !self.keys[vx] {
                     self.pc += 4
                 } else {
                     self.pc += 2
@@ -346,83 +661,351 @@ impl Chip8 {
             LdDtVx { x } => {
                 let val = *self.vx(x);
                 self.dt.store(val, Ordering::Release);
+                if let Some(latched) = &mut self.latched_dt {
+                    *latched = val;
+                }
                 self.pc += 2;
             }
             LdVxDt { x } => {
-                *self.vx(x) = self.dt.load(Ordering::Acquire);
+                *self.vx(x) = self
+                    .latched_dt
+                    .unwrap_or_else(|| self.dt.load(Ordering::Acquire));
                 self.pc += 2;
             }
-            LdVxK { x } => match self.key_state {
-                KeyState::AwaitingPress => {
-                    for (key, pressed) in self.keys.into_iter().enumerate() {
-                        if pressed {
-                            self.key_state = KeyState::AwaitingRelease;
-                            self.last_key = key as u8;
-                            break;
-                        }
-                    }
-                }
-                KeyState::AwaitingRelease => {
-                    let all_clear = self.keys.iter().all(|&k| !k);
-                    if all_clear {
-                        self.key_state = KeyState::AwaitingPress;
-                        *self.vx(x) = self.last_key;
-                        self.pc += 2;
-                    }
+            LdVxK { x } => {
+                self.key_log.begin(self.keys_bitmask());
+                if let Some(key) = self.key_log.advance() {
+                    *self.vx(x) = key;
+                    self.pc += 2;
                 }
-            },
+            }
             LdStVx { x } => {
                 let val = *self.vx(x);
                 self.st.store(val, Ordering::Release);
+                self.sound_loads += u32::from(val > 0);
                 self.pc += 2;
             }
             AddIVx { x } => {
                 let vx = *self.vx(x);
-                self.i += vx as usize;
+                self.advance_i(vx as usize);
                 self.pc += 2;
             }
             LdFVx { x } => {
                 // set I to the 5 line high hex sprite for the lowest nibble in vX
                 let vx = *self.vx(x) & 0x0F;
-                self.i = (vx * 5) as usize;
+                self.i = self.font_base + (vx * 5) as usize;
                 self.pc += 2;
             }
             LdBVx { x } => {
                 let vx = *self.vx(x);
-                self.memory[self.i] = (vx % 255) / 100;
-                self.memory[self.i + 1] = (vx % 100) / 10;
-                self.memory[self.i + 2] = vx % 10;
+                let digits = [vx / 100, (vx % 100) / 10, vx % 10];
+                self.write_at_i(&digits)?;
                 self.pc += 2;
             }
             LdIVx { x } => {
-                for vx in &mut self.v[0..=x] {
-                    self.memory[self.i] = *vx;
-                    self.i += 1;
+                let regs = self.v;
+                self.write_at_i(&regs[0..=x])?;
+                if self.quirks.load_store_increments_i {
+                    self.advance_i(x + 1);
                 }
                 self.pc += 2;
             }
             LdVxI { x } => {
-                for vx in &mut self.v[0..=x] {
-                    *vx = self.memory[self.i];
-                    self.i += 1;
+                let mut regs = [0; 16];
+                self.read_at_i(&mut regs[0..=x])?;
+                self.v[0..=x].copy_from_slice(&regs[0..=x]);
+                if self.quirks.load_store_increments_i {
+                    self.advance_i(x + 1);
                 }
                 self.pc += 2;
             }
-            Unknown(x) => {
-                panic!("Unkown opcode: {x:#05X}");
+            Sys { nnn } if self.strict_sys => {
+                log_event!(
+                    error,
+                    pc = %format_args!("{:#05x}", self.pc),
+                    nnn = %format_args!("{nnn:#05x}"),
+                    "machine code call"
+                );
+                return Err(ExecError::SysCall { nnn });
+            }
+            // As below, `nnn` is only read by the log line.
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            Sys { nnn } => {
+                log_event!(
+                    debug,
+                    pc = %format_args!("{:#05x}", self.pc),
+                    nnn = %format_args!("{nnn:#05x}"),
+                    "skipped machine code call"
+                );
+                self.pc += 2;
+            }
+            // The bindings are only read by the log line.
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            Unknown { word, family } if self.skip_unknown => {
+                log_event!(
+                    warn,
+                    pc = %format_args!("{:#05x}", self.pc),
+                    opcode = %format_args!("{word:#06x}"),
+                    family = %format_args!("{family:#x}"),
+                    "skipped unknown opcode"
+                );
+                self.pc += 2;
+            }
+            Unknown { word, family } => {
+                log_event!(
+                    error,
+                    pc = %format_args!("{:#05x}", self.pc),
+                    opcode = %format_args!("{word:#06x}"),
+                    family = %format_args!("{family:#x}"),
+                    "unknown opcode"
+                );
+                return Err(ExecError::UnknownOpcode { word, family });
             }
         }
+        Ok(())
     }
 
     #[inline]
     fn vx(&mut self, x: usize) -> &mut u8 {
         &mut self.v[x]
     }
+
+    /// `addr` masked to the machine's address width when the quirks say.
+    #[inline]
+    fn addr(&self, addr: usize) -> usize {
+        if self.quirks.mask_addr {
+            self.memory.mask(addr)
+        } else {
+            addr
+        }
+    }
+
+    /// Fill `buf` from memory at I, wrapping or faulting past the end of
+    /// RAM as the quirks say.
+    fn read_at_i(&self, buf: &mut [u8]) -> Result<(), ExecError> {
+        let i = self.addr(self.i);
+        if self.quirks.wrap_memory {
+            self.memory.read_wrapping(i, buf);
+        } else {
+            buf.copy_from_slice(self.memory.slice(i, buf.len())?);
+        }
+        Ok(())
+    }
+
+    /// Write `bytes` to memory at I, like [`Chip8::read_at_i`].
+    fn write_at_i(&mut self, bytes: &[u8]) -> Result<(), ExecError> {
+        let i = self.addr(self.i);
+        if let Some(policy) = self.protect_low_mem {
+            return self.write_protected(i, bytes, policy);
+        }
+        if self.quirks.wrap_memory {
+            self.memory.write_wrapping(i, bytes);
+        } else {
+            self.memory
+                .slice_mut(i, bytes.len())?
+                .copy_from_slice(bytes);
+        }
+        Ok(())
+    }
+
+    /// [`Chip8::write_at_i`] from `i`, keeping below the program start
+    /// as `policy` says.
+    fn write_protected(
+        &mut self,
+        i: usize,
+        bytes: &[u8],
+        policy: ProtectPolicy,
+    ) -> Result<(), ExecError> {
+        let (wrap, len) = (self.quirks.wrap_memory, self.memory.len());
+        if !wrap {
+            self.memory.slice(i, bytes.len())?;
+        }
+        let target = |offset: usize| if wrap { (i + offset) % len } else { i + offset };
+        let protected = (0..bytes.len())
+            .map(target)
+            .find(|&addr| addr < PROGRAM_START);
+        if let Some(addr) = protected {
+            if policy == ProtectPolicy::Fault {
+                log_event!(
+                    error,
+                    pc = %format_args!("{:#05x}", self.pc),
+                    addr = %format_args!("{addr:#05x}"),
+                    "protected write"
+                );
+                return Err(ExecError::ProtectedWrite { addr });
+            }
+            log_event!(
+                warn,
+                pc = %format_args!("{:#05x}", self.pc),
+                addr = %format_args!("{addr:#05x}"),
+                "ignored protected write"
+            );
+        }
+        for (offset, &byte) in bytes.iter().enumerate() {
+            let addr = target(offset);
+            if addr >= PROGRAM_START {
+                self.memory[addr] = byte;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move I on by `n`, wrapping at the end of RAM when memory wraps.
+    fn advance_i(&mut self, n: usize) {
+        self.i += n;
+        if self.quirks.wrap_memory {
+            self.i %= self.memory.len();
+        }
+    }
+
+    /// The key VX names for `EX9E`/`EXA1`, masked to a nibble or checked
+    /// as the quirks say.
+    #[inline]
+    fn skip_key(&self, x: usize) -> Result<usize, ExecError> {
+        let key = self.v[x];
+        match key {
+            0..=0xF => Ok(key as usize),
+            _ if self.quirks.mask_skip_key => Ok((key & 0xF) as usize),
+            _ => Err(ExecError::KeyOutOfRange { key }),
+        }
+    }
+
+    #[inline]
+    fn shift_src(&self, x: usize, y: usize) -> usize {
+        if self.quirks.shift_uses_vy {
+            y
+        } else {
+            x
+        }
+    }
+}
+
+impl fmt::Display for Chip8 {
+    /// Registers and timers in three lines:
+    ///
+    /// ```text
+    /// PC=0x200 I=0x000 SP=0 DT=0 ST=0 RES=Low
+    /// V0=00 V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00
+    /// V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=00
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "PC={:#05x} I={:#05x} SP={} DT={} ST={} RES={:?}",
+            self.pc,
+            self.i,
+            self.sp,
+            self.dt.load(Ordering::Acquire),
+            self.st.load(Ordering::Acquire),
+            self.resolution,
+        )?;
+        for (row, regs) in self.v.chunks(8).enumerate() {
+            let line: Vec<String> = regs
+                .iter()
+                .enumerate()
+                .map(|(n, v)| format!("V{:X}={v:02X}", row * 8 + n))
+                .collect();
+            write!(f, "{}", line.join(" "))?;
+            if row == 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::chip8::key_log::KeyWait;
+    use crate::chip8::quirks::Variant;
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_unknown_opcode_logs_pc() {
+        let logs = crate::utils::logging::tests::capture(|| {
+            let result = std::panic::catch_unwind(|| {
+                let mut chip = Chip8::new();
+                chip.exec(decode(0x5AB1));
+            });
+            assert!(result.is_err());
+        });
+        assert!(logs.contains("ERROR"));
+        assert!(
+            logs.contains("unknown opcode pc=0x200 opcode=0x5ab1 family=0x5"),
+            "{logs}"
+        );
+    }
+
+    #[cfg(feature = "xo-chip")]
+    #[test]
+    fn test_pixel_combines_planes() {
+        let mut chip = Chip8::new();
+        chip.screen.set_pixel(1, 0, true);
+        chip.extra_planes[0].set_pixel(2, 0, true);
+        let lit: Vec<bool> = (0..4).map(|x| chip.pixel(x, 0)).collect();
+        assert_eq!(lit, [false, true, true, false]);
+    }
+
+    #[test]
+    fn test_display_dimensions() {
+        let mut chip = Chip8::new();
+        assert_eq!(chip.display_dimensions(), (64, 32));
+        chip.resolution = Resolution::High;
+        assert_eq!(chip.display_dimensions(), (128, 64));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut chip = Chip8::new();
+        chip.v[0x3] = 0x2A;
+        chip.v[0xF] = 0x01;
+        chip.i = 0x50;
+        chip.dt.store(7, Ordering::Release);
+
+        let text = chip.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "PC=0x200 I=0x050 SP=0 DT=7 ST=0 RES=Low");
+        assert_eq!(lines[1], "V0=00 V1=00 V2=00 V3=2A V4=00 V5=00 V6=00 V7=00");
+        assert_eq!(lines[2], "V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=01");
+    }
+
+    #[test]
+    fn test_run_self_modifying_code() {
+        let mut chip = Chip8::new();
+        #[rustfmt::skip]
+        let program = [
+            0x60, 0x6F, // LD V0, 0x6F
+            0x61, 0x2A, // LD V1, 0x2A
+            0xA2, 0x0C, // LD I, 0x20C
+            0xF1, 0x55, // LD [I], V1
+            0x12, 0x0C, // JP 0x20C
+            0x00, 0x00,
+            0x00, 0xE0, // CLS, overwritten with LD VF, 0x2A
+        ];
+        chip.memory[0x200..0x200 + program.len()].copy_from_slice(&program);
+        chip.run_step(6);
+        assert_eq!(chip.memory[0x20C..0x20E], [0x6F, 0x2A]);
+        assert_eq!(chip.v[0xF], 0x2A);
+        assert_eq!(chip.pc, 0x20E);
+    }
+
+    #[test]
+    fn test_step_with_timers() {
+        let ipf = 10;
+        let mut chip = Chip8::new();
+        chip.memory[0x200..0x202].copy_from_slice(&[0x12, 0x00]); // JP 0x200
+        chip.dt.store(3, Ordering::Release);
+        chip.st.store(1, Ordering::Release);
+        for step in 1..=3 * ipf {
+            chip.step_with_timers(ipf);
+            let dt = chip.dt.load(Ordering::Acquire);
+            assert_eq!(dt, 3 - (step / ipf) as u8, "after {step} steps");
+        }
+        assert_eq!(chip.dt.load(Ordering::Acquire), 0);
+        assert_eq!(chip.st.load(Ordering::Acquire), 0);
+        assert_eq!(chip.pc, 0x200);
+    }
 
     #[test]
     fn test_exec_ret() {
@@ -437,6 +1020,105 @@ mod tests {
         assert!(chip.pc == pc);
     }
 
+    #[test]
+    fn test_call_stack_depth() {
+        let call = ChipOp::CallNnn { nnn: 0x200 };
+        let mut classic = Chip8::new();
+        for _ in 0..STACK_DEPTH {
+            classic.try_exec(call).unwrap();
+        }
+        assert_eq!(classic.try_exec(call), Err(ExecError::StackOverflow));
+        assert_eq!(classic.sp, 16);
+
+        let mut extended = Chip8::new();
+        extended.stack = CallStack::new(24);
+        for _ in 0..24 {
+            extended.try_exec(call).unwrap();
+        }
+        assert_eq!(extended.try_exec(call), Err(ExecError::StackOverflow));
+        extended.exec(ChipOp::Ret);
+        assert_eq!((extended.sp, extended.pc), (23, 0x202));
+    }
+
+    #[test]
+    fn test_parse_stack_depth() {
+        assert_eq!(parse_stack_depth("16"), Ok(16));
+        assert_eq!(parse_stack_depth("255"), Ok(255));
+        assert!(parse_stack_depth("0").is_err());
+        assert!(parse_stack_depth("256").is_err());
+    }
+
+    #[test]
+    fn test_snapshot_shares_timers_deep_clone_does_not() {
+        let chip = Chip8::new();
+        chip.dt.store(30, Ordering::Release);
+        let snapshot = chip.snapshot();
+        let copy = chip.deep_clone();
+        assert_eq!(copy.dt.load(Ordering::Acquire), 30);
+
+        chip.dt.store(10, Ordering::Release);
+        chip.st.store(5, Ordering::Release);
+        assert_eq!(snapshot.dt.load(Ordering::Acquire), 10);
+        assert_eq!(snapshot.st.load(Ordering::Acquire), 5);
+        assert_eq!(copy.dt.load(Ordering::Acquire), 30);
+        assert_eq!(copy.st.load(Ordering::Acquire), 0);
+    }
+
+    #[test]
+    fn test_skip_unknown() {
+        let mut chip = Chip8::new();
+        chip.memory[0x200..0x202].copy_from_slice(&[0xE1, 0xFF]);
+        assert_eq!(
+            chip.try_step(),
+            Err(ExecError::UnknownOpcode {
+                word: 0xE1FF,
+                family: 0xE
+            })
+        );
+        assert_eq!(chip.pc, 0x200);
+
+        chip.skip_unknown = true;
+        chip.v[1] = 3;
+        assert_eq!(chip.try_step(), Ok(()));
+        assert_eq!(chip.pc, 0x202);
+        assert_eq!(chip.v[1], 3);
+    }
+
+    #[test]
+    fn test_exec_sys() {
+        let mut chip = Chip8::new();
+        chip.exec(decode(0x0123));
+        assert_eq!(chip.pc, 0x202);
+
+        chip.strict_sys = true;
+        let err = ExecError::SysCall { nnn: 0x123 };
+        assert_eq!(chip.try_exec(decode(0x0123)), Err(err));
+        assert_eq!(chip.pc, 0x202);
+        assert_eq!(
+            err.to_string(),
+            "Machine code call: SYS 0x123 can't run here"
+        );
+    }
+
+    #[test]
+    fn test_last_op() {
+        let mut chip = Chip8::new();
+        assert_eq!(chip.last_op(), None);
+        chip.memory[0x200..0x204].copy_from_slice(&[0x6A, 0x42, 0xA3, 0x00]);
+        chip.v[0xA] = 7;
+
+        chip.try_step().unwrap();
+        assert_eq!(chip.last_op(), Some(ChipOp::LdVxNn { x: 0xA, nn: 0x42 }));
+        let step = chip.last_step().unwrap();
+        assert_eq!(step.pc, 0x200);
+        assert_eq!(step.changed_v().collect::<Vec<_>>(), [(0xA, 7, 0x42)]);
+
+        chip.try_step().unwrap();
+        let step = chip.last_step().unwrap();
+        assert_eq!(step.op, ChipOp::LdINnn { nnn: 0x300 });
+        assert_eq!((step.i, step.changed_v().count()), ((0, 0x300), 0));
+    }
+
     #[test]
     fn test_exec_jp() {
         let pc = 0x400;
@@ -595,7 +1277,7 @@ mod tests {
         chip.memory[img_loc] = 0xAB;
         chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 1 });
         assert_eq!(chip.pc, 0x202);
-        assert!(chip.screen[(0, 0)] == 0xAB);
+        assert!(chip.screen.byte(0, 0) == 0xAB);
     }
 
     #[test]
@@ -608,7 +1290,7 @@ mod tests {
         chip.memory[img_loc] = 0b11110000;
         chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 1 });
         assert_eq!(chip.pc, 0x202);
-        assert!(chip.screen[(0, 0)] == 0b01111000);
+        assert!(chip.screen.byte(0, 0) == 0b01111000);
     }
 
     #[test]
@@ -621,8 +1303,8 @@ mod tests {
         chip.memory[img_loc] = 0b11110000;
         chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 1 });
         assert_eq!(chip.pc, 0x202);
-        assert!(chip.screen[(0, 0)] == 0b00000011);
-        assert!(chip.screen[(0, 1)] == 0b11000000);
+        assert!(chip.screen.byte(0, 0) == 0b00000011);
+        assert!(chip.screen.byte(0, 1) == 0b11000000);
     }
 
     #[test]
@@ -635,8 +1317,28 @@ mod tests {
         chip.memory[img_loc] = 0b11110000;
         chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 1 });
         assert_eq!(chip.pc, 0x202);
-        assert!(chip.screen[(0, 1)] == 0b00000111);
-        assert!(chip.screen[(0, 2)] == 0b10000000);
+        assert!(chip.screen.byte(0, 1) == 0b00000111);
+        assert!(chip.screen.byte(0, 2) == 0b10000000);
+    }
+
+    #[test]
+    fn test_run_drw_origin_wraps_to_display() {
+        let mut chip = Chip8::new();
+        chip.v[0] = 70;
+        chip.v[1] = 33;
+        chip.i = 0x400;
+        chip.memory[0x400] = 0x80;
+        chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 1 });
+        assert!(chip.screen.pixel(6, 1));
+
+        let mut chip = Chip8::new();
+        chip.resolution = Resolution::High;
+        chip.v[0] = 200;
+        chip.v[1] = 70;
+        chip.i = 0x400;
+        chip.memory[0x400] = 0x80;
+        chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 1 });
+        assert!(chip.screen.pixel(72, 6));
     }
 
     #[test]
@@ -653,11 +1355,11 @@ mod tests {
         chip.memory[img_loc + 4] = 0xF0;
         chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 5 });
         assert_eq!(chip.pc, 0x202);
-        assert!(chip.screen[(0, 0)] == 0xF0);
-        assert!(chip.screen[(1, 0)] == 0x90);
-        assert!(chip.screen[(2, 0)] == 0x90);
-        assert!(chip.screen[(3, 0)] == 0x90);
-        assert!(chip.screen[(4, 0)] == 0xF0);
+        assert!(chip.screen.byte(0, 0) == 0xF0);
+        assert!(chip.screen.byte(1, 0) == 0x90);
+        assert!(chip.screen.byte(2, 0) == 0x90);
+        assert!(chip.screen.byte(3, 0) == 0x90);
+        assert!(chip.screen.byte(4, 0) == 0xF0);
     }
 
     #[test]
@@ -674,11 +1376,11 @@ mod tests {
         chip.memory[img_loc + 4] = 0xF0;
         chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 5 });
         assert_eq!(chip.pc, 0x202);
-        assert!(chip.screen[(1, 0)] == 0xF0);
-        assert!(chip.screen[(2, 0)] == 0x90);
-        assert!(chip.screen[(3, 0)] == 0x90);
-        assert!(chip.screen[(4, 0)] == 0x90);
-        assert!(chip.screen[(5, 0)] == 0xF0);
+        assert!(chip.screen.byte(1, 0) == 0xF0);
+        assert!(chip.screen.byte(2, 0) == 0x90);
+        assert!(chip.screen.byte(3, 0) == 0x90);
+        assert!(chip.screen.byte(4, 0) == 0x90);
+        assert!(chip.screen.byte(5, 0) == 0xF0);
     }
 
     #[test]
@@ -695,11 +1397,11 @@ mod tests {
         chip.memory[img_loc + 4] = 0xF0;
         chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 5 });
         assert_eq!(chip.pc, 0x202);
-        assert!(chip.screen[(1, 0)] == 0x0F);
-        assert!(chip.screen[(2, 0)] == 0x09);
-        assert!(chip.screen[(3, 0)] == 0x09);
-        assert!(chip.screen[(4, 0)] == 0x09);
-        assert!(chip.screen[(5, 0)] == 0x0F);
+        assert!(chip.screen.byte(1, 0) == 0x0F);
+        assert!(chip.screen.byte(2, 0) == 0x09);
+        assert!(chip.screen.byte(3, 0) == 0x09);
+        assert!(chip.screen.byte(4, 0) == 0x09);
+        assert!(chip.screen.byte(5, 0) == 0x0F);
     }
 
     #[test]
@@ -730,14 +1432,69 @@ mod tests {
     fn test_exec_clr() {
         let mut chip = Chip8::new();
 
-        chip.screen[(0, 0)] = 0xFF;
-        chip.screen[(10, 5)] = 0x0F;
+        chip.screen.set_byte(0, 0, 0xFF);
+        chip.screen.set_byte(10, 5, 0x0F);
         chip.v[0xF] = 1;
 
         chip.exec(ChipOp::Cls);
         assert_eq!(chip.pc, 0x202);
 
-        assert_eq!(chip.screen.iter().sum::<u8>(), 0);
+        assert_eq!(chip.screen.bytes().sum::<u8>(), 0);
+    }
+
+    #[test]
+    fn test_exec_cls_low_res_clears_high_res_region() {
+        let mut chip = Chip8::new();
+        chip.exec(ChipOp::HighRes);
+        chip.screen.set_byte(63, 15, 0x01); // Only visible in high-res
+        chip.screen.set_byte(0, 0, 0x80);
+
+        chip.exec(ChipOp::LowRes);
+        chip.exec(ChipOp::Cls);
+        chip.exec(ChipOp::HighRes);
+        assert_eq!(chip.screen.bytes().sum::<u8>(), 0);
+    }
+
+    #[test]
+    fn test_exec_cls_high_res() {
+        let mut chip = Chip8::new();
+        chip.exec(ChipOp::HighRes);
+        chip.screen.fill(0xFF);
+
+        chip.exec(ChipOp::Cls);
+        assert_eq!(chip.screen.bytes().sum::<u8>(), 0);
+        assert!(matches!(chip.resolution, Resolution::High));
+    }
+
+    #[cfg(not(feature = "xo-chip"))]
+    #[test]
+    fn test_single_plane_screen() {
+        let mut chip = Chip8::new();
+        assert_eq!(chip.screen.dim(), (64, 16));
+        assert!(chip.extra_planes.is_empty());
+
+        chip.screen.set_byte(1, 0, 0xF0);
+        chip.exec(ChipOp::ScdN { n: 1 });
+        chip.exec(ChipOp::Scr);
+        assert_eq!(chip.screen.byte(2, 0), 0x0F);
+        assert_eq!(chip.screen_byte(2, 0), 0x0F);
+    }
+
+    #[cfg(feature = "xo-chip")]
+    #[test]
+    fn test_extra_plane_scrolls_and_clears() {
+        let mut chip = Chip8::new();
+        assert_eq!(chip.extra_planes.len(), 1);
+        chip.screen.set_byte(0, 0, 0xF0);
+        chip.extra_planes[0].set_byte(1, 0, 0x0F);
+
+        chip.exec(ChipOp::ScdN { n: 1 });
+        assert_eq!(chip.extra_planes[0].byte(2, 0), 0x0F);
+        assert_eq!(chip.screen_byte(1, 0), 0xF0);
+        assert_eq!(chip.screen_byte(2, 0), 0x0F);
+
+        chip.exec(ChipOp::Cls);
+        assert_eq!(chip.extra_planes[0].bytes().sum::<u8>(), 0);
     }
 
     #[test]
@@ -832,6 +1589,18 @@ mod tests {
         assert_eq!(chip.v[0xF], 1);
     }
 
+    #[test]
+    fn test_exec_shr_vx_in_place_quirk() {
+        let mut chip = Chip8::new();
+        chip.quirks.shift_uses_vy = false;
+        chip.v[0] = 0b00000110;
+        chip.v[1] = 0b10101011;
+
+        chip.exec(ChipOp::ShrVxVy { x: 0, y: 1 });
+        assert_eq!(chip.v[0], 0b00000011);
+        assert_eq!(chip.v[0xF], 0);
+    }
+
     #[test]
     fn test_exec_subn_vx_vy_no_borrow() {
         let mut chip = Chip8::new();
@@ -905,6 +1674,26 @@ mod tests {
         assert_eq!(chip.pc, 0x310);
     }
 
+    #[test]
+    fn test_exec_jp_v0_nnn_past_ram() {
+        let mut chip = Chip8::new();
+        chip.v[0] = 0xFF;
+        let jump = ChipOp::JpV0Nnn { nnn: 0xFFF };
+
+        assert_eq!(
+            chip.try_exec(jump),
+            Err(ExecError::PcOutOfBounds { pc: 0x10FE })
+        );
+        assert_eq!(chip.pc, 0x200);
+        // The fault leaves PC where it was, so the next fetch is in RAM.
+        chip.memory[0x200..0x202].copy_from_slice(&[0x00, 0xE0]);
+        assert_eq!(chip.try_step(), Ok(()));
+
+        chip.quirks.wrap_memory = true;
+        chip.exec(jump);
+        assert_eq!(chip.pc, 0x0FE);
+    }
+
     #[test]
     fn test_exec_rnd_vx_nn() {
         let mut chip = Chip8::new();
@@ -914,6 +1703,34 @@ mod tests {
         assert_eq!(chip.v[0] & 0xF0, 0);
     }
 
+    #[test]
+    fn test_exec_rnd_vx_nn_seeded() {
+        let run = || {
+            let mut chip = Chip8::new();
+            chip.seed_rng(42);
+            (0..16)
+                .map(|_| {
+                    chip.exec(ChipOp::RndVxNn { x: 0, nn: 0xFF });
+                    chip.v[0]
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn test_exec_rnd_vx_nn_covers_every_byte() {
+        let mut chip = Chip8::new();
+        chip.seed_rng(1);
+        let mut seen = [false; 256];
+        for _ in 0..4096 {
+            chip.exec(ChipOp::RndVxNn { x: 0, nn: 0xFF });
+            seen[chip.v[0] as usize] = true;
+            chip.pc = 0x200;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
     #[test]
     fn test_exec_skp_vx_pressed() {
         let mut chip = Chip8::new();
@@ -954,6 +1771,67 @@ mod tests {
         assert_eq!(chip.pc, 0x202);
     }
 
+    #[test]
+    fn test_exec_skp_vx_masks_key() {
+        let mut chip = Chip8::new();
+        chip.v[0] = 0x25;
+        chip.keys[5] = true;
+
+        chip.exec(ChipOp::SkpVx { x: 0 });
+        assert_eq!(chip.pc, 0x204);
+        chip.exec(ChipOp::SknpVx { x: 0 });
+        assert_eq!(chip.pc, 0x206);
+    }
+
+    #[test]
+    fn test_press_key_masks_key() {
+        let mut chip = Chip8::new();
+        chip.press_key(0x15);
+        assert!(chip.keys[5]);
+        chip.press_key(0xFF);
+        assert!(chip.keys[0xF]);
+        chip.release_key(0x25);
+        assert!(!chip.keys[5]);
+        assert_eq!(chip.keys_bitmask(), 0x8000);
+    }
+
+    #[test]
+    fn test_keys_bitmask_round_trip() {
+        let mut chip = Chip8::new();
+        for mask in [0x0000, 0x0001, 0x8000, 0xA5C3, 0xFFFF] {
+            chip.set_keys(mask);
+            assert_eq!(chip.keys_bitmask(), mask);
+            for key in 0..16 {
+                assert_eq!(chip.keys[key], mask & (1 << key) != 0, "key {key:X}");
+            }
+        }
+        chip.keys = [false; 16];
+        chip.keys[3] = true;
+        chip.keys[0xC] = true;
+        assert_eq!(chip.keys_bitmask(), 0x1008);
+    }
+
+    #[test]
+    fn test_exec_skp_vx_key_out_of_range() {
+        let mut chip = Chip8::new();
+        chip.quirks.mask_skip_key = false;
+        chip.v[0] = 0x25;
+        chip.keys[5] = true;
+
+        let err = chip.try_exec(ChipOp::SkpVx { x: 0 }).unwrap_err();
+        assert_eq!(err, ExecError::KeyOutOfRange { key: 0x25 });
+        let err = chip.try_exec(ChipOp::SknpVx { x: 0 }).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Key out of range: 0x25 is not a key from 0 to F"
+        );
+        assert_eq!(chip.pc, 0x200);
+
+        chip.v[0] = 0xF;
+        chip.exec(ChipOp::SkpVx { x: 0 });
+        assert_eq!(chip.pc, 0x202);
+    }
+
     #[test]
     fn test_exec_ld_vx_dt() {
         let mut chip = Chip8::new();
@@ -964,6 +1842,25 @@ mod tests {
         assert_eq!(chip.v[0], 42);
     }
 
+    #[test]
+    fn test_exec_ld_vx_dt_latched() {
+        let mut chip = Chip8::new();
+        chip.dt.store(42, Ordering::Release);
+        chip.latched_dt = Some(42);
+
+        chip.dt.store(41, Ordering::Release);
+        chip.exec(ChipOp::LdVxDt { x: 0 });
+        assert_eq!(chip.v[0], 42);
+
+        chip.v[1] = 7;
+        chip.exec(ChipOp::LdDtVx { x: 1 });
+        assert_eq!(chip.latched_dt, Some(7));
+
+        chip.latched_dt = None;
+        chip.exec(ChipOp::LdVxDt { x: 2 });
+        assert_eq!(chip.v[2], 7);
+    }
+
     #[test]
     fn test_exec_ld_dt_vx() {
         let mut chip = Chip8::new();
@@ -982,6 +1879,10 @@ mod tests {
         chip.exec(ChipOp::LdStVx { x: 0 });
         assert_eq!(chip.pc, 0x202);
         assert_eq!(chip.st.load(Ordering::Acquire), 42);
+        assert_eq!(chip.sound_loads, 1);
+        chip.pc = 0x200;
+        chip.exec(ChipOp::LdStVx { x: 0 });
+        assert_eq!(chip.sound_loads, 2);
     }
 
     #[test]
@@ -995,6 +1896,164 @@ mod tests {
         assert_eq!(chip.i, 0x310);
     }
 
+    #[test]
+    fn test_drw_tallest_sprite_at_end_of_ram() {
+        use crate::chip8::consts::RAM_SIZE;
+
+        let run = |wrap: bool, i: usize| {
+            let mut chip = Chip8::new();
+            chip.quirks.wrap_memory = wrap;
+            chip.i = i;
+            chip.memory[RAM_SIZE - MAX_SPRITE_HEIGHT..].fill(0x80);
+            let result = chip.try_exec(ChipOp::DrwVxVyN { x: 0, y: 0, n: 15 });
+            (result, chip)
+        };
+        // The last 15 bytes of RAM are the highest a full sprite can start.
+        let (result, chip) = run(false, RAM_SIZE - MAX_SPRITE_HEIGHT);
+        assert_eq!(result, Ok(()));
+        assert!((0..MAX_SPRITE_HEIGHT).all(|y| chip.screen.pixel(0, y)));
+        assert!(!chip.screen.pixel(0, MAX_SPRITE_HEIGHT));
+
+        for i in RAM_SIZE - MAX_SPRITE_HEIGHT + 1..RAM_SIZE {
+            let (result, _) = run(false, i);
+            let fault = ExecError::MemoryOutOfBounds { addr: i, len: 15 };
+            assert_eq!(result, Err(fault));
+            let (result, chip) = run(true, i);
+            assert_eq!(result, Ok(()));
+            // Rows past the end come from the (empty) start of RAM.
+            let lit = RAM_SIZE - i;
+            assert!((0..lit).all(|y| chip.screen.pixel(0, y)));
+            assert!((lit..MAX_SPRITE_HEIGHT).all(|y| !chip.screen.pixel(0, y)));
+        }
+
+        // Only the low nibble of a hand-built op's height counts.
+        let mut chip = Chip8::new();
+        chip.i = RAM_SIZE - 2;
+        let op = ChipOp::DrwVxVyN {
+            x: 0,
+            y: 0,
+            n: 0x12,
+        };
+        assert_eq!(chip.try_exec(op), Ok(()));
+    }
+
+    #[test]
+    fn test_exec_memory_past_end_of_ram() {
+        let run = |wrap: bool, op: ChipOp| {
+            let mut chip = Chip8::new();
+            chip.quirks.wrap_memory = wrap;
+            chip.i = 0xFFE;
+            chip.v[..4].copy_from_slice(&[0xF0, 0xF0, 0xF0, 0xF0]);
+            chip.memory[0xFFE..].copy_from_slice(&[0x80, 0x80]);
+            chip.memory[..2].copy_from_slice(&[0x80, 0x80]);
+            let result = chip.try_exec(op);
+            (result, chip)
+        };
+        let out_of_bounds = |len| Err(ExecError::MemoryOutOfBounds { addr: 0xFFE, len });
+
+        let ops = [
+            (ChipOp::DrwVxVyN { x: 4, y: 4, n: 4 }, 4),
+            (ChipOp::LdIVx { x: 3 }, 4),
+            (ChipOp::LdVxI { x: 3 }, 4),
+            (ChipOp::LdBVx { x: 0 }, 3),
+        ];
+        for (op, len) in ops {
+            assert_eq!(run(false, op).0, out_of_bounds(len), "{op:?}");
+            assert_eq!(run(true, op).0, Ok(()), "{op:?}");
+        }
+
+        let (_, chip) = run(true, ChipOp::DrwVxVyN { x: 4, y: 4, n: 4 });
+        assert!((0..4).all(|y| chip.screen.pixel(0, y)));
+        let (_, chip) = run(true, ChipOp::LdIVx { x: 3 });
+        assert_eq!(
+            (chip.memory[0xFFF], chip.memory[1], chip.i),
+            (0xF0, 0xF0, 2)
+        );
+        let (_, chip) = run(true, ChipOp::LdVxI { x: 3 });
+        assert_eq!((&chip.v[..4], chip.i), (&[0x80; 4][..], 2));
+        let (_, chip) = run(true, ChipOp::LdBVx { x: 0 });
+        assert_eq!(
+            (chip.memory[0xFFE], chip.memory[0xFFF], chip.memory[0]),
+            (2, 4, 0)
+        );
+
+        // FX1E only moves I, so it never faults.
+        let (result, chip) = run(false, ChipOp::AddIVx { x: 0 });
+        assert_eq!((result, chip.i), (Ok(()), 0x10EE));
+        let (_, chip) = run(true, ChipOp::AddIVx { x: 0 });
+        assert_eq!(chip.i, 0x0EE);
+    }
+
+    #[test]
+    fn test_exec_mask_addr() {
+        let mut chip = Chip8::new();
+        chip.quirks.mask_addr = true;
+        chip.i = 0x1002;
+        chip.memory[0x002] = 0x42;
+        chip.exec(ChipOp::LdVxI { x: 0 });
+        assert_eq!(chip.v[0], 0x42);
+
+        // PC wraps to the bottom of RAM rather than faulting.
+        chip.v[0] = 0x02;
+        chip.exec(ChipOp::JpV0Nnn { nnn: 0xFFE });
+        assert_eq!(chip.pc, 0x000);
+
+        // A 64KB machine has the full 16 bits to address.
+        use crate::chip8::consts::XO_RAM_SIZE;
+        let mut chip = Chip8::new();
+        chip.memory = Memory::new(XO_RAM_SIZE);
+        chip.quirks.mask_addr = true;
+        chip.i = 0x1002;
+        chip.memory[0x1002] = 0x42;
+        chip.exec(ChipOp::LdVxI { x: 0 });
+        assert_eq!(chip.v[0], 0x42);
+    }
+
+    #[test]
+    fn test_exec_ld_i_vx_protected() {
+        // LD [I], V3 with I two bytes below the program start.
+        let run = |protect: Option<ProtectPolicy>| {
+            let mut chip = Chip8::new();
+            chip.protect_low_mem = protect;
+            // Skipping unknown opcodes has no say over protection.
+            chip.skip_unknown = true;
+            chip.i = PROGRAM_START - 2;
+            chip.v[..4].copy_from_slice(&[1, 2, 3, 4]);
+            let result = chip.try_exec(ChipOp::LdIVx { x: 3 });
+            (result, chip)
+        };
+        let stored = |chip: &Chip8| chip.memory[PROGRAM_START - 2..PROGRAM_START + 2].to_vec();
+
+        let (result, chip) = run(None);
+        assert_eq!((result, stored(&chip)), (Ok(()), vec![1, 2, 3, 4]));
+
+        let (result, chip) = run(Some(ProtectPolicy::Fault));
+        let err = ExecError::ProtectedWrite { addr: 0x1FE };
+        assert_eq!((result, stored(&chip)), (Err(err), vec![0; 4]));
+        assert_eq!(chip.pc, PROGRAM_START);
+        assert_eq!(
+            err.to_string(),
+            "Protected write: 0x1FE is below the program start"
+        );
+
+        // Ignoring drops the low half and lands the rest.
+        let (result, chip) = run(Some(ProtectPolicy::Ignore));
+        assert_eq!((result, stored(&chip)), (Ok(()), vec![0, 0, 3, 4]));
+        assert_eq!(chip.i, PROGRAM_START + 2);
+
+        // Protection leaves the bounds check as it was.
+        let mut chip = Chip8::new();
+        chip.protect_low_mem = Some(ProtectPolicy::Fault);
+        chip.i = 0xFFE;
+        assert_eq!(
+            chip.try_exec(ChipOp::LdIVx { x: 3 }),
+            Err(ExecError::MemoryOutOfBounds {
+                addr: 0xFFE,
+                len: 4
+            })
+        );
+    }
+
     #[test]
     fn test_exec_ld_f_vx() {
         let mut chip = Chip8::new();
@@ -1005,6 +2064,18 @@ mod tests {
         assert_eq!(chip.i, 50); // 0xA * 5
     }
 
+    #[test]
+    fn test_exec_ld_f_vx_font_base() {
+        let mut chip = Chip8::new();
+        chip.font_base = 0x50;
+        chip.load_font();
+        chip.v[0] = 1;
+
+        chip.exec(ChipOp::LdFVx { x: 0 });
+        assert_eq!(chip.i, 0x50 + 5);
+        assert_eq!(chip.memory[chip.i..chip.i + 5], CHIP8_FONTSET[5..10]);
+    }
+
     #[test]
     fn test_exec_ld_b_vx() {
         let mut chip = Chip8::new();
@@ -1018,6 +2089,16 @@ mod tests {
         assert_eq!(chip.memory[0x302], 3);
     }
 
+    #[test]
+    fn test_exec_ld_b_vx_255() {
+        let mut chip = Chip8::new();
+        chip.v[0] = 255;
+        chip.i = 0x300;
+
+        chip.exec(ChipOp::LdBVx { x: 0 });
+        assert_eq!(chip.memory[0x300..0x303], [2, 5, 5]);
+    }
+
     #[test]
     fn test_exec_ld_i_vx() {
         let mut chip = Chip8::new();
@@ -1050,56 +2131,141 @@ mod tests {
         assert_eq!(chip.i, 0x303);
     }
 
+    #[test]
+    fn test_exec_ld_i_vx_no_increment_quirk() {
+        let mut chip = Chip8::new();
+        chip.quirks.load_store_increments_i = false;
+        chip.v[0] = 0xAB;
+        chip.v[1] = 0xCD;
+        chip.i = 0x300;
+
+        chip.exec(ChipOp::LdIVx { x: 1 });
+        assert_eq!(chip.memory[0x301], 0xCD);
+        assert_eq!(chip.i, 0x300);
+    }
+
     #[test]
     fn test_exec_scd_n() {
         let mut chip = Chip8::new();
-        chip.screen[(0, 0)] = 0xFF;
-        chip.screen[(1, 0)] = 0xAA;
-        chip.screen[(2, 0)] = 0x55;
+        chip.screen.set_byte(0, 0, 0xFF);
+        chip.screen.set_byte(1, 0, 0xAA);
+        chip.screen.set_byte(2, 0, 0x55);
 
         chip.exec(ChipOp::ScdN { n: 1 });
         assert_eq!(chip.pc, 0x202);
-        assert_eq!(chip.screen[(0, 0)], 0);
-        assert_eq!(chip.screen[(1, 0)], 0xFF);
-        assert_eq!(chip.screen[(2, 0)], 0xAA);
+        assert_eq!(chip.screen.byte(0, 0), 0);
+        assert_eq!(chip.screen.byte(1, 0), 0xFF);
+        assert_eq!(chip.screen.byte(2, 0), 0xAA);
     }
 
     #[test]
     fn test_exec_scu_n() {
         let mut chip = Chip8::new();
-        chip.screen[(0, 0)] = 0xFF;
-        chip.screen[(1, 0)] = 0xAA;
-        chip.screen[(2, 0)] = 0x55;
+        chip.screen.set_byte(0, 0, 0xFF);
+        chip.screen.set_byte(1, 0, 0xAA);
+        chip.screen.set_byte(2, 0, 0x55);
 
         chip.exec(ChipOp::ScuN { n: 1 });
         assert_eq!(chip.pc, 0x202);
-        assert_eq!(chip.screen[(0, 0)], 0xAA);
-        assert_eq!(chip.screen[(1, 0)], 0x55);
-        assert_eq!(chip.screen[(2, 0)], 0);
+        assert_eq!(chip.screen.byte(0, 0), 0xAA);
+        assert_eq!(chip.screen.byte(1, 0), 0x55);
+        assert_eq!(chip.screen.byte(2, 0), 0);
+    }
+
+    #[test]
+    fn test_exec_scroll_by_zero_one_and_height() {
+        let mut chip = Chip8::new();
+        chip.screen.set_byte(0, 3, 0x81);
+        chip.screen.set_byte(63, 15, 0x18);
+        let before = chip.screen.clone();
+
+        chip.exec(ChipOp::ScdN { n: 0 });
+        chip.exec(ChipOp::ScuN { n: 0 });
+        assert_eq!(chip.screen, before);
+
+        chip.exec(ChipOp::ScdN { n: 1 });
+        assert_eq!(chip.screen.byte(1, 3), 0x81);
+        assert_eq!(chip.screen.byte(63, 15), 0);
+        assert_eq!(chip.screen.bytes().filter(|&b| b != 0).count(), 1);
+        chip.exec(ChipOp::ScuN { n: 1 });
+        assert_eq!(chip.screen.byte(0, 3), 0x81);
+        assert_eq!(chip.screen.byte(63, 15), 0);
+
+        let mut chip = Chip8::new();
+        chip.screen = before.clone();
+        chip.exec(ChipOp::ScdN { n: HEIGHT as u8 });
+        assert!(chip.screen.bytes().all(|b| b == 0));
+        chip.screen = before.clone();
+        chip.exec(ChipOp::ScuN { n: HEIGHT as u8 });
+        assert!(chip.screen.bytes().all(|b| b == 0));
+    }
+
+    #[test]
+    fn test_half_pixel_scroll_in_low_res() {
+        // A 4-pixel bar at the top left, scrolled down by one.
+        let scrolled = |variant: Variant| {
+            let mut chip = Chip8::new();
+            chip.quirks = variant.quirks();
+            chip.memory[0x300] = 0xF0;
+            chip.i = 0x300;
+            chip.exec(ChipOp::DrwVxVyN { x: 0, y: 0, n: 1 });
+            chip.exec(ChipOp::ScdN { n: 1 });
+            chip
+        };
+
+        // CHIP-8 moves it a whole low-res pixel.
+        let chip = scrolled(Variant::Chip8);
+        assert_eq!(chip.display_dimensions(), (64, 32));
+        assert_eq!((chip.screen.byte(0, 0), chip.screen.byte(1, 0)), (0, 0xF0));
+
+        // SUPER-CHIP draws it as 2x2 blocks and moves it half of one.
+        let mut chip = scrolled(Variant::SuperChip);
+        assert_eq!(chip.display_dimensions(), (128, 64));
+        let rows: Vec<_> = (0..4).map(|y| chip.screen.byte(y, 0)).collect();
+        assert_eq!(rows, [0, 0xFF, 0xFF, 0]);
+        chip.exec(ChipOp::Scr);
+        assert_eq!(
+            (chip.screen.byte(1, 0), chip.screen.byte(1, 1)),
+            (0x0F, 0xF0)
+        );
+        chip.exec(ChipOp::Scl);
+        chip.exec(ChipOp::Scl);
+        assert_eq!(chip.screen.byte(1, 0), 0xF0);
+
+        // Collisions count any block pixel turned off.
+        chip.exec(ChipOp::DrwVxVyN { x: 0, y: 0, n: 1 });
+        assert_eq!(chip.v[0xF], 1);
+
+        // High-res draws and scrolls the same either way.
+        chip.screen.clear();
+        chip.exec(ChipOp::HighRes);
+        chip.exec(ChipOp::DrwVxVyN { x: 0, y: 0, n: 1 });
+        chip.exec(ChipOp::ScdN { n: 1 });
+        assert_eq!((chip.screen.byte(0, 0), chip.screen.byte(1, 0)), (0, 0xF0));
     }
 
     #[test]
     fn test_exec_scr() {
         let mut chip = Chip8::new();
-        chip.screen[(0, 0)] = 0b11110000;
-        chip.screen[(0, 1)] = 0b10101010;
+        chip.screen.set_byte(0, 0, 0b11110000);
+        chip.screen.set_byte(0, 1, 0b10101010);
 
         chip.exec(ChipOp::Scr);
         assert_eq!(chip.pc, 0x202);
-        assert_eq!(chip.screen[(0, 0)], 0b00001111);
-        assert_eq!(chip.screen[(0, 1)], 0b00001010);
+        assert_eq!(chip.screen.byte(0, 0), 0b00001111);
+        assert_eq!(chip.screen.byte(0, 1), 0b00001010);
     }
 
     #[test]
     fn test_exec_scl() {
         let mut chip = Chip8::new();
-        chip.screen[(0, 0)] = 0b11110000;
-        chip.screen[(0, 1)] = 0b10101010;
+        chip.screen.set_byte(0, 0, 0b11110000);
+        chip.screen.set_byte(0, 1, 0b10101010);
 
         chip.exec(ChipOp::Scl);
         assert_eq!(chip.pc, 0x202);
-        assert_eq!(chip.screen[(0, 0)], 0b00001010);
-        assert_eq!(chip.screen[(0, 1)], 0b10100000);
+        assert_eq!(chip.screen.byte(0, 0), 0b00001010);
+        assert_eq!(chip.screen.byte(0, 1), 0b10100000);
     }
 
     #[test]
@@ -1134,25 +2300,31 @@ mod tests {
     #[test]
     fn test_exec_ld_vx_k_awaiting_press() {
         let mut chip = Chip8::new();
-        chip.key_state = KeyState::AwaitingPress;
-        chip.keys[5] = true;
+        chip.exec(ChipOp::LdVxK { x: 0 });
+        assert_eq!(chip.key_log.wait, KeyWait::AwaitingPress);
 
+        chip.press_key(5);
         chip.exec(ChipOp::LdVxK { x: 0 });
         assert_eq!(chip.pc, 0x200); // PC not incremented yet
-        assert!(matches!(chip.key_state, KeyState::AwaitingRelease));
-        assert_eq!(chip.last_key, 5);
+        assert_eq!(chip.key_log.wait, KeyWait::AwaitingRelease { key: 5 });
     }
 
     #[test]
     fn test_exec_ld_vx_k_awaiting_release() {
         let mut chip = Chip8::new();
-        chip.key_state = KeyState::AwaitingRelease;
-        chip.last_key = 5;
-        chip.keys.fill(false); // All keys released
+        chip.press_key(5);
+        chip.press_key(7);
+        chip.exec(ChipOp::LdVxK { x: 0 });
+        assert_eq!(chip.key_log.wait, KeyWait::AwaitingRelease { key: 5 });
 
+        // Only the release of the key that was pressed first counts.
+        chip.release_key(7);
+        chip.exec(ChipOp::LdVxK { x: 0 });
+        assert_eq!(chip.pc, 0x200);
+        chip.release_key(5);
         chip.exec(ChipOp::LdVxK { x: 0 });
         assert_eq!(chip.pc, 0x202);
-        assert!(matches!(chip.key_state, KeyState::AwaitingPress));
+        assert_eq!(chip.key_log.wait, KeyWait::Idle);
         assert_eq!(chip.v[0], 5);
     }
 }
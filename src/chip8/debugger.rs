@@ -0,0 +1,182 @@
+//! Instruction-level control of an [`Emulator`] for debugger frontends.
+//!
+//! Instructions run one at a time so execution can stop at breakpoints.
+//! The timers tick once every frame's worth of instructions, keeping their
+//! rate relative to the program the same as in [`Emulator::run_frame`].
+
+use std::collections::BTreeSet;
+
+use crate::chip8::cpu::ExecError;
+use crate::chip8::emulator::{Emulator, Halt};
+use crate::chip8::timers;
+
+/// Why [`Debugger::run`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// PC reached a breakpoint; the instruction there hasn't run yet.
+    Breakpoint(usize),
+    /// The program executed EXIT, or stopped as [`Emulator::halt`] says.
+    Halted,
+    /// The instruction at `pc` faulted. The program doesn't run again.
+    Fault { pc: usize, error: ExecError },
+    /// The instruction limit ran out.
+    Limit,
+}
+
+pub struct Debugger {
+    emu: Emulator,
+    breakpoints: BTreeSet<usize>,
+    executed: u64,
+    fault: Option<ExecError>,
+}
+
+impl Debugger {
+    pub fn new(emu: Emulator) -> Self {
+        Self {
+            emu,
+            breakpoints: BTreeSet::new(),
+            executed: 0,
+            fault: None,
+        }
+    }
+
+    pub fn emulator(&self) -> &Emulator {
+        &self.emu
+    }
+
+    pub fn emulator_mut(&mut self) -> &mut Emulator {
+        &mut self.emu
+    }
+
+    /// Instructions executed since the debugger was attached.
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    /// Add a breakpoint at `addr`, returning whether it was new.
+    pub fn add_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    /// Remove the breakpoint at `addr`, returning whether there was one.
+    pub fn remove_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Execute one instruction, returning false if the program has halted
+    /// or the instruction faulted.
+    pub fn step(&mut self) -> bool {
+        if self.emu.chip().exit {
+            return false;
+        }
+        if let Err(err) = self.emu.try_step_instruction() {
+            self.fault = Some(err);
+        }
+        // Every halt but EXIT stops before running the instruction.
+        if matches!(self.emu.halt(), Some(halt) if halt != Halt::Exit) {
+            return false;
+        }
+        self.executed += 1;
+        if self
+            .executed
+            .is_multiple_of(self.emu.config().instructions_per_frame().round().max(1.0) as u64)
+        {
+            let chip = self.emu.chip();
+            timers::tick(&chip.dt);
+            timers::tick(&chip.st);
+        }
+        true
+    }
+
+    /// Run until a breakpoint, EXIT, or `limit` instructions. The first
+    /// instruction always runs, so continuing from a breakpoint moves on.
+    pub fn run(&mut self, limit: u64) -> Stop {
+        for n in 0..limit {
+            let pc = self.emu.chip().pc;
+            if n > 0 && self.breakpoints.contains(&pc) {
+                return Stop::Breakpoint(pc);
+            }
+            if !self.step() {
+                return self.stop();
+            }
+        }
+        match self.emu.chip().pc {
+            pc if self.breakpoints.contains(&pc) => Stop::Breakpoint(pc),
+            _ if self.emu.chip().exit => self.stop(),
+            _ => Stop::Limit,
+        }
+    }
+
+    /// Why the program stopped, once it has.
+    pub fn stop(&self) -> Stop {
+        match (self.emu.halt(), self.fault) {
+            (Some(Halt::Fault { pc }), Some(error)) => Stop::Fault { pc, error },
+            _ => Stop::Halted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::EmulatorConfig;
+    use std::sync::atomic::Ordering;
+
+    fn debugger(rom: &[u8]) -> Debugger {
+        let mut emu = Emulator::new(EmulatorConfig {
+            cycles_per_frame: 2,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(rom).unwrap();
+        Debugger::new(emu)
+    }
+
+    #[test]
+    fn test_run_stops_at_breakpoint() {
+        // LD V0, 1; ADD V0, 1; JP 0x202
+        let mut dbg = debugger(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+        assert!(dbg.add_breakpoint(0x204));
+        assert_eq!(dbg.run(100), Stop::Breakpoint(0x204));
+        assert_eq!(dbg.emulator().chip().v[0], 2);
+        assert_eq!(dbg.run(100), Stop::Breakpoint(0x204));
+        assert_eq!(dbg.emulator().chip().v[0], 3);
+
+        assert!(dbg.remove_breakpoint(0x204));
+        assert_eq!(dbg.run(10), Stop::Limit);
+    }
+
+    #[test]
+    fn test_step_ticks_timers_and_halts() {
+        // LD V0, 5; LD DT, V0; EXIT
+        let mut dbg = debugger(&[0x60, 0x05, 0xF0, 0x15, 0x00, 0xFD]);
+        assert!(dbg.step());
+        assert!(dbg.step());
+        assert_eq!(dbg.emulator().chip().dt.load(Ordering::Acquire), 4);
+        assert_eq!(dbg.run(10), Stop::Halted);
+        assert!(!dbg.step());
+        assert_eq!(dbg.executed(), 3);
+    }
+
+    #[test]
+    fn test_fault_stops_without_panicking() {
+        // LD V0, 1; an unknown opcode.
+        let mut dbg = debugger(&[0x60, 0x01, 0x5A, 0xB1, 0x12, 0x00]);
+        assert!(dbg.step());
+        assert!(!dbg.step());
+        let fault = Stop::Fault {
+            pc: 0x202,
+            error: ExecError::UnknownOpcode {
+                word: 0x5AB1,
+                family: 0x5,
+            },
+        };
+        assert_eq!(dbg.stop(), fault);
+        assert_eq!(dbg.executed(), 1);
+        // It stays stopped.
+        assert_eq!(dbg.run(10), fault);
+    }
+}
@@ -13,7 +13,7 @@ pub fn decode(op: u16) -> ChipOp {
             0x00E0 => match op & 0xF {
                 0x0 => ChipOp::Cls,
                 0xE => ChipOp::Ret,
-                _ => ChipOp::Unknown(op),
+                _ => sys(op),
             },
             0x00F0 => match op & 0xF {
                 0xB => ChipOp::Scr,
@@ -21,9 +21,9 @@ pub fn decode(op: u16) -> ChipOp {
                 0xD => ChipOp::Exit,
                 0xE => ChipOp::LowRes,
                 0xF => ChipOp::HighRes,
-                _ => ChipOp::Unknown(op),
+                _ => sys(op),
             },
-            _ => ChipOp::Unknown(op),
+            _ => sys(op),
         },
         0x1000 => ChipOp::JpNnn {
             nnn: (op & 0x0FFF) as usize,
@@ -46,7 +46,7 @@ pub fn decode(op: u16) -> ChipOp {
                 0x0000 => ChipOp::SeVxVy { x, y },
                 0x0002 => ChipOp::LdIVxVy { x, y },
                 0x0003 => ChipOp::LdVxVyI { x, y },
-                _ => ChipOp::Unknown(op),
+                _ => unknown(op),
             }
         }
         0x6000 => ChipOp::LdVxNn {
@@ -70,7 +70,7 @@ pub fn decode(op: u16) -> ChipOp {
                 0x0006 => ChipOp::ShrVxVy { x, y },
                 0x0007 => ChipOp::SubnVxVy { x, y },
                 0x000E => ChipOp::ShlVxVy { x, y },
-                _ => ChipOp::Unknown(op),
+                _ => unknown(op),
             }
         }
         0x9000 => match op & 0x000F {
@@ -78,7 +78,7 @@ pub fn decode(op: u16) -> ChipOp {
                 x: ((op & 0x0F00) >> 8) as usize,
                 y: ((op & 0x00F0) >> 4) as usize,
             },
-            _ => ChipOp::Unknown(op),
+            _ => unknown(op),
         },
         0xA000 => ChipOp::LdINnn {
             nnn: (op & 0x0FFF) as usize,
@@ -100,9 +100,10 @@ pub fn decode(op: u16) -> ChipOp {
             0x00A1 => ChipOp::SknpVx {
                 x: ((op & 0x0F00) >> 8) as usize,
             },
-            _ => ChipOp::Unknown(op),
+            _ => unknown(op),
         },
-        0xF000 => {
+        // 0xF000, the last of the sixteen.
+        _ => {
             let x = ((op & 0x0F00) >> 8) as usize;
             match op & 0x00FF {
                 0x0015 => ChipOp::LdDtVx { x },
@@ -114,9 +115,64 @@ pub fn decode(op: u16) -> ChipOp {
                 0x0033 => ChipOp::LdBVx { x },
                 0x0055 => ChipOp::LdIVx { x },
                 0x0065 => ChipOp::LdVxI { x },
-                _ => ChipOp::Unknown(op),
+                _ => unknown(op),
             }
         }
-        _ => ChipOp::Unknown(op),
+    }
+}
+
+/// A 0NNN machine code call, which is what any 0x0xxx word that isn't
+/// one of the 00xx instructions is.
+fn sys(word: u16) -> ChipOp {
+    ChipOp::Sys {
+        nnn: (word & 0x0FFF) as usize,
+    }
+}
+
+/// An opcode whose family (top nibble) exists but whose remaining bits
+/// don't match any instruction in it.
+fn unknown(word: u16) -> ChipOp {
+    ChipOp::Unknown {
+        word,
+        family: (word >> 12) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_reports_family() {
+        assert_eq!(
+            decode(0x812A),
+            ChipOp::Unknown {
+                word: 0x812A,
+                family: 0x8
+            }
+        );
+        assert_eq!(
+            decode(0x5121),
+            ChipOp::Unknown {
+                word: 0x5121,
+                family: 0x5
+            }
+        );
+        assert_eq!(decode(0x8124), ChipOp::AddVxVy { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn test_scroll_down_and_up() {
+        assert_eq!(decode(0x00C3), ChipOp::ScdN { n: 3 });
+        assert_eq!(decode(0x00D3), ChipOp::ScuN { n: 3 });
+    }
+
+    #[test]
+    fn test_sys() {
+        assert_eq!(decode(0x0123), ChipOp::Sys { nnn: 0x123 });
+        assert_eq!(decode(0x00E1), ChipOp::Sys { nnn: 0x0E1 });
+        assert_eq!(decode(0x00E0), ChipOp::Cls);
+        assert_eq!(decode(0x00FD), ChipOp::Exit);
+        assert_eq!(decode(0x0123).to_string(), "SYS 0x123");
     }
 }
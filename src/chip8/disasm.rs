@@ -0,0 +1,158 @@
+//! Listing a ROM as CHIP-8 assembly.
+//!
+//! Each word is decoded in place, two bytes at a time from the program
+//! start, so data mixed into the code is shown as whatever it decodes to.
+//! Targets of `JP` and `CALL` get a label, which their operands then use.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::chip8::consts::PROGRAM_START;
+use crate::chip8::decode::decode;
+use crate::chip8::op::ChipOp;
+
+/// Every address jumped to or called from within `rom`, with its label.
+pub fn labels(rom: &[u8]) -> BTreeMap<usize, String> {
+    labels_at(rom, PROGRAM_START)
+}
+
+fn labels_at(bytes: &[u8], base: usize) -> BTreeMap<usize, String> {
+    words(bytes, base)
+        .filter_map(|(_, word)| match decode(word) {
+            ChipOp::JpNnn { nnn } | ChipOp::CallNnn { nnn } => Some(nnn),
+            _ => None,
+        })
+        .map(|addr| (addr, format!("L{addr:03X}")))
+        .collect()
+}
+
+/// The listing of `rom` as loaded at the program start, limited to the
+/// addresses in `range` if given. Lines look like
+/// `0x206  D125  DRW V1, V2, 0x5`.
+pub fn disassemble(rom: &[u8], range: Option<Range<usize>>) -> String {
+    let range = range.unwrap_or(PROGRAM_START..PROGRAM_START + rom.len());
+    listing(rom, PROGRAM_START, range)
+}
+
+/// The listing of `count` instructions of `memory` starting at `addr`.
+pub fn disassemble_memory(memory: &[u8], addr: usize, count: usize) -> String {
+    let start = addr.min(memory.len());
+    let end = addr.saturating_add(2 * count).min(memory.len());
+    listing(&memory[start..end], start, start..end)
+}
+
+/// List `bytes`, which are loaded at `base`, within `range`.
+fn listing(bytes: &[u8], base: usize, range: Range<usize>) -> String {
+    let labels = labels_at(bytes, base);
+    let mut out = String::new();
+    for (addr, word) in words(bytes, base).filter(|(addr, _)| range.contains(addr)) {
+        if let Some(label) = labels.get(&addr) {
+            writeln!(out, "{label}:").unwrap();
+        }
+        let op = decode(word);
+        let mnemonic = match op {
+            ChipOp::JpNnn { nnn } if labels.contains_key(&nnn) => format!("JP {}", labels[&nnn]),
+            ChipOp::CallNnn { nnn } if labels.contains_key(&nnn) => {
+                format!("CALL {}", labels[&nnn])
+            }
+            ChipOp::Unknown { family, .. } => format!("{op}  ; unknown {family:X}xxx opcode"),
+            _ => op.to_string(),
+        };
+        writeln!(out, "{addr:#05X}  {word:04X}  {mnemonic}").unwrap();
+    }
+    if let [.., last] = bytes.chunks_exact(2).remainder() {
+        let addr = base + bytes.len() - 1;
+        if range.contains(&addr) {
+            writeln!(out, "{addr:#05X}  {last:02X}    DB {last:#04X}").unwrap();
+        }
+    }
+    out
+}
+
+/// Parse an address range given as `range:START-END`, end exclusive.
+pub fn parse_range(s: &str) -> Result<Range<usize>, String> {
+    let invalid = || format!("invalid range {s:?}: expected range:0xSTART-0xEND");
+    let (start, end) = s
+        .strip_prefix("range:")
+        .and_then(|r| r.split_once('-'))
+        .ok_or_else(invalid)?;
+    let parse = |n: &str| {
+        let hex = n
+            .strip_prefix("0x")
+            .or_else(|| n.strip_prefix("0X"))
+            .unwrap_or(n);
+        usize::from_str_radix(hex, 16).map_err(|_| invalid())
+    };
+    let range = parse(start)?..parse(end)?;
+    if range.is_empty() {
+        return Err(invalid());
+    }
+    Ok(range)
+}
+
+/// Each whole word of `bytes` with its address, counting from `base`.
+fn words(bytes: &[u8], base: usize) -> impl Iterator<Item = (usize, u16)> + '_ {
+    bytes
+        .chunks_exact(2)
+        .enumerate()
+        .map(move |(i, pair)| (base + 2 * i, u16::from_be_bytes([pair[0], pair[1]])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble_keypad_rom() {
+        let listing = disassemble(include_bytes!("../../roms/keypad.ch8"), None);
+        assert_eq!(
+            listing,
+            "\
+L200:
+0x200  F00A  LD V0, K
+0x202  00E0  CLS
+0x204  F029  LD F, V0
+0x206  611C  LD V1, 0x1C
+0x208  620D  LD V2, 0x0D
+0x20A  D125  DRW V1, V2, 0x5
+0x20C  6304  LD V3, 0x04
+0x20E  F318  LD ST, V3
+0x210  1200  JP L200
+"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_range_and_odd_length() {
+        let rom = [0x22, 0x04, 0x00, 0xFD, 0x00, 0xEE, 0xAB];
+        assert_eq!(
+            disassemble(&rom, Some(0x204..0x208)),
+            "L204:\n0x204  00EE  RET\n0x206  AB    DB 0xAB\n"
+        );
+        assert_eq!(
+            disassemble(&[0x81, 0x2A], None),
+            "0x200  812A  DB 0x812A  ; unknown 8xxx opcode\n"
+        );
+        assert!(disassemble(&rom, None).starts_with("0x200  2204  CALL L204\n"));
+    }
+
+    #[test]
+    fn test_disassemble_memory() {
+        let mut memory = vec![0; 0x1000];
+        memory[0x300..0x306].copy_from_slice(&[0x63, 0x04, 0x13, 0x00, 0x00, 0xE0]);
+        assert_eq!(
+            disassemble_memory(&memory, 0x300, 2),
+            "L300:\n0x300  6304  LD V3, 0x04\n0x302  1300  JP L300\n"
+        );
+        assert_eq!(disassemble_memory(&memory, 0xFFE, 10).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("range:0x200-0x400"), Ok(0x200..0x400));
+        assert_eq!(parse_range("range:200-20A"), Ok(0x200..0x20A));
+        assert!(parse_range("0x200-0x400").is_err());
+        assert!(parse_range("range:0x400-0x200").is_err());
+    }
+}
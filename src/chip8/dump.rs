@@ -0,0 +1,163 @@
+//! A plain-text dump of the whole machine for bug reports: registers,
+//! stack, keys, the code around PC and a thumbnail of the screen. Written
+//! by the TUI's dump key and when the emulator crashes.
+
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+
+use crate::chip8::cpu::Chip8;
+use crate::chip8::disasm;
+
+/// Instructions listed before PC; the listing is 8 long.
+const CODE_BEFORE: usize = 3;
+const CODE_LEN: usize = 8;
+
+/// Size of the screen thumbnail. High-res screens are shrunk to fit, a
+/// cell lit when any of its four pixels is.
+const THUMB_WIDTH: usize = 64;
+const THUMB_HEIGHT: usize = 32;
+
+impl Chip8 {
+    /// The machine state as text, in a layout that stays the same from
+    /// version to version so dumps can be compared:
+    ///
+    /// ```text
+    /// PC=0x20a I=0x000 SP=1 DT=3 ST=0 RES=Low
+    /// V0=00 V1=01 V2=02 V3=00
+    /// ...
+    /// stack: 0x202
+    /// keys: 0x0402
+    ///
+    ///    0x208  A000  LD I, 0x000
+    /// -> 0x20A  D125  DRW V1, V2, 0x5
+    /// ...
+    ///
+    /// .####...
+    /// ```
+    pub fn dump(&self) -> String {
+        let mut out = self.dump_summary();
+        out.push('\n');
+        let scale = self.display_dimensions().0 / THUMB_WIDTH;
+        for y in 0..THUMB_HEIGHT {
+            for x in 0..THUMB_WIDTH {
+                let lit = (0..scale * scale).any(|n| {
+                    let (px, py) = (x * scale + n % scale, y * scale + n / scale);
+                    self.pixel(px, py)
+                });
+                out.push(if lit { '#' } else { '.' });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// [`dump`](Self::dump) without the screen, short enough to show over
+    /// it.
+    pub fn dump_summary(&self) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "PC={:#05x} I={:#05x} SP={} DT={} ST={} RES={:?}",
+            self.pc,
+            self.i,
+            self.sp,
+            self.dt.load(Ordering::Acquire),
+            self.st.load(Ordering::Acquire),
+            self.resolution,
+        )
+        .unwrap();
+        for (row, regs) in self.v.chunks(4).enumerate() {
+            let line: Vec<String> = regs
+                .iter()
+                .enumerate()
+                .map(|(n, v)| format!("V{:X}={v:02X}", row * 4 + n))
+                .collect();
+            writeln!(out, "{}", line.join(" ")).unwrap();
+        }
+        let stack: Vec<String> = self.stack[..self.sp.min(self.stack.len())]
+            .iter()
+            .map(|addr| format!("{addr:#05x}"))
+            .collect();
+        match stack.is_empty() {
+            true => writeln!(out, "stack: empty").unwrap(),
+            false => writeln!(out, "stack: {}", stack.join(" ")).unwrap(),
+        }
+        writeln!(out, "keys: {:#06x}", self.keys_bitmask()).unwrap();
+
+        out.push('\n');
+        let start = self.pc.saturating_sub(2 * CODE_BEFORE);
+        let here = format!("{:#05X} ", self.pc);
+        for line in disasm::disassemble_memory(&self.memory, start, CODE_LEN).lines() {
+            let marker = if line.starts_with(&here) { "->" } else { "  " };
+            writeln!(out, "{marker} {line}").unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::cpu::Resolution;
+
+    /// A machine part way through a small program: called from 0x200,
+    /// drawing a 0 at (1, 2) with keys 1 and A held down.
+    fn machine() -> Chip8 {
+        let mut chip = Chip8::new();
+        let program = [
+            0x22, 0x04, // CALL 0x204
+            0x12, 0x02, // JP 0x202
+            0x61, 0x01, // LD V1, 0x01
+            0x62, 0x02, // LD V2, 0x02
+            0xA0, 0x00, // LD I, 0x000
+            0xD1, 0x25, // DRW V1, V2, 0x5
+            0x00, 0xEE, // RET
+        ];
+        chip.memory[0x200..0x200 + program.len()].copy_from_slice(&program);
+        chip.pc = 0x20A;
+        chip.stack[0] = 0x202;
+        chip.sp = 1;
+        chip.v[1] = 0x01;
+        chip.v[2] = 0x02;
+        chip.v[0xF] = 0x01;
+        chip.dt.store(3, Ordering::Release);
+        chip.press_key(0x1);
+        chip.press_key(0xA);
+        for (row, &byte) in [0xF0u8, 0x90, 0x90, 0x90, 0xF0].iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) != 0 {
+                    chip.screen.set_pixel(1 + bit, 2 + row, true);
+                }
+            }
+        }
+        chip
+    }
+
+    #[test]
+    fn test_dump_golden() {
+        assert_eq!(
+            machine().dump(),
+            include_str!("../../tests/fixtures/chip-dump.txt")
+        );
+    }
+
+    #[test]
+    fn test_dump_high_res_thumbnail() {
+        let mut chip = Chip8::new();
+        chip.resolution = Resolution::High;
+        chip.screen.set_pixel(127, 63, true);
+        let dump = chip.dump();
+        let screen: Vec<&str> = dump.lines().rev().take(THUMB_HEIGHT).collect();
+        assert_eq!(screen[0], format!("{}#", ".".repeat(THUMB_WIDTH - 1)));
+        assert!(screen[1..].iter().all(|row| !row.contains('#')));
+    }
+
+    #[test]
+    fn test_dump_summary_is_dump_without_screen() {
+        let chip = machine();
+        let summary = chip.dump_summary();
+        assert!(chip.dump().starts_with(&format!("{summary}\n")));
+        assert_eq!(summary.lines().count(), 16);
+        assert!(summary.ends_with("SYS 0x000\n"), "{summary}");
+    }
+}
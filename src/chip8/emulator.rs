@@ -0,0 +1,1542 @@
+//! A frontend-agnostic driver for [`Chip8`].
+//!
+//! [`Emulator`] owns the machine, its timers and a queue of pending key
+//! events, and advances everything one 60 Hz frame at a time. Frontends only
+//! have to translate their input into [`KeyEvent`]s, call
+//! [`Emulator::run_frame`] on a schedule and present [`Emulator::snapshot`].
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{bail, Result};
+
+use crate::chip8::call_history::CallHistory;
+use crate::chip8::consts::{COSMAC_FRAME_CYCLES, PLANES, PROGRAM_START, RAM_SIZE, STACK_DEPTH};
+use crate::chip8::cpu::{CallStack, Chip8, ExecError, ProtectPolicy, Resolution, Screen};
+use crate::chip8::decode::decode;
+use crate::chip8::latency::LatencyHistogram;
+use crate::chip8::mem::{Memory, MemoryFill};
+use crate::chip8::op::{cycle_cost, ChipOp};
+use crate::chip8::quirks::Quirks;
+use crate::chip8::summary::StateSummary;
+use crate::chip8::timers;
+use crate::utils::hash::Fnv1a;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmulatorConfig {
+    /// Instructions executed per frame.
+    pub cycles_per_frame: u64,
+    /// Instructions per wall-clock second. When set it replaces
+    /// `cycles_per_frame`, spread over the frames with fractions carried
+    /// from one to the next.
+    pub cpu_hz: Option<u64>,
+    /// Frames per second; timers tick once per frame.
+    pub frame_hz: u64,
+    pub quirks: Quirks,
+    /// Seed for RND; `None` seeds from the system clock.
+    pub seed: Option<u64>,
+    /// Emulation speed relative to real time. Scales the instructions and
+    /// timer ticks per frame together, while frames are still presented at
+    /// `frame_hz`.
+    pub speed: f64,
+    pub beep_style: BeepStyle,
+    /// Power-on contents of RAM outside the font and ROM.
+    pub fill: MemoryFill,
+    /// Bytes of RAM, which also bounds the ROM size.
+    pub ram_size: usize,
+    /// Address the hex font is loaded at. Some interpreters use 0x50.
+    pub font_base: usize,
+    /// Deepest CALL nesting before a stack overflow. Octo output can need
+    /// more than the original 16.
+    pub stack_depth: usize,
+    /// Halt when the program jumps to the jump itself, the way many games
+    /// end, instead of spinning there forever.
+    pub halt_on_infinite_loop: bool,
+    /// CALLs and RETs to remember for the debug view; zero records none.
+    pub call_history: usize,
+    /// Instructions a key stays down before a queued release takes effect,
+    /// so a tap shorter than that is still seen by programs polling SKP.
+    pub min_key_hold: u64,
+    /// Power on in high-res, for SUPER-CHIP ROMs that draw at 128x64
+    /// without executing HIGH (00FF) first.
+    pub start_high_res: bool,
+    /// Time each key event from being queued to reaching the keypad, into
+    /// [`Emulator::input_latency`]. Off, events aren't timed at all.
+    pub input_latency: bool,
+    /// Read DT as it was at the start of the frame, as real interpreters
+    /// see it, even if something else ticks the shared timer mid-frame.
+    /// See [`Chip8::latched_dt`].
+    pub latch_timers: bool,
+    /// Step over unknown opcodes instead of faulting; see
+    /// [`Chip8::skip_unknown`].
+    pub skip_unknown: bool,
+    /// Keep stores off the font and interpreter area; see
+    /// [`Chip8::protect_low_mem`].
+    pub protect_low_mem: Option<ProtectPolicy>,
+    /// Fault on `0NNN` machine code calls; see [`Chip8::strict_sys`].
+    pub strict_sys: bool,
+    /// Pace the program by COSMAC VIP machine cycles instead of a fixed
+    /// instruction count; see [`TimingModel::CosmacCycles`].
+    pub cosmac_timing: bool,
+}
+
+/// Default [`EmulatorConfig::min_key_hold`]: a frame at the default speed.
+pub const MIN_KEY_HOLD: u64 = 12;
+
+impl Default for EmulatorConfig {
+    fn default() -> Self {
+        Self {
+            cycles_per_frame: 12,
+            cpu_hz: None,
+            frame_hz: 60,
+            quirks: Quirks::default(),
+            seed: None,
+            speed: 1.0,
+            beep_style: BeepStyle::default(),
+            fill: MemoryFill::default(),
+            ram_size: RAM_SIZE,
+            font_base: 0,
+            stack_depth: STACK_DEPTH,
+            halt_on_infinite_loop: false,
+            call_history: 0,
+            min_key_hold: MIN_KEY_HOLD,
+            start_high_res: false,
+            input_latency: false,
+            latch_timers: true,
+            skip_unknown: false,
+            protect_low_mem: None,
+            strict_sys: false,
+            cosmac_timing: false,
+        }
+    }
+}
+
+/// How much of the program [`Emulator::run_frame`] runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimingModel {
+    /// The same number of instructions every frame, whatever they are.
+    InstructionsPerFrame(f64),
+    /// Instructions until the frame's [`COSMAC_FRAME_CYCLES`] are spent,
+    /// each costing its [`cycle_cost`]. A frame of DRWs runs far fewer
+    /// instructions than a frame of LDs, so games pace themselves as they
+    /// did on the VIP. An instruction that overruns the budget borrows the
+    /// excess from the next frame.
+    CosmacCycles,
+}
+
+impl EmulatorConfig {
+    /// Instructions in an average frame at normal speed.
+    pub fn instructions_per_frame(&self) -> f64 {
+        match self.cpu_hz {
+            Some(hz) => hz as f64 / self.frame_hz.max(1) as f64,
+            None => self.cycles_per_frame as f64,
+        }
+    }
+
+    pub fn timing(&self) -> TimingModel {
+        if self.cosmac_timing {
+            TimingModel::CosmacCycles
+        } else {
+            TimingModel::InstructionsPerFrame(self.instructions_per_frame())
+        }
+    }
+}
+
+/// Parse an [`EmulatorConfig::speed`], any finite number above 0.
+pub fn parse_speed(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        _ => Err(format!("invalid speed {s:?}: expected a number above 0")),
+    }
+}
+
+/// How the sound timer drives the beeper.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BeepStyle {
+    /// Tone for as long as ST is nonzero.
+    #[default]
+    Sustained,
+    /// A one-frame blip each time ST is loaded, ignoring its duration.
+    Click,
+}
+
+impl FromStr for BeepStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sustained" => Ok(BeepStyle::Sustained),
+            "click" => Ok(BeepStyle::Click),
+            _ => Err(format!(
+                "invalid beep style {s:?}: expected sustained or click"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+    Down(u8),
+    Up(u8),
+}
+
+/// What happened during a call to [`Emulator::run_frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameResult {
+    /// Incremented every frame the screen contents change.
+    pub screen_version: u64,
+    /// Whether the beeper should sound for this frame, per the configured
+    /// [`BeepStyle`].
+    pub beep: bool,
+    /// Times the program loaded ST with a nonzero value this frame. Under
+    /// [`BeepStyle::Click`] each one is a click of its own.
+    pub sound_loads: u32,
+    /// The program stopped, see [`Emulator::halt`]; further frames do
+    /// nothing.
+    pub halted: bool,
+}
+
+/// Why a program stopped running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    /// It executed EXIT.
+    Exit,
+    /// Execution fell through to the end of the ROM, where there is no
+    /// complete instruction left, e.g. because the ROM is shorter than two
+    /// bytes. A jump there isn't one.
+    EndOfRom { pc: usize },
+    /// The program reached the word 0x0000, which padding and unwritten
+    /// RAM hold, rather than an instruction.
+    Unprogrammed { pc: usize },
+    /// A `JP` to its own address, with
+    /// [`EmulatorConfig::halt_on_infinite_loop`] set.
+    InfiniteLoop { pc: usize },
+    /// The machine panicked with PC at `pc`, see [`Emulator::fault`].
+    Fault { pc: usize },
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Halt::Exit => write!(f, "the program executed EXIT"),
+            Halt::EndOfRom { pc } => {
+                write!(f, "the program ran past the end of the ROM at {pc:#05x}")
+            }
+            Halt::Unprogrammed { pc } => {
+                write!(
+                    f,
+                    "the program reached unprogrammed memory (0x0000) at {pc:#05x}"
+                )
+            }
+            Halt::InfiniteLoop { pc } => {
+                write!(f, "the program jumped to itself at {pc:#05x}")
+            }
+            Halt::Fault { pc } => write!(f, "the program faulted at {pc:#05x}"),
+        }
+    }
+}
+
+/// The message a panic was raised with, as `panic!` formats it.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "the emulator panicked".to_string(),
+    }
+}
+
+/// Everything a frontend needs to draw a frame. `chip` shares the live
+/// machine's timers, see [`Chip8::snapshot`].
+#[derive(Clone, Default)]
+pub struct RenderSnapshot {
+    pub chip: Chip8,
+    pub frame: u64,
+    pub screen_version: u64,
+    pub call_history: CallHistory,
+}
+
+impl RenderSnapshot {
+    /// Stable hash of the framebuffer and registers, for comparing the end
+    /// state of two runs.
+    pub fn state_hash(&self) -> u64 {
+        let c8 = &self.chip;
+        let mut h = Fnv1a::new();
+        h.write(&c8.screen_hash().to_le_bytes());
+        h.write(&c8.v);
+        for n in [c8.i, c8.pc, c8.sp] {
+            h.write_usize(n);
+        }
+        for &n in c8.stack.iter() {
+            h.write_usize(n);
+        }
+        h.write(&[c8.dt.load(Ordering::Acquire), c8.st.load(Ordering::Acquire)]);
+        h.finish()
+    }
+}
+
+pub struct Emulator {
+    chip: Chip8,
+    config: EmulatorConfig,
+    /// Events for the next frame, with the instruction each is for if the
+    /// frontend gave one, and when each was queued if timing input.
+    input: VecDeque<(Option<u64>, KeyEvent, Option<Instant>)>,
+    /// Key events waiting for their instruction, as the value of
+    /// `executed` they're due at, earliest first.
+    pending: VecDeque<(u64, KeyEvent, Option<Instant>)>,
+    /// Per key, when its last scheduled event is due, and when it was
+    /// last pressed if a release hasn't been scheduled since.
+    key_due: [u64; 16],
+    pressed_at: [Option<u64>; 16],
+    frame: u64,
+    executed: u64,
+    /// One past the last byte of the loaded ROM.
+    rom_end: usize,
+    /// Whether PC got where it is by stepping past instructions rather
+    /// than by a jump, call or return. Only then is reaching `rom_end`
+    /// running off the end of the ROM.
+    fell_through: bool,
+    halt: Option<Halt>,
+    /// The panic message behind a [`Halt::Fault`].
+    fault: Option<String>,
+    call_history: CallHistory,
+    screen_version: u64,
+    last_screen: Screen,
+    last_extra_planes: [Screen; PLANES - 1],
+    cycle_carry: f64,
+    timer_carry: f64,
+    /// COSMAC cycles the last frame's final instruction ran past its
+    /// budget, owed by the next frame.
+    cycle_overrun: u64,
+    /// Instructions the last frame ran, to spread input over under
+    /// [`TimingModel::CosmacCycles`].
+    last_frame_executed: u64,
+    input_latency: Option<LatencyHistogram>,
+    speed_toggles: SpeedToggles,
+}
+
+/// Speed while turbo is held, relative to [`EmulatorConfig::speed`].
+pub const TURBO_RATIO: f64 = 8.0;
+/// Speed while slow motion is on, relative to [`EmulatorConfig::speed`].
+pub const SLOW_MOTION_RATIO: f64 = 0.25;
+
+/// A change to the turbo or slow-motion toggle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedChange {
+    TurboOn,
+    TurboOff,
+    /// Turbo for terminals that can't report Tab being let go.
+    ToggleTurbo,
+    ToggleSlowMotion,
+}
+
+/// Temporary speed-ups and slow-downs on top of
+/// [`EmulatorConfig::speed`]. Turbo wins while it's held, and slow motion
+/// resumes when it's let go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpeedToggles {
+    pub turbo: bool,
+    pub slow_motion: bool,
+}
+
+impl SpeedToggles {
+    pub fn apply(&mut self, change: SpeedChange) {
+        match change {
+            SpeedChange::TurboOn => self.turbo = true,
+            SpeedChange::TurboOff => self.turbo = false,
+            SpeedChange::ToggleTurbo => self.turbo ^= true,
+            SpeedChange::ToggleSlowMotion => self.slow_motion ^= true,
+        }
+    }
+
+    /// The multiplier on the configured speed. Always exactly one of
+    /// three constants, so letting go of turbo restores the speed exactly.
+    pub fn ratio(&self) -> f64 {
+        match (self.turbo, self.slow_motion) {
+            (true, _) => TURBO_RATIO,
+            (false, true) => SLOW_MOTION_RATIO,
+            (false, false) => 1.0,
+        }
+    }
+
+    /// Text for the status bar, when not at normal speed.
+    pub fn label(&self) -> Option<String> {
+        match (self.turbo, self.slow_motion) {
+            (true, _) => Some(format!("turbo {TURBO_RATIO}x")),
+            (false, true) => Some(format!("slow {SLOW_MOTION_RATIO}x")),
+            (false, false) => None,
+        }
+    }
+}
+
+/// Whole units of `per_frame * speed` due this frame, carrying the
+/// fractional remainder into later frames so slow speeds still average out.
+pub fn speed_scaled(per_frame: f64, speed: f64, carry: &mut f64) -> u64 {
+    *carry += per_frame * speed;
+    let whole = carry.floor();
+    *carry -= whole;
+    whole as u64
+}
+
+impl Emulator {
+    pub fn new(config: EmulatorConfig) -> Self {
+        let mut chip = Chip8::new();
+        chip.memory = Memory::new(config.ram_size);
+        chip.memory.fill(config.fill);
+        chip.stack = CallStack::new(config.stack_depth);
+        chip.quirks = config.quirks;
+        if let Some(seed) = config.seed {
+            chip.seed_rng(seed);
+        }
+        chip.font_base = config.font_base;
+        chip.skip_unknown = config.skip_unknown;
+        chip.protect_low_mem = config.protect_low_mem;
+        chip.strict_sys = config.strict_sys;
+        chip.load_font();
+        if config.start_high_res {
+            chip.resolution = Resolution::High;
+        }
+        let last_screen = chip.screen.clone();
+        let last_extra_planes = chip.extra_planes.clone();
+        Self {
+            chip,
+            config,
+            input: VecDeque::new(),
+            pending: VecDeque::new(),
+            key_due: [0; 16],
+            pressed_at: [None; 16],
+            frame: 0,
+            executed: 0,
+            rom_end: PROGRAM_START,
+            fell_through: true,
+            halt: None,
+            fault: None,
+            call_history: CallHistory::new(config.call_history),
+            screen_version: 0,
+            last_screen,
+            last_extra_planes,
+            cycle_carry: 0.0,
+            timer_carry: 0.0,
+            cycle_overrun: 0,
+            last_frame_executed: 0,
+            input_latency: config.input_latency.then(LatencyHistogram::new),
+            speed_toggles: SpeedToggles::default(),
+        }
+    }
+
+    /// Return the machine to its power-on state, keeping the configuration,
+    /// input latencies and speed toggles.
+    pub fn reset(&mut self) {
+        let input_latency = self.input_latency.take();
+        let speed_toggles = self.speed_toggles;
+        *self = Self::new(self.config);
+        self.input_latency = input_latency;
+        self.speed_toggles = speed_toggles;
+    }
+
+    /// Copy `rom` into memory at the program start address.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<()> {
+        let limit = self.max_rom_size();
+        if rom.len() > limit {
+            log_event!(warn, size = rom.len(), "rom too large");
+            bail!("Rom too large: {} bytes (limit {limit})", rom.len());
+        }
+        log_event!(info, size = rom.len(), "rom loaded");
+        self.chip.memory[PROGRAM_START..PROGRAM_START + rom.len()].copy_from_slice(rom);
+        self.rom_end = PROGRAM_START + rom.len();
+        Ok(())
+    }
+
+    /// [`reset`](Self::reset) and load `rom` in place of the current
+    /// program. A ROM that doesn't fit leaves the machine as it was.
+    pub fn switch_rom(&mut self, rom: &[u8]) -> Result<()> {
+        let limit = self.max_rom_size();
+        if rom.len() > limit {
+            bail!("Rom too large: {} bytes (limit {limit})", rom.len());
+        }
+        self.reset();
+        self.load_rom(rom)
+    }
+
+    /// Execute one instruction unless the program has stopped. Falling
+    /// through to the end of the ROM stops it rather than running whatever
+    /// follows; jumping there runs it.
+    ///
+    /// # Panics
+    ///
+    /// On any [`ExecError`]; see [`Emulator::try_step_instruction`].
+    pub fn step_instruction(&mut self) {
+        if let Err(err) = self.try_step_instruction() {
+            panic!("{err}");
+        }
+    }
+
+    /// [`step_instruction`](Self::step_instruction), but a fault halts
+    /// the program with [`Halt::Fault`] and is returned instead of
+    /// panicking, for debuggers that report it and carry on.
+    pub fn try_step_instruction(&mut self) -> Result<(), ExecError> {
+        if self.chip.exit {
+            self.halt.get_or_insert(Halt::Exit);
+            return Ok(());
+        }
+        let pc = self.chip.pc;
+        if self.fell_through && (self.rom_end.saturating_sub(1)..=self.rom_end).contains(&pc) {
+            log_event!(
+                warn,
+                pc = %format_args!("{pc:#05x}"),
+                rom_end = %format_args!("{:#05x}", self.rom_end),
+                "program ran past the end of the rom"
+            );
+            self.chip.exit = true;
+            self.halt = Some(Halt::EndOfRom { pc });
+            return Ok(());
+        }
+        if self.config.halt_on_infinite_loop && self.jumps_to_itself(pc) {
+            log_event!(info, pc = %format_args!("{pc:#05x}"), "program jumped to itself");
+            self.chip.exit = true;
+            self.halt = Some(Halt::InfiniteLoop { pc });
+            return Ok(());
+        }
+        let op = self.op_at(pc);
+        match self.chip.try_step() {
+            Ok(()) => {}
+            Err(ExecError::Unprogrammed { pc }) => {
+                log_event!(warn, pc = %format_args!("{pc:#05x}"), "program reached 0x0000");
+                self.chip.exit = true;
+                self.halt = Some(Halt::Unprogrammed { pc });
+                return Ok(());
+            }
+            Err(err) => {
+                self.chip.exit = true;
+                self.halt = Some(Halt::Fault { pc: self.chip.pc });
+                self.fault = Some(err.to_string());
+                return Err(err);
+            }
+        }
+        self.fell_through = !matches!(
+            op,
+            Some(
+                ChipOp::JpNnn { .. }
+                    | ChipOp::CallNnn { .. }
+                    | ChipOp::Ret
+                    | ChipOp::JpV0Nnn { .. }
+            )
+        );
+        if let Some(op) = op.filter(|_| self.call_history.enabled()) {
+            self.call_history.observe(op, pc, self.chip.pc);
+        }
+        if self.chip.exit {
+            self.halt = Some(Halt::Exit);
+        }
+        Ok(())
+    }
+
+    /// The instruction at `pc`, if it's in RAM.
+    fn op_at(&self, pc: usize) -> Option<ChipOp> {
+        match self.chip.memory.slice(pc, 2) {
+            Ok(&[b, s]) => Some(decode(u16::from_be_bytes([b, s]))),
+            _ => None,
+        }
+    }
+
+    /// Whether the instruction at `pc` is `JP pc`.
+    fn jumps_to_itself(&self, pc: usize) -> bool {
+        self.op_at(pc) == Some(ChipOp::JpNnn { nnn: pc })
+    }
+
+    /// Recent CALLs and RETs, if [`EmulatorConfig::call_history`] is set.
+    pub fn call_history(&self) -> &CallHistory {
+        &self.call_history
+    }
+
+    /// Why the program stopped, once it has.
+    pub fn halt(&self) -> Option<Halt> {
+        self.halt
+    }
+
+    /// What the machine panicked with, once [`run_frame_caught`] has
+    /// caught a panic.
+    ///
+    /// [`run_frame_caught`]: Self::run_frame_caught
+    pub fn fault(&self) -> Option<&str> {
+        self.fault.as_deref()
+    }
+
+    /// Largest ROM that fits between the program start and the end of RAM.
+    pub fn max_rom_size(&self) -> usize {
+        self.chip.memory.len() - PROGRAM_START
+    }
+
+    pub fn chip(&self) -> &Chip8 {
+        &self.chip
+    }
+
+    pub fn chip_mut(&mut self) -> &mut Chip8 {
+        &mut self.chip
+    }
+
+    pub fn config(&self) -> &EmulatorConfig {
+        &self.config
+    }
+
+    /// Run `cycles` instructions per frame, replacing any `cpu_hz`.
+    pub fn set_cycles_per_frame(&mut self, cycles: u64) {
+        self.config.cycles_per_frame = cycles;
+        self.config.cpu_hz = None;
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.config.speed = speed;
+    }
+
+    /// Turn turbo or slow motion on or off from the next frame. The
+    /// fraction of an instruction carried between frames is kept, so the
+    /// change causes no burst or pause.
+    pub fn change_speed(&mut self, change: SpeedChange) {
+        self.speed_toggles.apply(change);
+    }
+
+    pub fn speed_toggles(&self) -> SpeedToggles {
+        self.speed_toggles
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.config.quirks = quirks;
+        self.chip.quirks = quirks;
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Instructions [`Emulator::run_frame`] has stepped so far.
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    /// Wall-clock time one frame should take.
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_nanos(1_000_000_000 / self.config.frame_hz.max(1))
+    }
+
+    /// Queue a key event to be applied during the next [`run_frame`](Self::run_frame).
+    pub fn queue_key_event(&mut self, event: KeyEvent) {
+        self.input.push_back((None, event, self.queued_at()));
+    }
+
+    /// Queue a key event for just before instruction `at` of the next
+    /// frame, counting from 0. Events past the frame's last instruction
+    /// apply at its end.
+    pub fn queue_key_event_at(&mut self, event: KeyEvent, at: u64) {
+        self.input.push_back((Some(at), event, self.queued_at()));
+    }
+
+    /// The time to tag a newly queued event with, if timing input.
+    fn queued_at(&self) -> Option<Instant> {
+        self.input_latency.as_ref().map(|_| Instant::now())
+    }
+
+    /// How long key events waited between being queued and reaching the
+    /// keypad, with [`EmulatorConfig::input_latency`] set.
+    pub fn input_latency(&self) -> Option<&LatencyHistogram> {
+        self.input_latency.as_ref()
+    }
+
+    /// Run one frame: execute the cycle budget, applying queued input at
+    /// the instruction given or else at evenly spaced points within it,
+    /// then tick the timers once (both scaled by the configured speed and
+    /// any [`SpeedToggles`]).
+    ///
+    /// A release waits until its key has been down for
+    /// [`EmulatorConfig::min_key_hold`] instructions, carrying over into
+    /// later frames if need be, so a press and release queued in the same
+    /// frame are both observed by the program.
+    pub fn run_frame(&mut self) -> FrameResult {
+        self.chip.sound_loads = 0;
+        if !self.chip.exit {
+            self.chip.latched_dt = self
+                .config
+                .latch_timers
+                .then(|| self.chip.dt.load(Ordering::Acquire));
+            let speed = self.config.speed * self.speed_toggles.ratio();
+            // The instructions to run, or under COSMAC timing an estimate
+            // of them to spread input over, and the cycles to spend.
+            let (budget, cycles) = match self.config.timing() {
+                TimingModel::InstructionsPerFrame(n) => {
+                    (speed_scaled(n, speed, &mut self.cycle_carry), None)
+                }
+                TimingModel::CosmacCycles => {
+                    let cycles =
+                        speed_scaled(COSMAC_FRAME_CYCLES.into(), speed, &mut self.cycle_carry);
+                    (self.last_frame_executed, Some(cycles))
+                }
+            };
+            let events: Vec<_> = self.input.drain(..).collect();
+            let untimed = events.iter().filter(|(at, _, _)| at.is_none()).count();
+            let slices = untimed.max(1) as u64;
+            let start = self.executed;
+            let mut slice = 0;
+            for (at, event, queued) in events {
+                let offset = at.unwrap_or_else(|| {
+                    slice += 1;
+                    budget * (slice - 1) / slices
+                });
+                self.schedule(start + offset.min(budget), event, queued);
+            }
+            let end = start + budget;
+            let mut spent = self.cycle_overrun;
+            while !self.chip.exit {
+                match cycles {
+                    Some(cycles) if spent >= cycles => break,
+                    None if self.executed >= end => break,
+                    _ => {}
+                }
+                self.apply_due();
+                if cycles.is_some() {
+                    spent += self
+                        .op_at(self.chip.pc)
+                        .map_or(0, |op| cycle_cost(&op).into());
+                }
+                self.step_instruction();
+                self.executed += 1;
+            }
+            if let Some(cycles) = cycles {
+                self.cycle_overrun = spent.saturating_sub(cycles);
+                self.last_frame_executed = self.executed - start;
+            }
+            self.apply_due();
+            self.chip.latched_dt = None;
+            for _ in 0..speed_scaled(1.0, speed, &mut self.timer_carry) {
+                timers::tick(&self.chip.dt);
+                timers::tick(&self.chip.st);
+            }
+            self.frame += 1;
+        }
+
+        if self.chip.screen != self.last_screen || self.chip.extra_planes != self.last_extra_planes
+        {
+            self.last_screen.clone_from(&self.chip.screen);
+            self.last_extra_planes.clone_from(&self.chip.extra_planes);
+            self.screen_version += 1;
+        }
+
+        FrameResult {
+            screen_version: self.screen_version,
+            beep: match self.config.beep_style {
+                BeepStyle::Sustained => self.chip.st.load(Ordering::Acquire) > 0,
+                BeepStyle::Click => self.chip.sound_loads > 0,
+            },
+            sound_loads: self.chip.sound_loads,
+            halted: self.chip.exit,
+        }
+    }
+
+    /// [`run_frame`](Self::run_frame), but a panic from the machine (a
+    /// fault, or a bug) halts it with [`Halt::Fault`] rather than
+    /// unwinding any further.
+    ///
+    /// The machine may have stopped half way through an instruction, so
+    /// it is left exactly as the panic found it and never runs again:
+    /// later frames only present it, for inspecting, dumping or saving.
+    pub fn run_frame_caught(&mut self) -> FrameResult {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.run_frame())) {
+            Ok(result) => result,
+            Err(payload) => {
+                let message = panic_message(payload.as_ref());
+                log_event!(error, frame = self.frame, %message, "machine panicked");
+                self.chip.exit = true;
+                self.chip.latched_dt = None;
+                self.halt = Some(Halt::Fault { pc: self.chip.pc });
+                self.fault = Some(message);
+                self.run_frame()
+            }
+        }
+    }
+
+    /// The machine summary for `--dump-state`, with this run's counts.
+    pub fn state_summary(&self) -> StateSummary {
+        StateSummary {
+            frame: self.frame,
+            executed: self.executed,
+            ..self.chip.state_summary()
+        }
+    }
+
+    pub fn snapshot(&self) -> RenderSnapshot {
+        RenderSnapshot {
+            chip: self.chip.snapshot(),
+            frame: self.frame,
+            screen_version: self.screen_version,
+            call_history: self.call_history.clone(),
+        }
+    }
+
+    /// Queue `event` for when `executed` reaches `at`, or later if a
+    /// release would cut its key's press short or an earlier event for the
+    /// same key is due after `at`.
+    fn schedule(&mut self, at: u64, event: KeyEvent, queued: Option<Instant>) {
+        let key = match event {
+            KeyEvent::Down(key) | KeyEvent::Up(key) => usize::from(key & 0xF),
+        };
+        let mut due = at.max(self.key_due[key]);
+        match event {
+            KeyEvent::Down(_) => self.pressed_at[key] = Some(due),
+            KeyEvent::Up(_) => {
+                if let Some(pressed) = self.pressed_at[key].take() {
+                    due = due.max(pressed + self.config.min_key_hold);
+                }
+            }
+        }
+        self.key_due[key] = due;
+        let index = self.pending.partition_point(|&(t, _, _)| t <= due);
+        self.pending.insert(index, (due, event, queued));
+    }
+
+    /// Apply the scheduled events that are due.
+    fn apply_due(&mut self) {
+        while let Some(&(due, event, queued)) = self.pending.front() {
+            if due > self.executed {
+                break;
+            }
+            self.pending.pop_front();
+            self.apply(event);
+            if let (Some(histogram), Some(queued)) = (&mut self.input_latency, queued) {
+                histogram.record(queued.elapsed());
+            }
+        }
+    }
+
+    fn apply(&mut self, event: KeyEvent) {
+        match event {
+            KeyEvent::Down(key) => self.chip.press_key(key),
+            KeyEvent::Up(key) => self.chip.release_key(key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wait for key 5 with SKNP, then draw the "0" glyph and set ST.
+    const ROM: [u8; 14] = [
+        0x61, 0x05, // LD V1, 0x05
+        0xE1, 0xA1, // SKNP V1
+        0x12, 0x08, // JP 0x208
+        0x12, 0x02, // JP 0x202
+        0xD0, 0x05, // DRW V0, V0, 5
+        0xF1, 0x18, // LD ST, V1
+        0x12, 0x0C, // JP 0x20C
+    ];
+
+    fn emulator() -> Emulator {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&ROM).unwrap();
+        emu
+    }
+
+    #[test]
+    fn test_run_frame_idle_until_key() {
+        let mut emu = emulator();
+        for _ in 0..3 {
+            let result = emu.run_frame();
+            assert_eq!(result.screen_version, 0);
+            assert!(!result.beep);
+        }
+        assert_eq!(emu.frame(), 3);
+    }
+
+    #[test]
+    fn test_run_frame_ticks_each_timer_once() {
+        let mut emu = emulator();
+        emu.chip().dt.store(10, Ordering::Release);
+        emu.chip().st.store(4, Ordering::Release);
+        let timers = |emu: &Emulator| {
+            let chip = emu.chip();
+            (
+                chip.dt.load(Ordering::Acquire),
+                chip.st.load(Ordering::Acquire),
+            )
+        };
+        for _ in 0..4 {
+            emu.run_frame();
+        }
+        assert_eq!(timers(&emu), (6, 0));
+        for _ in 0..5 {
+            emu.run_frame();
+        }
+        assert_eq!(timers(&emu), (1, 0));
+        emu.run_frame();
+        assert_eq!(timers(&emu), (0, 0));
+    }
+
+    #[test]
+    fn test_run_frame_applies_queued_input() {
+        let mut emu = emulator();
+        emu.run_frame();
+        emu.queue_key_event(KeyEvent::Down(5));
+        let result = emu.run_frame();
+
+        assert_eq!(result.screen_version, 1);
+        assert!(result.beep);
+        assert_eq!(emu.chip().screen.byte(0, 0), 0xF0);
+        assert_eq!(emu.chip().pc, 0x20C);
+    }
+
+    #[cfg(feature = "xo-chip")]
+    #[test]
+    fn test_screen_version_counts_other_planes() {
+        let mut emu = emulator();
+        assert_eq!(emu.run_frame().screen_version, 0);
+        emu.chip_mut().extra_planes[0].set_pixel(3, 4, true);
+        assert_eq!(emu.run_frame().screen_version, 1);
+        assert_eq!(emu.run_frame().screen_version, 1);
+    }
+
+    #[test]
+    fn test_headless_screen_snapshot() {
+        use crate::chip8::frontend::{self, HeadlessFrontend, InputEvent};
+        use crate::chip8::snapshot::{assert_screen_eq, screen_ascii};
+
+        let mut emu = emulator();
+        let mut headless = HeadlessFrontend::new(3);
+        headless.push_input(1, InputEvent::Key(KeyEvent::Down(5)));
+        frontend::run(&mut emu, &mut headless);
+
+        let screen = screen_ascii(&headless.last_frame().unwrap().chip);
+        let corner: String = screen
+            .lines()
+            .take(6)
+            .map(|l| format!("{}\n", &l[..6]))
+            .collect();
+        let expected = "\
+####..
+#..#..
+#..#..
+#..#..
+####..
+......
+";
+        assert_screen_eq(&corner, expected);
+        assert_eq!(screen.matches('#').count(), 14);
+    }
+
+    #[test]
+    fn test_fill_spares_font_and_rom() {
+        use crate::chip8::consts::CHIP8_FONTSET;
+
+        let mut emu = Emulator::new(EmulatorConfig {
+            fill: MemoryFill::Byte(0xA5),
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&ROM).unwrap();
+        let memory = &emu.chip().memory;
+
+        assert_eq!(memory[..CHIP8_FONTSET.len()], CHIP8_FONTSET);
+        assert_eq!(memory[PROGRAM_START..PROGRAM_START + ROM.len()], ROM);
+        assert!(memory[CHIP8_FONTSET.len()..PROGRAM_START]
+            .iter()
+            .all(|&b| b == 0xA5));
+        assert!(memory[PROGRAM_START + ROM.len()..]
+            .iter()
+            .all(|&b| b == 0xA5));
+    }
+
+    #[test]
+    fn test_run_frame_press_and_release_same_frame() {
+        let mut emu = emulator();
+        emu.queue_key_event(KeyEvent::Down(5));
+        emu.queue_key_event(KeyEvent::Up(5));
+        let result = emu.run_frame();
+
+        assert_eq!(result.screen_version, 1);
+        assert!(!emu.chip().keys[5]);
+    }
+
+    #[test]
+    fn test_run_frame_holds_a_tap() {
+        // LD V1, 0x05; SKP V1; JP 0x202; LD V2, 0x01; JP 0x208
+        let rom = [0x61, 0x05, 0xE1, 0x9E, 0x12, 0x02, 0x62, 0x01, 0x12, 0x08];
+        let tap = |min_key_hold| {
+            let mut emu = Emulator::new(EmulatorConfig {
+                min_key_hold,
+                ..EmulatorConfig::default()
+            });
+            emu.load_rom(&rom).unwrap();
+            // Down before a JP and Up before the next instruction, so SKP
+            // only sees the key if the release waits.
+            emu.queue_key_event_at(KeyEvent::Down(5), 2);
+            emu.queue_key_event_at(KeyEvent::Up(5), 3);
+            emu.run_frame();
+            (emu.chip().v[2], emu.chip().keys[5])
+        };
+        assert_eq!(tap(0), (0, false));
+        assert_eq!(tap(2), (1, false));
+    }
+
+    #[test]
+    fn test_min_key_hold_spans_frames() {
+        let mut emu = Emulator::new(EmulatorConfig {
+            min_key_hold: 20,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&ROM).unwrap();
+        emu.queue_key_event(KeyEvent::Down(7));
+        emu.queue_key_event(KeyEvent::Up(7));
+        emu.run_frame();
+        assert!(emu.chip().keys[7]);
+        // A press queued meanwhile comes after the delayed release.
+        emu.queue_key_event_at(KeyEvent::Down(7), 0);
+        emu.run_frame();
+        assert!(emu.chip().keys[7]);
+        assert_eq!(emu.pending.len(), 0);
+        emu.queue_key_event(KeyEvent::Up(7));
+        emu.run_frame();
+        assert!(emu.chip().keys[7]);
+        emu.run_frame();
+        assert!(!emu.chip().keys[7]);
+    }
+
+    #[test]
+    fn test_run_frame_deterministic() {
+        let run = || {
+            let mut emu = emulator();
+            let mut results = Vec::new();
+            for frame in 0..10 {
+                if frame == 4 {
+                    emu.queue_key_event(KeyEvent::Down(5));
+                }
+                results.push(emu.run_frame());
+            }
+            (results, emu.chip().st.load(Ordering::Acquire))
+        };
+        assert_eq!(run(), run());
+        // ST was set to 5 during frame 4 and ticked once per frame since.
+        assert_eq!(run().1, 0);
+    }
+
+    #[test]
+    fn test_run_frame_halts_on_exit() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[0x00, 0xFD]).unwrap();
+        assert!(emu.run_frame().halted);
+        assert_eq!(emu.frame(), 1);
+        assert!(emu.run_frame().halted);
+        assert_eq!(emu.frame(), 1);
+        assert_eq!(emu.halt(), Some(Halt::Exit));
+    }
+
+    #[test]
+    fn test_run_frame_halts_at_end_of_rom() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[0x60]).unwrap();
+        assert!(emu.run_frame().halted);
+        assert_eq!(emu.halt(), Some(Halt::EndOfRom { pc: 0x200 }));
+        assert_eq!(emu.chip().pc, 0x200);
+        assert_eq!(
+            emu.halt().unwrap().to_string(),
+            "the program ran past the end of the ROM at 0x200"
+        );
+
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[]).unwrap();
+        assert!(emu.run_frame().halted);
+
+        // LD V0, 0x01 runs, then nothing is left.
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[0x60, 0x01]).unwrap();
+        assert!(emu.run_frame().halted);
+        assert_eq!(emu.chip().v[0], 1);
+        assert_eq!(emu.halt(), Some(Halt::EndOfRom { pc: 0x202 }));
+
+        // JP 0x202 jumps to the end on purpose, into code the ROM doesn't
+        // hold: here ADD V0, 0x70 from the fill.
+        let mut emu = Emulator::new(EmulatorConfig {
+            fill: MemoryFill::Byte(0x70),
+            cycles_per_frame: 3,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&[0x12, 0x02]).unwrap();
+        assert!(!emu.run_frame().halted);
+        assert_eq!(emu.chip().v[0], 0xE0);
+        assert_eq!(emu.halt(), None);
+    }
+
+    #[test]
+    fn test_run_frame_halts_on_zero_word() {
+        // LD V0, 0x01; then padding.
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[0x60, 0x01, 0x00, 0x00, 0x00, 0x00]).unwrap();
+        assert!(emu.run_frame().halted);
+        assert_eq!(emu.halt(), Some(Halt::Unprogrammed { pc: 0x202 }));
+        assert_eq!((emu.chip().pc, emu.chip().v[0]), (0x202, 1));
+        assert_eq!(
+            emu.halt().unwrap().to_string(),
+            "the program reached unprogrammed memory (0x0000) at 0x202"
+        );
+    }
+
+    #[test]
+    fn test_run_frame_halts_on_jump_to_self() {
+        // LD V0, 0x01; JP 0x202
+        let rom = [0x60, 0x01, 0x12, 0x02];
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&rom).unwrap();
+        assert!(!emu.run_frame().halted);
+        assert_eq!(emu.halt(), None);
+
+        let mut emu = Emulator::new(EmulatorConfig {
+            halt_on_infinite_loop: true,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&rom).unwrap();
+        assert!(emu.run_frame().halted);
+        assert_eq!(emu.halt(), Some(Halt::InfiniteLoop { pc: 0x202 }));
+        assert_eq!((emu.chip().pc, emu.chip().v[0]), (0x202, 1));
+        assert_eq!(
+            emu.halt().unwrap().to_string(),
+            "the program jumped to itself at 0x202"
+        );
+    }
+
+    #[test]
+    fn test_state_hash() {
+        let run = || {
+            let mut emu = emulator();
+            emu.queue_key_event(KeyEvent::Down(5));
+            for _ in 0..3 {
+                emu.run_frame();
+            }
+            emu.snapshot()
+        };
+        let a = run();
+        let mut b = run();
+        assert_eq!(a.state_hash(), b.state_hash());
+
+        b.chip.v[3] = 1;
+        assert_ne!(a.state_hash(), b.state_hash());
+    }
+
+    #[test]
+    fn test_state_summary_golden() {
+        use crate::chip8::frontend::{self, HeadlessFrontend, InputEvent};
+
+        let mut emu = emulator();
+        let mut headless = HeadlessFrontend::new(10);
+        headless.push_input(4, InputEvent::Key(KeyEvent::Down(5)));
+        let stop = frontend::run(&mut emu, &mut headless);
+        let summary = StateSummary {
+            halt_reason: Some(stop.name()),
+            ..emu.state_summary()
+        };
+        let expected = r#"{
+  "pc": 524,
+  "i": 0,
+  "sp": 0,
+  "v": [0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+  "stack": [],
+  "dt": 0,
+  "st": 0,
+  "resolution": "low",
+  "frame": 10,
+  "executed": 120,
+  "screen_hash": "fbe0ac0a8b8162dc",
+  "halt_reason": "quit"
+}
+"#;
+        assert_eq!(summary.to_json(), expected);
+    }
+
+    #[test]
+    fn test_speed_scaled() {
+        let mut carry = 0.0;
+        let ipf: Vec<u64> = (0..4)
+            .map(|_| speed_scaled(12.0, 0.25, &mut carry))
+            .collect();
+        assert_eq!(ipf, vec![3, 3, 3, 3]);
+
+        let mut carry = 0.0;
+        let ticks: Vec<u64> = (0..8)
+            .map(|_| speed_scaled(1.0, 0.25, &mut carry))
+            .collect();
+        assert_eq!(ticks, vec![0, 0, 0, 1, 0, 0, 0, 1]);
+
+        let mut carry = 0.0;
+        let ipf: u64 = (0..3).map(|_| speed_scaled(10.0, 0.1, &mut carry)).sum();
+        assert_eq!(ipf, 3);
+    }
+
+    #[test]
+    fn test_cpu_hz() {
+        let config = EmulatorConfig {
+            cpu_hz: Some(1000),
+            ..EmulatorConfig::default()
+        };
+        assert_eq!(config.instructions_per_frame(), 1000.0 / 60.0);
+
+        // JP 0x200
+        let mut emu = Emulator::new(config);
+        emu.load_rom(&[0x12, 0x00]).unwrap();
+        emu.run_frame();
+        assert_eq!(emu.executed, 16);
+        for _ in 1..60 {
+            emu.run_frame();
+        }
+        // A second's worth of frames runs a second's worth of instructions.
+        assert_eq!(emu.executed, 1000);
+        assert_eq!(emu.frame_interval(), Duration::from_nanos(16_666_666));
+
+        emu.set_cycles_per_frame(12);
+        assert_eq!(emu.config().instructions_per_frame(), 12.0);
+    }
+
+    #[test]
+    fn test_speed_toggles() {
+        let mut toggles = SpeedToggles::default();
+        assert_eq!((toggles.ratio(), toggles.label()), (1.0, None));
+        toggles.apply(SpeedChange::ToggleSlowMotion);
+        assert_eq!(toggles.ratio(), SLOW_MOTION_RATIO);
+        assert_eq!(toggles.label().as_deref(), Some("slow 0.25x"));
+        // Turbo overrides slow motion while held, then gives it back.
+        toggles.apply(SpeedChange::TurboOn);
+        toggles.apply(SpeedChange::TurboOn);
+        assert_eq!(toggles.ratio(), TURBO_RATIO);
+        assert_eq!(toggles.label().as_deref(), Some("turbo 8x"));
+        toggles.apply(SpeedChange::TurboOff);
+        assert_eq!(toggles.ratio(), SLOW_MOTION_RATIO);
+        toggles.apply(SpeedChange::ToggleSlowMotion);
+        assert_eq!(toggles, SpeedToggles::default());
+        toggles.apply(SpeedChange::ToggleTurbo);
+        assert_eq!(toggles.ratio(), TURBO_RATIO);
+        toggles.apply(SpeedChange::ToggleTurbo);
+        assert_eq!(toggles, SpeedToggles::default());
+    }
+
+    #[test]
+    fn test_turbo_runs_eight_times_the_instructions() {
+        let idle_emulator = || {
+            let mut emu = Emulator::new(EmulatorConfig::default());
+            emu.load_rom(&[0x12, 0x00]).unwrap(); // JP 0x200
+            emu
+        };
+        let second = |turbo: bool| {
+            let mut emu = idle_emulator();
+            if turbo {
+                emu.change_speed(SpeedChange::TurboOn);
+            }
+            emu.chip().dt.store(255, Ordering::Release);
+            for _ in 0..60 {
+                emu.run_frame();
+            }
+            (emu.executed(), 255 - emu.chip().dt.load(Ordering::Acquire))
+        };
+        let (normal, normal_ticks) = second(false);
+        let (turbo, turbo_ticks) = second(true);
+        assert_eq!(turbo, 8 * normal);
+        assert_eq!(normal_ticks, 60);
+        // The timers speed up with the instructions, until DT runs out.
+        assert_eq!(turbo_ticks, 255);
+
+        // Letting go mid-second goes straight back to the normal rate.
+        let mut emu = idle_emulator();
+        emu.change_speed(SpeedChange::TurboOn);
+        emu.run_frame();
+        emu.change_speed(SpeedChange::TurboOff);
+        emu.run_frame();
+        assert_eq!(emu.executed(), 8 * 12 + 12);
+        // Switching ROMs keeps slow motion on.
+        emu.change_speed(SpeedChange::ToggleSlowMotion);
+        emu.reset();
+        assert!(emu.speed_toggles().slow_motion);
+    }
+
+    #[test]
+    fn test_cosmac_timing() {
+        // A tight loop of the op at 0x200, then JP 0x200.
+        let frame_of = |op: ChipOp| {
+            let mut emu = Emulator::new(EmulatorConfig {
+                cosmac_timing: true,
+                ..EmulatorConfig::default()
+            });
+            let [b, s] = op.encode().to_be_bytes();
+            emu.load_rom(&[b, s, 0x12, 0x00]).unwrap();
+            emu.run_frame();
+            (emu.executed(), emu.cycle_overrun)
+        };
+        let jp = u64::from(cycle_cost(&ChipOp::JpNnn { nnn: 0x200 }));
+
+        // LD and JP cost 14 + 24 cycles a pair. 96 pairs leave 20 of the
+        // 3668, enough to start another LD and its JP, 18 cycles over.
+        let (lds, overrun) = frame_of(ChipOp::LdVxNn { x: 0, nn: 1 });
+        assert_eq!((lds, overrun), (194, 18));
+
+        // Four 884-cycle DRW and JP pairs, then one more DRW.
+        let (drws, _) = frame_of(ChipOp::DrwVxVyN { x: 0, y: 0, n: 15 });
+        assert_eq!(drws, 9);
+
+        // The overrun comes out of the next frame's budget.
+        let mut emu = Emulator::new(EmulatorConfig {
+            cosmac_timing: true,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&[0x12, 0x00]).unwrap();
+        emu.cycle_overrun = u64::from(COSMAC_FRAME_CYCLES) - jp;
+        emu.run_frame();
+        assert_eq!(emu.executed(), 1);
+        assert_eq!(emu.cycle_overrun, 0);
+    }
+
+    #[test]
+    fn test_run_frame_slow_motion() {
+        let mut emu = Emulator::new(EmulatorConfig {
+            speed: 0.25,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&[0x12, 0x00]).unwrap(); // JP 0x200
+        emu.chip().dt.store(4, Ordering::Release);
+        for _ in 0..8 {
+            emu.run_frame();
+        }
+        assert_eq!(emu.chip().dt.load(Ordering::Acquire), 2);
+    }
+
+    #[test]
+    fn test_run_frame_latches_dt() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+
+        // LD V0, DT, count V2 through 256 values, then LD V1, DT.
+        let rom = [
+            0xF0, 0x07, // LD V0, DT
+            0x72, 0x01, // ADD V2, 0x01
+            0x32, 0x00, // SE V2, 0x00
+            0x12, 0x02, // JP 0x202
+            0xF1, 0x07, // LD V1, DT
+            0x12, 0x0A, // JP 0x20A
+        ];
+        let mut emu = Emulator::new(EmulatorConfig {
+            cycles_per_frame: 1000,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&rom).unwrap();
+        emu.chip().dt.store(255, Ordering::Release);
+
+        // Tick DT as fast as possible while the frame runs, the way a
+        // timer thread might between two instructions.
+        let dt = Arc::clone(&emu.chip().dt);
+        let stop = Arc::new(AtomicBool::new(false));
+        let ticker = {
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Acquire) {
+                    match dt.load(Ordering::Acquire) {
+                        0 => dt.store(255, Ordering::Release),
+                        _ => timers::tick(&dt),
+                    }
+                }
+            })
+        };
+        emu.run_frame();
+        stop.store(true, Ordering::Release);
+        ticker.join().unwrap();
+
+        assert_eq!(emu.chip().pc, 0x20A);
+        assert_eq!(emu.chip().v[0], emu.chip().v[1]);
+        assert_eq!(emu.chip().latched_dt, None);
+    }
+
+    #[test]
+    fn test_latched_dt_follows_ld_dt_vx() {
+        // LD V0, 0x09 ; LD DT, V0 ; LD V1, DT ; JP 0x206
+        let rom = [0x60, 0x09, 0xF0, 0x15, 0xF1, 0x07, 0x12, 0x06];
+        for latch_timers in [true, false] {
+            let mut emu = Emulator::new(EmulatorConfig {
+                latch_timers,
+                ..EmulatorConfig::default()
+            });
+            emu.load_rom(&rom).unwrap();
+            emu.run_frame();
+            assert_eq!(emu.chip().v[1], 9);
+        }
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(parse_speed("0.25"), Ok(0.25));
+        assert!(parse_speed("0").is_err());
+        assert!(parse_speed("-1").is_err());
+        assert!(parse_speed("fast").is_err());
+    }
+
+    #[test]
+    fn test_beep_styles() {
+        let beeps = |beep_style| {
+            let mut emu = Emulator::new(EmulatorConfig {
+                beep_style,
+                ..EmulatorConfig::default()
+            });
+            // LD V1, 0x05 ; LD ST, V1 ; JP 0x204
+            emu.load_rom(&[0x61, 0x05, 0xF1, 0x18, 0x12, 0x04]).unwrap();
+            (0..6).map(|_| emu.run_frame().beep).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            beeps(BeepStyle::Sustained),
+            vec![true, true, true, true, false, false]
+        );
+        assert_eq!(
+            beeps(BeepStyle::Click),
+            vec![true, false, false, false, false, false]
+        );
+        assert_eq!("click".parse(), Ok(BeepStyle::Click));
+        assert!("loud".parse::<BeepStyle>().is_err());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_load_rom_logs() {
+        let logs = crate::utils::logging::tests::capture(|| {
+            emulator();
+        });
+        assert!(logs.contains("INFO"));
+        assert!(logs.contains("rom loaded size=14"), "{logs}");
+    }
+
+    #[test]
+    fn test_load_rom_too_large() {
+        use crate::chip8::consts::{MAX_ROM_SIZE, XO_RAM_SIZE};
+
+        let rom = vec![0xAA; MAX_ROM_SIZE + 1];
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        assert!(emu.load_rom(&rom).is_err());
+
+        let mut emu = Emulator::new(EmulatorConfig {
+            ram_size: XO_RAM_SIZE,
+            ..EmulatorConfig::default()
+        });
+        assert_eq!(emu.max_rom_size(), XO_RAM_SIZE - PROGRAM_START);
+        emu.load_rom(&rom).unwrap();
+        assert_eq!(emu.chip().memory[PROGRAM_START + MAX_ROM_SIZE], 0xAA);
+    }
+
+    #[test]
+    fn test_stack_depth_config() {
+        // CALL 0x200, recursing forever.
+        let run = |stack_depth| {
+            let mut emu = Emulator::new(EmulatorConfig {
+                stack_depth,
+                ..EmulatorConfig::default()
+            });
+            emu.load_rom(&[0x22, 0x00]).unwrap();
+            for _ in 0..17 {
+                emu.chip_mut().try_step()?;
+            }
+            Ok(emu.chip().sp)
+        };
+        assert_eq!(run(STACK_DEPTH), Err(ExecError::StackOverflow));
+        assert_eq!(run(32), Ok(17));
+    }
+
+    #[test]
+    fn test_input_latency() {
+        let mut emu = emulator();
+        emu.queue_key_event(KeyEvent::Down(5));
+        emu.run_frame();
+        assert!(emu.input_latency().is_none());
+
+        let mut emu = Emulator::new(EmulatorConfig {
+            input_latency: true,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&ROM).unwrap();
+        emu.queue_key_event(KeyEvent::Down(5));
+        emu.queue_key_event(KeyEvent::Up(5));
+        emu.run_frame();
+        let latency = emu.input_latency().unwrap();
+        assert_eq!(latency.count(), 2);
+        assert!(latency.max() < Duration::from_secs(1));
+        emu.reset();
+        assert_eq!(emu.input_latency().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_skip_unknown() {
+        let mut emu = Emulator::new(EmulatorConfig {
+            skip_unknown: true,
+            ..EmulatorConfig::default()
+        });
+        // An unknown word, then LD V0, 0x07 ; JP 0x204
+        emu.load_rom(&[0xE1, 0xFF, 0x60, 0x07, 0x12, 0x04]).unwrap();
+        emu.run_frame();
+        assert_eq!(emu.chip().v[0], 7);
+        assert_eq!(emu.chip().pc, 0x204);
+        assert_eq!(emu.halt(), None);
+        // Resets keep it.
+        emu.reset();
+        assert!(emu.chip().skip_unknown);
+    }
+
+    #[test]
+    fn test_start_high_res() {
+        let config = EmulatorConfig {
+            start_high_res: true,
+            ..EmulatorConfig::default()
+        };
+        let mut emu = Emulator::new(config);
+        assert_eq!(emu.chip().resolution, Resolution::High);
+        assert_eq!(emu.snapshot().chip.resolution, Resolution::High);
+        emu.reset();
+        assert_eq!(emu.chip().resolution, Resolution::High);
+
+        // A draw at column 100 isn't wrapped to the low-res width.
+        emu.load_rom(&[0x60, 100, 0xD0, 0x11]).unwrap(); // LD V0, 100; DRW V0, V1, 1
+        emu.step_instruction();
+        emu.step_instruction();
+        assert!(emu.chip().screen.pixel(100, 0));
+        assert_eq!(
+            Emulator::new(EmulatorConfig::default()).chip().resolution,
+            Resolution::Low
+        );
+    }
+
+    #[test]
+    fn test_call_history() {
+        use crate::chip8::call_history::CallEvent;
+
+        // CALL 0x204; JP 0x202; (0x204) CLS; RET
+        let rom = [0x22, 0x04, 0x12, 0x02, 0x00, 0xE0, 0x00, 0xEE];
+        let mut emu = Emulator::new(EmulatorConfig {
+            call_history: 8,
+            ..EmulatorConfig::default()
+        });
+        emu.load_rom(&rom).unwrap();
+        for _ in 0..5 {
+            emu.step_instruction();
+        }
+        let events: Vec<CallEvent> = emu.snapshot().call_history.events().copied().collect();
+        assert_eq!(
+            events,
+            [
+                CallEvent::Call {
+                    from: 0x200,
+                    to: 0x204
+                },
+                CallEvent::Ret {
+                    from: 0x206,
+                    to: 0x202
+                },
+            ]
+        );
+
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&rom).unwrap();
+        emu.step_instruction();
+        assert_eq!(emu.call_history().events().count(), 0);
+    }
+}
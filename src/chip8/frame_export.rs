@@ -0,0 +1,241 @@
+//! Saving every presented frame as a numbered image, for turning a play
+//! session into a GIF or video with external tools.
+//!
+//! [`FrameExport`] wraps a frontend and hands each frame, encoded as PPM,
+//! to a [`FrameSink`]. [`DirSink`] writes them to a directory from a
+//! background thread so the emulator never waits on the disk; if the
+//! disk falls behind, frames are skipped rather than queued without
+//! bound.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread;
+
+use crate::chip8::emulator::RenderSnapshot;
+use crate::chip8::frontend::{AudioCommand, Frontend, InputEvent};
+use crate::chip8::gfx::{self, Palette};
+
+/// Frames [`DirSink`] holds while the writer thread catches up.
+const QUEUE_FRAMES: usize = 120;
+
+/// File name of the `index`th exported frame, counting from 0.
+pub fn frame_name(index: u64) -> String {
+    format!("frame-{index:06}.ppm")
+}
+
+/// Where exported frames go.
+pub trait FrameSink {
+    /// Store `image` as `name`. An error skips this frame only.
+    fn write_frame(&mut self, name: String, image: Vec<u8>) -> io::Result<()>;
+}
+
+/// Writes frames into a directory on a background thread.
+pub struct DirSink {
+    tx: Option<SyncSender<(PathBuf, Vec<u8>)>>,
+    writer: Option<thread::JoinHandle<()>>,
+    dir: PathBuf,
+}
+
+impl DirSink {
+    /// Write into `dir`, creating it if needed.
+    pub fn create(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let (tx, rx) = mpsc::sync_channel::<(PathBuf, Vec<u8>)>(QUEUE_FRAMES);
+        let writer = thread::Builder::new()
+            .name("frame-export".into())
+            .spawn(move || {
+                for (path, image) in rx {
+                    if let Err(err) = fs::write(&path, image) {
+                        log_event!(warn, path = %path.display(), %err, "can't write frame");
+                        #[cfg(not(feature = "tracing"))]
+                        let _ = err;
+                    }
+                }
+            })?;
+        Ok(Self {
+            tx: Some(tx),
+            writer: Some(writer),
+            dir: dir.to_path_buf(),
+        })
+    }
+}
+
+impl FrameSink for DirSink {
+    fn write_frame(&mut self, name: String, image: Vec<u8>) -> io::Result<()> {
+        let tx = self.tx.as_ref().expect("the sender lives until drop");
+        tx.try_send((self.dir.join(name), image))
+            .map_err(|err| match err {
+                TrySendError::Full(_) => io::Error::new(io::ErrorKind::WouldBlock, "queue full"),
+                TrySendError::Disconnected(_) => io::ErrorKind::BrokenPipe.into(),
+            })
+    }
+}
+
+impl Drop for DirSink {
+    /// Finish writing the frames already queued.
+    fn drop(&mut self) {
+        self.tx.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// A frontend that also exports each frame it presents, up to `limit`
+/// frames. With no sink it only passes frames through.
+pub struct FrameExport<F, S = DirSink> {
+    pub inner: F,
+    sink: Option<S>,
+    palette: Palette,
+    limit: u64,
+    written: u64,
+    skipped: u64,
+}
+
+impl<F: Frontend, S: FrameSink> FrameExport<F, S> {
+    pub fn new(inner: F, sink: Option<S>, palette: Palette, limit: u64) -> Self {
+        Self {
+            inner,
+            sink,
+            palette,
+            limit,
+            written: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Frames handed to the sink so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Frames dropped because the sink couldn't take them.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+impl<F: Frontend, S: FrameSink> Frontend for FrameExport<F, S> {
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        self.inner.poll_input()
+    }
+
+    fn present(&mut self, snapshot: &RenderSnapshot) {
+        if let Some(sink) = &mut self.sink {
+            if self.written < self.limit {
+                let image = gfx::ppm(&snapshot.chip, &self.palette);
+                match sink.write_frame(frame_name(self.written), image) {
+                    Ok(()) => self.written += 1,
+                    Err(_) => self.skipped += 1,
+                }
+                if self.written == self.limit {
+                    log_event!(info, frames = self.limit, "frame export limit reached");
+                }
+            }
+        }
+        self.inner.present(snapshot);
+    }
+
+    fn set_audio(&mut self, command: AudioCommand) {
+        self.inner.set_audio(command);
+    }
+
+    fn realtime(&self) -> bool {
+        self.inner.realtime()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::{Emulator, EmulatorConfig};
+    use crate::chip8::frontend::{self, HeadlessFrontend};
+
+    /// Keeps every frame, refusing the ones listed in `refuse`.
+    #[derive(Default)]
+    struct MemorySink {
+        frames: Vec<(String, Vec<u8>)>,
+        refuse: Vec<usize>,
+        calls: usize,
+    }
+
+    impl FrameSink for MemorySink {
+        fn write_frame(&mut self, name: String, image: Vec<u8>) -> io::Result<()> {
+            self.calls += 1;
+            if self.refuse.contains(&self.calls) {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            self.frames.push((name, image));
+            Ok(())
+        }
+    }
+
+    /// Run a ROM that draws one digit and then loops, exporting `frames`
+    /// frames with at most `limit` written.
+    fn export(
+        frames: u64,
+        limit: u64,
+        sink: MemorySink,
+    ) -> FrameExport<HeadlessFrontend, MemorySink> {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        // LD F, V0; DRW V0, V0, 5; JP 0x204
+        emu.load_rom(&[0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04]).unwrap();
+        let mut export = FrameExport::new(
+            HeadlessFrontend::new(frames),
+            Some(sink),
+            Palette::default(),
+            limit,
+        );
+        frontend::run(&mut emu, &mut export);
+        export
+    }
+
+    #[test]
+    fn test_static_screen_exports_a_numbered_sequence() {
+        let export = export(5, 100, MemorySink::default());
+        let frames = &export.sink.as_ref().unwrap().frames;
+        let names: Vec<&str> = frames.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "frame-000000.ppm",
+                "frame-000001.ppm",
+                "frame-000002.ppm",
+                "frame-000003.ppm",
+                "frame-000004.ppm"
+            ]
+        );
+        assert!(frames.iter().all(|(_, image)| *image == frames[0].1));
+        assert!(frames[0].1.starts_with(b"P6\n128 64\n255\n"));
+        assert_eq!((export.written(), export.inner.presented()), (5, 5));
+    }
+
+    #[test]
+    fn test_limit_and_skipped_frames() {
+        let sink = MemorySink {
+            refuse: vec![2],
+            ..MemorySink::default()
+        };
+        let export = export(10, 3, sink);
+        let frames = &export.sink.as_ref().unwrap().frames;
+        // The refused frame doesn't leave a gap in the names.
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].0, "frame-000002.ppm");
+        assert_eq!((export.written(), export.skipped()), (3, 1));
+        assert_eq!(export.inner.presented(), 10);
+    }
+
+    #[test]
+    fn test_dir_sink() {
+        let dir = std::env::temp_dir().join(format!("oxid8-frames-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut sink = DirSink::create(&dir).unwrap();
+        sink.write_frame(frame_name(0), b"P6".to_vec()).unwrap();
+        sink.write_frame(frame_name(1), b"P6".to_vec()).unwrap();
+        drop(sink);
+        assert_eq!(fs::read(dir.join("frame-000001.ppm")).unwrap(), b"P6");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
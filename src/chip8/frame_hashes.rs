@@ -0,0 +1,296 @@
+//! Per-frame screen hashes, for checking a whole session rather than just
+//! where it ended.
+//!
+//! A trace is a plain text file with one line per presented frame: the
+//! frame number and a hash of the display.
+//!
+//! ```text
+//! # oxid8 frame hashes
+//! 1 6c4c1d7b2a5d3e90
+//! 2 6c4c1d7b2a5d3e90
+//! ```
+//!
+//! Recorded alongside a [`Replay`](crate::chip8::replay::Replay) and RND
+//! seed, a trace pins down every frame of the session, so a change to DRW,
+//! scrolling or a quirk shows up at the first frame it affects.
+
+use std::fmt;
+
+use color_eyre::eyre::{bail, eyre, Result};
+
+use crate::chip8::cpu::Chip8;
+use crate::chip8::emulator::RenderSnapshot;
+use crate::chip8::frontend::{AudioCommand, Frontend, InputEvent};
+use crate::chip8::snapshot::{screen_ascii, screen_diff};
+
+/// The screen hash of each frame of a session, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameHashes {
+    hashes: Vec<(u64, u64)>,
+}
+
+impl FrameHashes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, frame: u64, hash: u64) {
+        self.hashes.push((frame, hash));
+    }
+
+    /// (frame, hash) pairs, in the order they were recorded.
+    pub fn hashes(&self) -> &[(u64, u64)] {
+        &self.hashes
+    }
+
+    /// Number of frames needed to reach the last recorded one.
+    pub fn frames(&self) -> u64 {
+        self.hashes.last().map_or(0, |&(frame, _)| frame)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut hashes = FrameHashes::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parse = || -> Result<(u64, u64)> {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let [frame, hash] = fields[..] else {
+                    bail!("expected `<frame> <hash>`");
+                };
+                let frame = frame
+                    .parse::<u64>()
+                    .map_err(|_| eyre!("bad frame number {frame:?}"))?;
+                let hash = u64::from_str_radix(hash, 16).map_err(|_| eyre!("bad hash {hash:?}"))?;
+                Ok((frame, hash))
+            };
+            let (frame, hash) = parse().map_err(|e| eyre!("frame hashes line {}: {e}", n + 1))?;
+            if hashes.hashes.last().is_some_and(|&(last, _)| frame <= last) {
+                bail!("frame hashes line {}: frame {frame} out of order", n + 1);
+            }
+            hashes.record(frame, hash);
+        }
+        Ok(hashes)
+    }
+}
+
+impl fmt::Display for FrameHashes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# oxid8 frame hashes")?;
+        for (frame, hash) in &self.hashes {
+            writeln!(f, "{frame} {hash:016x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The first frame whose screen hash differed from the trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub frame: u64,
+    pub expected: u64,
+    pub actual: u64,
+    /// The last frame that matched, or 0 for the blank power-on screen.
+    pub last_match: u64,
+    /// [`screen_diff`] of this screen against the last matching one.
+    pub diff: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "frame {}: expected screen hash {:016x}, got {:016x}",
+            self.frame, self.expected, self.actual
+        )?;
+        writeln!(
+            f,
+            "this frame against frame {}, the last that matched:",
+            self.last_match
+        )?;
+        write!(f, "{}", self.diff)
+    }
+}
+
+/// Wraps a frontend, hashing every frame it presents and optionally
+/// checking each against an expected trace. The first mismatch quits the
+/// run.
+pub struct FrameHashing<F> {
+    pub inner: F,
+    /// Hashes of the frames presented so far, when recording.
+    pub hashes: Option<FrameHashes>,
+    /// Set once a frame differs from the expected trace.
+    pub mismatch: Option<Mismatch>,
+    expected: Option<FrameHashes>,
+    /// Frames checked against `expected` so far.
+    checked: usize,
+    /// The last screen that matched, for the diff.
+    last_match: (u64, Chip8),
+}
+
+impl<F: Frontend> FrameHashing<F> {
+    /// Wrap `inner`, recording a trace if `record` and checking against
+    /// `expected` if given.
+    pub fn new(inner: F, record: bool, expected: Option<FrameHashes>) -> Self {
+        Self {
+            inner,
+            hashes: record.then(FrameHashes::new),
+            mismatch: None,
+            expected,
+            checked: 0,
+            last_match: (0, Chip8::new()),
+        }
+    }
+
+    /// Whether every frame of the expected trace has been presented.
+    pub fn verified(&self) -> bool {
+        let expected = self.expected.as_ref().map_or(0, |e| e.hashes().len());
+        self.mismatch.is_none() && self.checked >= expected
+    }
+
+    /// Frames checked against the expected trace.
+    pub fn checked(&self) -> usize {
+        self.checked
+    }
+
+    fn check(&mut self, snapshot: &RenderSnapshot, actual: u64) {
+        let Some(expected) = &self.expected else {
+            return;
+        };
+        let Some(&(frame, hash)) = expected.hashes().get(self.checked) else {
+            return;
+        };
+        if frame != snapshot.frame {
+            return;
+        }
+        self.checked += 1;
+        if hash == actual {
+            self.last_match = (frame, snapshot.chip.clone());
+            return;
+        }
+        let (last_match, before) = &self.last_match;
+        self.mismatch = Some(Mismatch {
+            frame,
+            expected: hash,
+            actual,
+            last_match: *last_match,
+            diff: screen_diff(&screen_ascii(&snapshot.chip), &screen_ascii(before)),
+        });
+    }
+}
+
+impl<F: Frontend> Frontend for FrameHashing<F> {
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        if self.mismatch.is_some() {
+            return vec![InputEvent::Quit];
+        }
+        self.inner.poll_input()
+    }
+
+    fn present(&mut self, snapshot: &RenderSnapshot) {
+        if self.hashes.is_some() || self.expected.is_some() {
+            let hash = snapshot.chip.screen_hash();
+            if let Some(hashes) = &mut self.hashes {
+                hashes.record(snapshot.frame, hash);
+            }
+            self.check(snapshot, hash);
+        }
+        self.inner.present(snapshot);
+    }
+
+    fn set_audio(&mut self, command: AudioCommand) {
+        self.inner.set_audio(command);
+    }
+
+    fn realtime(&self) -> bool {
+        self.inner.realtime()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::{Emulator, EmulatorConfig, KeyEvent};
+    use crate::chip8::frontend::{self, HeadlessFrontend};
+
+    // Draw the digit of the key pressed, cleared each time.
+    const ROM: [u8; 10] = [
+        0xF0, 0x0A, // LD V0, K
+        0x00, 0xE0, // CLS
+        0xF0, 0x29, // LD F, V0
+        0xD1, 0x15, // DRW V1, V1, 5
+        0x12, 0x00, // JP 0x200
+    ];
+
+    fn session(frames: u64, key: u8) -> HeadlessFrontend {
+        let mut headless = HeadlessFrontend::new(frames);
+        headless.push_input(2, InputEvent::Key(KeyEvent::Down(key)));
+        headless.push_input(3, InputEvent::Key(KeyEvent::Up(key)));
+        headless
+    }
+
+    fn run(frontend: &mut FrameHashing<HeadlessFrontend>) {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&ROM).unwrap();
+        frontend::run(&mut emu, frontend);
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let mut hashes = FrameHashes::new();
+        hashes.record(1, 0xDEAD_BEEF);
+        hashes.record(2, u64::MAX);
+        let text = hashes.to_string();
+        assert!(text.contains("1 00000000deadbeef\n"));
+        let parsed = FrameHashes::parse(&text).unwrap();
+        assert_eq!(parsed, hashes);
+        assert_eq!(parsed.frames(), 2);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = FrameHashes::parse("1 00ff\n2 zz").unwrap_err();
+        assert_eq!(err.to_string(), "frame hashes line 2: bad hash \"zz\"");
+        assert!(FrameHashes::parse("1").is_err());
+        assert!(FrameHashes::parse("2 0\n2 0").is_err());
+    }
+
+    #[test]
+    fn test_verify_session() {
+        let mut recording = FrameHashing::new(session(8, 5), true, None);
+        run(&mut recording);
+        let trace = recording.hashes.unwrap();
+        assert_eq!(trace.hashes().len(), 8);
+        assert_eq!(trace.frames(), 8);
+
+        let mut same = FrameHashing::new(session(8, 5), false, Some(trace.clone()));
+        run(&mut same);
+        assert!(same.verified());
+        assert_eq!(same.checked(), 8);
+
+        // A different key draws a different digit on the same frame, once
+        // LD V0, K sees the release.
+        let mut other = FrameHashing::new(session(8, 6), false, Some(trace.clone()));
+        run(&mut other);
+        assert!(!other.verified());
+        let mismatch = other.mismatch.unwrap();
+        assert_eq!(mismatch.frame, trace.hashes()[3].0);
+        assert_eq!(mismatch.expected, trace.hashes()[3].1);
+        assert_eq!(mismatch.last_match, trace.hashes()[2].0);
+        // The run quit at the mismatch rather than finishing.
+        assert_eq!(other.inner.presented(), 4);
+        assert!(mismatch.diff.starts_with("  actual"));
+        assert!(mismatch.diff.lines().any(|row| row.starts_with("! ")));
+        assert!(mismatch.to_string().starts_with(&format!(
+            "frame {}: expected screen hash {:016x}",
+            mismatch.frame, mismatch.expected
+        )));
+
+        // Ending before the trace does isn't a pass either.
+        let mut short = FrameHashing::new(session(4, 5), false, Some(trace));
+        run(&mut short);
+        assert!(short.mismatch.is_none() && !short.verified());
+    }
+}
@@ -0,0 +1,291 @@
+//! One display plane, stored a bit per pixel with each row as two u64s.
+//!
+//! The leftmost pixel of a row is the most significant bit of its first
+//! word, so a row read as a u128 is the row's pixels in order. DRW and
+//! the scrolls work a whole row at a time this way rather than byte by
+//! byte. Byte accessors address a row as `W` bytes, most significant bit
+//! leftmost, matching the layout in memory and on the wire.
+
+use crate::chip8::consts::{H, W};
+
+/// Pixels per row in high-res. Low-res uses the top-left quarter, unless
+/// the screen is kept at physical resolution (`Quirks::half_pixel_scroll`).
+pub const WIDTH: usize = W * 8;
+/// Rows in high-res.
+pub const HEIGHT: usize = H;
+
+const WORDS: usize = WIDTH / 64;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Framebuffer {
+    rows: [[u64; WORDS]; HEIGHT],
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self {
+            rows: [[0; WORDS]; HEIGHT],
+        }
+    }
+}
+
+impl std::fmt::Debug for Framebuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lit = (0..HEIGHT).map(|y| self.row(y).count_ones()).sum::<u32>();
+        write!(f, "Framebuffer {{ {WIDTH}x{HEIGHT}, {lit} lit }}")
+    }
+}
+
+impl Framebuffer {
+    /// Bytes in [`Framebuffer::bytes`] and [`Framebuffer::from_bytes`].
+    pub const BYTES: usize = H * W;
+
+    /// Rows, and bytes per row.
+    pub fn dim(&self) -> (usize, usize) {
+        (H, W)
+    }
+
+    /// Row `y` as one number, its leftmost pixel the most significant bit.
+    pub fn row(&self, y: usize) -> u128 {
+        let [hi, lo] = self.rows[y];
+        (u128::from(hi) << 64) | u128::from(lo)
+    }
+
+    fn set_row(&mut self, y: usize, row: u128) {
+        self.rows[y] = [(row >> 64) as u64, row as u64];
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        (self.row(y) >> (WIDTH - 1 - x)) & 1 == 1
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        let bit = 1u128 << (WIDTH - 1 - x);
+        let row = self.row(y);
+        self.set_row(y, if on { row | bit } else { row & !bit });
+    }
+
+    /// Pixels `8 * x` to `8 * x + 7` of row `y`.
+    pub fn byte(&self, y: usize, x: usize) -> u8 {
+        (self.row(y) >> (WIDTH - 8 - 8 * x)) as u8
+    }
+
+    pub fn set_byte(&mut self, y: usize, x: usize, byte: u8) {
+        let shift = WIDTH - 8 - 8 * x;
+        let row = self.row(y) & !(0xFF << shift);
+        self.set_row(y, row | (u128::from(byte) << shift));
+    }
+
+    /// Every byte, row after row.
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..H).flat_map(move |y| self.row(y).to_be_bytes())
+    }
+
+    /// A plane from [`Framebuffer::BYTES`] bytes laid out as by
+    /// [`Framebuffer::bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::BYTES {
+            return None;
+        }
+        let mut fb = Self::default();
+        for (y, row) in bytes.chunks_exact(W).enumerate() {
+            fb.set_row(y, u128::from_be_bytes(row.try_into().unwrap()));
+        }
+        Some(fb)
+    }
+
+    /// The plane as the `H` x `W` byte array `Screen` used to be.
+    #[cfg(feature = "ndarray")]
+    pub fn as_array2(&self) -> ndarray::Array2<u8> {
+        ndarray::Array2::from_shape_vec((H, W), self.bytes().collect())
+            .expect("a plane is H x W bytes")
+    }
+
+    /// The pixels lit here but not in `previous`: what was drawn since.
+    pub fn newly_set(&self, previous: &Framebuffer) -> Framebuffer {
+        let mut fresh = Framebuffer::default();
+        for y in 0..HEIGHT {
+            fresh.set_row(y, self.row(y) & !previous.row(y));
+        }
+        fresh
+    }
+
+    /// Set every byte of every row to `byte`.
+    pub fn fill(&mut self, byte: u8) {
+        self.rows = [[u64::from_ne_bytes([byte; 8]); WORDS]; HEIGHT];
+    }
+
+    pub fn clear(&mut self) {
+        self.fill(0);
+    }
+
+    /// XOR the `width` low bits of `bits` onto row `y` with the first at
+    /// column `x`, wrapping around to the left edge. Returns whether any
+    /// lit pixel was turned off.
+    pub fn draw_sprite_row(&mut self, x: usize, y: usize, bits: u16, width: usize) -> bool {
+        let x = x % WIDTH;
+        // A byte-aligned 8-pixel row lies inside one word and can't wrap,
+        // so it's a single XOR.
+        if width == 8 && x.is_multiple_of(8) {
+            let word = &mut self.rows[y][x / 64];
+            let sprite = u64::from(bits as u8) << (56 - x % 64);
+            let collided = *word & sprite != 0;
+            *word ^= sprite;
+            return collided;
+        }
+        self.draw_rotated(x, y, bits, width)
+    }
+
+    /// [`Framebuffer::draw_sprite_row`] for any column and width.
+    fn draw_rotated(&mut self, x: usize, y: usize, bits: u16, width: usize) -> bool {
+        let sprite = (u128::from(bits) << (WIDTH - width)).rotate_right(x as u32);
+        let row = self.row(y);
+        self.set_row(y, row ^ sprite);
+        row & sprite != 0
+    }
+
+    /// Move every row down `n` rows, clearing the rows uncovered.
+    pub fn scroll_down(&mut self, n: usize) {
+        let n = n.min(HEIGHT);
+        self.rows.copy_within(..HEIGHT - n, n);
+        self.rows[..n].fill([0; WORDS]);
+    }
+
+    /// Move every row up `n` rows, clearing the rows uncovered.
+    pub fn scroll_up(&mut self, n: usize) {
+        let n = n.min(HEIGHT);
+        self.rows.copy_within(n.., 0);
+        self.rows[HEIGHT - n..].fill([0; WORDS]);
+    }
+
+    /// Move every pixel right `n` columns, clearing the columns uncovered.
+    pub fn scroll_right(&mut self, n: usize) {
+        for y in 0..HEIGHT {
+            self.set_row(y, self.row(y).checked_shr(n as u32).unwrap_or(0));
+        }
+    }
+
+    /// Move every pixel left `n` columns, clearing the columns uncovered.
+    pub fn scroll_left(&mut self, n: usize) {
+        for y in 0..HEIGHT {
+            self.set_row(y, self.row(y).checked_shl(n as u32).unwrap_or(0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixels_and_bytes() {
+        let mut fb = Framebuffer::default();
+        fb.set_pixel(0, 0, true);
+        fb.set_pixel(127, 63, true);
+        fb.set_byte(1, 15, 0x81);
+        assert!(fb.pixel(0, 0) && fb.pixel(127, 63) && fb.pixel(120, 1));
+        assert_eq!(fb.byte(0, 0), 0x80);
+        assert_eq!(fb.byte(63, 15), 0x01);
+        assert_eq!(fb.row(1), 0x81);
+
+        let bytes: Vec<u8> = fb.bytes().collect();
+        assert_eq!(bytes.len(), Framebuffer::BYTES);
+        assert_eq!((bytes[0], bytes[W + 15]), (0x80, 0x81));
+        assert_eq!(Framebuffer::from_bytes(&bytes), Some(fb.clone()));
+        assert_eq!(Framebuffer::from_bytes(&bytes[1..]), None);
+
+        fb.set_pixel(0, 0, false);
+        assert_eq!(fb.byte(0, 0), 0);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_as_array2() {
+        let mut fb = Framebuffer::default();
+        fb.set_byte(2, 3, 0xA5);
+        let array = fb.as_array2();
+        assert_eq!(array.dim(), (H, W));
+        assert_eq!(array[(2, 3)], 0xA5);
+        assert_eq!(array.iter().map(|&b| u32::from(b)).sum::<u32>(), 0xA5);
+    }
+
+    #[test]
+    fn test_newly_set() {
+        let mut previous = Framebuffer::default();
+        previous.set_byte(0, 0, 0b1100_0000);
+        previous.set_pixel(127, 63, true);
+        let mut current = previous.clone();
+        current.set_byte(0, 0, 0b0110_0000);
+        current.set_pixel(64, 10, true);
+
+        let fresh = current.newly_set(&previous);
+        assert_eq!(fresh.byte(0, 0), 0b0010_0000);
+        assert!(fresh.pixel(64, 10));
+        // Pixels unchanged or turned off aren't new.
+        assert!(!fresh.pixel(0, 0) && !fresh.pixel(127, 63));
+        assert_eq!(format!("{fresh:?}"), "Framebuffer { 128x64, 2 lit }");
+        assert_eq!(current.newly_set(&current), Framebuffer::default());
+    }
+
+    #[test]
+    fn test_draw_sprite_row() {
+        let mut fb = Framebuffer::default();
+        assert!(!fb.draw_sprite_row(60, 2, 0xF0, 8));
+        assert_eq!((fb.byte(2, 7), fb.byte(2, 8)), (0x0F, 0x00));
+        // Straddling the two words, then colliding with the first draw.
+        assert!(!fb.draw_sprite_row(62, 3, 0xFF, 8));
+        assert_eq!((fb.byte(3, 7), fb.byte(3, 8)), (0x03, 0xFC));
+        assert!(fb.draw_sprite_row(60, 2, 0x80, 8));
+        assert_eq!(fb.byte(2, 7), 0x07);
+        // Wrapping from the right edge to the left.
+        fb.draw_sprite_row(124, 4, 0xFF, 8);
+        assert_eq!((fb.byte(4, 15), fb.byte(4, 0)), (0x0F, 0xF0));
+        fb.draw_sprite_row(0, 5, 0xFFFF, 16);
+        assert_eq!((fb.byte(5, 0), fb.byte(5, 1)), (0xFF, 0xFF));
+    }
+
+    #[test]
+    fn test_aligned_draw_matches_rotated() {
+        let mut fast = Framebuffer::default();
+        for y in 0..HEIGHT {
+            fast.set_row(
+                y,
+                0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210u128.rotate_left(y as u32),
+            );
+        }
+        let mut general = fast.clone();
+        for x in (0..WIDTH + 16).step_by(8) {
+            for (y, bits) in [0x00, 0x01, 0x80, 0x3C, 0xFF, 0x1A5]
+                .into_iter()
+                .enumerate()
+            {
+                let y = (x + y) % HEIGHT;
+                let collided = fast.draw_sprite_row(x, y, bits, 8);
+                let expected = general.draw_rotated(x % WIDTH, y, bits, 8);
+                assert_eq!(collided, expected, "x {x} y {y} bits {bits:#x}");
+                assert_eq!(fast.row(y), general.row(y), "x {x} y {y} bits {bits:#x}");
+            }
+        }
+        assert_eq!(fast, general);
+    }
+
+    #[test]
+    fn test_scrolls() {
+        let mut fb = Framebuffer::default();
+        fb.set_byte(0, 0, 0xF0);
+        fb.set_byte(0, 8, 0x0F);
+        fb.scroll_right(4);
+        assert_eq!(
+            (fb.byte(0, 0), fb.byte(0, 8), fb.byte(0, 9)),
+            (0x0F, 0x00, 0xF0)
+        );
+        fb.scroll_left(8);
+        assert_eq!((fb.byte(0, 0), fb.byte(0, 8)), (0x00, 0xF0));
+        fb.scroll_down(63);
+        assert_eq!(fb.byte(63, 8), 0xF0);
+        fb.scroll_up(63);
+        assert_eq!(fb.byte(0, 8), 0xF0);
+        fb.scroll_down(HEIGHT);
+        assert_eq!(fb, Framebuffer::default());
+    }
+}
@@ -0,0 +1,507 @@
+//! The boundary between the emulator and whatever is showing it.
+//!
+//! A [`Frontend`] turns user input into [`InputEvent`]s, displays
+//! [`RenderSnapshot`]s and switches the beeper. [`run`] drives an
+//! [`Emulator`] against any frontend, so terminal, windowed and test
+//! frontends share one main loop.
+
+use std::collections::VecDeque;
+use std::thread;
+use std::time::Instant;
+
+use crate::chip8::emulator::{BeepStyle, Emulator, Halt, KeyEvent, RenderSnapshot, SpeedChange};
+use crate::chip8::replay::{Replay, ReplayEvent};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    Key(KeyEvent),
+    /// Reset the machine and start a different program.
+    LoadRom(Vec<u8>),
+    /// Turn turbo or slow motion on or off.
+    Speed(SpeedChange),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioCommand {
+    Start,
+    Stop,
+}
+
+/// Why [`run`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The frontend asked to quit.
+    Quit,
+    /// The program executed EXIT.
+    Halted,
+    /// The program reached the word 0x0000, past the end of its code.
+    ReachedEnd,
+    /// Execution fell through to the end of the ROM.
+    EndOfRom,
+    /// The program jumped to itself, with
+    /// [`halt_on_infinite_loop`](crate::chip8::emulator::EmulatorConfig::halt_on_infinite_loop)
+    /// set.
+    InfiniteLoop,
+    /// The machine panicked; see
+    /// [`Emulator::fault`](crate::chip8::emulator::Emulator::fault).
+    Faulted,
+}
+
+impl StopReason {
+    /// How `--dump-state` names this reason.
+    pub fn name(self) -> &'static str {
+        match self {
+            StopReason::Quit => "quit",
+            StopReason::Halted => "halted",
+            StopReason::ReachedEnd => "reached_end",
+            StopReason::EndOfRom => "end_of_rom",
+            StopReason::InfiniteLoop => "infinite_loop",
+            StopReason::Faulted => "fault",
+        }
+    }
+}
+
+pub trait Frontend {
+    /// Input that arrived since the last call.
+    fn poll_input(&mut self) -> Vec<InputEvent>;
+    /// Show a finished frame.
+    fn present(&mut self, snapshot: &RenderSnapshot);
+    fn set_audio(&mut self, command: AudioCommand);
+    /// Whether [`run`] should pace frames to wall-clock time.
+    fn realtime(&self) -> bool {
+        true
+    }
+}
+
+/// Run `emu` against `frontend` until the frontend quits or the program
+/// halts. A panic while running a frame halts the program with
+/// [`StopReason::Faulted`] instead of unwinding out of here, see
+/// [`Emulator::run_frame_caught`].
+pub fn run<F: Frontend>(emu: &mut Emulator, frontend: &mut F) -> StopReason {
+    let mut beeping = false;
+    let mut deadline = Instant::now();
+    loop {
+        for event in frontend.poll_input() {
+            match event {
+                InputEvent::Key(key) => emu.queue_key_event(key),
+                InputEvent::LoadRom(rom) => {
+                    log_event!(info, size = rom.len(), "switching rom");
+                    match emu.switch_rom(&rom) {
+                        Ok(()) => {}
+                        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+                        Err(err) => {
+                            log_event!(warn, error = %err, "keeping the current rom");
+                        }
+                    }
+                }
+                InputEvent::Speed(change) => emu.change_speed(change),
+                InputEvent::Quit => return StopReason::Quit,
+            }
+        }
+
+        let result = emu.run_frame_caught();
+        // The frame a program halts in is still shown, so its last
+        // drawing reaches the screen, exports and hashes.
+        frontend.present(&emu.snapshot());
+        if result.halted {
+            log_event!(info, frame = emu.frame(), "program halted");
+            if beeping {
+                frontend.set_audio(AudioCommand::Stop);
+            }
+            return match emu.halt() {
+                Some(Halt::Unprogrammed { .. }) => StopReason::ReachedEnd,
+                Some(Halt::EndOfRom { .. }) => StopReason::EndOfRom,
+                Some(Halt::InfiniteLoop { .. }) => StopReason::InfiniteLoop,
+                Some(Halt::Fault { .. }) => StopReason::Faulted,
+                _ => StopReason::Halted,
+            };
+        }
+
+        // Each ST load is a click of its own, so a click still sounding
+        // from the last frame stops before the next one starts.
+        if emu.config().beep_style == BeepStyle::Click {
+            for _ in 0..result.sound_loads {
+                if beeping {
+                    frontend.set_audio(AudioCommand::Stop);
+                }
+                frontend.set_audio(AudioCommand::Start);
+                beeping = true;
+            }
+        }
+        if result.beep != beeping {
+            beeping = result.beep;
+            frontend.set_audio(if beeping {
+                AudioCommand::Start
+            } else {
+                AudioCommand::Stop
+            });
+        }
+
+        if frontend.realtime() {
+            let interval = emu.frame_interval();
+            let now = Instant::now();
+            deadline += interval;
+            // A frame more than a frame late (a slow turbo frame, say)
+            // restarts the schedule rather than being caught up on with a
+            // burst of unpaced frames.
+            if deadline + interval < now {
+                deadline = now;
+            }
+            thread::sleep(deadline.saturating_duration_since(now));
+        }
+    }
+}
+
+/// A frontend with no display or audio, fed from a script of input events.
+///
+/// It quits after presenting `frames` frames and runs as fast as possible.
+#[derive(Default)]
+pub struct HeadlessFrontend {
+    script: VecDeque<(u64, InputEvent)>,
+    frames: u64,
+    presented: u64,
+    last: Option<RenderSnapshot>,
+    audio: Vec<AudioCommand>,
+}
+
+impl HeadlessFrontend {
+    pub fn new(frames: u64) -> Self {
+        Self {
+            frames,
+            ..Self::default()
+        }
+    }
+
+    /// Deliver `event` before frame `frame` runs.
+    pub fn push_input(&mut self, frame: u64, event: InputEvent) {
+        self.script.push_back((frame, event));
+    }
+
+    pub fn with_replay(mut self, replay: &Replay) -> Self {
+        for &(frame, event) in replay.events() {
+            let event = match event {
+                ReplayEvent::Key(key) => InputEvent::Key(key),
+                ReplayEvent::Speed(change) => InputEvent::Speed(change),
+            };
+            self.push_input(frame, event);
+        }
+        self
+    }
+
+    pub fn presented(&self) -> u64 {
+        self.presented
+    }
+
+    /// The most recently presented frame.
+    pub fn last_frame(&self) -> Option<&RenderSnapshot> {
+        self.last.as_ref()
+    }
+
+    /// Every audio command received, in order.
+    pub fn audio(&self) -> &[AudioCommand] {
+        &self.audio
+    }
+}
+
+impl Frontend for HeadlessFrontend {
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        if self.presented >= self.frames {
+            return vec![InputEvent::Quit];
+        }
+        let mut events = Vec::new();
+        while let Some((frame, _)) = self.script.front() {
+            if *frame > self.presented {
+                break;
+            }
+            events.extend(self.script.pop_front().map(|(_, event)| event));
+        }
+        events
+    }
+
+    fn present(&mut self, snapshot: &RenderSnapshot) {
+        self.presented += 1;
+        self.last = Some(snapshot.clone());
+    }
+
+    fn set_audio(&mut self, command: AudioCommand) {
+        self.audio.push(command);
+    }
+
+    fn realtime(&self) -> bool {
+        false
+    }
+}
+
+/// Wraps a frontend, recording its key and speed input into a [`Replay`].
+pub struct Recording<F> {
+    pub inner: F,
+    pub replay: Replay,
+    frame: u64,
+}
+
+impl<F: Frontend> Recording<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            replay: Replay::new(),
+            frame: 0,
+        }
+    }
+}
+
+impl<F: Frontend> Frontend for Recording<F> {
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        let events = self.inner.poll_input();
+        for event in &events {
+            let recorded = match *event {
+                InputEvent::Key(key) => ReplayEvent::Key(key),
+                InputEvent::Speed(change) => ReplayEvent::Speed(change),
+                InputEvent::LoadRom(_) | InputEvent::Quit => continue,
+            };
+            self.replay.record(self.frame, recorded);
+        }
+        events
+    }
+
+    fn present(&mut self, snapshot: &RenderSnapshot) {
+        self.frame += 1;
+        self.inner.present(snapshot);
+    }
+
+    fn set_audio(&mut self, command: AudioCommand) {
+        self.inner.set_audio(command);
+    }
+
+    fn realtime(&self) -> bool {
+        self.inner.realtime()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::EmulatorConfig;
+
+    // Beep for 2 frames and draw "0" each time key 5 is pressed.
+    const ROM: [u8; 14] = [
+        0x61, 0x02, // LD V1, 0x02
+        0x62, 0x05, // LD V2, 0x05
+        0xE2, 0x9E, // SKP V2
+        0x12, 0x04, // JP 0x204
+        0xF1, 0x18, // LD ST, V1
+        0xD0, 0x05, // DRW V0, V0, 5
+        0x12, 0x04, // JP 0x204
+    ];
+
+    fn emulator() -> Emulator {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&ROM).unwrap();
+        emu
+    }
+
+    #[test]
+    fn test_click_per_back_to_back_load() {
+        let mut emu = Emulator::new(EmulatorConfig {
+            beep_style: BeepStyle::Click,
+            cycles_per_frame: 2,
+            ..EmulatorConfig::default()
+        });
+        // LD V1, 0x05 ; LD ST, V1 ; JP 0x202, loading ST once a frame.
+        emu.load_rom(&[0x61, 0x05, 0xF1, 0x18, 0x12, 0x02]).unwrap();
+        let mut frontend = HeadlessFrontend::new(3);
+        run(&mut emu, &mut frontend);
+        use AudioCommand::{Start, Stop};
+        assert_eq!(frontend.audio(), &[Start, Stop, Start, Stop, Start]);
+    }
+
+    #[test]
+    fn test_run_headless_scripted() {
+        let mut emu = emulator();
+        let mut frontend = HeadlessFrontend::new(10);
+        frontend.push_input(2, InputEvent::Key(KeyEvent::Down(5)));
+        frontend.push_input(3, InputEvent::Key(KeyEvent::Up(5)));
+
+        assert_eq!(run(&mut emu, &mut frontend), StopReason::Quit);
+        assert_eq!(frontend.presented(), 10);
+        assert_eq!(frontend.audio(), &[AudioCommand::Start, AudioCommand::Stop]);
+        let last = frontend.last_frame().unwrap();
+        assert_eq!(last.frame, 10);
+        assert_ne!(last.screen_version, 0);
+    }
+
+    #[test]
+    fn test_run_quit_from_input() {
+        let mut emu = emulator();
+        let mut frontend = HeadlessFrontend::new(100);
+        frontend.push_input(4, InputEvent::Quit);
+
+        assert_eq!(run(&mut emu, &mut frontend), StopReason::Quit);
+        assert_eq!(frontend.presented(), 4);
+    }
+
+    #[test]
+    fn test_run_halted() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&[0x00, 0xFD]).unwrap();
+        let mut frontend = HeadlessFrontend::new(100);
+
+        assert_eq!(run(&mut emu, &mut frontend), StopReason::Halted);
+        assert_eq!(frontend.presented(), 1);
+    }
+
+    #[test]
+    fn test_run_halted_shows_last_frame_and_stops_audio() {
+        let mut emu = Emulator::new(EmulatorConfig {
+            cycles_per_frame: 2,
+            ..EmulatorConfig::default()
+        });
+        // LD V1, 0x10; LD ST, V1 in the first frame, then
+        // DRW V0, V0, 5; EXIT in the second.
+        emu.load_rom(&[0x61, 0x10, 0xF1, 0x18, 0xD0, 0x05, 0x00, 0xFD])
+            .unwrap();
+        let mut frontend = HeadlessFrontend::new(100);
+
+        assert_eq!(run(&mut emu, &mut frontend), StopReason::Halted);
+        assert_eq!(frontend.presented(), 2);
+        let last = frontend.last_frame().unwrap();
+        assert!(last.chip.screen.bytes().any(|b| b != 0));
+        assert_eq!(frontend.audio(), &[AudioCommand::Start, AudioCommand::Stop]);
+    }
+
+    #[test]
+    fn test_run_faulted() {
+        use crate::chip8::emulator::Halt;
+
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        // LD V0, 0x07; then a word that panics in exec.
+        emu.load_rom(&[0x60, 0x07, 0x5A, 0xB1]).unwrap();
+        let mut frontend = HeadlessFrontend::new(100);
+
+        let stop = run(&mut emu, &mut frontend);
+        assert_eq!((stop, stop.name()), (StopReason::Faulted, "fault"));
+        assert_eq!(emu.halt(), Some(Halt::Fault { pc: 0x202 }));
+        assert!(emu.fault().unwrap().starts_with("Unkown opcode: 0x5AB1"));
+        assert_eq!(emu.chip().v[0], 7);
+
+        // The machine is left alone from then on.
+        let executed = emu.executed();
+        assert!(emu.run_frame_caught().halted);
+        assert_eq!((emu.executed(), emu.chip().pc), (executed, 0x202));
+        assert_eq!(run(&mut emu, &mut frontend), StopReason::Faulted);
+    }
+
+    #[test]
+    fn test_run_reached_end() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        // CLS; then padding.
+        emu.load_rom(&[0x00, 0xE0, 0x00, 0x00, 0x00, 0x00]).unwrap();
+        let mut frontend = HeadlessFrontend::new(100);
+
+        let stop = run(&mut emu, &mut frontend);
+        assert_eq!((stop, stop.name()), (StopReason::ReachedEnd, "reached_end"));
+    }
+
+    #[test]
+    fn test_run_end_of_rom() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        // LD V0, 0x01; then nothing.
+        emu.load_rom(&[0x60, 0x01]).unwrap();
+        let mut frontend = HeadlessFrontend::new(100);
+
+        let stop = run(&mut emu, &mut frontend);
+        assert_eq!((stop, stop.name()), (StopReason::EndOfRom, "end_of_rom"));
+    }
+
+    #[test]
+    fn test_run_infinite_loop() {
+        let mut emu = Emulator::new(EmulatorConfig {
+            halt_on_infinite_loop: true,
+            ..EmulatorConfig::default()
+        });
+        // CLS; JP 0x202
+        emu.load_rom(&[0x00, 0xE0, 0x12, 0x02]).unwrap();
+        let mut frontend = HeadlessFrontend::new(100);
+
+        let stop = run(&mut emu, &mut frontend);
+        assert_eq!(
+            (stop, stop.name()),
+            (StopReason::InfiniteLoop, "infinite_loop")
+        );
+        assert_eq!(frontend.presented(), 1);
+    }
+
+    #[test]
+    fn test_run_keypad_rom() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(include_bytes!("../../roms/keypad.ch8"))
+            .unwrap();
+        let mut frontend = HeadlessFrontend::new(8);
+        frontend.push_input(1, InputEvent::Key(KeyEvent::Down(0xA)));
+        frontend.push_input(3, InputEvent::Key(KeyEvent::Up(0xA)));
+
+        assert_eq!(run(&mut emu, &mut frontend), StopReason::Quit);
+        assert_eq!(frontend.audio(), &[AudioCommand::Start, AudioCommand::Stop]);
+        // The "A" glyph is drawn at (28, 13): V1 = 0x1C, V2 = 0x0D.
+        let chip = &frontend.last_frame().unwrap().chip;
+        let rows: Vec<u16> = (13..18)
+            .map(|y| u16::from_be_bytes([chip.screen.byte(y, 3), chip.screen.byte(y, 4)]))
+            .collect();
+        let glyph: Vec<u16> = [0xF0, 0x90, 0xF0, 0x90, 0x90]
+            .iter()
+            .map(|&b| (b as u16) << 4)
+            .collect();
+        assert_eq!(rows, glyph);
+    }
+
+    #[test]
+    fn test_run_load_rom() {
+        let mut emu = emulator();
+        let mut frontend = HeadlessFrontend::new(4);
+        frontend.push_input(0, InputEvent::Key(KeyEvent::Down(5)));
+        frontend.push_input(2, InputEvent::LoadRom(vec![0x12, 0x00]));
+        run(&mut emu, &mut frontend);
+
+        let last = frontend.last_frame().unwrap();
+        assert_eq!(last.frame, 2);
+        assert_eq!(last.chip.pc, 0x200);
+        assert!(!last.chip.keys[5]);
+        assert_eq!(last.chip.screen.bytes().sum::<u8>(), 0);
+    }
+
+    #[test]
+    fn test_run_load_rom_too_large() {
+        let mut emu = emulator();
+        let mut frontend = HeadlessFrontend::new(4);
+        let rom = vec![0; emu.max_rom_size() + 1];
+        frontend.push_input(2, InputEvent::LoadRom(rom));
+        run(&mut emu, &mut frontend);
+
+        // The old program carried on.
+        assert_eq!(frontend.last_frame().unwrap().frame, 4);
+        assert_eq!(emu.chip().memory[0x200..0x202], ROM[..2]);
+        assert_eq!(emu.chip().v[1], 2);
+    }
+
+    #[test]
+    fn test_recording_matches_replay() {
+        let mut emu = emulator();
+        let mut frontend = HeadlessFrontend::new(6);
+        frontend.push_input(1, InputEvent::Key(KeyEvent::Down(5)));
+        frontend.push_input(2, InputEvent::Speed(SpeedChange::TurboOn));
+        frontend.push_input(4, InputEvent::Key(KeyEvent::Up(5)));
+        let mut recording = Recording::new(frontend);
+        run(&mut emu, &mut recording);
+
+        let mut replayed = emulator();
+        let mut frontend = HeadlessFrontend::new(6).with_replay(&recording.replay);
+        run(&mut replayed, &mut frontend);
+
+        assert_eq!(
+            replayed.snapshot().state_hash(),
+            emu.snapshot().state_hash()
+        );
+        assert!(replayed.speed_toggles().turbo);
+        assert_eq!(replayed.executed(), emu.executed());
+    }
+}
@@ -0,0 +1,416 @@
+//! A minimal GDB remote serial protocol stub.
+//!
+//! Supports reading and writing registers and memory, continue, single step
+//! and software breakpoints (`Z0`/`z0`). The machine only runs while GDB has
+//! asked it to continue or step.
+//!
+//! CHIP-8 has no GDB target description, so registers are presented as a
+//! flat file of 23 bytes: V0 to VF, then I and PC as little-endian 16-bit
+//! values, then SP, DT and ST as single bytes.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::Ordering;
+
+use crate::chip8::cpu::ExecError;
+use crate::chip8::debugger::{Debugger, Stop};
+
+/// Size of the register file sent for `g`, in bytes.
+pub const REGISTER_BYTES: usize = 23;
+
+/// Instructions run between checks for an interrupt from GDB.
+const RUN_CHUNK: u64 = 10_000;
+
+/// A connection to GDB.
+pub trait Transport: Read + Write {
+    /// Consume a pending interrupt (Ctrl-C, sent as 0x03) without blocking,
+    /// returning whether there was one.
+    fn interrupted(&mut self) -> io::Result<bool>;
+}
+
+impl Transport for TcpStream {
+    fn interrupted(&mut self) -> io::Result<bool> {
+        let mut byte = [0];
+        self.set_nonblocking(true)?;
+        let peeked = self.peek(&mut byte);
+        self.set_nonblocking(false)?;
+        match peeked {
+            Ok(1) if byte[0] == 0x03 => {
+                self.read_exact(&mut byte)?;
+                Ok(true)
+            }
+            Ok(_) => Ok(false),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// What to do after handling a packet.
+enum Action {
+    Reply(String),
+    /// Send the reply, if any, then end the session.
+    Close(Option<String>),
+}
+
+pub struct GdbStub<T> {
+    transport: T,
+    dbg: Debugger,
+}
+
+impl<T: Transport> GdbStub<T> {
+    pub fn new(transport: T, dbg: Debugger) -> Self {
+        Self { transport, dbg }
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.dbg
+    }
+
+    /// Answer packets until GDB detaches, kills the session or disconnects.
+    pub fn serve(&mut self) -> io::Result<()> {
+        while let Some(packet) = self.read_packet()? {
+            log_event!(debug, packet = %packet, "gdb packet");
+            match self.handle(&packet)? {
+                Action::Reply(reply) => self.send_packet(&reply)?,
+                Action::Close(reply) => {
+                    if let Some(reply) = reply {
+                        self.send_packet(&reply)?;
+                    }
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The next well-formed packet's contents, acknowledging it, or `None`
+    /// at end of input. Acks from GDB and corrupt packets are skipped.
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                Some(_) => continue,
+            }
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+            let mut checksum = [0; 2];
+            self.transport.read_exact(&mut checksum)?;
+            let expected = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if expected == Some(checksum_of(&data)) {
+                self.transport.write_all(b"+")?;
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+            self.transport.write_all(b"-")?;
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.transport.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    fn send_packet(&mut self, data: &str) -> io::Result<()> {
+        let checksum = checksum_of(data.as_bytes());
+        write!(self.transport, "${data}#{checksum:02x}")?;
+        self.transport.flush()
+    }
+
+    fn handle(&mut self, packet: &str) -> io::Result<Action> {
+        let mut chars = packet.chars();
+        let command = chars.next();
+        let args = chars.as_str();
+        let reply = match command {
+            Some('?') => "S05".to_string(),
+            Some('g') => hex_encode(&self.read_registers()),
+            Some('G') => match hex_decode(args) {
+                Some(regs) if regs.len() == REGISTER_BYTES => {
+                    self.write_registers(&regs);
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            Some('m') => self.read_memory(args).unwrap_or_else(|| "E01".to_string()),
+            Some('M') => match self.write_memory(args) {
+                Some(()) => "OK".to_string(),
+                None => "E01".to_string(),
+            },
+            Some('c') => self.resume()?,
+            Some('s') if self.dbg.step() => "S05".to_string(),
+            Some('s') => stop_reply(self.dbg.stop()),
+            Some('Z' | 'z') => match breakpoint_addr(args) {
+                Some(addr) if command == Some('Z') => {
+                    self.dbg.add_breakpoint(addr);
+                    "OK".to_string()
+                }
+                Some(addr) => {
+                    self.dbg.remove_breakpoint(addr);
+                    "OK".to_string()
+                }
+                None => String::new(),
+            },
+            Some('D') => return Ok(Action::Close(Some("OK".to_string()))),
+            Some('k') => return Ok(Action::Close(None)),
+            _ if packet.starts_with("qSupported") => "PacketSize=1000".to_string(),
+            _ if packet == "qAttached" => "1".to_string(),
+            // An empty reply tells GDB the packet isn't supported.
+            _ => String::new(),
+        };
+        Ok(Action::Reply(reply))
+    }
+
+    /// Run until a breakpoint, EXIT or an interrupt, returning the stop reply.
+    fn resume(&mut self) -> io::Result<String> {
+        loop {
+            match self.dbg.run(RUN_CHUNK) {
+                Stop::Limit if self.transport.interrupted()? => return Ok("S02".to_string()),
+                Stop::Limit => {}
+                stop => return Ok(stop_reply(stop)),
+            }
+        }
+    }
+
+    fn read_registers(&self) -> Vec<u8> {
+        let chip = self.dbg.emulator().chip();
+        let mut regs = chip.v.to_vec();
+        regs.extend_from_slice(&(chip.i as u16).to_le_bytes());
+        regs.extend_from_slice(&(chip.pc as u16).to_le_bytes());
+        regs.push(chip.sp as u8);
+        regs.push(chip.dt.load(Ordering::Acquire));
+        regs.push(chip.st.load(Ordering::Acquire));
+        regs
+    }
+
+    fn write_registers(&mut self, regs: &[u8]) {
+        let chip = self.dbg.emulator_mut().chip_mut();
+        let len = chip.memory.len();
+        chip.v.copy_from_slice(&regs[..16]);
+        chip.i = u16::from_le_bytes([regs[16], regs[17]]) as usize % len;
+        chip.pc = u16::from_le_bytes([regs[18], regs[19]]) as usize % len;
+        chip.sp = regs[20] as usize % chip.stack.len();
+        chip.dt.store(regs[21], Ordering::Release);
+        chip.st.store(regs[22], Ordering::Release);
+    }
+
+    /// `addr,len` in hex.
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let (addr, len) = addr_len(args)?;
+        let memory = &self.dbg.emulator().chip().memory;
+        Some(hex_encode(memory.get(addr..addr.checked_add(len)?)?))
+    }
+
+    /// `addr,len:bytes` in hex.
+    fn write_memory(&mut self, args: &str) -> Option<()> {
+        let (range, data) = args.split_once(':')?;
+        let (addr, len) = addr_len(range)?;
+        let data = hex_decode(data).filter(|data| data.len() == len)?;
+        let memory = &mut self.dbg.emulator_mut().chip_mut().memory;
+        memory
+            .get_mut(addr..addr.checked_add(len)?)?
+            .copy_from_slice(&data);
+        Some(())
+    }
+}
+
+/// The stop packet for `stop`: SIGTRAP for a breakpoint, SIGSEGV for a bad
+/// memory access, SIGILL for any other fault, and exited for a halt.
+fn stop_reply(stop: Stop) -> String {
+    match stop {
+        Stop::Breakpoint(_) | Stop::Limit => "S05",
+        Stop::Fault {
+            error:
+                ExecError::MemoryOutOfBounds { .. }
+                | ExecError::PcOutOfBounds { .. }
+                | ExecError::ProtectedWrite { .. },
+            ..
+        } => "S0b",
+        Stop::Fault { .. } => "S04",
+        Stop::Halted => "W00",
+    }
+    .to_string()
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &b| sum.wrapping_add(b))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn addr_len(args: &str) -> Option<(usize, usize)> {
+    let (addr, len) = args.split_once(',')?;
+    Some((
+        usize::from_str_radix(addr, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+/// The address of a software breakpoint packet body, `0,addr,kind`.
+fn breakpoint_addr(args: &str) -> Option<usize> {
+    let mut fields = args.split(',');
+    if fields.next()? != "0" {
+        return None;
+    }
+    usize::from_str_radix(fields.next()?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::{Emulator, EmulatorConfig};
+    use std::io::Cursor;
+
+    /// Replays scripted input from GDB and records everything sent back.
+    struct MockTransport {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for MockTransport {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockTransport {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn interrupted(&mut self) -> io::Result<bool> {
+            Ok(false)
+        }
+    }
+
+    fn packet(data: &str) -> String {
+        format!("${data}#{:02x}", checksum_of(data.as_bytes()))
+    }
+
+    /// Run a session over `packets` and return the stub's output.
+    fn session(rom: &[u8], packets: &[&str]) -> String {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(rom).unwrap();
+        let input: String = packets.iter().map(|p| packet(p) + "+").collect();
+        let transport = MockTransport {
+            input: Cursor::new(input.into_bytes()),
+            output: Vec::new(),
+        };
+        let mut stub = GdbStub::new(transport, Debugger::new(emu));
+        stub.serve().unwrap();
+        String::from_utf8(stub.transport.output).unwrap()
+    }
+
+    // LD V0, 0x2A; LD I, 0x300; ADD V0, 1; JP 0x204
+    const ROM: [u8; 8] = [0x60, 0x2A, 0xA3, 0x00, 0x70, 0x01, 0x12, 0x04];
+
+    /// The `g` reply for a machine where only V0, I and PC are set.
+    fn registers(v0: u8, i: u16, pc: u16) -> String {
+        let mut regs = vec![0; REGISTER_BYTES];
+        regs[0] = v0;
+        regs[16..18].copy_from_slice(&i.to_le_bytes());
+        regs[18..20].copy_from_slice(&pc.to_le_bytes());
+        hex_encode(&regs)
+    }
+
+    #[test]
+    fn test_read_registers() {
+        let output = session(&ROM, &["s", "s", "g"]);
+        assert_eq!(
+            output,
+            format!(
+                "+{}+{}+{}",
+                packet("S05"),
+                packet("S05"),
+                packet(&registers(0x2A, 0x300, 0x204))
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_and_write_memory() {
+        let output = session(&ROM, &["m200,4", "M300,2:beef", "m300,2", "m fff,2"]);
+        assert_eq!(
+            output,
+            [
+                packet("602aa300"),
+                packet("OK"),
+                packet("beef"),
+                packet("E01")
+            ]
+            .map(|p| format!("+{p}"))
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_hit() {
+        let output = session(&ROM, &["Z0,206,2", "c", "g", "z0,206,2", "D"]);
+        assert_eq!(
+            output,
+            [
+                packet("OK"),
+                packet("S05"),
+                packet(&registers(0x2B, 0x300, 0x206)),
+                packet("OK"),
+                packet("OK"),
+            ]
+            .map(|p| format!("+{p}"))
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_fault_stops_with_signal() {
+        // An unknown opcode, then a jump to itself.
+        let output = session(&[0x5A, 0xB1, 0x12, 0x00], &["s", "c"]);
+        assert_eq!(output, format!("+{}+{}", packet("S04"), packet("S04")));
+        // LD I, 0xFFF; LD V1, [I] reads past the end of RAM.
+        let output = session(&[0xAF, 0xFF, 0xF1, 0x65], &["c"]);
+        assert_eq!(output, format!("+{}", packet("S0b")));
+    }
+
+    #[test]
+    fn test_bad_checksum_is_nacked() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&ROM).unwrap();
+        let transport = MockTransport {
+            input: Cursor::new(format!("$g#00{}", packet("?")).into_bytes()),
+            output: Vec::new(),
+        };
+        let mut stub = GdbStub::new(transport, Debugger::new(emu));
+        stub.serve().unwrap();
+        assert_eq!(
+            String::from_utf8(stub.transport.output).unwrap(),
+            format!("-+{}", packet("S05"))
+        );
+    }
+}
@@ -1,18 +1,126 @@
+use std::cell::RefCell;
+use std::fmt::Write;
 use std::sync::atomic::Ordering;
 
-use random_number::random;
-
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Modifier, Style};
-use ratatui::text::Span;
-use ratatui::widgets::{Block, Borders, Row, Table};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Clear, RenderDirection, Row, Sparkline, Table};
 use ratatui::{style::Color, Frame};
 
-use crate::chip8::consts::{PROGRAM_START, WINDOW};
-use crate::chip8::cpu::Chip8;
+use crate::chip8::call_history::CallHistory;
+use crate::chip8::consts::{PROGRAM_START, W, WINDOW};
+use crate::chip8::cpu::{Chip8, Screen};
 use crate::chip8::decode::decode;
+use crate::chip8::keymap::{KeyMap, KEYPAD};
+use crate::chip8::op::ChipOp;
+use crate::chip8::rng::Rng;
+use crate::chip8::romdb::RomInfo;
+use crate::chip8::symbols::Symbols;
+use crate::chip8::timer_history::TimerHistory;
+
+/// SP against the configured stack depth, e.g. `3/16`.
+pub fn stack_pointer(c8: &Chip8) -> String {
+    format!("{}/{}", c8.sp, c8.stack.len())
+}
 
-pub fn render_chip8_debug(f: &mut Frame, area: Rect, c8: &Chip8) {
+/// A line of the instructions pane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstrRow {
+    /// A label from `--symbols`, above the instruction it names.
+    Label(String),
+    /// Address, instruction, and the source line it came from or else the
+    /// decoded variant; all `-` outside the program.
+    Instr { cells: [String; 3], at_pc: bool },
+}
+
+/// The instructions pane's row for `addr`.
+pub fn instruction_row(memory: &[u8], addr: usize, symbols: &Symbols) -> [String; 3] {
+    let word = memory.get(addr..addr + 2).filter(|_| addr >= PROGRAM_START);
+    let Some(&[b, s]) = word else {
+        return ["-".into(), "-".into(), "-".into()];
+    };
+    let op = decode(u16::from_be_bytes([b, s]));
+    let source = match symbols.line(addr) {
+        Some(line) => format!("{}: {}", line.number, line.text),
+        None => format!("({op:?})"),
+    };
+    let text = match op {
+        ChipOp::JpNnn { nnn } => symbols.label(nnn).map(|name| format!("JP {name}")),
+        ChipOp::CallNnn { nnn } => symbols.label(nnn).map(|name| format!("CALL {name}")),
+        _ => None,
+    }
+    .unwrap_or_else(|| op.to_string());
+    [format!("0x{addr:03X}"), text, source]
+}
+
+/// The instructions pane: [`WINDOW`] instructions either side of `pc`, or
+/// of `lock` while the view is locked, with a row for each label. Rows
+/// before the program or past the end of memory are padding.
+pub fn build_instruction_rows(
+    pc: usize,
+    lock: Option<usize>,
+    symbols: &Symbols,
+    memory: &[u8],
+) -> Vec<InstrRow> {
+    let centre = lock.unwrap_or(pc) as isize;
+    let mut rows = Vec::with_capacity(WINDOW as usize * 2 + 1);
+    for d in -WINDOW..=WINDOW {
+        let addr = centre + d * 2;
+        let cells = match usize::try_from(addr) {
+            Ok(addr) => {
+                if let Some(label) = symbols.label(addr) {
+                    rows.push(InstrRow::Label(label.to_string()));
+                }
+                instruction_row(memory, addr, symbols)
+            }
+            Err(_) => ["-".into(), "-".into(), "-".into()],
+        };
+        let at_pc = addr == pc as isize && cells[0] != "-";
+        rows.push(InstrRow::Instr { cells, at_pc });
+    }
+    rows
+}
+
+/// The calls pane: the return addresses on the stack, innermost first,
+/// then the recorded CALLs and RETs, newest first.
+pub fn call_rows(c8: &Chip8, history: &CallHistory) -> Vec<String> {
+    let live = c8.stack[..c8.sp.min(c8.stack.len())]
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(level, addr)| format!("{level:>2} ret {addr:#05x}"));
+    live.chain(history.events().rev().map(ToString::to_string))
+        .collect()
+}
+
+/// Style of register `reg`'s cell in the V-register grid: VF stands out
+/// in red while it's set, so collisions are easy to spot.
+pub fn v_register_style(reg: usize, value: u8) -> Style {
+    if reg == 0xF && value != 0 {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    }
+}
+
+/// What the debug pane shows besides the machine itself.
+#[derive(Clone, Copy)]
+pub struct DebugPane<'a> {
+    pub calls: &'a CallHistory,
+    pub timers: &'a TimerHistory,
+    pub symbols: &'a Symbols,
+    /// Where the instructions pane is locked, or `None` to follow PC.
+    pub lock: Option<usize>,
+}
+
+pub fn render_chip8_debug(f: &mut Frame, area: Rect, c8: &Chip8, pane: DebugPane) {
+    let DebugPane {
+        calls: history,
+        timers,
+        symbols,
+        lock,
+    } = pane;
     // ── split the screen ────────────────────────────────────────────────────────
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -23,17 +131,22 @@ pub fn render_chip8_debug(f: &mut Frame, area: Rect, c8: &Chip8) {
         ])
         .split(area);
 
-    // ── left-hand side: scalar regs + V-regs ────────────────────────────────────
+    // ── left-hand side: scalar regs + timer history + V-regs ────────────────────
     let left = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Min(3)])
+        .constraints([
+            Constraint::Length(8),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(3),
+        ])
         .split(chunks[0]);
 
     // ----- small scalar register table (PC / I / SP / DT / ST) -----
     let reg_rows = vec![
         Row::new(vec!["PC".into(), format!("0x{:03X}", c8.pc)]),
         Row::new(vec!["I".into(), format!("0x{:03X}", c8.i)]),
-        Row::new(vec!["SP".into(), c8.sp.to_string()]),
+        Row::new(vec!["SP".into(), stack_pointer(c8)]),
         Row::new(vec!["DT".into(), c8.dt.load(Ordering::Acquire).to_string()]),
         Row::new(vec!["ST".into(), c8.st.load(Ordering::Acquire).to_string()]),
     ];
@@ -43,17 +156,34 @@ pub fn render_chip8_debug(f: &mut Frame, area: Rect, c8: &Chip8) {
         .block(Block::default().borders(Borders::ALL).title("Registers"));
     f.render_widget(reg_table, left[0]);
 
+    // ----- DT / ST over the last rendered frames, newest on the right -----
+    let dt_line = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title("DT"))
+        .data(timers.dt().rev())
+        .direction(RenderDirection::RightToLeft)
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(dt_line, left[1]);
+    let st_color = if timers.audible() {
+        Color::Yellow
+    } else {
+        Color::DarkGray
+    };
+    let st_line = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title("ST"))
+        .data(timers.st().rev())
+        .direction(RenderDirection::RightToLeft)
+        .style(Style::default().fg(st_color));
+    f.render_widget(st_line, left[2]);
+
     // ----- V0..VF in a 4×4 grid -----
     let mut v_rows = Vec::with_capacity(4);
     for g in 0..4 {
         let b = g * 4;
-        v_rows.push(Row::new(vec![
-            format!("V{:X}..V{:X}", b, b + 3),
-            format!("0x{:02X}", c8.v[b]),
-            format!("0x{:02X}", c8.v[b + 1]),
-            format!("0x{:02X}", c8.v[b + 2]),
-            format!("0x{:02X}", c8.v[b + 3]),
-        ]));
+        let values = (b..b + 4)
+            .map(|r| Cell::from(format!("0x{:02X}", c8.v[r])).style(v_register_style(r, c8.v[r])));
+        v_rows.push(Row::new(
+            std::iter::once(Cell::from(format!("V{:X}..V{:X}", b, b + 3))).chain(values),
+        ));
     }
     let v_widths = [
         Constraint::Length(9),
@@ -68,7 +198,7 @@ pub fn render_chip8_debug(f: &mut Frame, area: Rect, c8: &Chip8) {
                 .style(Style::default().add_modifier(Modifier::BOLD)),
         )
         .block(Block::default().borders(Borders::ALL).title("V Registers"));
-    f.render_widget(v_table, left[1]);
+    f.render_widget(v_table, left[3]);
 
     // ── right-hand side: CHIP-8 keypad (pressed = green) ────────────────────────
     const MAP: [[(&str, u8); 4]; 4] = [
@@ -100,100 +230,931 @@ pub fn render_chip8_debug(f: &mut Frame, area: Rect, c8: &Chip8) {
     let key_widths = [Constraint::Length(3); 4];
     let key_table = Table::new(key_rows, key_widths)
         .block(Block::default().borders(Borders::ALL).title("Keypad"));
-    f.render_widget(key_table, chunks[1]);
+    let middle = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(3)])
+        .split(chunks[1]);
+    f.render_widget(key_table, middle[0]);
+
+    let call_table = Table::new(
+        call_rows(c8, history)
+            .into_iter()
+            .map(|r| Row::new(vec![r])),
+        [Constraint::Min(10)],
+    )
+    .block(Block::default().borders(Borders::ALL).title("Calls"));
+    f.render_widget(call_table, middle[1]);
 
     let cmd_widths = [
         Constraint::Length(7),
-        Constraint::Length(14),
-        Constraint::Length(6),
+        Constraint::Length(18),
+        Constraint::Min(6),
     ];
-    let mut cmd_rows: Vec<Row> = Vec::with_capacity((WINDOW as usize * 2) + 1);
 
     let hilite = Style::default()
         .fg(Color::Green)
         .add_modifier(Modifier::BOLD);
+    let label_style = Style::default().fg(Color::Yellow);
 
-    for d in -WINDOW..=WINDOW {
-        let addr_isize = c8.pc as isize + d * 2;
+    let cmd_rows = build_instruction_rows(c8.pc, lock, symbols, &c8.memory)
+        .into_iter()
+        .map(|row| match row {
+            InstrRow::Label(name) => {
+                Row::new(vec![String::new(), format!("{name}:")]).style(label_style)
+            }
+            InstrRow::Instr { cells, at_pc } => {
+                let row = Row::new(cells);
+                if at_pc {
+                    row.style(hilite)
+                } else {
+                    row
+                }
+            }
+        });
+
+    let title = match lock {
+        Some(addr) => format!("Instructions (locked at 0x{addr:03X})"),
+        None => "Instructions".to_string(),
+    };
+    let cmd_table =
+        Table::new(cmd_rows, cmd_widths).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(cmd_table, chunks[2]);
+}
+
+/// Control keys listed in the help overlay.
+pub const CONTROLS: &[(&str, &str)] = &[
+    ("?", "Toggle this help"),
+    ("PgUp / F9", "Previous ROM (--rom-dir)"),
+    ("PgDn / F10", "Next ROM (--rom-dir)"),
+    ("Tab (hold)", "Turbo, 8x speed"),
+    ("Shift+Tab", "Toggle slow motion, 0.25x"),
+    ("F2", "Next colour theme"),
+    ("F3", "Lock/unlock the instructions pane (--debug)"),
+    ("F12", "Save a machine dump"),
+    ("Esc", "Quit"),
+];
+
+/// Rows of the help overlay: the keypad as CHIP-8 key and keyboard key,
+/// followed by [`CONTROLS`].
+pub fn help_rows(keymap: &KeyMap) -> Vec<[String; 2]> {
+    let mut rows: Vec<[String; 2]> = KEYPAD
+        .iter()
+        .map(|row| {
+            let chip8: Vec<String> = row.iter().map(|k| format!("{k:X}")).collect();
+            let keys: Vec<String> = row
+                .iter()
+                .map(|&k| keymap.char_of_key(k).to_ascii_uppercase().to_string())
+                .collect();
+            [chip8.join(" "), keys.join(" ")]
+        })
+        .collect();
+    rows.push([String::new(), String::new()]);
+    rows.extend(
+        CONTROLS
+            .iter()
+            .map(|(key, action)| [key.to_string(), action.to_string()]),
+    );
+    rows
+}
+
+/// Draw the key help centred over `area`.
+pub fn render_help(f: &mut Frame, area: Rect, keymap: &KeyMap) {
+    let rows = help_rows(keymap);
+    let height = (rows.len() as u16 + 3).min(area.height);
+    let width = 44.min(area.width);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let table = Table::new(
+        rows.into_iter().map(Row::new),
+        [Constraint::Length(12), Constraint::Min(10)],
+    )
+    .header(
+        Row::new(vec!["CHIP-8", "Keyboard"]).style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::bordered().title("Help"));
+    f.render_widget(Clear, popup);
+    f.render_widget(table, popup);
+}
+
+/// Draw a fault report centred over `area`, with a prompt to dismiss it.
+pub fn render_fault(f: &mut Frame, area: Rect, details: &str) {
+    let lines: Vec<&str> = details.lines().collect();
+    let height = (lines.len() as u16 + 4).min(area.height);
+    let width = (lines.iter().map(|l| l.len()).max().unwrap_or(0) as u16 + 4)
+        .clamp(30, area.width.max(30))
+        .min(area.width);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let rows = lines
+        .into_iter()
+        .chain(["", "F5 save state · F12 save dump · Esc exit"])
+        .map(|line| Row::new(vec![line.to_string()]));
+    let table = Table::new(rows, [Constraint::Min(10)]).block(Block::bordered().title("Fault"));
+    f.render_widget(Clear, popup);
+    f.render_widget(table, popup);
+}
 
-        let mut row = if addr_isize < PROGRAM_START as isize {
-            Row::new(vec!["-".to_string(), "-".into(), "-".into()])
+/// Text framing the display: the ROM's title above and a hint below.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusBar {
+    pub title: Option<String>,
+    pub hint: Option<String>,
+    /// Turbo or slow motion, shown above on the right.
+    pub speed: Option<String>,
+}
+
+impl StatusBar {
+    /// Use the ROM database's title and keymap hint when known, otherwise
+    /// the ROM's file name.
+    pub fn for_rom(name: &str, info: Option<&RomInfo>) -> Self {
+        match info {
+            Some(info) => Self {
+                title: Some(info.title.to_string()),
+                hint: Some(info.keymap_hint.to_string()),
+                speed: None,
+            },
+            None => Self {
+                title: Some(name.to_string()),
+                hint: None,
+                speed: None,
+            },
+        }
+    }
+}
+
+/// Display colours as RGB triples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub on: [u8; 3],
+    pub off: [u8; 3],
+    /// XO-CHIP colours for pixels lit only in the second plane and in
+    /// both planes. Without them every lit pixel is drawn in `on`.
+    pub planes: Option<[[u8; 3]; 2]>,
+    /// Draw lit pixels in `off` and unlit pixels in `on`, for a
+    /// dark-on-light look.
+    pub invert: bool,
+    /// The colours the terminal can show.
+    pub depth: ColorDepth,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            on: [3, 220, 135],
+            off: [5, 24, 18],
+            planes: None,
+            invert: false,
+            depth: ColorDepth::TrueColor,
+        }
+    }
+}
+
+impl Palette {
+    /// The colour of a pixel that is `lit`, after any inversion.
+    pub fn color(&self, lit: bool) -> [u8; 3] {
+        if lit != self.invert {
+            self.on
         } else {
-            let addr = addr_isize as usize;
-            if addr + 1 >= c8.memory.len() {
-                Row::new(vec!["-".to_string(), "-".into(), "-".into()])
-            } else {
-                let b = c8.memory[addr];
-                let s = c8.memory[addr + 1];
-                let op = decode(u16::from_be_bytes([b, s]));
-                Row::new(vec![
-                    format!("0x{addr:03X}"),
-                    format!("{op}"),
-                    format!("({op:?})"),
-                ])
-            }
-        };
+            self.off
+        }
+    }
 
-        if d == 0 {
-            row = row.style(hilite);
+    /// The colour of a pixel lit in `planes`, bit 0 for the first plane
+    /// and bit 1 for the second.
+    pub fn plane_color(&self, planes: u8) -> [u8; 3] {
+        match (planes, self.planes) {
+            (0, _) => self.color(false),
+            (2, Some([second, _])) => second,
+            (3, Some([_, both])) => both,
+            _ => self.color(true),
         }
-        cmd_rows.push(row);
     }
 
-    let cmd_table = Table::new(cmd_rows, cmd_widths)
-        .block(Block::default().borders(Borders::ALL).title("Instructions"));
-    f.render_widget(cmd_table, chunks[2]);
+    /// This palette with theme `index`'s lit and unlit colours: 0 keeps
+    /// its own, and 1 on are the [`THEMES`] in order. Inversion and the
+    /// colour depth carry over; the XO-CHIP plane colours don't.
+    pub fn with_theme(self, index: usize) -> Self {
+        match index.checked_sub(1).and_then(|i| THEMES.get(i)) {
+            Some(&(_, on, off)) => Self {
+                on,
+                off,
+                planes: None,
+                ..self
+            },
+            None => self,
+        }
+    }
+}
+
+/// Preset colour themes as (name, lit, unlit), cycled through at runtime.
+pub const THEMES: &[(&str, [u8; 3], [u8; 3])] = &[
+    ("green phosphor", [51, 255, 102], [2, 20, 6]),
+    ("amber", [255, 176, 0], [26, 16, 0]),
+    ("blue", [120, 190, 255], [6, 14, 40]),
+    ("white", [240, 240, 240], [16, 16, 16]),
+];
+
+/// The theme after `index`, wrapping from the last preset back to the
+/// configured palette.
+pub fn next_theme(index: usize) -> usize {
+    (index + 1) % (THEMES.len() + 1)
+}
+
+/// Whether the terminal takes 24-bit colour or only the xterm 256-colour
+/// palette.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorDepth {
+    #[default]
+    TrueColor,
+    Ansi256,
+}
+
+impl ColorDepth {
+    /// Read the terminal's depth from `COLORTERM`.
+    pub fn detect() -> Self {
+        Self::from_colorterm(std::env::var("COLORTERM").ok().as_deref())
+    }
+
+    /// Terminals with 24-bit colour set `COLORTERM` to `truecolor` or
+    /// `24bit`; anything else might draw 24-bit escapes as black.
+    pub fn from_colorterm(colorterm: Option<&str>) -> Self {
+        match colorterm {
+            Some("truecolor" | "24bit") => Self::TrueColor,
+            _ => Self::Ansi256,
+        }
+    }
+}
+
+/// The xterm 256-colour index closest to `rgb`, from the 6x6x6 colour cube
+/// (16-231) or the grey ramp (232-255).
+pub fn ansi256(rgb: [u8; 3]) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let distance = |a: [u8; 3], b: [u8; 3]| -> u32 {
+        a.iter()
+            .zip(b)
+            .map(|(&x, y)| (x as i32 - y as i32).pow(2) as u32)
+            .sum()
+    };
+    let level = |c: u8| {
+        (0..LEVELS.len())
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let [r, g, b] = rgb.map(level);
+    let cube = [LEVELS[r], LEVELS[g], LEVELS[b]];
+    let cube_index = 16 + 36 * r + 6 * g + b;
+    // The grey ramp runs 8, 18, ... 238.
+    let mean = rgb.iter().map(|&c| c as u32).sum::<u32>() / 3;
+    let step = (mean.saturating_sub(3) / 10).min(23) as u8;
+    let grey = 8 + 10 * step;
+    if distance(rgb, [grey; 3]) < distance(rgb, cube) {
+        232 + step
+    } else {
+        cube_index as u8
+    }
+}
+
+const COLOR_FORMATS: &str = "expected 3 or 6 hex digits, like #fff or #1a1b26";
+
+/// Parse `#rrggbb` or the shorthand `#rgb` (the `#` is optional).
+pub fn parse_hex_color(s: &str) -> Result<[u8; 3], String> {
+    let invalid = || format!("invalid color {s:?}: {COLOR_FORMATS}");
+    let digits = s.strip_prefix('#').unwrap_or(s);
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let channel = |hex: &str| u8::from_str_radix(hex, 16).unwrap();
+    match digits.len() {
+        3 => Ok(std::array::from_fn(|i| channel(&digits[i..i + 1]) * 0x11)),
+        6 => Ok(std::array::from_fn(|i| channel(&digits[i * 2..i * 2 + 2]))),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse a palette of comma-separated colours: `bg,fg`, or
+/// `bg,fg,plane2,both` for XO-CHIP's second plane and the pixels lit in
+/// both.
+pub fn parse_palette(s: &str) -> Result<Palette, String> {
+    let colors = s
+        .split(',')
+        .enumerate()
+        .map(|(i, color)| {
+            parse_hex_color(color.trim())
+                .map_err(|err| format!("invalid palette {s:?}: color {}: {err}", i + 1))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (off, on, planes) = match colors[..] {
+        [off, on] => (off, on, None),
+        [off, on, second, both] => (off, on, Some([second, both])),
+        _ => {
+            return Err(format!(
+                "invalid palette {s:?}: expected 2 colors (bg,fg) or 4 (bg,fg,plane2,both)"
+            ))
+        }
+    };
+    Ok(Palette {
+        on,
+        off,
+        planes,
+        ..Palette::default()
+    })
+}
+
+fn tint(rgb: [u8; 3], percent: u16) -> (i16, i16, i16) {
+    let scale = |c: u8| (c as u16 * percent / 100).min(255) as i16;
+    (scale(rgb[0]), scale(rgb[1]), scale(rgb[2]))
+}
+
+thread_local! {
+    static FUZZ_RNG: RefCell<Rng> = RefCell::new(Rng::from_entropy());
 }
 
 fn fuzz(rgb: (i16, i16, i16)) -> Color {
+    // Each channel nudged by -3 to 1.
+    let jitter = || FUZZ_RNG.with(|rng| (rng.borrow_mut().next_u8() % 5) as i16 - 3);
     Color::Rgb(
-        ((rgb.0 + random!(-3..=1)) % 255) as u8,
-        ((rgb.1 + random!(-3..=1)) % 255) as u8,
-        ((rgb.2 + random!(-3..=1)) % 255) as u8,
+        ((rgb.0 + jitter()) % 255) as u8,
+        ((rgb.1 + jitter()) % 255) as u8,
+        ((rgb.2 + jitter()) % 255) as u8,
     )
 }
 
-pub fn view(chip: &Chip8, frame: &mut Frame, debug: bool) {
+/// Colour of pixels in [`FreshPixels`] when they're highlighted.
+pub const FRESH_COLOR: [u8; 3] = [255, 96, 64];
+
+/// The pixels the last change to the display turned on, tracked by the
+/// render thread to highlight the sprites being redrawn.
+#[derive(Debug, Clone, Default)]
+pub struct FreshPixels {
+    version: u64,
+    previous: Screen,
+    fresh: Screen,
+}
+
+impl FreshPixels {
+    /// Diff `screen` against the one before it, when `version` says it
+    /// has changed since the last call.
+    pub fn update(&mut self, version: u64, screen: &Screen) {
+        if version != self.version {
+            self.version = version;
+            self.fresh = screen.newly_set(&self.previous);
+            self.previous.clone_from(screen);
+        }
+    }
+
+    pub fn pixels(&self) -> &Screen {
+        &self.fresh
+    }
+}
+
+/// Draw the display, and the debug pane beside it when `debug` is given.
+/// Pixels lit in `fresh` are drawn in [`FRESH_COLOR`].
+pub fn view(
+    chip: &Chip8,
+    frame: &mut Frame,
+    debug: Option<DebugPane>,
+    status: &StatusBar,
+    palette: &Palette,
+    fresh: Option<&Screen>,
+) {
     let main_area = frame.area();
 
     let [left_area, right_area] = Layout::horizontal([
-        Constraint::Length((64 * chip.resolution.factor() as u16) + 4),
+        Constraint::Length(chip.display_dimensions().0 as u16 + 4),
         Constraint::Percentage(60),
     ])
     .areas(main_area);
 
-    let outer_left_block = Block::bordered().title("Oxid-8");
+    let mut outer_left_block = match &status.title {
+        Some(title) => Block::bordered().title(format!("Oxid-8 · {title}")),
+        None => Block::bordered().title("Oxid-8"),
+    };
+    if let Some(hint) = &status.hint {
+        outer_left_block = outer_left_block.title_bottom(hint.as_str());
+    }
+    if let Some(speed) = &status.speed {
+        outer_left_block = outer_left_block.title_top(Line::from(speed.as_str()).right_aligned());
+    }
     let inner_left = outer_left_block.inner(left_area);
 
     frame.render_widget(outer_left_block, left_area);
-    if debug {
-        render_chip8_debug(frame, right_area, chip);
+    if let Some(pane) = debug {
+        render_chip8_debug(frame, right_area, chip, pane);
     }
 
     let buf = frame.buffer_mut();
-    for y in 0..(16 * chip.resolution.factor()) {
-        for x in 0..(8 * chip.resolution.factor()) {
-            let mut fg = chip.screen[(y * 2, x)];
-            let mut bg = chip.screen[((y * 2) + 1, x)];
-
-            let x_buf = (x * 8) as u16 + inner_left.x;
-            let y_buf = y as u16 + inner_left.y;
-
-            for bit in 0..8 {
-                if let Some(cell) = buf.cell_mut((x_buf + (8 - bit), y_buf)) {
-                    cell.set_symbol("▀");
-                    cell.set_fg(fuzz((10, 25, 20)));
-                    cell.set_bg(fuzz((5, 24, 18)));
-                    if fg & 0x1 == 0x1 {
-                        cell.set_fg(fuzz((3, 220, 135)));
-                    }
-                    if bg & 0x1 == 0x1 {
-                        cell.set_bg(fuzz((3, 180, 96)));
+    let (width, height) = chip.display_dimensions();
+    for y in (0..height).step_by(2) {
+        for x in 0..width {
+            let cell_pos = (inner_left.x + x as u16 + 1, inner_left.y + (y / 2) as u16);
+            if let Some(cell) = buf.cell_mut(cell_pos) {
+                let (top, bottom) = (pixel_planes(chip, x, y), pixel_planes(chip, x, y + 1));
+                let color = |y: usize, planes: u8| match fresh {
+                    Some(fresh) if fresh.pixel(x, y) => FRESH_COLOR,
+                    _ => palette.plane_color(planes),
+                };
+                cell.set_symbol("▀");
+                cell.set_fg(terminal_color(palette, color(y, top), 100));
+                // Shade a lit lower half like a scanline.
+                let shade = if bottom != 0 { 82 } else { 100 };
+                cell.set_bg(terminal_color(palette, color(y + 1, bottom), shade));
+            }
+        }
+    }
+}
+
+/// Which planes light buffer pixel (`x`, `y`): bit 0 for the first plane
+/// and bit 1 for the second.
+fn pixel_planes(chip: &Chip8, x: usize, y: usize) -> u8 {
+    std::iter::once(&chip.screen)
+        .chain(&chip.extra_planes)
+        .enumerate()
+        .fold(0, |planes, (i, plane)| {
+            planes | u8::from(plane.pixel(x, y)) << i
+        })
+}
+
+/// `rgb` tinted to `percent` brightness, as the terminal can draw it.
+fn terminal_color(palette: &Palette, rgb: [u8; 3], percent: u16) -> Color {
+    let tinted = tint(rgb, percent);
+    match palette.depth {
+        ColorDepth::TrueColor => fuzz(tinted),
+        ColorDepth::Ansi256 => {
+            Color::Indexed(ansi256([tinted.0 as u8, tinted.1 as u8, tinted.2 as u8]))
+        }
+    }
+}
+
+/// Width in pixels of the buffer filled by [`write_rgb24`].
+pub const FRAMEBUFFER_WIDTH: usize = W * 8;
+/// Height in pixels of the buffer filled by [`write_rgb24`].
+pub const FRAMEBUFFER_HEIGHT: usize = 64;
+
+/// Fill `buf` with the display as packed RGB24 rows `pitch` bytes apart.
+///
+/// The buffer is always 128x64; in low-res each CHIP-8 pixel covers 2x2
+/// buffer pixels, so a frontend can keep one texture across resolution
+/// changes.
+pub fn write_rgb24(chip: &Chip8, buf: &mut [u8], pitch: usize, palette: &Palette) {
+    let scale = FRAMEBUFFER_WIDTH / chip.display_dimensions().0;
+    for y in 0..FRAMEBUFFER_HEIGHT {
+        let row = &mut buf[y * pitch..y * pitch + FRAMEBUFFER_WIDTH * 3];
+        for (x, px) in row.chunks_exact_mut(3).enumerate() {
+            let (cx, cy) = (x / scale, y / scale);
+            px.copy_from_slice(&palette.plane_color(pixel_planes(chip, cx, cy)));
+        }
+    }
+}
+
+/// The display as a binary PPM (P6) image in `palette`'s colours, laid
+/// out like [`write_rgb24`].
+pub fn ppm(chip: &Chip8, palette: &Palette) -> Vec<u8> {
+    let mut out = format!("P6\n{FRAMEBUFFER_WIDTH} {FRAMEBUFFER_HEIGHT}\n255\n").into_bytes();
+    let header = out.len();
+    let pitch = FRAMEBUFFER_WIDTH * 3;
+    out.resize(header + pitch * FRAMEBUFFER_HEIGHT, 0);
+    write_rgb24(chip, &mut out[header..], pitch, palette);
+    out
+}
+
+/// How [`screen_to_ansi`] colours its output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnsiMode {
+    /// Colour escapes in the palette's colours, 24-bit or 256-colour by
+    /// its depth.
+    #[default]
+    Color,
+    /// Block characters only, ignoring the palette.
+    Plain,
+}
+
+/// The visible display as text, two pixel rows per line drawn with half
+/// blocks: 64x16 characters in low-res and 128x32 in high-res. Unlike
+/// [`view`] it needs no terminal, so it can be printed, piped or pasted.
+pub fn screen_to_ansi(chip: &Chip8, palette: &Palette, mode: AnsiMode) -> String {
+    let (width, height) = chip.display_dimensions();
+    let mut out = String::new();
+    for y in (0..height).step_by(2) {
+        let mut last = None;
+        for x in 0..width {
+            let (top, bottom) = (pixel_planes(chip, x, y), pixel_planes(chip, x, y + 1));
+            match mode {
+                AnsiMode::Plain => out.push(match (top != 0, bottom != 0) {
+                    (false, false) => ' ',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (true, true) => '█',
+                }),
+                AnsiMode::Color => {
+                    // Only emit an escape when the colours change.
+                    let colors = (palette.plane_color(top), palette.plane_color(bottom));
+                    if last != Some(colors) {
+                        let ([fr, fg, fb], [br, bg, bb]) = colors;
+                        match palette.depth {
+                            ColorDepth::TrueColor => {
+                                write!(out, "\x1b[38;2;{fr};{fg};{fb};48;2;{br};{bg};{bb}m")
+                            }
+                            ColorDepth::Ansi256 => {
+                                let (fg, bg) = (ansi256(colors.0), ansi256(colors.1));
+                                write!(out, "\x1b[38;5;{fg};48;5;{bg}m")
+                            }
+                        }
+                        .unwrap();
+                        last = Some(colors);
                     }
+                    out.push('▀');
                 }
-                fg >>= 1;
-                bg >>= 1;
             }
         }
+        if mode == AnsiMode::Color {
+            out.push_str("\x1b[0m");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::cpu::{CallStack, Resolution};
+
+    const ON: [u8; 3] = [0xFF, 0xFF, 0xFF];
+    const OFF: [u8; 3] = [0, 0, 0];
+    const PALETTE: Palette = Palette {
+        on: ON,
+        off: OFF,
+        planes: None,
+        invert: false,
+        depth: ColorDepth::TrueColor,
+    };
+
+    fn lit(buf: &[u8], pitch: usize, x: usize, y: usize) -> bool {
+        buf[y * pitch + x * 3..y * pitch + x * 3 + 3] == ON
+    }
+
+    #[test]
+    fn test_instruction_row_shows_symbols() {
+        let mut chip = Chip8::new();
+        chip.memory[0x200..0x206].copy_from_slice(&[0x60, 0x01, 0x12, 0x00, 0x23, 0x00]);
+        let symbols: Symbols = "label 0x200 start\nline 0x200 3 LD V0, 1\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            instruction_row(&chip.memory, 0x200, &symbols),
+            ["0x200", "LD V0, 0x01", "3: LD V0, 1"]
+        );
+        assert_eq!(
+            instruction_row(&chip.memory, 0x202, &symbols),
+            ["0x202", "JP start", "(JpNnn)"]
+        );
+        // Targets without a label keep their address.
+        assert_eq!(
+            instruction_row(&chip.memory, 0x204, &symbols)[1],
+            "CALL 0x300"
+        );
+        assert_eq!(instruction_row(&chip.memory, 0xFFF, &symbols)[0], "-");
+        assert_eq!(instruction_row(&chip.memory, 0x1FE, &symbols)[0], "-");
+    }
+
+    /// The addresses of the instruction rows, `-` for padding.
+    fn row_addrs(rows: &[InstrRow]) -> Vec<&str> {
+        rows.iter()
+            .filter_map(|row| match row {
+                InstrRow::Instr { cells, .. } => Some(cells[0].as_str()),
+                InstrRow::Label(_) => None,
+            })
+            .collect()
+    }
+
+    fn pc_row(rows: &[InstrRow]) -> Option<&str> {
+        rows.iter().find_map(|row| match row {
+            InstrRow::Instr { cells, at_pc: true } => Some(cells[0].as_str()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_instruction_rows_pad_at_the_ends_of_memory() {
+        let memory = [0u8; 0x1000];
+        let none = Symbols::default();
+        let window = WINDOW as usize;
+
+        let rows = build_instruction_rows(0x200, None, &none, &memory);
+        let addrs = row_addrs(&rows);
+        assert_eq!(addrs.len(), 2 * window + 1);
+        assert!(addrs[..window].iter().all(|&a| a == "-"));
+        assert_eq!(addrs[window], "0x200");
+        assert_eq!(addrs[2 * window], format!("0x{:03X}", 0x200 + 2 * window));
+        assert_eq!(pc_row(&rows), Some("0x200"));
+
+        let rows = build_instruction_rows(0xFFE, None, &none, &memory);
+        let addrs = row_addrs(&rows);
+        assert_eq!(addrs[window - 1], "0xFFC");
+        assert_eq!(addrs[window], "0xFFE");
+        assert!(addrs[window + 1..].iter().all(|&a| a == "-"));
+
+        // Near 0 the window runs below address 0 as well.
+        let rows = build_instruction_rows(0x002, None, &none, &memory);
+        assert_eq!(row_addrs(&rows), vec!["-"; 2 * window + 1]);
+        assert_eq!(pc_row(&rows), None);
+    }
+
+    #[test]
+    fn test_instruction_rows_lock_and_labels() {
+        let mut memory = [0u8; 0x1000];
+        memory[0x200..0x202].copy_from_slice(&[0x23, 0x00]); // CALL 0x300
+        let symbols: Symbols = "label 0x200 start\nlabel 0x300 draw_sprite\n"
+            .parse()
+            .unwrap();
+
+        let rows = build_instruction_rows(0x202, None, &symbols, &memory);
+        let start = rows
+            .iter()
+            .position(|row| *row == InstrRow::Label("start".into()))
+            .unwrap();
+        let InstrRow::Instr { cells, at_pc } = &rows[start + 1] else {
+            panic!("no instruction after the label");
+        };
+        assert_eq!(cells[..2], ["0x200", "CALL draw_sprite"]);
+        assert!(!at_pc);
+        assert_eq!(pc_row(&rows), Some("0x202"));
+
+        // Locked on the caller, the window stays put while PC is in the
+        // subroutine, and nothing is highlighted.
+        let rows = build_instruction_rows(0x302, Some(0x200), &symbols, &memory);
+        assert_eq!(row_addrs(&rows)[WINDOW as usize], "0x200");
+        assert_eq!(pc_row(&rows), None);
+        assert!(!rows.contains(&InstrRow::Label("draw_sprite".into())));
+        let rows = build_instruction_rows(0x206, Some(0x200), &symbols, &memory);
+        assert_eq!(pc_row(&rows), Some("0x206"));
+    }
+
+    #[test]
+    fn test_stack_pointer_shows_depth() {
+        let mut chip = Chip8::new();
+        assert_eq!(stack_pointer(&chip), "0/16");
+        chip.stack = CallStack::new(64);
+        chip.sp = 3;
+        assert_eq!(stack_pointer(&chip), "3/64");
+    }
+
+    /// A low-res screen with its top-left corner showing all four half
+    /// block combinations.
+    fn corner_chip() -> Chip8 {
+        let mut chip = Chip8::new();
+        chip.screen.set_byte(0, 0, 0b1100_0000);
+        chip.screen.set_byte(1, 0, 0b1010_0000);
+        chip
+    }
+
+    #[test]
+    fn test_screen_to_ansi_matches_fixtures() {
+        let chip = corner_chip();
+        let palette = Palette::default();
+        assert_eq!(
+            screen_to_ansi(&chip, &palette, AnsiMode::Color),
+            include_str!("../../tests/fixtures/screen-color.ans")
+        );
+        assert_eq!(
+            screen_to_ansi(&chip, &palette, AnsiMode::Plain),
+            include_str!("../../tests/fixtures/screen-plain.txt")
+        );
+    }
+
+    #[test]
+    fn test_screen_to_ansi_size() {
+        let mut chip = corner_chip();
+        let plain = screen_to_ansi(&chip, &Palette::default(), AnsiMode::Plain);
+        assert_eq!(plain.lines().count(), 16);
+        assert!(plain.lines().all(|l| l.chars().count() == 64));
+        chip.resolution = Resolution::High;
+        let plain = screen_to_ansi(&chip, &Palette::default(), AnsiMode::Plain);
+        assert_eq!(plain.lines().count(), 32);
+        assert!(plain.lines().all(|l| l.chars().count() == 128));
+    }
+
+    #[test]
+    fn test_vf_style_flags_collisions() {
+        assert_eq!(v_register_style(0xF, 0), Style::default());
+        assert_eq!(v_register_style(0xF, 1).fg, Some(Color::Red));
+        assert_eq!(v_register_style(0xF, 0xFF).fg, Some(Color::Red));
+        assert_eq!(v_register_style(0xE, 1), Style::default());
+    }
+
+    #[test]
+    fn test_call_rows() {
+        let mut chip = Chip8::new();
+        chip.stack[0] = 0x202;
+        chip.stack[1] = 0x30A;
+        chip.sp = 2;
+        let mut history = CallHistory::new(4);
+        history.observe(ChipOp::CallNnn { nnn: 0x300 }, 0x200, 0x300);
+        history.observe(ChipOp::CallNnn { nnn: 0x400 }, 0x308, 0x400);
+        assert_eq!(
+            call_rows(&chip, &history),
+            [
+                " 1 ret 0x30a",
+                " 0 ret 0x202",
+                "CALL 0x308 > 0x400",
+                "CALL 0x200 > 0x300"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_rgb24_low_res_doubles_pixels() {
+        let mut chip = Chip8::new();
+        chip.screen.set_byte(0, 0, 0x90); // top row of the "0" glyph's sides
+        chip.screen.set_byte(31, 7, 0x01); // bottom-right low-res pixel
+        let pitch = FRAMEBUFFER_WIDTH * 3 + 4;
+        let mut buf = vec![0xAA; pitch * FRAMEBUFFER_HEIGHT];
+        write_rgb24(&chip, &mut buf, pitch, &PALETTE);
+
+        let row: Vec<bool> = (0..10).map(|x| lit(&buf, pitch, x, 1)).collect();
+        let expected = [
+            true, true, false, false, false, false, true, true, false, false,
+        ];
+        assert_eq!(row, expected);
+        assert!(lit(&buf, pitch, 127, 63));
+        assert!(!lit(&buf, pitch, 125, 63));
+        // Padding past the row is left alone.
+        assert_eq!(buf[pitch - 1], 0xAA);
+    }
+
+    #[test]
+    fn test_help_rows_follow_keymap() {
+        let rows = help_rows(&KeyMap::default());
+        assert_eq!(rows[0], ["1 2 3 C".to_string(), "1 2 3 4".to_string()]);
+
+        let keymap: KeyMap = "0123456789abcdef".parse().unwrap();
+        let rows = help_rows(&keymap);
+        let keypad: Vec<&str> = rows[..4].iter().map(|r| r[1].as_str()).collect();
+        assert_eq!(keypad, vec!["1 2 3 C", "4 5 6 D", "7 8 9 E", "A 0 B F"]);
+        assert!(rows.iter().any(|r| r[0] == "Esc" && r[1] == "Quit"));
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff8000"), Ok([0xFF, 0x80, 0x00]));
+        assert_eq!(parse_hex_color("0A0b0C"), Ok([0x0A, 0x0B, 0x0C]));
+        assert_eq!(parse_hex_color("#f80"), Ok([0xFF, 0x88, 0x00]));
+        for bad in ["#gg0000", "#ffff", "", "#ff80001", "+f80"] {
+            let err = parse_hex_color(bad).unwrap_err();
+            assert!(err.contains("expected 3 or 6 hex digits"), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_parse_palette() {
+        let palette = parse_palette("1a1b26,#c0caf5").unwrap();
+        assert_eq!(palette.off, [0x1A, 0x1B, 0x26]);
+        assert_eq!(palette.on, [0xC0, 0xCA, 0xF5]);
+        assert_eq!(palette.planes, None);
+
+        let palette = parse_palette("000,fff,f00,#0000ff").unwrap();
+        assert_eq!(palette.planes, Some([[0xFF, 0, 0], [0, 0, 0xFF]]));
+
+        assert_eq!(
+            parse_palette("000,fff,zzz,00f").unwrap_err(),
+            "invalid palette \"000,fff,zzz,00f\": color 3: \
+             invalid color \"zzz\": expected 3 or 6 hex digits, like #fff or #1a1b26"
+        );
+        for bad in ["fff", "000,fff,f00"] {
+            let err = parse_palette(bad).unwrap_err();
+            assert!(
+                err.contains("expected 2 colors (bg,fg) or 4"),
+                "{bad}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_plane_colors() {
+        let palette = parse_palette("000,fff,f00,00f").unwrap();
+        let colors = [0, 1, 2, 3].map(|planes| palette.plane_color(planes));
+        assert_eq!(colors, [[0; 3], [0xFF; 3], [0xFF, 0, 0], [0, 0, 0xFF]]);
+        // Two colours draw every lit pixel in the foreground.
+        assert_eq!(PALETTE.plane_color(2), ON);
+        assert_eq!(PALETTE.plane_color(3), ON);
+    }
+
+    #[cfg(feature = "xo-chip")]
+    #[test]
+    fn test_ppm_plane_colors() {
+        let mut chip = Chip8::new();
+        chip.resolution = Resolution::High;
+        // Pixels 0-3 of row 0: plane 1, plane 2, both, neither.
+        chip.screen.set_byte(0, 0, 0b1010_0000);
+        chip.extra_planes[0].set_byte(0, 0, 0b0110_0000);
+        let palette = parse_palette("000,fff,f00,00f").unwrap();
+        let image = ppm(&chip, &palette);
+        let pixels: Vec<&[u8]> = image[image.len() - 128 * 64 * 3..]
+            .chunks_exact(3)
+            .take(4)
+            .collect();
+        assert_eq!(
+            pixels,
+            [&[0xFF; 3][..], &[0xFF, 0, 0], &[0, 0, 0xFF], &[0; 3]]
+        );
+    }
+
+    #[test]
+    fn test_theme_cycling() {
+        let palette = Palette {
+            invert: true,
+            ..PALETTE
+        };
+        let mut index = 0;
+        let mut seen = Vec::new();
+        for _ in 0..THEMES.len() + 1 {
+            let themed = palette.with_theme(index);
+            seen.push((themed.on, themed.off));
+            assert!(themed.invert);
+            index = next_theme(index);
+        }
+        // Back to the configured colours after every preset.
+        assert_eq!(index, 0);
+        assert_eq!(seen[0], (PALETTE.on, PALETTE.off));
+        assert_eq!(seen[2], ([255, 176, 0], [26, 16, 0]));
+        assert_eq!(seen.len(), 5);
+        assert_eq!(palette.with_theme(99), palette);
+    }
+
+    #[test]
+    fn test_color_depth() {
+        assert_eq!(
+            ColorDepth::from_colorterm(Some("truecolor")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_colorterm(Some("24bit")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(ColorDepth::from_colorterm(None), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::from_colorterm(Some("")), ColorDepth::Ansi256);
+    }
+
+    #[test]
+    fn test_ansi256() {
+        assert_eq!(ansi256([0, 0, 0]), 16);
+        assert_eq!(ansi256([255, 255, 255]), 231);
+        assert_eq!(ansi256([255, 0, 0]), 196);
+        assert_eq!(ansi256([128, 128, 128]), 244);
+        // The default palette's green and near-black.
+        assert_eq!(ansi256([3, 220, 135]), 42);
+        assert_eq!(ansi256([5, 24, 18]), 233);
+        assert_eq!(ansi256([0x1A, 0x1B, 0x26]), 234);
+    }
+
+    #[test]
+    fn test_ppm() {
+        let mut chip = Chip8::new();
+        chip.screen.set_byte(0, 0, 0x80);
+        let image = ppm(&chip, &PALETTE);
+        let header = b"P6\n128 64\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        let pixels = &image[header.len()..];
+        assert_eq!(pixels.len(), 128 * 64 * 3);
+        // One low-res pixel covers 2x2 image pixels.
+        assert_eq!(pixels.chunks_exact(3).filter(|px| *px == ON).count(), 4);
+        assert!(lit(pixels, 128 * 3, 1, 1) && !lit(pixels, 128 * 3, 2, 0));
+    }
+
+    #[test]
+    fn test_palette_color_invert() {
+        let mut palette = Palette {
+            on: [0xFF, 0xB0, 0x00],
+            off: [0x1A, 0x10, 0x00],
+            ..Palette::default()
+        };
+        assert_eq!(palette.color(true), palette.on);
+        assert_eq!(palette.color(false), palette.off);
+        palette.invert = true;
+        assert_eq!(palette.color(true), palette.off);
+        assert_eq!(palette.color(false), palette.on);
+    }
+
+    #[test]
+    fn test_write_rgb24_high_res() {
+        let mut chip = Chip8::new();
+        chip.resolution = Resolution::High;
+        chip.screen.set_byte(63, 15, 0x01);
+        let pitch = FRAMEBUFFER_WIDTH * 3;
+        let mut buf = vec![0; pitch * FRAMEBUFFER_HEIGHT];
+        write_rgb24(&chip, &mut buf, pitch, &PALETTE);
+
+        let lit_count = buf.chunks_exact(3).filter(|px| *px == ON).count();
+        assert_eq!(lit_count, 1);
+        assert!(lit(&buf, pitch, 127, 63));
     }
 }
@@ -0,0 +1,221 @@
+//! Fixed-length key presses, for terminals whose release events can't be
+//! trusted.
+//!
+//! Auto-repeat turns one held key into a stream of presses, and over SSH a
+//! release can arrive before its press. [`KeyHold`] wraps a frontend and
+//! turns every press of a key with a hold time into "down for N frames",
+//! ignoring its releases. Presses that arrive while the key is still held
+//! restart the hold rather than pressing it again.
+
+use crate::chip8::emulator::{KeyEvent, RenderSnapshot};
+use crate::chip8::frontend::{AudioCommand, Frontend, InputEvent};
+
+/// Per-key hold timers, advanced once per emulated frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HoldTimers {
+    /// Frames each press holds its key for; 0 passes its events through.
+    hold: [u32; 16],
+    /// Frames left before each held key is released.
+    remaining: [u32; 16],
+    /// When each held key was last pressed, to release keys that expire
+    /// together in the order they were pressed.
+    pressed: [u64; 16],
+    presses: u64,
+}
+
+impl HoldTimers {
+    pub fn new(hold: [u32; 16]) -> Self {
+        Self {
+            hold,
+            ..Self::default()
+        }
+    }
+
+    /// Whether any key has a hold time.
+    pub fn enabled(&self) -> bool {
+        self.hold.iter().any(|&frames| frames > 0)
+    }
+
+    /// Start a frame: count down the held keys and release the ones whose
+    /// time is up.
+    pub fn tick(&mut self) -> Vec<KeyEvent> {
+        let mut expired = Vec::new();
+        for (key, remaining) in self.remaining.iter_mut().enumerate() {
+            if *remaining > 0 {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    expired.push(key);
+                }
+            }
+        }
+        expired.sort_by_key(|&key| self.pressed[key]);
+        expired
+            .into_iter()
+            .map(|key| KeyEvent::Up(key as u8))
+            .collect()
+    }
+
+    /// What the emulator should see of `event`, if anything.
+    pub fn filter(&mut self, event: KeyEvent) -> Option<KeyEvent> {
+        let key = match event {
+            KeyEvent::Down(key) | KeyEvent::Up(key) => usize::from(key & 0xF),
+        };
+        let hold = self.hold[key];
+        if hold == 0 {
+            return Some(event);
+        }
+        match event {
+            KeyEvent::Up(_) => None,
+            KeyEvent::Down(_) => {
+                let held = self.remaining[key] > 0;
+                self.remaining[key] = hold;
+                self.presses += 1;
+                self.pressed[key] = self.presses;
+                (!held).then_some(event)
+            }
+        }
+    }
+
+    /// Forget every held key, as when the machine is reset.
+    pub fn clear(&mut self) {
+        self.remaining = [0; 16];
+    }
+}
+
+/// A frontend whose key presses last a fixed number of frames. [`run`]
+/// polls input once per frame, so each poll advances the timers.
+///
+/// [`run`]: crate::chip8::frontend::run
+pub struct KeyHold<F> {
+    pub inner: F,
+    timers: HoldTimers,
+}
+
+impl<F: Frontend> KeyHold<F> {
+    /// Wrap `inner`, holding key K for `hold[K]` frames per press.
+    pub fn new(inner: F, hold: [u32; 16]) -> Self {
+        Self {
+            inner,
+            timers: HoldTimers::new(hold),
+        }
+    }
+}
+
+impl<F: Frontend> Frontend for KeyHold<F> {
+    fn poll_input(&mut self) -> Vec<InputEvent> {
+        let events = self.inner.poll_input();
+        if !self.timers.enabled() {
+            return events;
+        }
+        let mut out: Vec<InputEvent> = self
+            .timers
+            .tick()
+            .into_iter()
+            .map(InputEvent::Key)
+            .collect();
+        for event in events {
+            match event {
+                InputEvent::Key(key) => out.extend(self.timers.filter(key).map(InputEvent::Key)),
+                InputEvent::LoadRom(_) => {
+                    self.timers.clear();
+                    out.push(event);
+                }
+                InputEvent::Speed(_) | InputEvent::Quit => out.push(event),
+            }
+        }
+        out
+    }
+
+    fn present(&mut self, snapshot: &RenderSnapshot) {
+        self.inner.present(snapshot);
+    }
+
+    fn set_audio(&mut self, command: AudioCommand) {
+        self.inner.set_audio(command);
+    }
+
+    fn realtime(&self) -> bool {
+        self.inner.realtime()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::frontend::HeadlessFrontend;
+
+    fn hold(frames: u32) -> [u32; 16] {
+        [frames; 16]
+    }
+
+    #[test]
+    fn test_press_lasts_hold_frames() {
+        let mut timers = HoldTimers::new(hold(3));
+        assert_eq!(timers.filter(KeyEvent::Down(5)), Some(KeyEvent::Down(5)));
+        // The release is ignored; the hold decides.
+        assert_eq!(timers.filter(KeyEvent::Up(5)), None);
+        assert!(timers.tick().is_empty());
+        assert!(timers.tick().is_empty());
+        assert_eq!(timers.tick(), [KeyEvent::Up(5)]);
+        assert!(timers.tick().is_empty());
+    }
+
+    #[test]
+    fn test_repeats_coalesce() {
+        let mut timers = HoldTimers::new(hold(2));
+        assert_eq!(timers.filter(KeyEvent::Down(5)), Some(KeyEvent::Down(5)));
+        assert!(timers.tick().is_empty());
+        // Auto-repeat while held: no second press, and the hold restarts.
+        assert_eq!(timers.filter(KeyEvent::Down(5)), None);
+        assert_eq!(timers.filter(KeyEvent::Down(5)), None);
+        assert!(timers.tick().is_empty());
+        assert_eq!(timers.tick(), [KeyEvent::Up(5)]);
+        // Once released, the next press goes through again.
+        assert_eq!(timers.filter(KeyEvent::Down(5)), Some(KeyEvent::Down(5)));
+    }
+
+    #[test]
+    fn test_expiry_order() {
+        let mut per_key = hold(4);
+        per_key[0xA] = 2;
+        let mut timers = HoldTimers::new(per_key);
+        timers.filter(KeyEvent::Down(0xC));
+        timers.filter(KeyEvent::Down(0x3));
+        timers.tick();
+        timers.tick();
+        // A later press of a shorter-held key ends on the same frame.
+        timers.filter(KeyEvent::Down(0xA));
+        timers.tick();
+        assert_eq!(
+            timers.tick(),
+            [KeyEvent::Up(0xC), KeyEvent::Up(0x3), KeyEvent::Up(0xA)]
+        );
+    }
+
+    #[test]
+    fn test_zero_hold_passes_events_through() {
+        let mut per_key = hold(0);
+        per_key[1] = 2;
+        let mut timers = HoldTimers::new(per_key);
+        assert_eq!(timers.filter(KeyEvent::Down(2)), Some(KeyEvent::Down(2)));
+        assert_eq!(timers.filter(KeyEvent::Up(2)), Some(KeyEvent::Up(2)));
+        assert!(!HoldTimers::new(hold(0)).enabled());
+    }
+
+    #[test]
+    fn test_key_hold_frontend() {
+        let mut headless = HeadlessFrontend::new(10);
+        headless.push_input(0, InputEvent::Key(KeyEvent::Down(5)));
+        headless.push_input(0, InputEvent::Key(KeyEvent::Up(5)));
+        headless.push_input(1, InputEvent::Key(KeyEvent::Down(5)));
+        let mut frontend = KeyHold::new(headless, hold(2));
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            frames.push(frontend.poll_input());
+            frontend.present(&RenderSnapshot::default());
+        }
+        let down = InputEvent::Key(KeyEvent::Down(5));
+        let up = InputEvent::Key(KeyEvent::Up(5));
+        assert_eq!(frames, [vec![down], vec![], vec![], vec![up]]);
+    }
+}
@@ -0,0 +1,136 @@
+//! The key events `FX0A` waits on.
+//!
+//! `FX0A` finishes once a key is pressed and then released. Sampling the
+//! live keypad for that made the result depend on which instruction
+//! happened to see a release, and so on whichever copy of a snapshot was
+//! running. Instead every press and release is numbered, and while `FX0A`
+//! waits the events are logged for it to consume in order. The wait is
+//! then a function of the logged events alone, and saves with the rest of
+//! the machine.
+
+use std::collections::VecDeque;
+
+/// A keypad press or release, numbered in the order the machine got it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyLogEvent {
+    pub seq: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+/// How far `FX0A` has got.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KeyWait {
+    /// No `FX0A` is running, so events aren't logged.
+    #[default]
+    Idle,
+    AwaitingPress,
+    /// `key` was pressed first; the wait ends when it is released.
+    AwaitingRelease {
+        key: u8,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyLog {
+    pub wait: KeyWait,
+    /// Events since `FX0A` last looked, oldest first.
+    pub events: VecDeque<KeyLogEvent>,
+    /// The number the next event gets.
+    pub next_seq: u64,
+}
+
+impl KeyLog {
+    /// Number a press or release, keeping it if `FX0A` is waiting.
+    pub fn record(&mut self, key: u8, pressed: bool) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if self.wait != KeyWait::Idle {
+            self.events.push_back(KeyLogEvent { seq, key, pressed });
+        }
+    }
+
+    /// Start waiting if not already. A key held as the wait starts counts
+    /// as its press, the lowest if several are: `held` is the keypad as a
+    /// bitmask.
+    pub fn begin(&mut self, held: u16) {
+        if self.wait == KeyWait::Idle {
+            self.wait = match held {
+                0 => KeyWait::AwaitingPress,
+                _ => KeyWait::AwaitingRelease {
+                    key: held.trailing_zeros() as u8,
+                },
+            };
+        }
+    }
+
+    /// Consume the logged events, returning the key once the first key
+    /// pressed has been released. Other keys pressed meanwhile are ignored.
+    pub fn advance(&mut self) -> Option<u8> {
+        while let Some(event) = self.events.pop_front() {
+            self.wait = match (self.wait, event.pressed) {
+                (KeyWait::AwaitingPress, true) => KeyWait::AwaitingRelease { key: event.key },
+                (KeyWait::AwaitingRelease { key }, false) if key == event.key => {
+                    self.wait = KeyWait::Idle;
+                    self.events.clear();
+                    return Some(key);
+                }
+                (wait, _) => wait,
+            };
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_only_logged_while_waiting() {
+        let mut log = KeyLog::default();
+        log.record(5, true);
+        log.record(5, false);
+        assert!(log.events.is_empty());
+        log.begin(0);
+        log.record(7, true);
+        assert_eq!(
+            log.events,
+            [KeyLogEvent {
+                seq: 2,
+                key: 7,
+                pressed: true
+            }]
+        );
+    }
+
+    #[test]
+    fn test_first_press_wins() {
+        let mut log = KeyLog::default();
+        log.begin(0);
+        assert_eq!(log.advance(), None);
+        log.record(3, true);
+        log.record(9, true);
+        // Releasing the second key doesn't finish the wait.
+        log.record(9, false);
+        assert_eq!(log.advance(), None);
+        assert_eq!(log.wait, KeyWait::AwaitingRelease { key: 3 });
+        log.record(3, false);
+        log.record(4, true);
+        assert_eq!(log.advance(), Some(3));
+        assert_eq!(log.wait, KeyWait::Idle);
+        assert!(log.events.is_empty());
+    }
+
+    #[test]
+    fn test_held_key_counts_as_press() {
+        let mut log = KeyLog::default();
+        log.begin(0b1010_0000);
+        assert_eq!(log.wait, KeyWait::AwaitingRelease { key: 5 });
+        // Beginning again while waiting changes nothing.
+        log.begin(0b1);
+        assert_eq!(log.wait, KeyWait::AwaitingRelease { key: 5 });
+        log.record(5, false);
+        assert_eq!(log.advance(), Some(5));
+    }
+}
@@ -0,0 +1,170 @@
+//! Keyboard layout for the CHIP-8 hex keypad.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The keypad as laid out on the COSMAC VIP, row by row.
+pub const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Which keyboard character drives each CHIP-8 key, and for how long a
+/// press holds it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    /// Indexed by CHIP-8 key.
+    chars: [char; 16],
+    /// Frames every press holds its key, ignoring the release. 0 follows
+    /// release events instead.
+    hold: u32,
+    /// Per-key overrides of `hold`, indexed by CHIP-8 key.
+    key_hold: [Option<u32>; 16],
+}
+
+impl Default for KeyMap {
+    /// The usual 1234/QWER/ASDF/ZXCV block, matching [`KEYPAD`]'s shape.
+    fn default() -> Self {
+        "x123qweasdzc4rfv".parse().unwrap()
+    }
+}
+
+impl KeyMap {
+    pub fn key_of_char(&self, c: char) -> Option<u8> {
+        let c = c.to_ascii_lowercase();
+        self.chars.iter().position(|&k| k == c).map(|k| k as u8)
+    }
+
+    pub fn char_of_key(&self, key: u8) -> char {
+        self.chars[key as usize & 0xF]
+    }
+
+    /// Hold every key for `frames` frames per press, except those the
+    /// keymap gives their own.
+    pub fn set_hold_frames(&mut self, frames: u32) {
+        self.hold = frames;
+    }
+
+    /// Frames a press of each CHIP-8 key holds it for, 0 for as long as
+    /// the keyboard key is down.
+    pub fn hold_frames(&self) -> [u32; 16] {
+        std::array::from_fn(|key| self.key_hold[key].unwrap_or(self.hold))
+    }
+}
+
+/// Parse a `hold=N` or `hold.K=N` setting into `keymap`.
+fn parse_setting(keymap: &mut KeyMap, setting: &str) -> Result<(), String> {
+    let invalid = || format!("invalid keymap setting {setting:?}: expected hold=N or hold.K=N");
+    let (name, frames) = setting.split_once('=').ok_or_else(invalid)?;
+    let frames: u32 = frames.parse().map_err(|_| invalid())?;
+    match name.strip_prefix("hold") {
+        Some("") => keymap.hold = frames,
+        Some(key) => {
+            let key = key
+                .strip_prefix('.')
+                .and_then(|k| u8::from_str_radix(k, 16).ok())
+                .filter(|&k| k <= 0xF)
+                .ok_or_else(invalid)?;
+            keymap.key_hold[key as usize] = Some(frames);
+        }
+        None => return Err(invalid()),
+    }
+    Ok(())
+}
+
+impl FromStr for KeyMap {
+    type Err = String;
+
+    /// Sixteen characters giving the keyboard key for CHIP-8 keys 0 to F,
+    /// then optionally `hold=N` to hold every key N frames per press and
+    /// `hold.K=N` to set CHIP-8 key K's own hold, separated by spaces.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let layout = parts.next().unwrap_or_default();
+        let lower: Vec<char> = layout.chars().map(|c| c.to_ascii_lowercase()).collect();
+        let Ok(chars) = <[char; 16]>::try_from(lower) else {
+            return Err(format!("invalid keymap {s:?}: expected 16 keys for 0-F"));
+        };
+        for (i, c) in chars.iter().enumerate() {
+            if chars[..i].contains(c) {
+                return Err(format!("invalid keymap {s:?}: {c:?} is used twice"));
+            }
+        }
+        let mut keymap = Self {
+            chars,
+            hold: 0,
+            key_hold: [None; 16],
+        };
+        for setting in parts {
+            parse_setting(&mut keymap, setting)?;
+        }
+        Ok(keymap)
+    }
+}
+
+impl fmt::Display for KeyMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chars.iter().try_for_each(|c| write!(f, "{c}"))?;
+        if self.hold > 0 {
+            write!(f, " hold={}", self.hold)?;
+        }
+        for (key, frames) in self.key_hold.iter().enumerate() {
+            if let Some(frames) = frames {
+                write!(f, " hold.{key:x}={frames}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout() {
+        let keymap = KeyMap::default();
+        let rows: Vec<String> = KEYPAD
+            .iter()
+            .map(|row| row.iter().map(|&k| keymap.char_of_key(k)).collect())
+            .collect();
+        assert_eq!(rows, vec!["1234", "qwer", "asdf", "zxcv"]);
+        assert_eq!(keymap.key_of_char('W'), Some(0x5));
+        assert_eq!(keymap.key_of_char('p'), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let keymap: KeyMap = "0123456789ABCDEF".parse().unwrap();
+        assert_eq!(keymap.key_of_char('b'), Some(0xB));
+        assert_eq!(keymap.to_string(), "0123456789abcdef");
+        assert!("0123".parse::<KeyMap>().is_err());
+        assert!("00123456789abcde".parse::<KeyMap>().is_err());
+    }
+
+    #[test]
+    fn test_parse_hold() {
+        let keymap: KeyMap = "x123qweasdzc4rfv hold=4 hold.a=8 hold.5=0".parse().unwrap();
+        let hold = keymap.hold_frames();
+        assert_eq!((hold[0x1], hold[0xA], hold[0x5]), (4, 8, 0));
+        assert_eq!(
+            keymap.to_string(),
+            "x123qweasdzc4rfv hold=4 hold.5=0 hold.a=8"
+        );
+        assert_eq!(keymap.to_string().parse::<KeyMap>().unwrap(), keymap);
+
+        let mut keymap = KeyMap::default();
+        assert_eq!(keymap.hold_frames(), [0; 16]);
+        keymap.set_hold_frames(3);
+        assert_eq!(keymap.hold_frames(), [3; 16]);
+
+        for bad in ["hold", "hold=x", "hold.g=1", "hold.10=1", "hols=1"] {
+            let err = format!("x123qweasdzc4rfv {bad}")
+                .parse::<KeyMap>()
+                .unwrap_err();
+            assert!(err.contains("expected hold=N"), "{bad}: {err}");
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! A histogram of input latency: how long key events wait between reaching
+//! the emulator and being applied to the keypad, see
+//! [`EmulatorConfig::input_latency`](crate::chip8::emulator::EmulatorConfig::input_latency).
+
+use std::fmt;
+use std::time::Duration;
+
+/// Buckets in a [`LatencyHistogram`]. The last takes everything from
+/// about a quarter of a second up.
+pub const BUCKETS: usize = 20;
+
+/// The bucket `latency` falls in: 0 for under a microsecond, then bucket
+/// `i` for 2^(i-1) up to 2^i microseconds.
+pub fn bucket(latency: Duration) -> usize {
+    let micros = latency.as_micros();
+    let bits = (u128::BITS - micros.leading_zeros()) as usize;
+    bits.min(BUCKETS - 1)
+}
+
+/// The smallest latency in bucket `i`.
+pub fn bucket_start(i: usize) -> Duration {
+    match i {
+        0 => Duration::ZERO,
+        _ => Duration::from_micros(1 << (i - 1)),
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [u64; BUCKETS],
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        self.counts[bucket(latency)] += 1;
+        self.max = self.max.max(latency);
+    }
+
+    /// Events recorded in each bucket.
+    pub fn counts(&self) -> &[u64; BUCKETS] {
+        &self.counts
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+}
+
+/// Width of the longest bar in the printed histogram.
+const BAR_WIDTH: u64 = 40;
+
+fn format_micros(micros: u128) -> String {
+    match micros {
+        0..1000 => format!("{micros}µs"),
+        _ => format!("{:.1}ms", micros as f64 / 1000.0),
+    }
+}
+
+impl fmt::Display for LatencyHistogram {
+    /// A line per bucket from the first used to the last, with a bar
+    /// scaled to the fullest.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max = format_micros(self.max.as_micros());
+        writeln!(f, "{} key events, slowest {max}", self.count())?;
+        let used = || self.counts.iter().enumerate().filter(|&(_, &n)| n > 0);
+        let (Some((first, _)), Some((last, _))) = (used().next(), used().next_back()) else {
+            return Ok(());
+        };
+        let fullest = self.counts.iter().copied().max().unwrap_or(1);
+        for i in first..=last {
+            let start = format_micros(bucket_start(i).as_micros());
+            let label = match i {
+                _ if i == BUCKETS - 1 => format!("{start}+"),
+                _ => format!("{start}-{}", format_micros(bucket_start(i + 1).as_micros())),
+            };
+            let bar = "#".repeat(self.counts[i].div_ceil(fullest / BAR_WIDTH + 1) as usize);
+            writeln!(f, "{label:>15} {bar} {}", self.counts[i])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket() {
+        let us = Duration::from_micros;
+        assert_eq!(bucket(Duration::ZERO), 0);
+        assert_eq!(bucket(Duration::from_nanos(999)), 0);
+        assert_eq!(bucket(us(1)), 1);
+        assert_eq!((bucket(us(2)), bucket(us(3))), (2, 2));
+        assert_eq!((bucket(us(1023)), bucket(us(1024))), (10, 11));
+        // A 60 Hz frame.
+        assert_eq!(bucket(us(16_667)), 15);
+        assert_eq!(bucket(Duration::from_secs(60)), BUCKETS - 1);
+        for i in 1..BUCKETS {
+            assert_eq!(bucket(bucket_start(i)), i);
+            assert_eq!(bucket(bucket_start(i) - Duration::from_nanos(1)), i - 1);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.to_string(), "0 key events, slowest 0µs\n");
+        histogram.record(Duration::from_micros(3));
+        histogram.record(Duration::from_micros(2));
+        histogram.record(Duration::from_micros(1500));
+        assert_eq!(histogram.count(), 3);
+        let text = histogram.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "3 key events, slowest 1.5ms");
+        assert_eq!(lines[1], "        2µs-4µs ## 2");
+        // Empty buckets between used ones are still listed.
+        assert_eq!(lines[2], "        4µs-8µs  0");
+        assert_eq!(lines.last(), Some(&"    1.0ms-2.0ms # 1"));
+    }
+}
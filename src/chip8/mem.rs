@@ -1,10 +1,17 @@
-use crate::chip8::consts::RAM_SIZE;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chip8::consts::{CHIP8_FONTSET, PROGRAM_START, RAM_SIZE, XO_RAM_SIZE};
+use crate::chip8::cpu::ExecError;
+
+/// Bytes from the program start shown by `Debug`.
+const PREVIEW_LEN: usize = 16;
 
 #[derive(Clone)]
-pub struct Memory(pub [u8; RAM_SIZE]);
+pub struct Memory(pub Box<[u8]>);
 impl Default for Memory {
     fn default() -> Self {
-        Self([0; RAM_SIZE])
+        Self::new(RAM_SIZE)
     }
 }
 impl std::ops::Deref for Memory {
@@ -18,3 +25,229 @@ impl std::ops::DerefMut for Memory {
         &mut self.0
     }
 }
+impl Memory {
+    /// `size` bytes of zeroed RAM.
+    pub fn new(size: usize) -> Self {
+        Self(vec![0; size].into_boxed_slice())
+    }
+    /// `len` bytes from `addr`, or an error if they run past the end of RAM.
+    pub fn slice(&self, addr: usize, len: usize) -> Result<&[u8], ExecError> {
+        addr.checked_add(len)
+            .and_then(|end| self.0.get(addr..end))
+            .ok_or(ExecError::MemoryOutOfBounds { addr, len })
+    }
+    /// Mutable `len` bytes from `addr`, bounds-checked like [`Memory::slice`].
+    pub fn slice_mut(&mut self, addr: usize, len: usize) -> Result<&mut [u8], ExecError> {
+        addr.checked_add(len)
+            .and_then(|end| self.0.get_mut(addr..end))
+            .ok_or(ExecError::MemoryOutOfBounds { addr, len })
+    }
+    /// The widest address this RAM decodes: 12 bits for the standard 4KB,
+    /// 16 for anything larger.
+    pub fn addr_mask(&self) -> usize {
+        if self.0.len() > RAM_SIZE {
+            0xFFFF
+        } else {
+            0x0FFF
+        }
+    }
+    /// `addr` cut to [`Memory::addr_mask`], as a machine with registers
+    /// that wide would see it.
+    pub fn mask(&self, addr: usize) -> usize {
+        addr & self.addr_mask()
+    }
+    /// Fill `buf` from `addr` onwards, wrapping from the end of RAM to the
+    /// start.
+    pub fn read_wrapping(&self, addr: usize, buf: &mut [u8]) {
+        for (offset, byte) in buf.iter_mut().enumerate() {
+            *byte = self.0[(addr + offset) % self.0.len()];
+        }
+    }
+    /// Write `bytes` from `addr` onwards, wrapping like
+    /// [`Memory::read_wrapping`].
+    pub fn write_wrapping(&mut self, addr: usize, bytes: &[u8]) {
+        let len = self.0.len();
+        for (offset, &byte) in bytes.iter().enumerate() {
+            self.0[(addr + offset) % len] = byte;
+        }
+    }
+    /// Overwrite all of RAM with `fill`.
+    pub fn fill(&mut self, fill: MemoryFill) {
+        for (addr, byte) in self.0.iter_mut().enumerate() {
+            *byte = fill.byte_at(addr);
+        }
+    }
+}
+impl fmt::Debug for Memory {
+    /// Shows only the start of the program, e.g.
+    /// `Memory { len: 4096, 0x200: [12 00 00 ...] }`, or what there is of
+    /// it in a RAM too small to hold it all.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.0.len();
+        let preview = &self.0[PROGRAM_START.min(len)..(PROGRAM_START + PREVIEW_LEN).min(len)];
+        write!(
+            f,
+            "Memory {{ len: {}, {PROGRAM_START:#05x}: [",
+            self.0.len()
+        )?;
+        for byte in preview {
+            write!(f, "{byte:02x} ")?;
+        }
+        write!(f, "...] }}")
+    }
+}
+
+/// Parse a RAM size in bytes, decimal or `0x` hex, between the standard
+/// 4KB and XO-CHIP's 64KB.
+pub fn parse_ram_size(s: &str) -> Result<usize, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    match parsed {
+        Ok(size) if (RAM_SIZE..=XO_RAM_SIZE).contains(&size) => Ok(size),
+        _ => Err(format!(
+            "invalid RAM size {s:?}: expected {RAM_SIZE} to {XO_RAM_SIZE} bytes"
+        )),
+    }
+}
+
+/// Parse a font address, decimal or `0x` hex, low enough that the font
+/// ends before the program start.
+pub fn parse_font_base(s: &str) -> Result<usize, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    let limit = PROGRAM_START - CHIP8_FONTSET.len();
+    match parsed {
+        Ok(base) if base <= limit => Ok(base),
+        _ => Err(format!(
+            "invalid font address {s:?}: expected 0 to {limit:#05x}"
+        )),
+    }
+}
+
+/// What RAM holds at power-on, before the font and ROM are loaded. Anything
+/// but zeros exposes ROMs that read memory they never wrote.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemoryFill {
+    #[default]
+    Zero,
+    Byte(u8),
+    /// Even addresses get the first byte, odd addresses the second.
+    Alternate(u8, u8),
+}
+
+impl MemoryFill {
+    pub fn byte_at(self, addr: usize) -> u8 {
+        match self {
+            MemoryFill::Zero => 0,
+            MemoryFill::Byte(b) => b,
+            MemoryFill::Alternate(even, odd) => {
+                if addr.is_multiple_of(2) {
+                    even
+                } else {
+                    odd
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for MemoryFill {
+    type Err = String;
+
+    /// One hex byte (`0xAA`) to repeat, or two (`0xDEAD`) to alternate.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
+        let invalid =
+            || format!("invalid fill {s:?}: expected one or two hex bytes, e.g. 0xAA or 0xDEAD");
+        let word = u16::from_str_radix(hex, 16).map_err(|_| invalid())?;
+        match hex.len() {
+            1 | 2 => Ok(MemoryFill::Byte(word as u8)),
+            4 => {
+                let [even, odd] = word.to_be_bytes();
+                Ok(MemoryFill::Alternate(even, odd))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_preview() {
+        let mut memory = Memory::default();
+        memory[PROGRAM_START..PROGRAM_START + 2].copy_from_slice(&[0x12, 0x00]);
+        assert_eq!(
+            format!("{memory:?}"),
+            "Memory { len: 4096, 0x200: [12 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 ...] }"
+        );
+        assert_eq!(
+            format!("{:?}", Memory::new(PROGRAM_START + 2)),
+            "Memory { len: 514, 0x200: [00 00 ...] }"
+        );
+        assert_eq!(
+            format!("{:?}", Memory::new(16)),
+            "Memory { len: 16, 0x200: [...] }"
+        );
+    }
+
+    #[test]
+    fn test_wrapping_access() {
+        let mut memory = Memory::default();
+        memory.write_wrapping(0xFFE, &[1, 2, 3, 4]);
+        assert_eq!((memory[0xFFE], memory[0xFFF]), (1, 2));
+        assert_eq!(memory[..2], [3, 4]);
+        let mut buf = [0; 4];
+        memory.read_wrapping(0x1FFE, &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_mask() {
+        let memory = Memory::default();
+        assert_eq!(memory.mask(0x1002), 0x002);
+        assert_eq!(memory.mask(0xFFF), 0xFFF);
+        assert_eq!(Memory::new(XO_RAM_SIZE).mask(0x1002), 0x1002);
+        assert_eq!(Memory::new(XO_RAM_SIZE).mask(0x10002), 0x0002);
+    }
+
+    #[test]
+    fn test_parse_fill() {
+        assert_eq!("0xAA".parse(), Ok(MemoryFill::Byte(0xAA)));
+        assert_eq!("f".parse(), Ok(MemoryFill::Byte(0x0F)));
+        assert_eq!("0xDEAD".parse(), Ok(MemoryFill::Alternate(0xDE, 0xAD)));
+        assert!("0xABC".parse::<MemoryFill>().is_err());
+        assert!("zz".parse::<MemoryFill>().is_err());
+
+        let mut memory = Memory::default();
+        memory.fill(MemoryFill::Alternate(0xDE, 0xAD));
+        assert_eq!(memory[0x300..0x304], [0xDE, 0xAD, 0xDE, 0xAD]);
+    }
+
+    #[test]
+    fn test_parse_ram_size() {
+        assert_eq!(parse_ram_size("4096"), Ok(RAM_SIZE));
+        assert_eq!(parse_ram_size("0x10000"), Ok(XO_RAM_SIZE));
+        assert!(parse_ram_size("2048").is_err());
+        assert!(parse_ram_size("0x10001").is_err());
+        assert_eq!(Memory::new(XO_RAM_SIZE).len(), XO_RAM_SIZE);
+    }
+
+    #[test]
+    fn test_parse_font_base() {
+        assert_eq!(parse_font_base("0x50"), Ok(0x50));
+        assert_eq!(parse_font_base("0"), Ok(0));
+        assert_eq!(parse_font_base("0x1B0"), Ok(0x1B0));
+        assert!(parse_font_base("0x1B1").is_err());
+        assert!(parse_font_base("font").is_err());
+    }
+}
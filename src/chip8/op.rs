@@ -1,9 +1,24 @@
 use std::fmt;
+use std::str::FromStr;
+
+use crate::chip8::decode::decode;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ChipOp {
-    ScdN { n: u8 },
-    ScuN { n: u8 },
+    /// `00CN`: scroll the display down N pixels (SUPER-CHIP).
+    ScdN {
+        n: u8,
+    },
+    /// `00DN`: scroll the display up N pixels. XO-CHIP only; SUPER-CHIP
+    /// has no upward scroll.
+    ScuN {
+        n: u8,
+    },
+    /// `0NNN`: call the machine code routine at NNN on the original
+    /// interpreter's CPU. Nothing modern can run it.
+    Sys {
+        nnn: usize,
+    },
     Cls,
     Ret,
     Scr,
@@ -11,41 +26,164 @@ pub enum ChipOp {
     Exit,
     LowRes,
     HighRes,
-    JpNnn { nnn: usize },
-    CallNnn { nnn: usize },
-    SeVxNn { x: usize, nn: u8 },
-    SneVxNn { x: usize, nn: u8 },
-    SeVxVy { x: usize, y: usize },
-    LdIVxVy { x: usize, y: usize },
-    LdVxVyI { x: usize, y: usize },
-    LdVxNn { x: usize, nn: u8 },
-    AddVxNn { x: usize, nn: u8 },
-    LdVxVy { x: usize, y: usize },
-    OrVxVy { x: usize, y: usize },
-    AndVxVy { x: usize, y: usize },
-    XorVxVy { x: usize, y: usize },
-    AddVxVy { x: usize, y: usize },
-    SubVxVy { x: usize, y: usize },
-    ShrVxVy { x: usize, y: usize },
-    SubnVxVy { x: usize, y: usize },
-    ShlVxVy { x: usize, y: usize },
-    SneVxVy { x: usize, y: usize },
-    LdINnn { nnn: usize },
-    JpV0Nnn { nnn: u16 },
-    RndVxNn { x: usize, nn: u8 },
-    DrwVxVyN { x: usize, y: usize, n: u8 },
-    SkpVx { x: usize },
-    SknpVx { x: usize },
-    LdVxDt { x: usize },
-    LdVxK { x: usize },
-    LdDtVx { x: usize },
-    LdStVx { x: usize },
-    AddIVx { x: usize },
-    LdFVx { x: usize },
-    LdBVx { x: usize },
-    LdIVx { x: usize },
-    LdVxI { x: usize },
-    Unknown(u16),
+    JpNnn {
+        nnn: usize,
+    },
+    CallNnn {
+        nnn: usize,
+    },
+    SeVxNn {
+        x: usize,
+        nn: u8,
+    },
+    SneVxNn {
+        x: usize,
+        nn: u8,
+    },
+    SeVxVy {
+        x: usize,
+        y: usize,
+    },
+    LdIVxVy {
+        x: usize,
+        y: usize,
+    },
+    LdVxVyI {
+        x: usize,
+        y: usize,
+    },
+    LdVxNn {
+        x: usize,
+        nn: u8,
+    },
+    AddVxNn {
+        x: usize,
+        nn: u8,
+    },
+    LdVxVy {
+        x: usize,
+        y: usize,
+    },
+    OrVxVy {
+        x: usize,
+        y: usize,
+    },
+    AndVxVy {
+        x: usize,
+        y: usize,
+    },
+    XorVxVy {
+        x: usize,
+        y: usize,
+    },
+    AddVxVy {
+        x: usize,
+        y: usize,
+    },
+    SubVxVy {
+        x: usize,
+        y: usize,
+    },
+    ShrVxVy {
+        x: usize,
+        y: usize,
+    },
+    SubnVxVy {
+        x: usize,
+        y: usize,
+    },
+    ShlVxVy {
+        x: usize,
+        y: usize,
+    },
+    SneVxVy {
+        x: usize,
+        y: usize,
+    },
+    LdINnn {
+        nnn: usize,
+    },
+    JpV0Nnn {
+        nnn: u16,
+    },
+    RndVxNn {
+        x: usize,
+        nn: u8,
+    },
+    DrwVxVyN {
+        x: usize,
+        y: usize,
+        n: u8,
+    },
+    SkpVx {
+        x: usize,
+    },
+    SknpVx {
+        x: usize,
+    },
+    LdVxDt {
+        x: usize,
+    },
+    LdVxK {
+        x: usize,
+    },
+    LdDtVx {
+        x: usize,
+    },
+    LdStVx {
+        x: usize,
+    },
+    AddIVx {
+        x: usize,
+    },
+    LdFVx {
+        x: usize,
+    },
+    LdBVx {
+        x: usize,
+    },
+    LdIVx {
+        x: usize,
+    },
+    LdVxI {
+        x: usize,
+    },
+    /// No instruction matches `word`; `family` is its top nibble, which
+    /// says what kind of instruction it was probably meant to be.
+    Unknown {
+        word: u16,
+        family: u8,
+    },
+}
+
+/// The instruction set an instruction first appeared in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InstructionSet {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl fmt::Display for InstructionSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InstructionSet::Chip8 => "CHIP-8",
+            InstructionSet::SuperChip => "SUPER-CHIP",
+            InstructionSet::XoChip => "XO-CHIP",
+        })
+    }
+}
+
+/// One instance of every instruction [`decode`] recognises, in opcode order.
+pub fn instructions() -> Vec<ChipOp> {
+    let mut ops: Vec<ChipOp> = Vec::new();
+    for op in (0..=u16::MAX).map(decode) {
+        let known = !matches!(op, ChipOp::Unknown { .. });
+        if known && !ops.iter().any(|seen| seen.pattern() == op.pattern()) {
+            ops.push(op);
+        }
+    }
+    ops
 }
 
 impl fmt::Debug for ChipOp {
@@ -54,13 +192,14 @@ impl fmt::Debug for ChipOp {
         let name = match self {
             ScdN { .. } => "ScdN",
             ScuN { .. } => "ScuN",
+            Sys { .. } => "Sys",
             Cls => "Cls",
             Ret => "Ret",
             Scr => "Scr",
             Scl => "Scl",
-            Exit => "Ret",
-            LowRes => "HighRes",
-            HighRes => "LowRes",
+            Exit => "Exit",
+            LowRes => "LowRes",
+            HighRes => "HighRes",
             JpNnn { .. } => "JpNnn",
             CallNnn { .. } => "CallNnn",
             SeVxNn { .. } => "SeVxNn",
@@ -95,7 +234,7 @@ impl fmt::Debug for ChipOp {
             LdBVx { .. } => "LdBVx",
             LdIVx { .. } => "LdIVx",
             LdVxI { .. } => "LdVxI",
-            Unknown(_) => "Unknown",
+            Unknown { .. } => "Unknown",
         };
         f.write_str(name)
     }
@@ -105,15 +244,16 @@ impl fmt::Display for ChipOp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use ChipOp::*;
         match *self {
-            ScdN { n } => write!(f, "SCD {n:X}"),
-            ScuN { n } => write!(f, "SCU {n:X}"),
+            ScdN { n } => write!(f, "SCD {n:#X}"),
+            ScuN { n } => write!(f, "SCU {n:#X}"),
+            Sys { nnn } => write!(f, "SYS {nnn:#05X}"),
             Cls => write!(f, "CLS"),
             Ret => write!(f, "RET"),
             Scr => write!(f, "SCR"),
             Scl => write!(f, "SCL"),
             Exit => write!(f, "EXIT"),
-            LowRes => write!(f, "HIGH"),
-            HighRes => write!(f, "LOW"),
+            LowRes => write!(f, "LOW"),
+            HighRes => write!(f, "HIGH"),
             JpNnn { nnn } => write!(f, "JP {nnn:#05X}"),
             CallNnn { nnn } => write!(f, "CALL {nnn:#05X}"),
             SeVxNn { x, nn } => write!(f, "SE V{x:X}, {nn:#04X}"),
@@ -148,7 +288,392 @@ impl fmt::Display for ChipOp {
             LdBVx { x } => write!(f, "LD B, V{x:X}"),
             LdIVx { x } => write!(f, "LD [I], V{x:X}"),
             LdVxI { x } => write!(f, "LD V{x:X}, [I]"),
-            Unknown(op) => write!(f, "DB {op:#06X}"),
+            Unknown { word, .. } => write!(f, "DB {word:#06X}"),
+        }
+    }
+}
+
+impl ChipOp {
+    /// The opcode word for this instruction; the inverse of
+    /// [`decode`](crate::chip8::decode::decode).
+    pub fn encode(&self) -> u16 {
+        use ChipOp::*;
+        let xy = |x: usize, y: usize| ((x as u16) << 8) | ((y as u16) << 4);
+        let xnn = |x: usize, nn: u8| ((x as u16) << 8) | nn as u16;
+        match *self {
+            ScdN { n } => 0x00C0 | n as u16,
+            ScuN { n } => 0x00D0 | n as u16,
+            Sys { nnn } => nnn as u16,
+            Cls => 0x00E0,
+            Ret => 0x00EE,
+            Scr => 0x00FB,
+            Scl => 0x00FC,
+            Exit => 0x00FD,
+            LowRes => 0x00FE,
+            HighRes => 0x00FF,
+            JpNnn { nnn } => 0x1000 | nnn as u16,
+            CallNnn { nnn } => 0x2000 | nnn as u16,
+            SeVxNn { x, nn } => 0x3000 | xnn(x, nn),
+            SneVxNn { x, nn } => 0x4000 | xnn(x, nn),
+            SeVxVy { x, y } => 0x5000 | xy(x, y),
+            LdIVxVy { x, y } => 0x5002 | xy(x, y),
+            LdVxVyI { x, y } => 0x5003 | xy(x, y),
+            LdVxNn { x, nn } => 0x6000 | xnn(x, nn),
+            AddVxNn { x, nn } => 0x7000 | xnn(x, nn),
+            LdVxVy { x, y } => 0x8000 | xy(x, y),
+            OrVxVy { x, y } => 0x8001 | xy(x, y),
+            AndVxVy { x, y } => 0x8002 | xy(x, y),
+            XorVxVy { x, y } => 0x8003 | xy(x, y),
+            AddVxVy { x, y } => 0x8004 | xy(x, y),
+            SubVxVy { x, y } => 0x8005 | xy(x, y),
+            ShrVxVy { x, y } => 0x8006 | xy(x, y),
+            SubnVxVy { x, y } => 0x8007 | xy(x, y),
+            ShlVxVy { x, y } => 0x800E | xy(x, y),
+            SneVxVy { x, y } => 0x9000 | xy(x, y),
+            LdINnn { nnn } => 0xA000 | nnn as u16,
+            JpV0Nnn { nnn } => 0xB000 | nnn,
+            RndVxNn { x, nn } => 0xC000 | xnn(x, nn),
+            DrwVxVyN { x, y, n } => 0xD000 | xy(x, y) | n as u16,
+            SkpVx { x } => 0xE09E | xnn(x, 0),
+            SknpVx { x } => 0xE0A1 | xnn(x, 0),
+            LdVxDt { x } => 0xF007 | xnn(x, 0),
+            LdVxK { x } => 0xF00A | xnn(x, 0),
+            LdDtVx { x } => 0xF015 | xnn(x, 0),
+            LdStVx { x } => 0xF018 | xnn(x, 0),
+            AddIVx { x } => 0xF01E | xnn(x, 0),
+            LdFVx { x } => 0xF029 | xnn(x, 0),
+            LdBVx { x } => 0xF033 | xnn(x, 0),
+            LdIVx { x } => 0xF055 | xnn(x, 0),
+            LdVxI { x } => 0xF065 | xnn(x, 0),
+            Unknown { word, .. } => word,
+        }
+    }
+
+    /// The opcode with its operands as letters, e.g. `8XY4` or `DXYN`.
+    pub fn pattern(&self) -> &'static str {
+        use ChipOp::*;
+        match self {
+            ScdN { .. } => "00CN",
+            ScuN { .. } => "00DN",
+            Sys { .. } => "0NNN",
+            Cls => "00E0",
+            Ret => "00EE",
+            Scr => "00FB",
+            Scl => "00FC",
+            Exit => "00FD",
+            LowRes => "00FE",
+            HighRes => "00FF",
+            JpNnn { .. } => "1NNN",
+            CallNnn { .. } => "2NNN",
+            SeVxNn { .. } => "3XNN",
+            SneVxNn { .. } => "4XNN",
+            SeVxVy { .. } => "5XY0",
+            LdIVxVy { .. } => "5XY2",
+            LdVxVyI { .. } => "5XY3",
+            LdVxNn { .. } => "6XNN",
+            AddVxNn { .. } => "7XNN",
+            LdVxVy { .. } => "8XY0",
+            OrVxVy { .. } => "8XY1",
+            AndVxVy { .. } => "8XY2",
+            XorVxVy { .. } => "8XY3",
+            AddVxVy { .. } => "8XY4",
+            SubVxVy { .. } => "8XY5",
+            ShrVxVy { .. } => "8XY6",
+            SubnVxVy { .. } => "8XY7",
+            ShlVxVy { .. } => "8XYE",
+            SneVxVy { .. } => "9XY0",
+            LdINnn { .. } => "ANNN",
+            JpV0Nnn { .. } => "BNNN",
+            RndVxNn { .. } => "CXNN",
+            DrwVxVyN { .. } => "DXYN",
+            SkpVx { .. } => "EX9E",
+            SknpVx { .. } => "EXA1",
+            LdVxDt { .. } => "FX07",
+            LdVxK { .. } => "FX0A",
+            LdDtVx { .. } => "FX15",
+            LdStVx { .. } => "FX18",
+            AddIVx { .. } => "FX1E",
+            LdFVx { .. } => "FX29",
+            LdBVx { .. } => "FX33",
+            LdIVx { .. } => "FX55",
+            LdVxI { .. } => "FX65",
+            Unknown { .. } => "????",
+        }
+    }
+
+    /// The assembly mnemonic, e.g. `DRW`.
+    pub fn mnemonic(&self) -> String {
+        let text = self.to_string();
+        text.split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    pub fn instruction_set(&self) -> InstructionSet {
+        use ChipOp::*;
+        match self {
+            ScdN { .. } | Scr | Scl | Exit | LowRes | HighRes => InstructionSet::SuperChip,
+            ScuN { .. } | LdIVxVy { .. } | LdVxVyI { .. } => InstructionSet::XoChip,
+            _ => InstructionSet::Chip8,
+        }
+    }
+}
+
+/// Machine cycles the COSMAC VIP interpreter spends fetching and decoding
+/// any instruction.
+const FETCH_CYCLES: u32 = 10;
+
+/// Approximate COSMAC VIP machine cycles to fetch and execute `op`, out of
+/// [`COSMAC_FRAME_CYCLES`](crate::chip8::consts::COSMAC_FRAME_CYCLES) a
+/// frame. DRW grows with its sprite's height, and FX55/FX65 with the
+/// registers they move. Instructions the VIP never had are priced like
+/// their nearest CHIP-8 relative.
+pub fn cycle_cost(op: &ChipOp) -> u32 {
+    use ChipOp::*;
+    let execute = match *op {
+        Cls | ScdN { .. } | ScuN { .. } | Scr | Scl => 670,
+        Ret | JpNnn { .. } | CallNnn { .. } | JpV0Nnn { .. } => 14,
+        Sys { .. } | Exit | LowRes | HighRes | Unknown { .. } => 0,
+        SeVxNn { .. } | SneVxNn { .. } | SeVxVy { .. } | SneVxVy { .. } => 12,
+        LdVxNn { .. } | LdINnn { .. } => 4,
+        AddVxNn { .. } | LdVxDt { .. } | LdDtVx { .. } | LdStVx { .. } => 8,
+        LdVxVy { .. }
+        | OrVxVy { .. }
+        | AndVxVy { .. }
+        | XorVxVy { .. }
+        | AddVxVy { .. }
+        | SubVxVy { .. }
+        | ShrVxVy { .. }
+        | SubnVxVy { .. }
+        | ShlVxVy { .. } => 34,
+        RndVxNn { .. } => 26,
+        DrwVxVyN { n, .. } => 160 + 46 * u32::from(n & 0xF),
+        SkpVx { .. } | SknpVx { .. } => 6,
+        LdVxK { .. } | LdFVx { .. } => 10,
+        AddIVx { .. } => 9,
+        LdBVx { .. } => 194,
+        LdIVx { x } | LdVxI { x } => 14 + 14 * (x as u32 + 1),
+        LdIVxVy { x, y } | LdVxVyI { x, y } => 14 + 14 * (x.abs_diff(y) as u32 + 1),
+    };
+    FETCH_CYCLES + execute
+}
+
+/// One operand of an assembly instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    V(usize),
+    /// `Vx-Vy`
+    VRange(usize, usize),
+    I,
+    /// `[I]`
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    Num(u16),
+}
+
+impl FromStr for Operand {
+    type Err = String;
+
+    /// Numbers are hex with a `0x` prefix, decimal if they're all digits,
+    /// and hex otherwise.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let reg = |r: &str| {
+            r.strip_prefix('V')
+                .filter(|x| x.len() == 1)
+                .and_then(|x| usize::from_str_radix(x, 16).ok())
+        };
+        let operand = match s {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::Dt,
+            "ST" => Operand::St,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "B" => Operand::B,
+            _ => {
+                if let Some(x) = reg(s) {
+                    Operand::V(x)
+                } else if let Some((x, y)) = s.split_once('-').and_then(|(x, y)| reg(x).zip(reg(y)))
+                {
+                    Operand::VRange(x, y)
+                } else {
+                    let num = match s.strip_prefix("0X") {
+                        Some(hex) => u16::from_str_radix(hex, 16),
+                        None if s.bytes().all(|b| b.is_ascii_digit()) => s.parse(),
+                        None => u16::from_str_radix(s, 16),
+                    };
+                    Operand::Num(num.map_err(|_| format!("invalid operand {s:?}"))?)
+                }
+            }
+        };
+        Ok(operand)
+    }
+}
+
+impl FromStr for ChipOp {
+    type Err = String;
+
+    /// Parse an instruction in the syntax [`Display`](fmt::Display) writes,
+    /// e.g. `DRW V1, V2, 0xF`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use ChipOp::*;
+        use Operand::*;
+
+        let upper = s.trim().to_ascii_uppercase();
+        let (mnemonic, rest) = upper
+            .split_once(char::is_whitespace)
+            .unwrap_or((&upper, ""));
+        let operands = rest
+            .split(',')
+            .map(str::trim)
+            .filter(|op| !op.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Operand>, _>>()?;
+        let out_of_range = |max: u16| format!("operand out of range in {s:?} (max {max:#X})");
+        let n = |v: u16| {
+            u8::try_from(v)
+                .ok()
+                .filter(|&n| n <= 0xF)
+                .ok_or_else(|| out_of_range(0xF))
+        };
+        let nn = |v: u16| u8::try_from(v).map_err(|_| out_of_range(0xFF));
+        let nnn = |v: u16| {
+            Some(v)
+                .filter(|&v| v <= 0xFFF)
+                .ok_or_else(|| out_of_range(0xFFF))
+        };
+
+        let op = match (mnemonic, operands.as_slice()) {
+            ("SCD", &[Num(v)]) => ScdN { n: n(v)? },
+            ("SCU", &[Num(v)]) => ScuN { n: n(v)? },
+            ("SYS", &[Num(v)]) => Sys {
+                nnn: nnn(v)? as usize,
+            },
+            ("CLS", []) => Cls,
+            ("RET", []) => Ret,
+            ("SCR", []) => Scr,
+            ("SCL", []) => Scl,
+            ("EXIT", []) => Exit,
+            ("LOW", []) => LowRes,
+            ("HIGH", []) => HighRes,
+            ("JP", &[Num(v)]) => JpNnn {
+                nnn: nnn(v)? as usize,
+            },
+            ("JP", &[V(0), Num(v)]) => JpV0Nnn { nnn: nnn(v)? },
+            ("CALL", &[Num(v)]) => CallNnn {
+                nnn: nnn(v)? as usize,
+            },
+            ("SE", &[V(x), Num(v)]) => SeVxNn { x, nn: nn(v)? },
+            ("SNE", &[V(x), Num(v)]) => SneVxNn { x, nn: nn(v)? },
+            ("SE", &[V(x), V(y)]) => SeVxVy { x, y },
+            ("SNE", &[V(x), V(y)]) => SneVxVy { x, y },
+            ("LD", &[IndirectI, VRange(x, y)]) => LdIVxVy { x, y },
+            ("LD", &[VRange(x, y), IndirectI]) => LdVxVyI { x, y },
+            ("LD", &[V(x), Num(v)]) => LdVxNn { x, nn: nn(v)? },
+            ("ADD", &[V(x), Num(v)]) => AddVxNn { x, nn: nn(v)? },
+            ("LD", &[V(x), V(y)]) => LdVxVy { x, y },
+            ("OR", &[V(x), V(y)]) => OrVxVy { x, y },
+            ("AND", &[V(x), V(y)]) => AndVxVy { x, y },
+            ("XOR", &[V(x), V(y)]) => XorVxVy { x, y },
+            ("ADD", &[V(x), V(y)]) => AddVxVy { x, y },
+            ("SUB", &[V(x), V(y)]) => SubVxVy { x, y },
+            ("SHR", &[V(x), V(y)]) => ShrVxVy { x, y },
+            ("SUBN", &[V(x), V(y)]) => SubnVxVy { x, y },
+            ("SHL", &[V(x), V(y)]) => ShlVxVy { x, y },
+            ("LD", &[I, Num(v)]) => LdINnn {
+                nnn: nnn(v)? as usize,
+            },
+            ("RND", &[V(x), Num(v)]) => RndVxNn { x, nn: nn(v)? },
+            ("DRW", &[V(x), V(y), Num(v)]) => DrwVxVyN { x, y, n: n(v)? },
+            ("SKP", &[V(x)]) => SkpVx { x },
+            ("SKNP", &[V(x)]) => SknpVx { x },
+            ("LD", &[V(x), Dt]) => LdVxDt { x },
+            ("LD", &[V(x), K]) => LdVxK { x },
+            ("LD", &[Dt, V(x)]) => LdDtVx { x },
+            ("LD", &[St, V(x)]) => LdStVx { x },
+            ("ADD", &[I, V(x)]) => AddIVx { x },
+            ("LD", &[F, V(x)]) => LdFVx { x },
+            ("LD", &[B, V(x)]) => LdBVx { x },
+            ("LD", &[IndirectI, V(x)]) => LdIVx { x },
+            ("LD", &[V(x), IndirectI]) => LdVxI { x },
+            ("DB", &[Num(word)]) => decode(word),
+            _ => return Err(format!("invalid instruction {s:?}")),
+        };
+        Ok(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_inverts_decode() {
+        for word in 0..=u16::MAX {
+            let op = decode(word);
+            assert_eq!(decode(op.encode()), op, "{word:#06X}");
+        }
+        assert_eq!(decode(0xD12F).encode(), 0xD12F);
+    }
+
+    #[test]
+    fn test_parse_inverts_display() {
+        for word in 0..=u16::MAX {
+            let op = decode(word);
+            assert_eq!(op.to_string().parse::<ChipOp>(), Ok(op), "{op}");
         }
     }
+
+    #[test]
+    fn test_instructions() {
+        let ops = instructions();
+        assert_eq!(ops.len(), 44);
+        assert_eq!(ops[0].pattern(), "0NNN");
+        for op in &ops {
+            let word = u16::from_str_radix(&op.pattern().replace(['N', 'X', 'Y'], "0"), 16);
+            assert_eq!(decode(word.unwrap()).pattern(), op.pattern());
+        }
+        let drw = ops.iter().find(|op| op.pattern() == "DXYN").unwrap();
+        assert_eq!(drw.mnemonic(), "DRW");
+        assert_eq!(drw.instruction_set(), InstructionSet::Chip8);
+        assert_eq!(
+            ChipOp::ScuN { n: 1 }.instruction_set(),
+            InstructionSet::XoChip
+        );
+    }
+
+    #[test]
+    fn test_cycle_cost() {
+        let ld = cycle_cost(&ChipOp::LdVxNn { x: 0, nn: 1 });
+        let drw = |n| cycle_cost(&ChipOp::DrwVxVyN { x: 0, y: 0, n });
+        assert_eq!(ld, 14);
+        assert_eq!(drw(1), 216);
+        assert_eq!(drw(15), 860);
+        assert!(drw(0) < drw(1));
+        assert_eq!(
+            cycle_cost(&ChipOp::LdIVx { x: 0xF }),
+            cycle_cost(&ChipOp::LdVxI { x: 0xF })
+        );
+        for op in instructions() {
+            assert!(cycle_cost(&op) >= FETCH_CYCLES, "{op}");
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("ld v1, 0x20".parse(), Ok(ChipOp::LdVxNn { x: 1, nn: 0x20 }));
+        assert_eq!(
+            "DRW V0,V1,15".parse(),
+            Ok(ChipOp::DrwVxVyN { x: 0, y: 1, n: 15 })
+        );
+        assert_eq!("high".parse(), Ok(ChipOp::HighRes));
+        assert!("LD V1, 0x100".parse::<ChipOp>().is_err());
+        assert!("JP 0x1000".parse::<ChipOp>().is_err());
+        assert!("MOV V1, V2".parse::<ChipOp>().is_err());
+    }
 }
@@ -0,0 +1,256 @@
+//! A list of ROMs, or a directory of them, to flip through without
+//! restarting. Each ROM is read from disk once and kept in memory.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::chip8::rom;
+
+/// A ROM's path and contents.
+pub type Entry = (PathBuf, Vec<u8>);
+
+/// Reads the ROM at a path, or says why it can't be played.
+pub type Loader = Arc<dyn Fn(&Path) -> Result<Vec<u8>, String> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Playlist {
+    paths: Vec<PathBuf>,
+    /// Contents of each ROM that has been read.
+    roms: Vec<Option<Vec<u8>>>,
+    current: usize,
+    load: Loader,
+    /// ROMs skipped since [`Playlist::take_failures`] was last called, and
+    /// why.
+    failures: Vec<(PathBuf, String)>,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl fmt::Debug for Playlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Playlist")
+            .field("paths", &self.paths)
+            .field("current", &self.current)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Playlist {
+    /// A playlist of `paths`, read with [`rom::read_rom`] unless
+    /// [`Playlist::with_loader`] says otherwise.
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self {
+            roms: vec![None; paths.len()],
+            paths,
+            current: 0,
+            load: Arc::new(|path| {
+                rom::read_rom(&path.to_string_lossy()).map_err(|err| err.to_string())
+            }),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Read ROMs with `load`, so they get the same size limit, assembly and
+    /// checks as a ROM given on its own.
+    pub fn with_loader(
+        mut self,
+        load: impl Fn(&Path) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.load = Arc::new(load);
+        self
+    }
+
+    /// Every regular file in `dir`, sorted by name.
+    pub fn from_dir(dir: &Path) -> io::Result<Self> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+        Ok(Self::new(paths))
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Position of the current ROM, counting from 0.
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    /// The ROMs skipped as unloadable since the last call, and why.
+    pub fn take_failures(&mut self) -> Vec<(PathBuf, String)> {
+        std::mem::take(&mut self.failures)
+    }
+
+    /// Load the current ROM, moving forward past any that can't be read.
+    pub fn load_current(&mut self) -> Option<Entry> {
+        self.seek(0, 1)
+    }
+
+    /// Load the next readable ROM, wrapping at the end.
+    pub fn next_rom(&mut self) -> Option<Entry> {
+        self.seek(1, 1)
+    }
+
+    /// Load the previous readable ROM, wrapping at the start.
+    pub fn prev_rom(&mut self) -> Option<Entry> {
+        self.seek(-1, -1)
+    }
+
+    /// Try entries `first`, `first + step`, ... away from the current one
+    /// until one loads, visiting each entry at most once.
+    fn seek(&mut self, first: isize, step: isize) -> Option<Entry> {
+        let len = self.paths.len() as isize;
+        for attempt in 0..len {
+            let index = (self.current as isize + first + attempt * step).rem_euclid(len) as usize;
+            match self.rom(index).map(<[u8]>::to_vec) {
+                Ok(rom) => {
+                    self.current = index;
+                    return Some((self.paths[index].clone(), rom));
+                }
+                Err(err) => {
+                    log_event!(
+                        warn,
+                        path = %self.paths[index].display(),
+                        error = %err,
+                        "skipping rom"
+                    );
+                    self.failures.push((self.paths[index].clone(), err));
+                }
+            }
+        }
+        None
+    }
+
+    /// Entry `index`'s contents, read on first use.
+    fn rom(&mut self, index: usize) -> Result<&[u8], String> {
+        let rom = match self.roms[index].take() {
+            Some(rom) => rom,
+            None => (self.load)(&self.paths[index])?,
+        };
+        Ok(self.roms[index].insert(rom))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::consts::MAX_ROM_SIZE;
+
+    /// A fresh directory holding `files`, where `None` contents are written
+    /// as an over-sized ROM.
+    fn rom_dir(name: &str, files: &[(&str, Option<&[u8]>)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("oxid8-playlist-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            let contents = contents.map_or(vec![0; MAX_ROM_SIZE + 1], <[u8]>::to_vec);
+            fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    fn name(entry: Option<Entry>) -> String {
+        let (path, _) = entry.unwrap();
+        path.file_name().unwrap().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_playlist_wraps() {
+        let dir = rom_dir(
+            "wrap",
+            &[
+                ("b.ch8", Some(&[2])),
+                ("a.ch8", Some(&[1])),
+                ("c.ch8", Some(&[3])),
+            ],
+        );
+        fs::create_dir(dir.join("subdir")).unwrap();
+        let mut playlist = Playlist::from_dir(&dir).unwrap();
+
+        assert_eq!(playlist.len(), 3);
+        assert_eq!(name(playlist.load_current()), "a.ch8");
+        assert_eq!(name(playlist.next_rom()), "b.ch8");
+        assert_eq!(name(playlist.next_rom()), "c.ch8");
+        assert_eq!(name(playlist.next_rom()), "a.ch8");
+        assert_eq!(name(playlist.prev_rom()), "c.ch8");
+        assert_eq!(playlist.position(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_playlist_keeps_roms_in_memory() {
+        let dir = rom_dir("cache", &[("a.ch8", Some(&[1])), ("b.ch8", Some(&[2]))]);
+        let mut playlist = Playlist::new(vec![dir.join("a.ch8"), dir.join("b.ch8")]);
+        assert_eq!(name(playlist.load_current()), "a.ch8");
+        assert_eq!(name(playlist.next_rom()), "b.ch8");
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(playlist.next_rom().unwrap().1, vec![1]);
+        assert_eq!(playlist.prev_rom().unwrap().1, vec![2]);
+    }
+
+    #[test]
+    fn test_playlist_skips_unreadable() {
+        let dir = rom_dir(
+            "skip",
+            &[
+                ("a.ch8", Some(&[1])),
+                ("b.ch8", None),
+                ("c.ch8", Some(&[3])),
+            ],
+        );
+        let mut paths = Playlist::from_dir(&dir).unwrap().paths;
+        paths.insert(1, dir.join("missing.ch8"));
+        let mut playlist = Playlist::new(paths);
+
+        assert_eq!(name(playlist.load_current()), "a.ch8");
+        let (_, rom) = playlist.next_rom().unwrap();
+        assert_eq!(rom, vec![3]);
+        let skipped: Vec<_> = playlist
+            .take_failures()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(skipped, [dir.join("missing.ch8"), dir.join("b.ch8")]);
+        assert_eq!(name(playlist.prev_rom()), "a.ch8");
+
+        let mut broken = Playlist::new(vec![dir.join("missing.ch8"), dir.join("b.ch8")]);
+        assert!(broken.next_rom().is_none());
+        assert!(Playlist::default().load_current().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_playlist_with_loader() {
+        let paths = vec![PathBuf::from("bad.ch8"), PathBuf::from("good.ch8")];
+        let mut playlist = Playlist::new(paths).with_loader(|path| match path.to_str() {
+            Some("good.ch8") => Ok(vec![0x12, 0x00]),
+            _ => Err("too large".to_string()),
+        });
+
+        assert_eq!(name(playlist.load_current()), "good.ch8");
+        assert_eq!(
+            playlist.take_failures(),
+            [(PathBuf::from("bad.ch8"), "too large".to_string())]
+        );
+        assert!(playlist.take_failures().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+use crate::chip8::op::InstructionSet;
+
+/// Behavioural differences between CHIP-8 interpreters that ROMs may rely on.
+///
+/// The defaults match the original COSMAC VIP interpreter, which is what
+/// `Chip8::exec` has always implemented.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8XY6`/`8XYE` shift VY and store the result in VX. When false VX is
+    /// shifted in place (SUPER-CHIP behaviour).
+    pub shift_uses_vy: bool,
+    /// `FX55`/`FX65` leave I pointing one past the last register touched.
+    pub load_store_increments_i: bool,
+    /// `EX9E`/`EXA1` use the low nibble of VX as the key. When false a VX
+    /// above 0xF is a fault, exposing ROMs that test garbage.
+    pub mask_skip_key: bool,
+    /// Memory accessed through I wraps from the end of RAM to the start,
+    /// and `FX1E` wraps I and `BNNN` its target the same way. When false
+    /// an access or jump past the end is a fault.
+    pub wrap_memory: bool,
+    /// Addresses are masked to the machine's address width before access:
+    /// 12 bits on a 4KB machine, so I = 0x1002 reads 0x002, and 16 bits on
+    /// a larger XO-CHIP one. PC is masked after every step, and `BNNN` its
+    /// target, as on interpreters with 12-bit registers.
+    pub mask_addr: bool,
+    /// The screen is kept at the physical high-res resolution in low-res
+    /// too, each low-res pixel drawn as a 2x2 block, so `00CN`/`00FB`/
+    /// `00FC` always scroll by physical pixels and `00C1` in low-res moves
+    /// half a pixel (SUPER-CHIP 1.1 behaviour). When false low-res uses
+    /// a 64x32 screen and scrolls by its pixels.
+    pub half_pixel_scroll: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            mask_skip_key: true,
+            wrap_memory: false,
+            mask_addr: false,
+            half_pixel_scroll: false,
+        }
+    }
+}
+
+impl Quirks {
+    /// Each quirk's field name and setting, in declaration order.
+    pub fn entries(&self) -> [(&'static str, bool); 6] {
+        let Quirks {
+            shift_uses_vy,
+            load_store_increments_i,
+            mask_skip_key,
+            wrap_memory,
+            mask_addr,
+            half_pixel_scroll,
+        } = *self;
+        [
+            ("shift_uses_vy", shift_uses_vy),
+            ("load_store_increments_i", load_store_increments_i),
+            ("mask_skip_key", mask_skip_key),
+            ("wrap_memory", wrap_memory),
+            ("mask_addr", mask_addr),
+            ("half_pixel_scroll", half_pixel_scroll),
+        ]
+    }
+}
+
+/// Interpreter families a ROM may be written for, each implying a set of
+/// [`Quirks`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Variant {
+    /// The original COSMAC VIP interpreter.
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1 on the HP48.
+    SuperChip,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 2] = [Variant::Chip8, Variant::SuperChip];
+
+    /// The name `--variant` accepts.
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Chip8 => "chip8",
+            Variant::SuperChip => "schip",
+        }
+    }
+
+    /// The newest instruction set this variant's interpreter runs.
+    pub fn instruction_set(self) -> InstructionSet {
+        match self {
+            Variant::Chip8 => InstructionSet::Chip8,
+            Variant::SuperChip => InstructionSet::SuperChip,
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Variant::Chip8 => Quirks::default(),
+            Variant::SuperChip => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                mask_skip_key: true,
+                wrap_memory: false,
+                mask_addr: false,
+                half_pixel_scroll: true,
+            },
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Variant::ALL
+            .into_iter()
+            .find(|variant| variant.name() == s)
+            .ok_or_else(|| format!("invalid variant {s:?}: expected chip8 or schip"))
+    }
+}
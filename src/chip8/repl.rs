@@ -0,0 +1,541 @@
+//! A line-based debugger and monitor, reading commands such as `step 10`
+//! or `poke 0x300 AA BB` and printing the results. Commands may start with
+//! `:` as they do in the TUI.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::chip8::debugger::{Debugger, Stop};
+use crate::chip8::decode::decode;
+use crate::chip8::disasm;
+use crate::chip8::op::ChipOp;
+use crate::chip8::state;
+
+/// Instructions `run` executes before giving up on reaching a breakpoint.
+pub const RUN_LIMIT: u64 = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(usize),
+    I,
+    Pc,
+}
+
+/// Instructions `disasm` lists when no count is given.
+const DISASM_COUNT: usize = 10;
+
+pub const HELP: &str = "\
+step [n]              execute n instructions (default 1)
+regs                  show the registers
+mem <addr> <len>      dump memory (alias: peek)
+poke <addr> <bytes>   write hex bytes, e.g. poke 0x300 AA BB
+set <reg> <val>       set v0-vf, i or pc (alias: reg)
+bp <addr>             add a breakpoint (alias: break)
+run                   run to a breakpoint or EXIT
+asm <addr> <op>       assemble one instruction, e.g. asm 0x400 LD V1, 0x20
+disasm <addr> [n]     list n instructions (default 10)
+save <path>           write a save state
+load <path>           restore a save state
+history               list the commands entered so far
+help                  show this text
+quit                  stop debugging";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Step(u64),
+    Regs,
+    Mem { addr: usize, len: usize },
+    Poke { addr: usize, bytes: Vec<u8> },
+    Set { reg: Register, value: usize },
+    Breakpoint(usize),
+    Run,
+    Asm { addr: usize, op: ChipOp },
+    Disasm { addr: usize, count: usize },
+    Save(PathBuf),
+    Load(PathBuf),
+    History,
+    Help,
+    Quit,
+}
+
+/// A number in decimal, or in hex with a `0x` prefix.
+fn parse_number(s: &str) -> Result<usize, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number {s:?}"))
+}
+
+/// A byte in hex, with or without a `0x` prefix.
+fn parse_byte(s: &str) -> Result<u8, String> {
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u8::from_str_radix(hex, 16).map_err(|_| format!("invalid byte {s:?}"))
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "i" => Ok(Register::I),
+            "pc" => Ok(Register::Pc),
+            reg => reg
+                .strip_prefix('v')
+                .filter(|x| x.len() == 1)
+                .and_then(|x| usize::from_str_radix(x, 16).ok())
+                .map(Register::V)
+                .ok_or_else(|| format!("invalid register {s:?}: expected v0-vf, i or pc")),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let line = line.strip_prefix(':').unwrap_or(line).trim_start();
+        // `asm` takes the rest of the line, which has its own commas and spaces.
+        if let Some(rest) = line.strip_prefix("asm ") {
+            let (addr, op) = rest
+                .trim()
+                .split_once(' ')
+                .ok_or("usage: asm <addr> <op>")?;
+            return Ok(Command::Asm {
+                addr: parse_number(addr)?,
+                op: op.parse()?,
+            });
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let command = match words.as_slice() {
+            ["step"] => Command::Step(1),
+            ["step", n] => Command::Step(parse_number(n)? as u64),
+            ["regs"] => Command::Regs,
+            ["mem" | "peek", addr, len] => Command::Mem {
+                addr: parse_number(addr)?,
+                len: parse_number(len)?,
+            },
+            ["poke", addr, bytes @ ..] if !bytes.is_empty() => Command::Poke {
+                addr: parse_number(addr)?,
+                bytes: bytes
+                    .iter()
+                    .map(|b| parse_byte(b))
+                    .collect::<Result<_, _>>()?,
+            },
+            ["set" | "reg", reg, value] => Command::Set {
+                reg: reg.parse()?,
+                value: parse_number(value)?,
+            },
+            ["bp" | "break", addr] => Command::Breakpoint(parse_number(addr)?),
+            ["run"] => Command::Run,
+            ["disasm", addr] => Command::Disasm {
+                addr: parse_number(addr)?,
+                count: DISASM_COUNT,
+            },
+            ["disasm", addr, count] => Command::Disasm {
+                addr: parse_number(addr)?,
+                count: parse_number(count)?,
+            },
+            ["save", path] => Command::Save(path.into()),
+            ["load", path] => Command::Load(path.into()),
+            ["history"] => Command::History,
+            ["help"] => Command::Help,
+            ["quit"] => Command::Quit,
+            _ => return Err(format!("unknown command {line:?}: type help for a list")),
+        };
+        Ok(command)
+    }
+}
+
+/// A debugging session that remembers the lines it was given.
+pub struct Monitor {
+    dbg: Debugger,
+    history: Vec<String>,
+}
+
+impl Monitor {
+    pub fn new(dbg: Debugger) -> Self {
+        Self {
+            dbg,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.dbg
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Parse and run one line, returning what to print, or `None` on quit.
+    /// Blank lines print nothing.
+    pub fn handle(&mut self, line: &str) -> Option<Result<String, String>> {
+        if line.trim().is_empty() {
+            return Some(Ok(String::new()));
+        }
+        self.history.push(line.trim().to_string());
+        let command = match line.parse() {
+            Ok(Command::Quit) => return None,
+            Ok(command) => command,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(match command {
+            Command::History => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(n, line)| format!("{:>4}  {line}", n + 1))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            command => execute(&mut self.dbg, command),
+        })
+    }
+}
+
+/// Run `command`, returning what to print.
+pub fn execute(dbg: &mut Debugger, command: Command) -> Result<String, String> {
+    let mut out = String::new();
+    match command {
+        Command::Step(n) => {
+            for _ in 0..n {
+                if !dbg.step() {
+                    break;
+                }
+            }
+            if let Stop::Fault { pc, error } = dbg.stop() {
+                writeln!(out, "fault at {pc:#05x}: {error}").unwrap();
+            }
+            out += &next_instruction(dbg);
+        }
+        Command::Regs => out = dbg.emulator().chip().to_string(),
+        Command::Mem { addr, len } => {
+            let memory = &dbg.emulator().chip().memory;
+            let end = addr.saturating_add(len);
+            if end > memory.len() {
+                return Err(format!("{addr:#05x}+{len} is past the end of memory"));
+            }
+            for (row, chunk) in memory[addr..end].chunks(16).enumerate() {
+                let bytes: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+                writeln!(out, "{:#05x}: {}", addr + 16 * row, bytes.join(" ")).unwrap();
+            }
+            out.pop();
+        }
+        Command::Poke { addr, bytes } => {
+            let memory = &mut dbg.emulator_mut().chip_mut().memory;
+            let end = addr.saturating_add(bytes.len());
+            if end > memory.len() {
+                return Err(format!(
+                    "{addr:#05x}+{} is past the end of memory",
+                    bytes.len()
+                ));
+            }
+            memory[addr..end].copy_from_slice(&bytes);
+        }
+        Command::Set { reg, value } => {
+            let chip = dbg.emulator_mut().chip_mut();
+            match reg {
+                Register::V(x) => {
+                    chip.v[x] =
+                        u8::try_from(value).map_err(|_| format!("{value} doesn't fit in V{x:X}"))?
+                }
+                _ if value >= chip.memory.len() => {
+                    return Err(format!("{value:#x} is past the end of memory"))
+                }
+                Register::I => chip.i = value,
+                Register::Pc => chip.pc = value,
+            }
+        }
+        Command::Breakpoint(addr) => {
+            dbg.add_breakpoint(addr);
+            out = format!("breakpoint at {addr:#05x}");
+        }
+        Command::Run => {
+            out = match dbg.run(RUN_LIMIT) {
+                Stop::Breakpoint(pc) => format!("breakpoint at {pc:#05x}"),
+                Stop::Halted => "halted".to_string(),
+                Stop::Fault { pc, error } => format!("fault at {pc:#05x}: {error}"),
+                Stop::Limit => format!("stopped after {RUN_LIMIT} instructions"),
+            };
+            write!(out, "\n{}", next_instruction(dbg)).unwrap();
+        }
+        Command::Asm { addr, op } => {
+            let memory = &mut dbg.emulator_mut().chip_mut().memory;
+            if addr.saturating_add(2) > memory.len() {
+                return Err(format!("{addr:#05x} is past the end of memory"));
+            }
+            memory[addr..addr + 2].copy_from_slice(&op.encode().to_be_bytes());
+            out = disasm::disassemble_memory(memory, addr, 1);
+            out.pop();
+        }
+        Command::Disasm { addr, count } => {
+            out = disasm::disassemble_memory(&dbg.emulator().chip().memory, addr, count);
+            out.pop();
+        }
+        Command::Save(path) => {
+            fs::write(&path, state::save(dbg.emulator().chip()))
+                .map_err(|err| format!("can't write {}: {err}", path.display()))?;
+            out = format!("saved {}", path.display());
+        }
+        Command::Load(path) => {
+            let bytes =
+                fs::read(&path).map_err(|err| format!("can't read {}: {err}", path.display()))?;
+            state::load(dbg.emulator_mut().chip_mut(), &bytes)
+                .map_err(|err| format!("can't load {}: {err}", path.display()))?;
+            out = next_instruction(dbg);
+        }
+        Command::History => {}
+        Command::Help => out = HELP.to_string(),
+        Command::Quit => {}
+    }
+    Ok(out)
+}
+
+/// The instruction at PC, e.g. `0x202  00E0  CLS`.
+fn next_instruction(dbg: &Debugger) -> String {
+    let chip = dbg.emulator().chip();
+    match chip.memory.get(chip.pc..chip.pc + 2) {
+        Some(&[hi, lo]) => {
+            let word = u16::from_be_bytes([hi, lo]);
+            format!("{:#05x}  {word:04X}  {}", chip.pc, decode(word))
+        }
+        _ => format!("{:#05x}  past the end of memory", chip.pc),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::{Emulator, EmulatorConfig};
+
+    fn parse(line: &str) -> Result<Command, String> {
+        line.parse()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse("step"), Ok(Command::Step(1)));
+        assert_eq!(parse("  step 10 "), Ok(Command::Step(10)));
+        assert_eq!(parse("regs"), Ok(Command::Regs));
+        assert_eq!(
+            parse("mem 0x200 16"),
+            Ok(Command::Mem {
+                addr: 0x200,
+                len: 16
+            })
+        );
+        assert_eq!(
+            parse("set vA 0x2a"),
+            Ok(Command::Set {
+                reg: Register::V(0xA),
+                value: 0x2A
+            })
+        );
+        assert_eq!(
+            parse("set pc 0x300"),
+            Ok(Command::Set {
+                reg: Register::Pc,
+                value: 0x300
+            })
+        );
+        assert_eq!(parse("bp 0x20a"), Ok(Command::Breakpoint(0x20A)));
+        assert_eq!(parse("run"), Ok(Command::Run));
+        assert_eq!(parse("quit"), Ok(Command::Quit));
+    }
+
+    #[test]
+    fn test_parse_monitor_commands() {
+        assert_eq!(
+            parse(":peek 0x300 16"),
+            Ok(Command::Mem {
+                addr: 0x300,
+                len: 16
+            })
+        );
+        assert_eq!(
+            parse("poke 0x300 AA 0xbb"),
+            Ok(Command::Poke {
+                addr: 0x300,
+                bytes: vec![0xAA, 0xBB]
+            })
+        );
+        assert_eq!(
+            parse(": reg v3 0x1f"),
+            Ok(Command::Set {
+                reg: Register::V(3),
+                value: 0x1F
+            })
+        );
+        assert_eq!(parse("break 0x2a0"), Ok(Command::Breakpoint(0x2A0)));
+        assert_eq!(
+            parse("asm 0x400 LD V1, 0x20"),
+            Ok(Command::Asm {
+                addr: 0x400,
+                op: ChipOp::LdVxNn { x: 1, nn: 0x20 }
+            })
+        );
+        assert_eq!(
+            parse("disasm 0x200"),
+            Ok(Command::Disasm {
+                addr: 0x200,
+                count: 10
+            })
+        );
+        assert_eq!(
+            parse("disasm 0x200 3"),
+            Ok(Command::Disasm {
+                addr: 0x200,
+                count: 3
+            })
+        );
+        assert_eq!(
+            parse("save state.o8s"),
+            Ok(Command::Save("state.o8s".into()))
+        );
+        assert_eq!(parse("history"), Ok(Command::History));
+        assert_eq!(parse("help"), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("jump 0x200").is_err());
+        assert!(parse("step many").is_err());
+        assert!(parse("set v10 1").is_err());
+        assert!(parse("mem 0x200").is_err());
+        assert!(parse("poke 0x300").is_err());
+        assert!(parse("poke 0x300 AAB").is_err());
+        assert!(parse("asm 0x400").is_err());
+        assert!(parse("asm 0x400 LD V1").is_err());
+        assert!(parse("save").is_err());
+    }
+
+    #[test]
+    fn test_execute() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        // LD V0, 1; ADD V0, 1; JP 0x202
+        emu.load_rom(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]).unwrap();
+        let mut dbg = Debugger::new(emu);
+        let mut run = |line: &str| execute(&mut dbg, parse(line).unwrap());
+
+        assert_eq!(run("step"), Ok("0x202  7001  ADD V0, 0x01".to_string()));
+        assert_eq!(run("mem 0x200 4"), Ok("0x200: 60 01 70 01".to_string()));
+        assert_eq!(run("set v0 0x10"), Ok(String::new()));
+        assert!(run("set v0 256").is_err());
+        run("bp 0x204").unwrap();
+        assert_eq!(
+            run("run"),
+            Ok("breakpoint at 0x204\n0x204  1202  JP 0x202".to_string())
+        );
+        assert!(run("regs").unwrap().contains("V0=11"));
+        assert!(run("mem 0xFFF 2").is_err());
+        assert!(run("set i 0x1000").is_err());
+    }
+
+    #[test]
+    fn test_execute_fault() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        // An unknown opcode; JP 0x200
+        emu.load_rom(&[0x5A, 0xB1, 0x12, 0x00]).unwrap();
+        let mut dbg = Debugger::new(emu);
+        let fault = "fault at 0x200: Unkown opcode: 0x5AB1 (family 0x5)";
+
+        let out = execute(&mut dbg, Command::Step(1)).unwrap();
+        assert_eq!(out, format!("{fault}\n0x200  5AB1  DB 0x5AB1"));
+        let out = execute(&mut dbg, Command::Run).unwrap();
+        assert!(out.starts_with(fault), "{out}");
+    }
+
+    fn monitor() -> Monitor {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        // LD V0, 1; ADD V0, 1; JP 0x202
+        emu.load_rom(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]).unwrap();
+        Monitor::new(Debugger::new(emu))
+    }
+
+    fn chip(mon: &Monitor) -> &crate::chip8::cpu::Chip8 {
+        mon.debugger().emulator().chip()
+    }
+
+    #[test]
+    fn test_monitor_poke_and_peek() {
+        let mut mon = monitor();
+        assert_eq!(mon.handle(":poke 0x300 AA BB"), Some(Ok(String::new())));
+        assert_eq!(chip(&mon).memory[0x300..0x302], [0xAA, 0xBB]);
+        assert_eq!(
+            mon.handle("peek 0x300 2"),
+            Some(Ok("0x300: aa bb".to_string()))
+        );
+        assert!(mon.handle("poke 0xFFF AA BB").unwrap().is_err());
+        assert_eq!(chip(&mon).memory[0xFFF], 0);
+    }
+
+    #[test]
+    fn test_monitor_reg_step_and_break() {
+        let mut mon = monitor();
+        mon.handle("reg v3 0x1f").unwrap().unwrap();
+        assert_eq!(chip(&mon).v[3], 0x1F);
+        mon.handle("step 2").unwrap().unwrap();
+        assert_eq!(chip(&mon).v[0], 2);
+        mon.handle("break 0x204").unwrap().unwrap();
+        mon.handle("run").unwrap().unwrap();
+        assert_eq!(chip(&mon).pc, 0x204);
+    }
+
+    #[test]
+    fn test_monitor_asm_and_disasm() {
+        let mut mon = monitor();
+        assert_eq!(
+            mon.handle("asm 0x400 LD V1, 0x20"),
+            Some(Ok("0x400  6120  LD V1, 0x20".to_string()))
+        );
+        assert_eq!(chip(&mon).memory[0x400..0x402], [0x61, 0x20]);
+        assert_eq!(
+            mon.handle("disasm 0x200 2"),
+            Some(Ok(
+                "0x200  6001  LD V0, 0x01\n0x202  7001  ADD V0, 0x01".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_monitor_save_and_load() {
+        let path = std::env::temp_dir().join(format!("oxid8-monitor-{}.o8s", std::process::id()));
+        let mut mon = monitor();
+        mon.handle("reg v5 0x42").unwrap().unwrap();
+        mon.handle(&format!("save {}", path.display()))
+            .unwrap()
+            .unwrap();
+        mon.handle("reg v5 0").unwrap().unwrap();
+        mon.handle(&format!("load {}", path.display()))
+            .unwrap()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(chip(&mon).v[5], 0x42);
+        assert!(mon
+            .handle(&format!("load {}", path.display()))
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn test_monitor_history_help_and_quit() {
+        let mut mon = monitor();
+        assert_eq!(mon.handle("  "), Some(Ok(String::new())));
+        assert!(mon.handle("bogus").unwrap().is_err());
+        mon.handle("step").unwrap().unwrap();
+        assert_eq!(
+            mon.handle("history"),
+            Some(Ok("   1  bogus\n   2  step\n   3  history".to_string()))
+        );
+        assert!(mon.handle("help").unwrap().unwrap().contains("poke <addr>"));
+        assert_eq!(mon.handle(":quit"), None);
+    }
+}
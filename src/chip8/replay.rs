@@ -0,0 +1,171 @@
+//! Recorded input sessions that can be played back headless.
+//!
+//! A replay is a plain text file with one key or speed change per line,
+//! tagged with the frame it was queued for:
+//!
+//! ```text
+//! # frame  event  key
+//! 12 down 5
+//! 20 up 5
+//! 31 speed turbo-on
+//! ```
+//!
+//! Played back against the same ROM and RND seed, a replay reproduces the
+//! session exactly, which makes it usable as a regression test.
+
+use std::fmt;
+
+use color_eyre::eyre::{bail, eyre, Result};
+
+use crate::chip8::emulator::{KeyEvent, SpeedChange};
+
+/// Input that changes how a session plays out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayEvent {
+    Key(KeyEvent),
+    /// Turbo and slow motion change how many instructions run per frame.
+    Speed(SpeedChange),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    events: Vec<(u64, ReplayEvent)>,
+}
+
+/// How replay files name each speed change.
+const SPEED_NAMES: [(SpeedChange, &str); 4] = [
+    (SpeedChange::TurboOn, "turbo-on"),
+    (SpeedChange::TurboOff, "turbo-off"),
+    (SpeedChange::ToggleTurbo, "toggle-turbo"),
+    (SpeedChange::ToggleSlowMotion, "toggle-slow"),
+];
+
+impl Replay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `event` as queued before `frame` ran.
+    pub fn record(&mut self, frame: u64, event: ReplayEvent) {
+        self.events.push((frame, event));
+    }
+
+    pub fn events(&self) -> &[(u64, ReplayEvent)] {
+        &self.events
+    }
+
+    /// Number of frames needed to play back every event.
+    pub fn frames(&self) -> u64 {
+        self.events.last().map_or(0, |&(frame, _)| frame + 1)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut replay = Replay::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parse = || -> Result<(u64, ReplayEvent)> {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let [frame, kind, arg] = fields[..] else {
+                    bail!("expected `<frame> down|up <key>` or `<frame> speed <change>`");
+                };
+                let frame = frame
+                    .parse::<u64>()
+                    .map_err(|_| eyre!("bad frame number {frame:?}"))?;
+                let key = || {
+                    u8::from_str_radix(arg, 16)
+                        .ok()
+                        .filter(|&k| k <= 0xF)
+                        .ok_or_else(|| eyre!("bad key {arg:?}"))
+                };
+                let event = match kind {
+                    "down" => ReplayEvent::Key(KeyEvent::Down(key()?)),
+                    "up" => ReplayEvent::Key(KeyEvent::Up(key()?)),
+                    "speed" => SPEED_NAMES
+                        .iter()
+                        .find(|(_, name)| *name == arg)
+                        .map(|&(change, _)| ReplayEvent::Speed(change))
+                        .ok_or_else(|| eyre!("bad speed change {arg:?}"))?,
+                    _ => bail!("bad event {kind:?}"),
+                };
+                Ok((frame, event))
+            };
+            let (frame, event) = parse().map_err(|e| eyre!("replay line {}: {e}", n + 1))?;
+            if frame < replay.frames().saturating_sub(1) {
+                bail!("replay line {}: frame {frame} out of order", n + 1);
+            }
+            replay.record(frame, event);
+        }
+        Ok(replay)
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# oxid8 replay")?;
+        for (frame, event) in &self.events {
+            match event {
+                ReplayEvent::Key(KeyEvent::Down(key)) => writeln!(f, "{frame} down {key:X}")?,
+                ReplayEvent::Key(KeyEvent::Up(key)) => writeln!(f, "{frame} up {key:X}")?,
+                ReplayEvent::Speed(change) => {
+                    let (_, name) = SPEED_NAMES.iter().find(|(c, _)| c == change).unwrap();
+                    writeln!(f, "{frame} speed {name}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::emulator::{Emulator, EmulatorConfig};
+    use crate::chip8::frontend::{self, HeadlessFrontend};
+
+    #[test]
+    fn test_parse_round_trip() {
+        let mut replay = Replay::new();
+        replay.record(3, ReplayEvent::Key(KeyEvent::Down(0xA)));
+        replay.record(3, ReplayEvent::Key(KeyEvent::Down(0x1)));
+        replay.record(5, ReplayEvent::Speed(SpeedChange::TurboOn));
+        replay.record(9, ReplayEvent::Key(KeyEvent::Up(0xA)));
+        replay.record(9, ReplayEvent::Speed(SpeedChange::ToggleSlowMotion));
+
+        let parsed = Replay::parse(&replay.to_string()).unwrap();
+        assert_eq!(parsed, replay);
+        assert_eq!(parsed.frames(), 10);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = Replay::parse("1 down 5\n2 sideways 5").unwrap_err();
+        assert_eq!(err.to_string(), "replay line 2: bad event \"sideways\"");
+        assert!(Replay::parse("1 down 10").is_err());
+        let err = Replay::parse("1 speed warp").unwrap_err();
+        assert_eq!(err.to_string(), "replay line 1: bad speed change \"warp\"");
+        assert!(Replay::parse("5 down 1\n2 up 1").is_err());
+    }
+
+    #[test]
+    fn test_play_same_hash() {
+        // RND V0, 0xFF ; LD F, V0 ; SKNP V1 ; DRW V2, V2, 5 ; JP 0x200
+        let rom = [0xC0, 0xFF, 0xF0, 0x29, 0xE1, 0xA1, 0xD2, 0x25, 0x12, 0x00];
+        let replay = Replay::parse("2 down 0\n5 up 0\n7 down 0").unwrap();
+        let run = || {
+            let mut emu = Emulator::new(EmulatorConfig {
+                seed: Some(7),
+                ..EmulatorConfig::default()
+            });
+            emu.load_rom(&rom).unwrap();
+            frontend::run(
+                &mut emu,
+                &mut HeadlessFrontend::new(10).with_replay(&replay),
+            );
+            emu.snapshot().state_hash()
+        };
+        assert_eq!(run(), run());
+    }
+}
@@ -0,0 +1,85 @@
+//! The random number generator behind RND (splitmix64).
+//!
+//! Small, fast, and the same sequence for a seed on every platform and
+//! release, which is what replays and `--seed` need. Not for anything
+//! that has to be unpredictable.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Bumped per entropy-seeded generator so two made in the same clock tick
+/// still differ.
+static ENTROPY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng(u64);
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+impl Rng {
+    /// A generator that always produces the same sequence for `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// A generator seeded from the system clock.
+    pub fn from_entropy() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let count = ENTROPY_COUNTER.fetch_add(1, Ordering::Relaxed);
+        Self(nanos ^ count.wrapping_mul(GAMMA))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(GAMMA);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_values() {
+        // From the splitmix64 reference implementation, seeded with 0.
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn test_fixed_seed_reproducible() {
+        let run = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..32).map(|_| rng.next_u8()).collect::<Vec<_>>()
+        };
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+        assert_ne!(Rng::from_entropy(), Rng::from_entropy());
+    }
+
+    #[test]
+    fn test_every_byte_appears() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 256];
+        for _ in 0..4096 {
+            seen[rng.next_u8() as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+}
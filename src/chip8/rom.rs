@@ -0,0 +1,202 @@
+//! Reading ROM images from files or standard input.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+
+use crate::chip8::consts::MAX_ROM_SIZE;
+use crate::chip8::decode::decode;
+use crate::chip8::op::ChipOp;
+use crate::chip8::quirks::Variant;
+
+/// Path that selects standard input instead of a file.
+pub const STDIN_PATH: &str = "-";
+
+/// Read the ROM at `path`, or from stdin when `path` is `-`.
+pub fn read_rom(path: &str) -> io::Result<Vec<u8>> {
+    read_rom_limited(path, MAX_ROM_SIZE)
+}
+
+/// Like [`read_rom`], for a machine that fits ROMs of up to `limit` bytes.
+pub fn read_rom_limited(path: &str, limit: usize) -> io::Result<Vec<u8>> {
+    if path == STDIN_PATH {
+        return read_rom_from(io::stdin().lock(), limit);
+    }
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    if metadata.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "is a directory, not a ROM file",
+        ));
+    }
+    if metadata.len() > limit as u64 {
+        return Err(too_large(&format!("{} bytes", metadata.len()), limit));
+    }
+    read_rom_from(file, limit)
+}
+
+/// Read a ROM from `reader`, failing once it exceeds `limit` bytes without
+/// buffering the rest.
+pub fn read_rom_from<R: Read>(reader: R, limit: usize) -> io::Result<Vec<u8>> {
+    let mut rom = Vec::new();
+    reader.take(limit as u64 + 1).read_to_end(&mut rom)?;
+    if rom.len() > limit {
+        return Err(too_large(&format!("over {limit} bytes"), limit));
+    }
+    Ok(rom)
+}
+
+fn too_large(size: &str, limit: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Rom too large: {size} (limit {limit})"),
+    )
+}
+
+/// Words from the start of a ROM checked for unknown opcodes.
+const CHECKED_WORDS: usize = 64;
+/// Percentage of checked words that may be unknown before it's suspicious.
+const UNKNOWN_PERCENT: usize = 40;
+
+/// Leading bytes of file formats that get loaded as ROMs by mistake.
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"PK\x03\x04", "zip archive"),
+    (b"\x1F\x8B", "gzip archive"),
+    (b"\x89PNG", "PNG image"),
+    (b"GIF8", "GIF image"),
+    (b"\xFF\xD8\xFF", "JPEG image"),
+    (b"%PDF", "PDF document"),
+    (b"\x7FELF", "ELF executable"),
+];
+
+/// Something about a ROM suggesting it isn't a program for this machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomWarning {
+    /// Instructions are two bytes, so programs are almost always even.
+    OddLength(usize),
+    /// Too many of the first words aren't instructions of the variant.
+    UnknownOpcodes { unknown: usize, checked: usize },
+    /// The file starts like another format.
+    Magic(&'static str),
+    /// The start of the file is all printable text.
+    Text,
+}
+
+impl fmt::Display for RomWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomWarning::OddLength(len) => write!(f, "odd length ({len} bytes)"),
+            RomWarning::UnknownOpcodes { unknown, checked } => {
+                write!(f, "{unknown} of the first {checked} opcodes are unknown")
+            }
+            RomWarning::Magic(format) => write!(f, "looks like a {format}"),
+            RomWarning::Text => write!(f, "looks like a text file"),
+        }
+    }
+}
+
+/// Cheap checks for files that were probably loaded by mistake.
+pub fn validate_rom(rom: &[u8], variant: Variant) -> Vec<RomWarning> {
+    let mut warnings = Vec::new();
+    if rom.len() % 2 == 1 {
+        warnings.push(RomWarning::OddLength(rom.len()));
+    }
+    if let Some(&(_, format)) = MAGIC.iter().find(|(magic, _)| rom.starts_with(magic)) {
+        warnings.push(RomWarning::Magic(format));
+    }
+    let head = &rom[..rom.len().min(2 * CHECKED_WORDS)];
+    if !head.is_empty()
+        && head
+            .iter()
+            .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+    {
+        warnings.push(RomWarning::Text);
+    }
+
+    let words: Vec<u16> = head
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    // 0x0000 is padding or data, not an unknown instruction.
+    let unknown = words
+        .iter()
+        .filter(|&&word| word != 0)
+        .map(|&word| decode(word))
+        .filter(|op| {
+            matches!(op, ChipOp::Unknown { .. } | ChipOp::Sys { .. })
+                || op.instruction_set() > variant.instruction_set()
+        })
+        .count();
+    if unknown * 100 > words.len() * UNKNOWN_PERCENT {
+        warnings.push(RomWarning::UnknownOpcodes {
+            unknown,
+            checked: words.len(),
+        });
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_rom_from_reader() {
+        let rom = read_rom_from(Cursor::new(vec![0x12, 0x00]), MAX_ROM_SIZE).unwrap();
+        assert_eq!(rom, vec![0x12, 0x00]);
+
+        let rom = read_rom_from(Cursor::new(vec![0xAA; MAX_ROM_SIZE]), MAX_ROM_SIZE).unwrap();
+        assert_eq!(rom.len(), MAX_ROM_SIZE);
+    }
+
+    #[test]
+    fn test_validate_rom() {
+        let keypad = include_bytes!("../../roms/keypad.ch8");
+        assert_eq!(validate_rom(keypad, Variant::Chip8), []);
+
+        let text = b"Remember to buy milk and eggs on the way home.\n";
+        let warnings = validate_rom(text, Variant::Chip8);
+        assert!(warnings.contains(&RomWarning::OddLength(text.len())));
+        assert!(warnings.contains(&RomWarning::Text));
+
+        let png =
+            b"\x89PNG\r\n\x1A\n\0\0\0\x0DIHDR\0\0\0\x10\0\0\0\x10\x08\x06\0\0\0\x1F\xF3\xFF\x61";
+        let warnings = validate_rom(png, Variant::Chip8);
+        assert_eq!(warnings[1], RomWarning::Magic("PNG image"));
+        assert_eq!(warnings[1].to_string(), "looks like a PNG image");
+        assert!(matches!(
+            warnings[2],
+            RomWarning::UnknownOpcodes { checked: 16, .. }
+        ));
+    }
+
+    #[test]
+    fn test_validate_rom_by_variant() {
+        // HIGH; SCR; SCL; EXIT: fine on SUPER-CHIP, unknown to CHIP-8.
+        let rom = [0x00, 0xFF, 0x00, 0xFB, 0x00, 0xFC, 0x00, 0xFD];
+        assert_eq!(validate_rom(&rom, Variant::SuperChip), []);
+        assert_eq!(
+            validate_rom(&rom, Variant::Chip8),
+            [RomWarning::UnknownOpcodes {
+                unknown: 4,
+                checked: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn test_validate_rom_ignores_padding() {
+        // CLS; JP 0x202, then padding.
+        let mut rom = vec![0x00, 0xE0, 0x12, 0x02];
+        rom.resize(64, 0);
+        assert_eq!(validate_rom(&rom, Variant::Chip8), []);
+    }
+
+    #[test]
+    fn test_read_rom_from_reader_too_large() {
+        let err = read_rom_from(io::repeat(0).take(1 << 20), MAX_ROM_SIZE).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
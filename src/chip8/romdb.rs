@@ -0,0 +1,175 @@
+//! Known ROMs and the settings they play best with.
+//!
+//! ROMs are identified by their [`rom_hash`]. To add one, append a
+//! [`RomInfo`] to [`ROMS`] with the `rom_hash` that `oxid8 --log-file`
+//! logs when the ROM loads.
+
+use crate::chip8::emulator::EmulatorConfig;
+use crate::chip8::quirks::{Quirks, Variant};
+use crate::utils::hash::fnv1a;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomInfo {
+    /// [`rom_hash`] of the ROM contents.
+    pub hash: u64,
+    pub title: &'static str,
+    pub variant: Variant,
+    pub cycles_per_frame: u64,
+    /// Which keys the game uses, for display alongside the title.
+    pub keymap_hint: &'static str,
+}
+
+pub const ROMS: &[RomInfo] = &[RomInfo {
+    hash: 0xc615_6731_cc9c_9d40,
+    title: "Keypad test",
+    variant: Variant::Chip8,
+    cycles_per_frame: 12,
+    keymap_hint: "any key shows its hex digit",
+}];
+
+/// The settings the database recommends for a ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub variant: Variant,
+    pub quirks: Quirks,
+    pub cycles_per_frame: u64,
+}
+
+impl RomInfo {
+    pub fn settings(&self) -> Settings {
+        Settings {
+            variant: self.variant,
+            quirks: self.variant.quirks(),
+            cycles_per_frame: self.cycles_per_frame,
+        }
+    }
+}
+
+/// A hash of the program bytes that identifies a ROM. It's FNV-1a, so it
+/// stays the same across runs, builds and platforms.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    fnv1a(rom)
+}
+
+/// Look `rom` up in the embedded database.
+pub fn lookup(rom: &[u8]) -> Option<&'static RomInfo> {
+    lookup_in(ROMS, rom)
+}
+
+/// The settings `rom` plays best with, if it's a known ROM.
+pub fn recommended_settings(rom: &[u8]) -> Option<Settings> {
+    lookup(rom).map(RomInfo::settings)
+}
+
+pub fn lookup_in<'a>(db: &'a [RomInfo], rom: &[u8]) -> Option<&'a RomInfo> {
+    let hash = rom_hash(rom);
+    db.iter().find(|info| info.hash == hash)
+}
+
+/// Settings given explicitly by the user, which win over the database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Overrides {
+    pub variant: Option<Variant>,
+    pub cycles_per_frame: Option<u64>,
+}
+
+/// Fill in `config` from the user's overrides, then the ROM's database
+/// entry, then the existing defaults.
+pub fn apply(config: &mut EmulatorConfig, info: Option<&RomInfo>, overrides: &Overrides) {
+    if let Some(variant) = overrides.variant.or(info.map(|i| i.variant)) {
+        config.quirks = variant.quirks();
+    }
+    if let Some(cycles) = overrides
+        .cycles_per_frame
+        .or(info.map(|i| i.cycles_per_frame))
+    {
+        config.cycles_per_frame = cycles;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DB: &[RomInfo] = &[
+        RomInfo {
+            hash: 0x086f_b407_b51f_68cf, // 12 00
+            title: "Spin",
+            variant: Variant::SuperChip,
+            cycles_per_frame: 30,
+            keymap_hint: "",
+        },
+        RomInfo {
+            hash: 0xe375_c27c_8d02_e1f7, // 00 E0 12 00
+            title: "Clear",
+            variant: Variant::Chip8,
+            cycles_per_frame: 8,
+            keymap_hint: "",
+        },
+    ];
+
+    #[test]
+    fn test_rom_hash() {
+        let keypad = include_bytes!("../../roms/keypad.ch8");
+        // Pinned, so a change to the hash that would orphan every entry
+        // in the database fails here.
+        assert_eq!(rom_hash(keypad), 0xc615_6731_cc9c_9d40);
+
+        let mut changed = keypad.to_vec();
+        changed[10] ^= 0x01;
+        assert_ne!(rom_hash(&changed), rom_hash(keypad));
+        assert_ne!(rom_hash(&[0x12, 0x00]), rom_hash(&[0x12, 0x00, 0x00]));
+    }
+
+    #[test]
+    fn test_lookup_in() {
+        assert_eq!(lookup_in(DB, &[0x12, 0x00]).unwrap().title, "Spin");
+        assert_eq!(
+            lookup_in(DB, &[0x00, 0xE0, 0x12, 0x00]).unwrap().title,
+            "Clear"
+        );
+        assert!(lookup_in(DB, &[0x12, 0x02]).is_none());
+        assert!(lookup(include_bytes!("../../roms/keypad.ch8")).is_some());
+    }
+
+    #[test]
+    fn test_recommended_settings() {
+        let keypad = include_bytes!("../../roms/keypad.ch8");
+        assert_eq!(
+            recommended_settings(keypad),
+            Some(Settings {
+                variant: Variant::Chip8,
+                quirks: Quirks::default(),
+                cycles_per_frame: 12,
+            })
+        );
+        assert_eq!(recommended_settings(&[0x12, 0x00]), None);
+        assert_eq!(
+            lookup_in(DB, &[0x12, 0x00]).unwrap().settings().quirks,
+            Variant::SuperChip.quirks()
+        );
+    }
+
+    #[test]
+    fn test_apply_precedence() {
+        let info = lookup_in(DB, &[0x12, 0x00]);
+
+        let mut config = EmulatorConfig::default();
+        apply(&mut config, info, &Overrides::default());
+        assert_eq!(config.cycles_per_frame, 30);
+        assert_eq!(config.quirks, Variant::SuperChip.quirks());
+
+        let mut config = EmulatorConfig::default();
+        let overrides = Overrides {
+            variant: Some(Variant::Chip8),
+            cycles_per_frame: Some(20),
+        };
+        apply(&mut config, info, &overrides);
+        assert_eq!(config.cycles_per_frame, 20);
+        assert_eq!(config.quirks, Variant::Chip8.quirks());
+
+        let mut config = EmulatorConfig::default();
+        apply(&mut config, None, &Overrides::default());
+        assert_eq!(config, EmulatorConfig::default());
+    }
+}
@@ -0,0 +1,296 @@
+//! The operations DRW and the scrolls need from a display plane, so
+//! alternative layouts can be benchmarked against [`Framebuffer`].
+//!
+//! With the `flat-screen` feature this also provides [`FlatScreen`], the
+//! byte-per-8-pixels layout the screen used before it was bit-packed, and
+//! [`exercise`], a fixed mix of draws and scrolls to time either with.
+
+use crate::chip8::framebuffer::Framebuffer;
+
+pub trait ScreenOps {
+    /// Pixels `8 * x` to `8 * x + 7` of row `y`, leftmost pixel highest.
+    fn byte(&self, y: usize, x: usize) -> u8;
+    fn clear(&mut self);
+    /// XOR the `width` low bits of `bits` onto row `y` with the first at
+    /// column `x`, wrapping at the right edge. Returns whether any lit
+    /// pixel was turned off.
+    fn draw_sprite_row(&mut self, x: usize, y: usize, bits: u16, width: usize) -> bool;
+    fn scroll_down(&mut self, n: usize);
+    fn scroll_up(&mut self, n: usize);
+    fn scroll_right(&mut self, n: usize);
+    fn scroll_left(&mut self, n: usize);
+}
+
+impl ScreenOps for Framebuffer {
+    fn byte(&self, y: usize, x: usize) -> u8 {
+        Framebuffer::byte(self, y, x)
+    }
+
+    fn clear(&mut self) {
+        Framebuffer::clear(self)
+    }
+
+    fn draw_sprite_row(&mut self, x: usize, y: usize, bits: u16, width: usize) -> bool {
+        Framebuffer::draw_sprite_row(self, x, y, bits, width)
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        Framebuffer::scroll_down(self, n)
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        Framebuffer::scroll_up(self, n)
+    }
+
+    fn scroll_right(&mut self, n: usize) {
+        Framebuffer::scroll_right(self, n)
+    }
+
+    fn scroll_left(&mut self, n: usize) {
+        Framebuffer::scroll_left(self, n)
+    }
+}
+
+#[cfg(feature = "flat-screen")]
+pub use flat::{exercise, FlatScreen, ScreenBackend};
+
+#[cfg(feature = "flat-screen")]
+mod flat {
+    use std::str::FromStr;
+
+    use super::ScreenOps;
+    use crate::chip8::consts::{H, W};
+    use crate::chip8::framebuffer::WIDTH;
+
+    /// One byte per 8 pixels, row after row.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct FlatScreen(Box<[u8]>);
+
+    impl Default for FlatScreen {
+        fn default() -> Self {
+            Self(vec![0; H * W].into_boxed_slice())
+        }
+    }
+
+    impl FlatScreen {
+        fn row_mut(&mut self, y: usize) -> &mut [u8] {
+            &mut self.0[y * W..(y + 1) * W]
+        }
+    }
+
+    impl ScreenOps for FlatScreen {
+        fn byte(&self, y: usize, x: usize) -> u8 {
+            self.0[y * W + x]
+        }
+
+        fn clear(&mut self) {
+            self.0.fill(0);
+        }
+
+        fn draw_sprite_row(&mut self, x: usize, y: usize, bits: u16, width: usize) -> bool {
+            let x = x % WIDTH;
+            // The sprite left-aligned in the top of 32 bits, then shifted
+            // to its offset within the first byte it touches.
+            let shifted = (u32::from(bits) << (32 - width)) >> (x % 8);
+            let row = self.row_mut(y);
+            let mut collided = false;
+            for (i, byte) in shifted.to_be_bytes().into_iter().enumerate() {
+                let cell = &mut row[(x / 8 + i) % W];
+                collided |= *cell & byte != 0;
+                *cell ^= byte;
+            }
+            collided
+        }
+
+        fn scroll_down(&mut self, n: usize) {
+            let shift = n.min(H) * W;
+            let len = self.0.len();
+            self.0.copy_within(..len - shift, shift);
+            self.0[..shift].fill(0);
+        }
+
+        fn scroll_up(&mut self, n: usize) {
+            let shift = n.min(H) * W;
+            let len = self.0.len();
+            self.0.copy_within(shift.., 0);
+            self.0[len - shift..].fill(0);
+        }
+
+        // The horizontal scrolls work along each row away from the edge
+        // being shifted towards, so every byte is read before it's
+        // overwritten.
+
+        fn scroll_right(&mut self, n: usize) {
+            let (bytes, bits) = (n / 8, n % 8);
+            for y in 0..H {
+                let row = self.row_mut(y);
+                for x in (0..W).rev() {
+                    let src = |i: usize| x.checked_sub(bytes + i);
+                    let carry = if bits > 0 {
+                        at(row, src(1)) << (8 - bits)
+                    } else {
+                        0
+                    };
+                    row[x] = (at(row, src(0)) >> bits) | carry;
+                }
+            }
+        }
+
+        fn scroll_left(&mut self, n: usize) {
+            let (bytes, bits) = (n / 8, n % 8);
+            for y in 0..H {
+                let row = self.row_mut(y);
+                for x in 0..W {
+                    let src = |i: usize| Some(x + bytes + i);
+                    let carry = if bits > 0 {
+                        at(row, src(1)) >> (8 - bits)
+                    } else {
+                        0
+                    };
+                    row[x] = (at(row, src(0)) << bits) | carry;
+                }
+            }
+        }
+    }
+
+    /// The byte at `x`, or zero off either end of the row.
+    fn at(row: &[u8], x: Option<usize>) -> u8 {
+        x.and_then(|x| row.get(x)).copied().unwrap_or(0)
+    }
+
+    /// Which [`ScreenOps`] implementation to time.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ScreenBackend {
+        /// [`Framebuffer`](crate::chip8::framebuffer::Framebuffer), the
+        /// screen the interpreter uses.
+        Bits,
+        /// [`FlatScreen`].
+        Flat,
+    }
+
+    impl ScreenBackend {
+        pub const ALL: [ScreenBackend; 2] = [ScreenBackend::Bits, ScreenBackend::Flat];
+
+        /// The name `--screen` accepts.
+        pub fn name(self) -> &'static str {
+            match self {
+                ScreenBackend::Bits => "bits",
+                ScreenBackend::Flat => "flat",
+            }
+        }
+    }
+
+    impl FromStr for ScreenBackend {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Self::ALL
+                .into_iter()
+                .find(|backend| backend.name() == s)
+                .ok_or_else(|| format!("unknown screen backend {s:?}: expected bits or flat"))
+        }
+    }
+
+    /// Run `ops` screen operations modelled on the draw and scroll
+    /// workloads: maximum-height sprite rows at every alignment, with a
+    /// scroll in turn down, right, up and left every 64th operation.
+    /// Returns how many draws collided.
+    pub fn exercise<S: ScreenOps>(screen: &mut S, ops: u64) -> u64 {
+        let mut collisions = 0;
+        for op in 0..ops {
+            let step = op as usize;
+            if op % 64 == 63 {
+                match (op / 64) % 4 {
+                    0 => screen.scroll_down(4),
+                    1 => screen.scroll_right(4),
+                    2 => screen.scroll_up(4),
+                    _ => screen.scroll_left(4),
+                }
+            } else {
+                let bits = 0xA5u16.rotate_left(op as u32 % 8) & 0xFF;
+                let x = step * 13 % (WIDTH + 8);
+                collisions += u64::from(screen.draw_sprite_row(x, step % H, bits, 8));
+            }
+        }
+        collisions
+    }
+}
+
+#[cfg(all(test, feature = "flat-screen"))]
+mod tests {
+    use super::*;
+    use crate::chip8::consts::{H, W};
+
+    fn assert_same(bits: &Framebuffer, flat: &FlatScreen) {
+        for y in 0..H {
+            for x in 0..W {
+                assert_eq!(bits.byte(y, x), flat.byte(y, x), "byte ({y}, {x})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends_draw_alike() {
+        let mut bits = Framebuffer::default();
+        let mut flat = FlatScreen::default();
+        for (x, y, sprite, width) in [
+            (0, 0, 0xF0, 8),
+            (3, 0, 0xFF, 8),
+            (62, 5, 0xFF, 8),
+            (124, 6, 0xFF, 8),
+            (127, 7, 0xFFFF, 16),
+            (130, 8, 0x81, 8),
+            (9, 9, 0xA55A, 16),
+        ] {
+            let collided = ScreenOps::draw_sprite_row(&mut bits, x, y, sprite, width);
+            assert_eq!(flat.draw_sprite_row(x, y, sprite, width), collided, "x {x}");
+        }
+        assert_same(&bits, &flat);
+    }
+
+    #[test]
+    fn test_backends_scroll_alike() {
+        let mut bits = Framebuffer::default();
+        let mut flat = FlatScreen::default();
+        exercise(&mut bits, 500);
+        exercise(&mut flat, 500);
+        for n in [0, 1, 4, 8, 12, 64, 200] {
+            ScreenOps::scroll_right(&mut bits, n);
+            flat.scroll_right(n);
+            assert_same(&bits, &flat);
+            exercise(&mut bits, 100);
+            exercise(&mut flat, 100);
+            ScreenOps::scroll_left(&mut bits, n);
+            flat.scroll_left(n);
+            assert_same(&bits, &flat);
+            ScreenOps::scroll_down(&mut bits, n);
+            flat.scroll_down(n);
+            assert_same(&bits, &flat);
+            exercise(&mut bits, 100);
+            exercise(&mut flat, 100);
+            ScreenOps::scroll_up(&mut bits, n);
+            flat.scroll_up(n);
+            assert_same(&bits, &flat);
+        }
+        ScreenOps::clear(&mut bits);
+        flat.clear();
+        assert_same(&bits, &flat);
+    }
+
+    #[test]
+    fn test_exercise_matches() {
+        let mut bits = Framebuffer::default();
+        let mut flat = FlatScreen::default();
+        let collisions = exercise(&mut bits, 10_000);
+        assert!(collisions > 0);
+        assert_eq!(exercise(&mut flat, 10_000), collisions);
+        assert_same(&bits, &flat);
+    }
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!("flat".parse(), Ok(ScreenBackend::Flat));
+        assert_eq!("bits".parse(), Ok(ScreenBackend::Bits));
+        assert!("array2".parse::<ScreenBackend>().is_err());
+    }
+}
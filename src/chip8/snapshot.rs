@@ -0,0 +1,182 @@
+//! Text snapshots of the display for tests, with a diff that shows which
+//! pixels differ when a snapshot doesn't match.
+
+use crate::chip8::cpu::{Chip8, Resolution};
+use crate::chip8::gfx::{self, AnsiMode, Palette};
+
+const LIT: char = '#';
+const UNLIT: char = '.';
+
+/// The visible display, a line per row with `#` for lit pixels and `.` for
+/// unlit ones: 64x32 in low-res and 128x64 in high-res.
+pub fn screen_ascii(chip: &Chip8) -> String {
+    let (width, height) = chip.display_dimensions();
+    let mut out = String::with_capacity((width + 1) * height);
+    for y in 0..height {
+        for x in 0..width {
+            out.push(if chip.pixel(x, y) { LIT } else { UNLIT });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// A [`screen_ascii`] snapshot of a `resolution` display that is blank but
+/// for each `(x, y, art)` in `placed`. Art is drawn from text, a line per
+/// row, with `#` lit and anything else (`.` or a space) left alone.
+/// Pixels off the display are dropped, so placing the same art again a
+/// display's width or height away shows wrapping:
+///
+/// ```
+/// # use oxid8::chip8::cpu::Resolution;
+/// # use oxid8::chip8::snapshot::screen_with;
+/// let glyph = "####\n#..#";
+/// let screen = screen_with(Resolution::Low, &[(62, 0, glyph), (-2, 0, glyph)]);
+/// assert!(screen.starts_with("##"));
+/// assert!(screen.lines().next().unwrap().ends_with("##"));
+/// ```
+pub fn screen_with(resolution: Resolution, placed: &[(isize, isize, &str)]) -> String {
+    let mut chip = Chip8::new();
+    chip.resolution = resolution;
+    let (width, height) = chip.display_dimensions();
+    let mut rows = vec![vec![UNLIT; width]; height];
+    for &(x, y, art) in placed {
+        for (dy, line) in art.lines().enumerate() {
+            for (dx, c) in line.chars().enumerate() {
+                let (px, py) = (x + dx as isize, y + dy as isize);
+                if c != LIT || px < 0 || py < 0 {
+                    continue;
+                }
+                if let Some(cell) = rows
+                    .get_mut(py as usize)
+                    .and_then(|r| r.get_mut(px as usize))
+                {
+                    *cell = LIT;
+                }
+            }
+        }
+    }
+    rows.into_iter()
+        .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+        .collect()
+}
+
+/// `actual` and `expected` side by side, a row per line, with a third
+/// column marking each cell that differs with `^`. Rows that match are
+/// marked with two spaces and rows that differ with `! `.
+pub fn screen_diff(actual: &str, expected: &str) -> String {
+    let actual: Vec<Vec<char>> = actual.lines().map(|l| l.chars().collect()).collect();
+    let expected: Vec<Vec<char>> = expected.lines().map(|l| l.chars().collect()).collect();
+    let width = actual
+        .iter()
+        .chain(&expected)
+        .map(Vec::len)
+        .max()
+        .unwrap_or(0);
+    let column = width.max("expected".len());
+    let cell = |row: Option<&Vec<char>>, x: usize| row.and_then(|r| r.get(x)).copied();
+
+    let mut out = format!("  {:<column$} | {:<column$} | diff\n", "actual", "expected");
+    for y in 0..actual.len().max(expected.len()) {
+        let (a, e) = (actual.get(y), expected.get(y));
+        let marks: String = (0..width)
+            .map(|x| match cell(a, x) == cell(e, x) {
+                true => ' ',
+                false => '^',
+            })
+            .collect();
+        let row = |r: Option<&Vec<char>>| -> String {
+            (0..column).map(|x| cell(r, x).unwrap_or(' ')).collect()
+        };
+        let flag = if marks.contains('^') { "! " } else { "  " };
+        let line = format!("{flag}{} | {} | {marks}", row(a), row(e));
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Panic with a [`screen_diff`] unless the two snapshots are equal.
+#[track_caller]
+pub fn assert_screen_eq(actual: &str, expected: &str) {
+    if actual != expected {
+        panic!("screens differ:\n{}", screen_diff(actual, expected));
+    }
+}
+
+/// Panic unless `chip`'s display matches the [`screen_ascii`] snapshot
+/// `expected`, showing the diff and the whole display as half blocks.
+#[track_caller]
+pub fn assert_chip_screen(chip: &Chip8, expected: &str) {
+    let actual = screen_ascii(chip);
+    if actual != expected {
+        panic!(
+            "screens differ:\n{}\nactual display:\n{}",
+            screen_diff(&actual, expected),
+            gfx::screen_to_ansi(chip, &Palette::default(), AnsiMode::Plain)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_ascii() {
+        let mut chip = Chip8::new();
+        chip.screen.set_byte(1, 0, 0b1010_0000);
+        let ascii = screen_ascii(&chip);
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 32);
+        assert_eq!(&lines[1][..4], "#.#.");
+        assert!(lines.iter().all(|line| line.len() == 64));
+
+        chip.resolution = Resolution::High;
+        assert_eq!(screen_ascii(&chip).lines().count(), 64);
+    }
+
+    #[test]
+    fn test_screen_with() {
+        let mut chip = Chip8::new();
+        chip.screen.set_byte(3, 1, 0b1001_0000);
+        assert_chip_screen(&chip, &screen_with(Resolution::Low, &[(8, 3, "#  #")]));
+
+        let screen = screen_with(Resolution::High, &[(127, 63, "##\n##"), (-1, -1, ".#")]);
+        let lines: Vec<&str> = screen.lines().collect();
+        assert_eq!(lines.len(), 64);
+        assert_eq!(screen.matches(LIT).count(), 1);
+        assert!(lines[63].ends_with(LIT));
+    }
+
+    #[test]
+    fn test_assert_chip_screen_dumps_the_display() {
+        let mut chip = Chip8::new();
+        assert_chip_screen(&chip, &screen_ascii(&chip));
+
+        let expected = screen_ascii(&chip);
+        chip.screen.set_byte(0, 0, 0x80);
+        let failure = std::panic::catch_unwind(|| assert_chip_screen(&chip, &expected));
+        let message = *failure.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("! #..."), "{message}");
+        assert!(message.contains("actual display:\n▀ "), "{message}");
+    }
+
+    #[test]
+    fn test_screen_diff_marks_changed_cells() {
+        let expected = "#..#\n....\n.##.\n";
+        let actual = "#..#\n.#..\n.#..\n";
+        let diff = screen_diff(actual, expected);
+        assert_eq!(
+            diff,
+            concat!(
+                "  actual   | expected | diff\n",
+                "  #..#     | #..#     |\n",
+                "! .#..     | ....     |  ^\n",
+                "! .#..     | .##.     |   ^\n",
+            )
+        );
+        assert_eq!(diff.matches('^').count(), 2);
+        assert_eq!(screen_diff(expected, expected).matches('^').count(), 0);
+    }
+}
@@ -0,0 +1,268 @@
+//! Saving and restoring the machine state, for `.o8s` files.
+//!
+//! The format is little-endian: the magic `O8S` and a version byte; PC and
+//! I as u16; SP, DT, ST, the resolution (0 low, 1 high) and the EXIT flag as
+//! bytes; V0 to VF; the stack depth as a byte and that many stack entries
+//! as u16; the RAM size as u32 and RAM itself; then the plane count and
+//! each plane's screen bytes; then the keypad as a u16 bitmask and the
+//! `FX0A` key log: its wait as a byte (0 idle, 1 awaiting a press, 2
+//! awaiting a release) and the key byte, the next sequence number as u64,
+//! and the logged event count as u16 with each event's sequence number as
+//! u64, key and pressed flag. Quirks and the RNG aren't saved.
+//!
+//! Version 1 files, from before the stack depth was configurable, have no
+//! depth byte and always 16 stack entries. Version 1 and 2 files end after
+//! the planes and leave the keys as they were.
+
+use std::sync::atomic::Ordering;
+
+use color_eyre::eyre::{bail, eyre, Result};
+
+use crate::chip8::consts::{RAM_SIZE, STACK_DEPTH, XO_RAM_SIZE};
+use crate::chip8::cpu::{CallStack, Chip8, Resolution};
+use crate::chip8::framebuffer::Framebuffer;
+use crate::chip8::key_log::{KeyLogEvent, KeyWait};
+use crate::chip8::mem::Memory;
+
+const MAGIC: &[u8; 3] = b"O8S";
+const VERSION: u8 = 3;
+
+pub fn save(chip: &Chip8) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    out.extend_from_slice(&(chip.pc as u16).to_le_bytes());
+    out.extend_from_slice(&(chip.i as u16).to_le_bytes());
+    out.push(chip.sp as u8);
+    out.push(chip.dt.load(Ordering::Acquire));
+    out.push(chip.st.load(Ordering::Acquire));
+    out.push(matches!(chip.resolution, Resolution::High) as u8);
+    out.push(chip.exit as u8);
+    out.extend_from_slice(&chip.v);
+    out.push(chip.stack.len() as u8);
+    for &entry in chip.stack.iter() {
+        out.extend_from_slice(&(entry as u16).to_le_bytes());
+    }
+    out.extend_from_slice(&(chip.memory.len() as u32).to_le_bytes());
+    out.extend_from_slice(&chip.memory);
+    out.push(1 + chip.extra_planes.len() as u8);
+    for plane in std::iter::once(&chip.screen).chain(&chip.extra_planes) {
+        out.extend(plane.bytes());
+    }
+    out.extend_from_slice(&chip.keys_bitmask().to_le_bytes());
+    let log = &chip.key_log;
+    out.extend_from_slice(&match log.wait {
+        KeyWait::Idle => [0, 0],
+        KeyWait::AwaitingPress => [1, 0],
+        KeyWait::AwaitingRelease { key } => [2, key],
+    });
+    out.extend_from_slice(&log.next_seq.to_le_bytes());
+    out.extend_from_slice(&(log.events.len() as u16).to_le_bytes());
+    for event in &log.events {
+        out.extend_from_slice(&event.seq.to_le_bytes());
+        out.extend_from_slice(&[event.key, event.pressed as u8]);
+    }
+    out
+}
+
+/// Overwrite `chip`'s state with a file written by [`save`]. On error
+/// `chip` is left unchanged.
+pub fn load(chip: &mut Chip8, bytes: &[u8]) -> Result<()> {
+    let mut reader = Reader(bytes);
+    if reader.take(MAGIC.len())? != MAGIC {
+        bail!("Not an oxid8 save state");
+    }
+    let version = reader.u8()?;
+    if !(1..=VERSION).contains(&version) {
+        bail!("Unsupported save state version {version}");
+    }
+    // Keep `chip`'s timers, which the timer thread may be ticking.
+    let mut loaded = chip.snapshot();
+    loaded.pc = reader.u16()? as usize;
+    loaded.i = reader.u16()? as usize;
+    loaded.sp = reader.u8()? as usize;
+    let (dt, st) = (reader.u8()?, reader.u8()?);
+    loaded.resolution = match reader.u8()? {
+        0 => Resolution::Low,
+        _ => Resolution::High,
+    };
+    loaded.exit = reader.u8()? != 0;
+    loaded.v.copy_from_slice(reader.take(16)?);
+    let depth = match version {
+        1 => STACK_DEPTH,
+        _ => reader.u8()? as usize,
+    };
+    loaded.stack = CallStack::new(depth);
+    for entry in loaded.stack.iter_mut() {
+        *entry = reader.u16()? as usize;
+    }
+    let ram_size = u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+    if !(RAM_SIZE..=XO_RAM_SIZE).contains(&ram_size) {
+        bail!("Save state has an invalid RAM size of {ram_size} bytes");
+    }
+    loaded.memory = Memory(reader.take(ram_size)?.into());
+    if reader.u8()? as usize != 1 + loaded.extra_planes.len() {
+        bail!("Save state has a different number of display planes");
+    }
+    for plane in std::iter::once(&mut loaded.screen).chain(&mut loaded.extra_planes) {
+        *plane = Framebuffer::from_bytes(reader.take(Framebuffer::BYTES)?).unwrap();
+    }
+    if version >= 3 {
+        let keys = reader.u16()?;
+        for (key, pressed) in loaded.keys.iter_mut().enumerate() {
+            *pressed = keys & (1 << key) != 0;
+        }
+        let log = &mut loaded.key_log;
+        log.wait = match (reader.u8()?, reader.u8()?) {
+            (0, _) => KeyWait::Idle,
+            (1, _) => KeyWait::AwaitingPress,
+            (2, key) => KeyWait::AwaitingRelease { key },
+            (wait, _) => bail!("Save state has an unknown key wait {wait}"),
+        };
+        log.next_seq = reader.u64()?;
+        log.events.clear();
+        for _ in 0..reader.u16()? {
+            let seq = reader.u64()?;
+            let (key, pressed) = (reader.u8()?, reader.u8()? != 0);
+            log.events.push_back(KeyLogEvent { seq, key, pressed });
+        }
+    }
+    if loaded.sp > loaded.stack.len() || loaded.pc + 1 >= loaded.memory.len() {
+        bail!("Save state has SP or PC out of range");
+    }
+
+    *chip = loaded;
+    // The timers are shared with the timer thread, so set them in place.
+    chip.dt.store(dt, Ordering::Release);
+    chip.st.store(st, Ordering::Release);
+    Ok(())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.0.len() {
+            return Err(eyre!("Save state is truncated"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let mut chip = Chip8::new();
+        chip.load_font();
+        chip.pc = 0x2A4;
+        chip.i = 0x300;
+        chip.v[0x3] = 0x1F;
+        chip.stack[0] = 0x206;
+        chip.sp = 1;
+        chip.dt.store(9, Ordering::Release);
+        chip.resolution = Resolution::High;
+        chip.memory[0x300] = 0xAA;
+        chip.screen.set_byte(63, 15, 0x81);
+        let saved = save(&chip);
+
+        let mut restored = Chip8::new();
+        load(&mut restored, &saved).unwrap();
+        assert_eq!(save(&restored), saved);
+        assert_eq!(restored.to_string(), chip.to_string());
+        assert_eq!(restored.memory[0x300], 0xAA);
+        assert_eq!(restored.screen, chip.screen);
+    }
+
+    #[test]
+    fn test_stack_depth_round_trip() {
+        let mut chip = Chip8::new();
+        chip.stack = CallStack::new(40);
+        chip.stack[39] = 0x2FE;
+        chip.sp = 40;
+        let saved = save(&chip);
+
+        let mut restored = Chip8::new();
+        load(&mut restored, &saved).unwrap();
+        assert_eq!(restored.stack, chip.stack);
+        assert_eq!(restored.sp, 40);
+    }
+
+    #[test]
+    fn test_key_wait_round_trip() {
+        let mut chip = Chip8::new();
+        chip.memory[0x200..0x204].copy_from_slice(&[0xF3, 0x0A, 0x12, 0x02]); // LD V3, K; JP 0x202
+        chip.run_step(1);
+        chip.press_key(0xB);
+        chip.press_key(0x2);
+        chip.run_step(1);
+        assert_eq!(chip.key_log.wait, KeyWait::AwaitingRelease { key: 0xB });
+        // A release the wait hasn't consumed yet is saved too.
+        chip.release_key(0x2);
+        let saved = save(&chip);
+
+        let mut restored = Chip8::new();
+        load(&mut restored, &saved).unwrap();
+        assert_eq!(restored.key_log, chip.key_log);
+        for chip in [&mut chip, &mut restored] {
+            chip.release_key(0xB);
+            chip.run_step(1);
+        }
+        assert_eq!((restored.pc, restored.v[3]), (0x202, 0xB));
+        assert_eq!(restored.to_string(), chip.to_string());
+        assert_eq!(save(&restored), save(&chip));
+    }
+
+    #[test]
+    fn test_load_version_1() {
+        let mut chip = Chip8::new();
+        chip.stack[15] = 0x20A;
+        let mut saved = save(&chip);
+        // Version 1 had no depth byte after V0 to VF.
+        saved[3] = 1;
+        saved.remove(4 + 4 + 5 + 16);
+
+        let mut restored = Chip8::new();
+        restored.stack = CallStack::new(32);
+        load(&mut restored, &saved).unwrap();
+        assert_eq!(restored.stack, chip.stack);
+
+        saved[3] = 4;
+        assert!(load(&mut restored, &saved).is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_bad_input() {
+        let mut chip = Chip8::new();
+        let saved = save(&chip);
+        assert!(load(&mut chip, b"nope").is_err());
+        assert!(load(&mut chip, &saved[..saved.len() - 1]).is_err());
+        chip.v[0] = 1;
+        assert!(load(&mut chip, &saved[..10]).is_err());
+        assert_eq!(chip.v[0], 1);
+
+        let mut tiny = Chip8::new();
+        tiny.memory = Memory::new(0x100);
+        tiny.pc = 0;
+        let err = load(&mut chip, &save(&tiny)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Save state has an invalid RAM size of 256 bytes"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! A machine summary for `--dump-state`, shaped for assertions in shell
+//! scripts and CI rather than for restoring (see `state` for that).
+
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+
+use crate::chip8::consts::{H, W};
+use crate::chip8::cpu::{Chip8, Resolution};
+use crate::utils::hash::Fnv1a;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateSummary {
+    pub pc: usize,
+    pub i: usize,
+    pub sp: usize,
+    pub v: [u8; 16],
+    /// Return addresses in use, oldest first.
+    pub stack: Vec<usize>,
+    pub dt: u8,
+    pub st: u8,
+    pub resolution: Resolution,
+    pub frame: u64,
+    /// Instructions executed since power-on.
+    pub executed: u64,
+    /// [`Chip8::screen_hash`] of the display.
+    pub screen_hash: u64,
+    /// Why the run ended, when known: `quit`, `halted`, `reached_end`,
+    /// `end_of_rom`, `infinite_loop` or `fault`.
+    pub halt_reason: Option<&'static str>,
+}
+
+impl Chip8 {
+    /// FNV-1a hash of the display, every plane combined, and the
+    /// resolution, ignoring registers and timers. Builds with and without
+    /// `xo-chip` give the same hashes for the same pixels.
+    pub fn screen_hash(&self) -> u64 {
+        let mut h = Fnv1a::new();
+        for y in 0..H {
+            for x in 0..W {
+                h.write(&[self.screen_byte(y, x)]);
+            }
+        }
+        h.write(&[self.resolution.factor() as u8]);
+        h.finish()
+    }
+
+    /// The registers, timers and display of this machine. Frame and
+    /// instruction counts are left at zero for the emulator to fill in.
+    pub fn state_summary(&self) -> StateSummary {
+        StateSummary {
+            pc: self.pc,
+            i: self.i,
+            sp: self.sp,
+            v: self.v,
+            stack: self.stack[..self.sp.min(self.stack.len())].to_vec(),
+            dt: self.dt.load(Ordering::Acquire),
+            st: self.st.load(Ordering::Acquire),
+            resolution: self.resolution,
+            frame: 0,
+            executed: 0,
+            screen_hash: self.screen_hash(),
+            halt_reason: None,
+        }
+    }
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    let items: Vec<String> = items.iter().map(ToString::to_string).collect();
+    items.join(", ")
+}
+
+impl StateSummary {
+    /// A JSON object with one key per line. The screen hash is a hex
+    /// string, since JSON numbers can't hold all of a u64 exactly.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n");
+        let mut field = |name: &str, value: String| {
+            if out.len() > 2 {
+                out.push_str(",\n");
+            }
+            write!(out, "  \"{name}\": {value}").unwrap();
+        };
+        field("pc", self.pc.to_string());
+        field("i", self.i.to_string());
+        field("sp", self.sp.to_string());
+        field("v", format!("[{}]", join(&self.v)));
+        field("stack", format!("[{}]", join(&self.stack)));
+        field("dt", self.dt.to_string());
+        field("st", self.st.to_string());
+        let resolution = match self.resolution {
+            Resolution::Low => "low",
+            Resolution::High => "high",
+        };
+        field("resolution", format!("\"{resolution}\""));
+        field("frame", self.frame.to_string());
+        field("executed", self.executed.to_string());
+        field("screen_hash", format!("\"{:016x}\"", self.screen_hash));
+        let reason = self
+            .halt_reason
+            .map_or("null".into(), |r| format!("\"{r}\""));
+        field("halt_reason", reason);
+        out.push_str("\n}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_hash() {
+        let mut chip = Chip8::new();
+        let blank = chip.screen_hash();
+        chip.v[0] = 7;
+        assert_eq!(chip.screen_hash(), blank);
+        chip.resolution = Resolution::High;
+        assert_ne!(chip.screen_hash(), blank);
+        chip.resolution = Resolution::Low;
+        chip.screen.set_pixel(3, 4, true);
+        assert_ne!(chip.screen_hash(), blank);
+    }
+
+    #[test]
+    fn test_state_summary() {
+        let mut chip = Chip8::new();
+        chip.stack[0] = 0x204;
+        chip.stack[1] = 0x300;
+        chip.sp = 1;
+        chip.v[0xF] = 1;
+        let summary = chip.state_summary();
+        assert_eq!(summary.stack, [0x204]);
+        assert_eq!(summary.halt_reason, None);
+
+        chip.screen.set_byte(0, 0, 0x80);
+        assert_ne!(chip.state_summary().screen_hash, summary.screen_hash);
+
+        let json = summary.to_json();
+        assert!(json.contains("\n  \"v\": [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],\n"));
+        assert!(json.ends_with("\"halt_reason\": null\n}\n"));
+    }
+}
@@ -0,0 +1,127 @@
+//! Symbol maps written next to ROMs by `asm8`, so listings can show the
+//! labels and source lines an address came from.
+//!
+//! The side-car is plain text, one record per line, with `#` comments:
+//!
+//! ```text
+//! label 0x200 start
+//! line 0x200 3 LD V0, 0x01
+//! ```
+//!
+//! `label ADDR NAME` names an address; `line ADDR N SOURCE` says the
+//! instruction at ADDR was assembled from line N, whose text is SOURCE.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A source line an instruction was assembled from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    /// 1-based line number.
+    pub number: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Symbols {
+    labels: BTreeMap<usize, String>,
+    lines: BTreeMap<usize, SourceLine>,
+}
+
+impl Symbols {
+    pub fn add_label(&mut self, addr: usize, name: &str) {
+        self.labels.insert(addr, name.to_string());
+    }
+
+    pub fn add_line(&mut self, addr: usize, number: usize, text: &str) {
+        let text = text.trim().to_string();
+        self.lines.insert(addr, SourceLine { number, text });
+    }
+
+    pub fn label(&self, addr: usize) -> Option<&str> {
+        self.labels.get(&addr).map(String::as_str)
+    }
+
+    pub fn line(&self, addr: usize) -> Option<&SourceLine> {
+        self.lines.get(&addr)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.lines.is_empty()
+    }
+}
+
+impl fmt::Display for Symbols {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (addr, name) in &self.labels {
+            writeln!(f, "label {addr:#05x} {name}")?;
+        }
+        for (addr, line) in &self.lines {
+            writeln!(f, "line {addr:#05x} {} {}", line.number, line.text)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_addr(s: &str) -> Option<usize> {
+    usize::from_str_radix(s.strip_prefix("0x")?, 16).ok()
+}
+
+impl FromStr for Symbols {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut symbols = Symbols::default();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || format!("line {}: invalid symbol record {line:?}", n + 1);
+            let mut fields = line.splitn(4, ' ');
+            match (fields.next(), fields.next().and_then(parse_addr)) {
+                (Some("label"), Some(addr)) => {
+                    let name = fields.next().filter(|name| !name.is_empty());
+                    symbols.add_label(addr, name.ok_or_else(invalid)?);
+                }
+                (Some("line"), Some(addr)) => {
+                    let number = fields.next().and_then(|n| n.parse().ok());
+                    let text = fields.next().unwrap_or_default();
+                    symbols.add_line(addr, number.ok_or_else(invalid)?, text);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(symbols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let text = "# from game.asm\nlabel 0x200 start\nline 0x200 3 LD V0, 0x01\n";
+        let symbols: Symbols = text.parse().unwrap();
+        assert_eq!(symbols.label(0x200), Some("start"));
+        assert_eq!(
+            symbols.line(0x200),
+            Some(&SourceLine {
+                number: 3,
+                text: "LD V0, 0x01".into()
+            })
+        );
+        assert_eq!(symbols.label(0x202), None);
+        assert_eq!(symbols.to_string().parse::<Symbols>(), Ok(symbols));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("label 0x200".parse::<Symbols>().is_err());
+        assert!("line 0x200 three CLS".parse::<Symbols>().is_err());
+        assert!("label 512 start".parse::<Symbols>().is_err());
+        assert!("symbol 0x200 start".parse::<Symbols>().is_err());
+    }
+}
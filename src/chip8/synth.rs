@@ -0,0 +1,222 @@
+//! Synthetic ROMs for benchmarks and tests.
+
+use std::str::FromStr;
+
+use crate::chip8::consts::PROGRAM_START;
+
+/// Instruction mixes for measuring the interpreter, each a ROM that loops
+/// forever. They don't depend on RND or input, so runs are repeatable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Workload {
+    /// Register arithmetic and logic, see [`alu_loop`].
+    Alu,
+    /// Maximum-height sprites at every alignment, see [`drw_loop`].
+    #[default]
+    Draw,
+    /// Register stores, loads and BCD, see [`mem_loop`].
+    Memory,
+    /// SUPER-CHIP and XO-CHIP scrolls, see [`scroll_loop`].
+    Scroll,
+}
+
+impl Workload {
+    pub const ALL: [Workload; 4] = [
+        Workload::Alu,
+        Workload::Draw,
+        Workload::Memory,
+        Workload::Scroll,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Workload::Alu => "alu",
+            Workload::Draw => "draw",
+            Workload::Memory => "memory",
+            Workload::Scroll => "scroll",
+        }
+    }
+
+    pub fn rom(self) -> Vec<u8> {
+        match self {
+            Workload::Alu => alu_loop(),
+            Workload::Draw => drw_loop(),
+            Workload::Memory => mem_loop(),
+            Workload::Scroll => scroll_loop(),
+        }
+    }
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Workload::ALL
+            .into_iter()
+            .find(|workload| workload.name() == s)
+            .ok_or_else(|| format!("invalid workload {s:?}: expected alu, draw, memory or scroll"))
+    }
+}
+
+/// `JP` back to `addr`, as the last two bytes of a loop.
+fn jump(addr: usize) -> [u8; 2] {
+    (0x1000 | addr as u16).to_be_bytes()
+}
+
+/// A tight loop that redraws a 15-row sprite at a moving position, making
+/// every fourth instruction a maximum-height DRW:
+///
+/// ```text
+/// 0x200  LD I, 0x000       ; font data, always loaded
+/// 0x202  LD V0, 0x00
+/// 0x204  LD V1, 0x00
+/// 0x206  DRW V0, V1, 15
+/// 0x208  ADD V0, 0x03
+/// 0x20A  ADD V1, 0x05
+/// 0x20C  JP 0x206
+/// ```
+pub fn drw_loop() -> Vec<u8> {
+    let [jp_hi, jp_lo] = jump(PROGRAM_START + 6);
+    vec![
+        0xA0, 0x00, // LD I, 0x000
+        0x60, 0x00, // LD V0, 0x00
+        0x61, 0x00, // LD V1, 0x00
+        0xD0, 0x1F, // DRW V0, V1, 15
+        0x70, 0x03, // ADD V0, 0x03
+        0x71, 0x05, // ADD V1, 0x05
+        jp_hi, jp_lo, // JP loop_start
+    ]
+}
+
+/// Every `8XYN` operation in turn, all but the jump back being ALU work:
+///
+/// ```text
+/// 0x200  LD V0, 0x5A
+/// 0x202  LD V1, 0x33
+/// 0x204  LD V2, V0
+/// 0x206  OR V2, V1
+/// 0x208  AND V2, V0
+/// 0x20A  XOR V2, V1
+/// 0x20C  ADD V0, V1
+/// 0x20E  SUB V1, V2
+/// 0x210  SHR V2, V0
+/// 0x212  SUBN V1, V0
+/// 0x214  SHL V2, V1
+/// 0x216  JP 0x204
+/// ```
+pub fn alu_loop() -> Vec<u8> {
+    let [jp_hi, jp_lo] = jump(PROGRAM_START + 4);
+    vec![
+        0x60, 0x5A, // LD V0, 0x5A
+        0x61, 0x33, // LD V1, 0x33
+        0x82, 0x00, // LD V2, V0
+        0x82, 0x11, // OR V2, V1
+        0x82, 0x02, // AND V2, V0
+        0x82, 0x13, // XOR V2, V1
+        0x80, 0x14, // ADD V0, V1
+        0x81, 0x25, // SUB V1, V2
+        0x82, 0x06, // SHR V2, V0
+        0x81, 0x07, // SUBN V1, V0
+        0x82, 0x1E, // SHL V2, V1
+        jp_hi, jp_lo, // JP 0x204
+    ]
+}
+
+/// Stores, loads and BCD conversions of every register to a scratch area:
+///
+/// ```text
+/// 0x200  LD I, 0x300
+/// 0x202  LD [I], VF
+/// 0x204  LD I, 0x300
+/// 0x206  LD VF, [I]
+/// 0x208  LD B, V3
+/// 0x20A  ADD V3, 0x07
+/// 0x20C  JP 0x200
+/// ```
+pub fn mem_loop() -> Vec<u8> {
+    let [jp_hi, jp_lo] = jump(PROGRAM_START);
+    vec![
+        0xA3, 0x00, // LD I, 0x300
+        0xFF, 0x55, // LD [I], VF
+        0xA3, 0x00, // LD I, 0x300
+        0xFF, 0x65, // LD VF, [I]
+        0xF3, 0x33, // LD B, V3
+        0x73, 0x07, // ADD V3, 0x07
+        jp_hi, jp_lo, // JP 0x200
+    ]
+}
+
+/// A sprite scrolled in every direction, four of every five instructions
+/// being scrolls:
+///
+/// ```text
+/// 0x200  LD I, 0x000
+/// 0x202  DRW V0, V0, 5
+/// 0x204  SCD 1
+/// 0x206  SCR
+/// 0x208  SCU 1
+/// 0x20A  SCL
+/// 0x20C  JP 0x204
+/// ```
+pub fn scroll_loop() -> Vec<u8> {
+    let [jp_hi, jp_lo] = jump(PROGRAM_START + 4);
+    vec![
+        0xA0, 0x00, // LD I, 0x000
+        0xD0, 0x05, // DRW V0, V0, 5
+        0x00, 0xC1, // SCD 1
+        0x00, 0xFB, // SCR
+        0x00, 0xD1, // SCU 1
+        0x00, 0xFC, // SCL
+        jp_hi, jp_lo, // JP 0x204
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::decode::decode;
+    use crate::chip8::emulator::{Emulator, EmulatorConfig};
+    use crate::chip8::op::ChipOp;
+
+    #[test]
+    fn test_drw_loop_decodes() {
+        let rom = drw_loop();
+        let ops: Vec<ChipOp> = rom
+            .chunks(2)
+            .map(|w| decode(u16::from_be_bytes([w[0], w[1]])))
+            .collect();
+        assert!(matches!(ops[3], ChipOp::DrwVxVyN { x: 0, y: 1, n: 15 }));
+        assert!(matches!(ops[6], ChipOp::JpNnn { nnn: 0x206 }));
+    }
+
+    #[test]
+    fn test_drw_loop_runs_forever() {
+        let mut emu = Emulator::new(EmulatorConfig::default());
+        emu.load_rom(&drw_loop()).unwrap();
+        for _ in 0..100 {
+            assert!(!emu.run_frame().halted);
+            assert!((0x206..=0x20C).contains(&emu.chip().pc));
+        }
+        assert_ne!(emu.chip().screen.bytes().map(u32::from).sum::<u32>(), 0);
+        assert_ne!(emu.chip().v[0], 0);
+    }
+
+    #[test]
+    fn test_workloads_loop_without_faults() {
+        for workload in Workload::ALL {
+            let rom = workload.rom();
+            let end = PROGRAM_START + rom.len();
+            let mut chip = crate::chip8::cpu::Chip8::new();
+            chip.memory[PROGRAM_START..end].copy_from_slice(&rom);
+            for _ in 0..1000 {
+                chip.try_step().unwrap();
+                assert!((PROGRAM_START..end).contains(&chip.pc), "{workload:?}");
+            }
+            assert_eq!(workload.name().parse(), Ok(workload));
+        }
+        let ops: Vec<ChipOp> = alu_loop()
+            .chunks(2)
+            .map(|w| decode(u16::from_be_bytes([w[0], w[1]])))
+            .collect();
+        assert!(ops[2..10].iter().all(|op| op.pattern().starts_with('8')));
+    }
+}
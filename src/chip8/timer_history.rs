@@ -0,0 +1,93 @@
+//! Recent delay and sound timer values, for the debug view's sparklines.
+//!
+//! The render side samples the timers once per frame it draws, so the
+//! history spans the last `capacity` rendered frames.
+
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+
+use crate::chip8::cpu::Chip8;
+
+/// Samples the debug view keeps: two seconds at 60 fps.
+pub const TIMER_SAMPLES: usize = 120;
+
+/// The last `capacity` (DT, ST) samples, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimerHistory {
+    samples: VecDeque<(u8, u8)>,
+    capacity: usize,
+}
+
+impl TimerHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record `chip`'s timers as they are now.
+    pub fn sample(&mut self, chip: &Chip8) {
+        self.push(
+            chip.dt.load(Ordering::Acquire),
+            chip.st.load(Ordering::Acquire),
+        );
+    }
+
+    pub fn push(&mut self, dt: u8, st: u8) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((dt, st));
+    }
+
+    pub fn dt(&self) -> impl DoubleEndedIterator<Item = u64> + '_ {
+        self.samples.iter().map(|&(dt, _)| dt.into())
+    }
+
+    pub fn st(&self) -> impl DoubleEndedIterator<Item = u64> + '_ {
+        self.samples.iter().map(|&(_, st)| st.into())
+    }
+
+    /// Whether the newest sample has the beeper on.
+    pub fn audible(&self) -> bool {
+        self.samples.back().is_some_and(|&(_, st)| st > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_the_last_samples() {
+        let mut history = TimerHistory::new(3);
+        for t in 1..=5 {
+            history.push(t, 10 - t);
+        }
+        assert_eq!(history.dt().collect::<Vec<_>>(), [3, 4, 5]);
+        assert_eq!(history.st().collect::<Vec<_>>(), [7, 6, 5]);
+
+        let mut off = TimerHistory::default();
+        off.push(1, 1);
+        assert_eq!(off.dt().count(), 0);
+    }
+
+    #[test]
+    fn test_sample_and_audible() {
+        let mut history = TimerHistory::new(TIMER_SAMPLES);
+        assert!(!history.audible());
+        let chip = Chip8::new();
+        chip.dt.store(30, Ordering::Release);
+        chip.st.store(4, Ordering::Release);
+        history.sample(&chip);
+        assert_eq!(history.dt().last(), Some(30));
+        assert!(history.audible());
+        chip.st.store(0, Ordering::Release);
+        history.sample(&chip);
+        assert!(!history.audible());
+    }
+}
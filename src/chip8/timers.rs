@@ -7,25 +7,29 @@ use std::{
     time::Duration,
 };
 
+/// Decrement a CHIP-8 timer by one, saturating at zero.
+pub fn tick(timer: &AtomicU8) {
+    let _ = timer.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| {
+        (v > 0).then(|| v - 1)
+    });
+}
+
 pub fn spawn_timers(dt: Arc<AtomicU8>, st: Arc<AtomicU8>) -> mpsc::Receiver<bool> {
     let (tx, rx) = mpsc::channel::<bool>();
     thread::spawn(move || {
+        log_event!(debug, "timer thread started");
         let mut sounding = false;
         loop {
-            let _ = dt.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| {
-                (v > 0).then(|| v - 1)
-            });
-            let _ = st.fetch_update(Ordering::AcqRel, Ordering::Acquire, |v| {
-                (v > 0).then(|| v - 1)
-            });
+            tick(&dt);
+            tick(&st);
 
             let st_now = st.load(Ordering::Acquire);
-            if st_now > 0 && !sounding {
-                let _ = tx.send(true);
-                sounding = true;
-            } else if st_now == 0 && sounding {
-                let _ = tx.send(false);
-                sounding = false;
+            if (st_now > 0) != sounding {
+                sounding = st_now > 0;
+                if tx.send(sounding).is_err() {
+                    log_event!(debug, "timer thread stopped: receiver dropped");
+                    break;
+                }
             }
 
             thread::sleep(Duration::from_nanos(16_666_667)); // ~60 Hz
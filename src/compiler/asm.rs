@@ -0,0 +1,133 @@
+//! A line-based assembler for the syntax [`ChipOp`] displays.
+//!
+//! Each line holds an optional `label:`, an optional instruction and an
+//! optional `; comment`. Labels may be used anywhere an address is expected,
+//! e.g. `JP loop` or `LD I, sprite`, and `DB 0x1234` emits a raw word.
+
+use std::collections::HashMap;
+
+use crate::chip8::consts::PROGRAM_START;
+use crate::chip8::op::ChipOp;
+use crate::chip8::symbols::Symbols;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembled {
+    pub rom: Vec<u8>,
+    /// Labels and the source line of every instruction.
+    pub symbols: Symbols,
+}
+
+/// Split `line` into its label, if any, and its instruction text.
+fn split_label(line: &str) -> (Option<&str>, &str) {
+    let code = line.split(';').next().unwrap_or_default().trim();
+    match code.split_once(':') {
+        Some((label, rest)) if is_identifier(label) => (Some(label), rest.trim()),
+        _ => (None, code),
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `text` with every whole word naming a label replaced by its address.
+fn resolve(text: &str, labels: &HashMap<&str, usize>) -> String {
+    let mut out = String::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut String| {
+        match labels.get(word.as_str()) {
+            Some(addr) => out.push_str(&format!("{addr:#05X}")),
+            None => out.push_str(word),
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut word, &mut out);
+    out
+}
+
+pub fn assemble(source: &str) -> Result<Assembled, String> {
+    // First pass: every instruction is one word, so labels are known up front.
+    let mut labels = HashMap::new();
+    let mut addr = PROGRAM_START;
+    for (n, line) in source.lines().enumerate() {
+        let (label, code) = split_label(line);
+        if let Some(label) = label {
+            if labels.insert(label, addr).is_some() {
+                return Err(format!("line {}: label {label:?} defined twice", n + 1));
+            }
+        }
+        if !code.is_empty() {
+            addr += 2;
+        }
+    }
+
+    let mut assembled = Assembled {
+        rom: Vec::new(),
+        symbols: Symbols::default(),
+    };
+    for (&label, &addr) in &labels {
+        assembled.symbols.add_label(addr, label);
+    }
+    for (n, line) in source.lines().enumerate() {
+        let (_, code) = split_label(line);
+        if code.is_empty() {
+            continue;
+        }
+        let op: ChipOp = resolve(code, &labels)
+            .parse()
+            .map_err(|err| format!("line {}: {err}", n + 1))?;
+        let addr = PROGRAM_START + assembled.rom.len();
+        assembled.symbols.add_line(addr, n + 1, code);
+        assembled.rom.extend_from_slice(&op.encode().to_be_bytes());
+    }
+    Ok(assembled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assemble_with_labels() {
+        let source = "\
+; count up forever
+start:  LD V0, 0x01
+loop:   ADD V0, 1   ; increment
+        JP loop
+sprite: DB 0xF090
+";
+        let assembled = assemble(source).unwrap();
+        assert_eq!(
+            assembled.rom,
+            [0x60, 0x01, 0x70, 0x01, 0x12, 0x02, 0xF0, 0x90]
+        );
+        let symbols = &assembled.symbols;
+        assert_eq!(symbols.label(0x200), Some("start"));
+        assert_eq!(symbols.label(0x202), Some("loop"));
+        assert_eq!(symbols.label(0x206), Some("sprite"));
+        let line = symbols.line(0x204).unwrap();
+        assert_eq!((line.number, line.text.as_str()), (4, "JP loop"));
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("CLS\nMOV V1, V2"),
+            Err("line 2: invalid instruction \"MOV V1, V2\"".to_string())
+        );
+        assert!(assemble("a: CLS\na: RET").is_err());
+        assert!(assemble("JP nowhere").is_err());
+    }
+}
@@ -1,18 +1,82 @@
+/// Emit a `tracing` event when the `tracing` feature is enabled; expands to
+/// nothing otherwise so the core stays dependency-free.
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
 pub mod chip8 {
     pub mod audio;
+    pub mod benchmark;
+    pub mod broadcast;
+    pub mod call_history;
+    pub mod capabilities;
     pub mod consts;
     pub mod cpu;
+    pub mod debugger;
     pub mod decode;
+    pub mod disasm;
+    pub mod dump;
+    pub mod emulator;
+    pub mod frame_export;
+    pub mod frame_hashes;
+    pub mod framebuffer;
+    pub mod frontend;
+    pub mod gdb;
     pub mod gfx;
+    pub mod key_hold;
+    pub mod key_log;
+    pub mod keymap;
+    pub mod latency;
     pub mod mem;
     pub mod op;
+    pub mod playlist;
+    pub mod quirks;
+    pub mod repl;
+    pub mod replay;
+    pub mod rng;
+    pub mod rom;
+    pub mod romdb;
+    pub mod screen;
+    pub mod snapshot;
+    pub mod state;
+    pub mod summary;
+    pub mod symbols;
+    pub mod synth;
+    pub mod timer_history;
     pub mod timers;
 }
 
 pub mod utils {
+    pub mod config;
+    pub mod hash;
+    #[cfg(feature = "tracing")]
+    pub mod logging;
     pub mod triple_buffer;
 }
 
+// Flat paths for the core modules, so `oxid8::cpu::Chip8` and
+// `oxid8::triple_buffer::triple_buffer` work as well as the nested ones.
+pub use chip8::{audio, consts, cpu, decode, gfx, mem, op, timers};
+/// Re-export of [`utils::triple_buffer`].
+///
+/// ```
+/// use oxid8::triple_buffer::triple_buffer;
+///
+/// let (mut writer, reader) = triple_buffer(0u8);
+/// *writer.write() = 1;
+/// assert_eq!(*reader.read(), 1);
+/// ```
+pub use utils::triple_buffer;
+
 pub mod compiler {
+    pub mod asm;
+    // The token-level lexer isn't used by the assembler yet.
+    #[allow(dead_code)]
     pub mod lex;
 }
+
+#[cfg(feature = "python")]
+pub mod python;
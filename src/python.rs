@@ -0,0 +1,217 @@
+//! Python bindings for scripting and agent training.
+//!
+//! Built with `--features python`; `maturin build` picks the feature up from
+//! `pyproject.toml`.
+//!
+//! ```python
+//! from oxid8 import PyChip8
+//!
+//! chip = PyChip8()
+//! chip.load_rom(open("pong.ch8", "rb").read())
+//! chip.step(1000)
+//! pixels = chip.screen()  # numpy.array(pixels) gives a (rows, cols) array
+//! ```
+
+use std::sync::atomic::Ordering;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::chip8::cpu::ExecError;
+use crate::chip8::emulator::{Emulator, EmulatorConfig};
+
+#[pyclass(name = "PyChip8")]
+pub struct PyChip8 {
+    emu: Emulator,
+}
+
+#[pymethods]
+impl PyChip8 {
+    #[new]
+    fn new() -> Self {
+        Self {
+            emu: Emulator::new(EmulatorConfig::default()),
+        }
+    }
+
+    /// Instructions executed by `frame()`.
+    #[getter]
+    fn cycles_per_frame(&self) -> u64 {
+        self.emu.config().cycles_per_frame
+    }
+
+    #[setter]
+    fn set_cycles_per_frame(&mut self, cycles: u64) {
+        self.emu.set_cycles_per_frame(cycles);
+    }
+
+    /// Reset the machine and copy `rom` into memory at 0x200.
+    fn load_rom(&mut self, rom: &[u8]) -> PyResult<()> {
+        self.emu.reset();
+        self.emu
+            .load_rom(rom)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Execute up to `n` instructions with the GIL released, stopping
+    /// early if the program halts. Returns True if it halted; a fault
+    /// raises RuntimeError.
+    fn step(&mut self, py: Python<'_>, n: u64) -> PyResult<bool> {
+        let emu = &mut self.emu;
+        py.detach(|| {
+            for _ in 0..n {
+                if emu.halt().is_some() {
+                    break;
+                }
+                emu.try_step_instruction()?;
+            }
+            Ok(emu.halt().is_some())
+        })
+        .map_err(|err: ExecError| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    /// Execute one 60 Hz frame: `cycles_per_frame` instructions followed by
+    /// a single delay/sound timer tick. Returns True if the program halted;
+    /// a fault raises RuntimeError.
+    fn frame(&mut self, py: Python<'_>) -> PyResult<bool> {
+        let emu = &mut self.emu;
+        let halted = py.detach(|| emu.run_frame_caught().halted);
+        match emu.fault() {
+            Some(message) => Err(PyRuntimeError::new_err(message.to_string())),
+            None => Ok(halted),
+        }
+    }
+
+    fn press(&mut self, key: u8) -> PyResult<()> {
+        self.emu.chip_mut().press_key(check_key(key)?);
+        Ok(())
+    }
+
+    fn release(&mut self, key: u8) -> PyResult<()> {
+        self.emu.chip_mut().release_key(check_key(key)?);
+        Ok(())
+    }
+
+    /// The visible display as rows of 0/1 pixels, 64x32 in low-res and
+    /// 128x64 in high-res or with the `half_pixel_scroll` quirk.
+    fn screen<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let chip = self.emu.chip();
+        let (cols, rows) = chip.display_dimensions();
+        // Build lists explicitly: a Vec<u8> would convert to `bytes`.
+        let rows = (0..rows)
+            .map(|y| {
+                let row = (0..cols).map(|x| u8::from(chip.pixel(x, y)));
+                PyList::new(py, row)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        PyList::new(py, rows)
+    }
+
+    fn registers<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let chip = self.emu.chip();
+        let regs = PyDict::new(py);
+        regs.set_item("pc", chip.pc)?;
+        regs.set_item("i", chip.i)?;
+        regs.set_item("sp", chip.sp)?;
+        regs.set_item("dt", chip.dt.load(Ordering::Acquire))?;
+        regs.set_item("st", chip.st.load(Ordering::Acquire))?;
+        regs.set_item("v", PyList::new(py, chip.v)?)?;
+        Ok(regs)
+    }
+
+    #[pyo3(signature = (
+        *,
+        shift_uses_vy=None,
+        load_store_increments_i=None,
+        mask_skip_key=None,
+        wrap_memory=None,
+        mask_addr=None,
+        half_pixel_scroll=None,
+    ))]
+    fn set_quirks(
+        &mut self,
+        shift_uses_vy: Option<bool>,
+        load_store_increments_i: Option<bool>,
+        mask_skip_key: Option<bool>,
+        wrap_memory: Option<bool>,
+        mask_addr: Option<bool>,
+        half_pixel_scroll: Option<bool>,
+    ) {
+        let mut quirks = self.emu.config().quirks;
+        if let Some(v) = shift_uses_vy {
+            quirks.shift_uses_vy = v;
+        }
+        if let Some(v) = load_store_increments_i {
+            quirks.load_store_increments_i = v;
+        }
+        if let Some(v) = mask_skip_key {
+            quirks.mask_skip_key = v;
+        }
+        if let Some(v) = wrap_memory {
+            quirks.wrap_memory = v;
+        }
+        if let Some(v) = mask_addr {
+            quirks.mask_addr = v;
+        }
+        if let Some(v) = half_pixel_scroll {
+            quirks.half_pixel_scroll = v;
+        }
+        self.emu.set_quirks(quirks);
+    }
+}
+
+fn check_key(key: u8) -> PyResult<u8> {
+    if key > 0xF {
+        return Err(PyValueError::new_err(format!("Invalid key: {key:#X}")));
+    }
+    Ok(key)
+}
+
+#[pymodule]
+fn oxid8(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyChip8>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_smoke() {
+        Python::initialize();
+        Python::attach(|py| {
+            let locals = PyDict::new(py);
+            locals
+                .set_item("PyChip8", py.get_type::<PyChip8>())
+                .unwrap();
+            py.run(
+                cr#"
+chip = PyChip8()
+# LD V0, 0x00 ; LD F, V0 ; DRW V0, V0, 5 ; JP 0x206
+chip.load_rom(bytes([0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06]))
+chip.step(10)
+screen = chip.screen()
+assert len(screen) == 32 and len(screen[0]) == 64
+assert screen[0][:4] == [1, 1, 1, 1]
+assert screen[1][:4] == [1, 0, 0, 1]
+assert chip.registers()["pc"] == 0x206
+
+# EXIT stops step early; an unknown opcode raises.
+chip.load_rom(bytes([0x00, 0xFD, 0x60, 0x01]))
+assert chip.step(10)
+assert chip.registers()["v"][0] == 0
+chip.load_rom(bytes([0x5A, 0xB1]))
+try:
+    chip.step(1)
+    raise AssertionError("no fault")
+except RuntimeError as err:
+    assert "0x5AB1" in str(err)
+"#,
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+    }
+}
@@ -0,0 +1,108 @@
+//! `key = value` config files for the binaries.
+//!
+//! Keys are long flag names (`cpu-cycles` or `cpu_cycles`), so a config file
+//! can set any option the command line can. The entries are turned back into
+//! arguments and placed before the real ones, letting explicit flags win.
+//!
+//! ```text
+//! # ~/.config/oxid8.conf
+//! cpu-cycles = 20
+//! fg = #ffb000
+//! debug = true
+//! ```
+
+use color_eyre::eyre::{bail, Result};
+
+pub fn parse(text: &str) -> Result<Vec<(String, String)>> {
+    let mut entries = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("config line {}: expected `key = value`", n + 1);
+        };
+        let key = key.trim().replace('_', "-");
+        if key.is_empty() {
+            bail!("config line {}: missing key", n + 1);
+        }
+        entries.push((key, value.trim().to_string()));
+    }
+    Ok(entries)
+}
+
+/// Command-line arguments equivalent to `entries`. `true` and `false` switch
+/// flags on and off.
+pub fn to_args(entries: &[(String, String)]) -> Vec<String> {
+    let mut args = Vec::new();
+    for (key, value) in entries {
+        match value.as_str() {
+            "true" => args.push(format!("--{key}")),
+            "false" => {}
+            _ => args.extend([format!("--{key}"), value.clone()]),
+        }
+    }
+    args
+}
+
+/// The value of `--config` in `argv`, if given.
+pub fn config_path(argv: &[String]) -> Option<&str> {
+    let mut args = argv.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(String::as_str);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// `argv` with the settings from `config` inserted after the program name.
+pub fn merge_args(argv: &[String], config: &str) -> Result<Vec<String>> {
+    let mut merged = argv[..1.min(argv.len())].to_vec();
+    merged.extend(to_args(&parse(config)?));
+    merged.extend(argv.iter().skip(1).cloned());
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let entries = parse("# comment\ncpu_cycles = 20\n\nfg=#ff0000\ndebug = true").unwrap();
+        assert_eq!(
+            to_args(&entries),
+            strings(&["--cpu-cycles", "20", "--fg", "#ff0000", "--debug"])
+        );
+        let err = parse("cpu-cycles 20").unwrap_err();
+        assert_eq!(err.to_string(), "config line 1: expected `key = value`");
+    }
+
+    #[test]
+    fn test_config_path() {
+        let argv = strings(&["oxid8", "game.ch8", "--config", "a.conf"]);
+        assert_eq!(config_path(&argv), Some("a.conf"));
+        let argv = strings(&["oxid8", "--config=b.conf"]);
+        assert_eq!(config_path(&argv), Some("b.conf"));
+        assert_eq!(config_path(&strings(&["oxid8", "game.ch8"])), None);
+    }
+
+    #[test]
+    fn test_merge_args() {
+        let argv = strings(&["oxid8", "-c", "30"]);
+        let merged = merge_args(&argv, "cpu-cycles = 20").unwrap();
+        assert_eq!(
+            merged,
+            strings(&["oxid8", "--cpu-cycles", "20", "-c", "30"])
+        );
+    }
+}
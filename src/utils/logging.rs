@@ -0,0 +1,69 @@
+//! File logging for the binaries, which can't print while a frontend owns
+//! the terminal.
+
+use std::fs::File;
+use std::sync::Mutex;
+
+use color_eyre::eyre::{eyre, Result};
+use tracing::Level;
+
+/// Send events at `level` and above to `path`, truncating it.
+pub fn init(path: &str, level: Level) -> Result<()> {
+    let file = File::create(path)?;
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_max_level(level)
+        .with_ansi(false)
+        .with_thread_names(true)
+        .try_init()
+        .map_err(|e| eyre!(e))
+}
+
+/// Parse a `--log-level` argument such as `debug` or `WARN`.
+pub fn parse_level(s: &str) -> Result<Level, String> {
+    s.parse()
+        .map_err(|_| format!("invalid log level {s:?}: expected trace, debug, info, warn or error"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run `f` and return everything it logged, one event per line.
+    pub(crate) fn capture(f: impl FnOnce()) -> String {
+        let out = Capture::default();
+        let writer = out.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_max_level(Level::TRACE)
+            .with_ansi(false)
+            .without_time()
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let bytes = out.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug"), Ok(Level::DEBUG));
+        assert_eq!(parse_level("WARN"), Ok(Level::WARN));
+        assert!(parse_level("loud").is_err());
+    }
+}
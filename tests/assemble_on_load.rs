@@ -0,0 +1,54 @@
+//! `oxid8` assembles `.asm` sources itself, and other files with `--asm`:
+//! running one headless ends in the same state as running the ROM `asm8`
+//! makes from it.
+
+use std::process::Command;
+
+/// The state hash `oxid8 --headless` prints for `rom`.
+fn headless_hash(rom: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_oxid8"))
+        .args([rom, "--headless", "--frames", "10", "--seed", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[test]
+fn source_runs_like_its_rom() {
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let from_source = headless_hash(&format!("{fixtures}/digit.asm"));
+    let from_rom = headless_hash(&format!("{fixtures}/digit.ch8"));
+    assert_eq!(from_source.len(), 16);
+    assert_eq!(from_source, from_rom);
+}
+
+#[test]
+fn asm_flag_assembles_any_extension() {
+    let path = std::env::temp_dir().join(format!("oxid8-asm-{}.c8", std::process::id()));
+    std::fs::write(&path, "start:\n  LD V3, 0x2A\n  JP start\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_oxid8"))
+        .args(["--asm", path.to_str().unwrap(), "--disassemble"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let listing = String::from_utf8(output.stdout).unwrap();
+    let first = listing.lines().find(|line| line.starts_with("0x")).unwrap();
+    assert_eq!(first, "0x200  632A  LD V3, 0x2A");
+}
+
+#[test]
+fn assembler_errors_are_reported() {
+    let path = std::env::temp_dir().join(format!("oxid8-bad-{}.asm", std::process::id()));
+    std::fs::write(&path, "LD V0, 1\nFROB V1\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_oxid8"))
+        .args([path.to_str().unwrap(), "--headless"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to assemble"), "{stderr}");
+    assert!(stderr.contains("line 2"), "{stderr}");
+}
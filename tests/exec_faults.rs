@@ -0,0 +1,103 @@
+//! ROMs found by the `exec` fuzz target that used to panic inside
+//! `Chip8::exec`. Each must now stop with an `ExecError` instead.
+
+use oxid8::chip8::consts::PROGRAM_START;
+use oxid8::chip8::cpu::{Chip8, ExecError};
+
+/// Step `rom` until it faults, failing if it runs for too long.
+fn fault(rom: &[u8]) -> (ExecError, Chip8) {
+    let mut chip = Chip8::new();
+    chip.load_font();
+    chip.memory[PROGRAM_START..PROGRAM_START + rom.len()].copy_from_slice(rom);
+    for _ in 0..1000 {
+        if let Err(err) = chip.try_step() {
+            return (err, chip);
+        }
+        assert!(chip.pc < chip.memory.len());
+    }
+    panic!("no fault after 1000 steps");
+}
+
+#[test]
+fn call_with_a_full_stack() {
+    // CALL 0x200 forever.
+    let (err, chip) = fault(&[0x22, 0x00]);
+    assert_eq!(err, ExecError::StackOverflow);
+    assert_eq!(chip.sp, 16);
+}
+
+#[test]
+fn ret_with_an_empty_stack() {
+    let (err, chip) = fault(&[0x00, 0xEE]);
+    assert_eq!(err, ExecError::StackUnderflow);
+    assert_eq!(chip.sp, 0);
+}
+
+#[test]
+fn store_past_the_end_of_ram() {
+    // LD I, 0xFFF; LD [I], V2
+    let (err, _) = fault(&[0xAF, 0xFF, 0xF2, 0x55]);
+    assert_eq!(
+        err,
+        ExecError::MemoryOutOfBounds {
+            addr: 0xFFF,
+            len: 3
+        }
+    );
+    // LD I, 0xFFF; LD B, V0
+    let (err, _) = fault(&[0xAF, 0xFF, 0xF0, 0x33]);
+    assert_eq!(
+        err,
+        ExecError::MemoryOutOfBounds {
+            addr: 0xFFF,
+            len: 3
+        }
+    );
+}
+
+#[test]
+fn sprite_past_the_end_of_ram() {
+    // LD I, 0xFFE; DRW V0, V0, 5
+    let (err, _) = fault(&[0xAF, 0xFE, 0xD0, 0x05]);
+    assert_eq!(
+        err,
+        ExecError::MemoryOutOfBounds {
+            addr: 0xFFE,
+            len: 5
+        }
+    );
+}
+
+#[test]
+fn reversed_register_range() {
+    // LD V1-V0, [I]
+    let (err, _) = fault(&[0x51, 0x03]);
+    assert_eq!(err, ExecError::ReversedRange { x: 1, y: 0 });
+}
+
+#[test]
+fn pc_leaves_ram() {
+    // JP 0xFFF: the fetch there straddles the end of RAM.
+    let (err, _) = fault(&[0x1F, 0xFF]);
+    assert_eq!(err, ExecError::PcOutOfBounds { pc: 0xFFF });
+
+    // A skip from the last word lands past the end.
+    let mut rom = vec![0; 0x1000 - PROGRAM_START];
+    rom[..2].copy_from_slice(&[0x1F, 0xFE]); // JP 0xFFE
+    rom[0xFFE - PROGRAM_START..].copy_from_slice(&[0x30, 0x00]); // SE V0, 0
+    let (err, _) = fault(&rom);
+    assert_eq!(err, ExecError::PcOutOfBounds { pc: 0x1002 });
+
+    // LD V0, 0xFF; JP V0, 0xFFF: the jump faults without moving PC.
+    let (err, chip) = fault(&[0x60, 0xFF, 0xBF, 0xFF]);
+    assert_eq!(err, ExecError::PcOutOfBounds { pc: 0x10FE });
+    assert_eq!(chip.pc, 0x202);
+}
+
+#[test]
+fn unprogrammed_memory_stops_the_machine() {
+    // CLS, then the zeroed RAM after the ROM.
+    let (err, chip) = fault(&[0x00, 0xE0]);
+    assert_eq!(err, ExecError::Unprogrammed { pc: 0x202 });
+    assert_eq!(chip.pc, 0x202);
+}
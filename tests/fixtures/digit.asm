@@ -0,0 +1,5 @@
+; Draw the digit 7 at (7, 7), then spin.
+start:  LD V0, 0x07
+        LD F, V0
+        DRW V0, V0, 5
+loop:   JP loop
@@ -0,0 +1,102 @@
+//! Every hex font glyph drawn with `LD F, Vx` and `DRW` at byte-aligned and
+//! unaligned columns and at the display's right and bottom edges, checked
+//! against the glyphs below. Low-res sprites run off the edge into the
+//! hidden part of the framebuffer; high-res ones wrap around.
+
+use oxid8::chip8::cpu::{Chip8, Resolution};
+use oxid8::chip8::op::ChipOp;
+use oxid8::chip8::snapshot::{assert_chip_screen, screen_with};
+
+/// The font as drawn, 0 to F.
+const GLYPHS: [&str; 16] = [
+    "####\n#..#\n#..#\n#..#\n####",
+    "..#.\n.##.\n..#.\n..#.\n.###",
+    "####\n...#\n####\n#...\n####",
+    "####\n...#\n####\n...#\n####",
+    "#..#\n#..#\n####\n...#\n...#",
+    "####\n#...\n####\n...#\n####",
+    "####\n#...\n####\n#..#\n####",
+    "####\n...#\n..#.\n.#..\n.#..",
+    "####\n#..#\n####\n#..#\n####",
+    "####\n#..#\n####\n...#\n####",
+    "####\n#..#\n####\n#..#\n#..#",
+    "###.\n#..#\n###.\n#..#\n###.",
+    "####\n#...\n#...\n#...\n####",
+    "###.\n#..#\n#..#\n#..#\n###.",
+    "####\n#...\n####\n#...\n####",
+    "####\n#...\n####\n#...\n#...",
+];
+
+/// A blank machine at `resolution` with the font loaded.
+fn machine(resolution: Resolution) -> Chip8 {
+    let mut chip = Chip8::new();
+    chip.load_font();
+    chip.resolution = resolution;
+    chip
+}
+
+/// Draw the glyph for `digit` at (`x`, `y`) the way a program would.
+fn draw_glyph(chip: &mut Chip8, digit: u8, x: u8, y: u8) {
+    chip.v[0] = x;
+    chip.v[1] = y;
+    chip.v[2] = digit;
+    chip.exec(ChipOp::LdFVx { x: 2 });
+    chip.exec(ChipOp::DrwVxVyN { x: 0, y: 1, n: 5 });
+}
+
+#[test]
+fn glyphs_at_aligned_and_unaligned_columns() {
+    for (digit, glyph) in GLYPHS.iter().enumerate() {
+        for (x, y) in [(0, 0), (8, 3), (3, 1), (29, 14)] {
+            let mut chip = machine(Resolution::Low);
+            draw_glyph(&mut chip, digit as u8, x as u8, y as u8);
+            assert_chip_screen(&chip, &screen_with(Resolution::Low, &[(x, y, glyph)]));
+            assert_eq!(chip.v[0xF], 0, "glyph {digit:X} collided with nothing");
+        }
+    }
+}
+
+#[test]
+fn glyphs_run_off_the_low_res_edges() {
+    for (digit, glyph) in GLYPHS.iter().enumerate() {
+        // Right edge, bottom edge and the corner: only the part on the
+        // display shows.
+        for (x, y) in [(62, 4), (10, 29), (61, 30)] {
+            let mut chip = machine(Resolution::Low);
+            draw_glyph(&mut chip, digit as u8, x as u8, y as u8);
+            assert_chip_screen(&chip, &screen_with(Resolution::Low, &[(x, y, glyph)]));
+        }
+    }
+}
+
+#[test]
+fn glyphs_wrap_at_the_high_res_edges() {
+    for (digit, glyph) in GLYPHS.iter().enumerate() {
+        let mut chip = machine(Resolution::High);
+        draw_glyph(&mut chip, digit as u8, 126, 20);
+        let expected = screen_with(Resolution::High, &[(126, 20, glyph), (-2, 20, glyph)]);
+        assert_chip_screen(&chip, &expected);
+
+        let mut chip = machine(Resolution::High);
+        draw_glyph(&mut chip, digit as u8, 40, 62);
+        let expected = screen_with(Resolution::High, &[(40, 62, glyph), (40, -2, glyph)]);
+        assert_chip_screen(&chip, &expected);
+    }
+}
+
+#[test]
+fn glyph_origin_wraps_to_the_display() {
+    // X 64 + 5 and Y 32 + 2 start the glyph at (5, 2) in low-res.
+    let mut chip = machine(Resolution::Low);
+    draw_glyph(&mut chip, 0xA, 69, 34);
+    assert_chip_screen(&chip, &screen_with(Resolution::Low, &[(5, 2, GLYPHS[0xA])]));
+}
+
+#[test]
+fn drawing_a_glyph_twice_erases_it() {
+    let mut chip = machine(Resolution::Low);
+    draw_glyph(&mut chip, 0x8, 13, 7);
+    draw_glyph(&mut chip, 0x8, 13, 7);
+    assert_eq!(chip.v[0xF], 1);
+    assert_chip_screen(&chip, &screen_with(Resolution::Low, &[]));
+}
@@ -0,0 +1,47 @@
+//! A recorded session of `roms/keypad.ch8` checked frame by frame: pressing
+//! 5 and then A must draw the same screens on the same frames as when
+//! `keypad.hashes` was recorded.
+
+use std::process::{Command, Output};
+
+fn verify(replay: &str) -> Output {
+    let root = env!("CARGO_MANIFEST_DIR");
+    Command::new(env!("CARGO_BIN_EXE_oxid8"))
+        .args(["--rom", &format!("{root}/roms/keypad.ch8"), "--seed", "1"])
+        .args(["--replay", replay])
+        .args([
+            "--verify-frame-hashes",
+            &format!("{root}/tests/fixtures/keypad.hashes"),
+        ])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn recorded_session_matches() {
+    let output = verify(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/keypad.replay"
+    ));
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn first_differing_frame_is_reported() {
+    let replay = include_str!("fixtures/keypad.replay").replace(" A\n", " B\n");
+    let path = std::env::temp_dir().join(format!("oxid8-keypad-{}.replay", std::process::id()));
+    std::fs::write(&path, replay).unwrap();
+    let output = verify(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("frame 35: expected screen hash"),
+        "{stderr}"
+    );
+    // The diff flags each row of the B glyph that differs from the last
+    // matching screen.
+    assert!(stderr.contains("| diff"), "{stderr}");
+    let flagged = stderr.lines().filter(|l| l.starts_with("! ")).count();
+    assert_eq!(flagged, 5, "{stderr}");
+}
@@ -0,0 +1,167 @@
+//! A second, deliberately naive implementation of the arithmetic
+//! instructions (7XNN, 8XY0 to 8XYE and FX33), written from their
+//! descriptions rather than from `Chip8::try_exec`, and checked against it
+//! on random machine states.
+//!
+//! Every flag is computed from the operands as they were before the
+//! instruction, and VF is written last, so with X = F the flag wins.
+
+use oxid8::chip8::cpu::Chip8;
+use oxid8::chip8::decode::decode;
+use oxid8::chip8::rng::Rng;
+
+/// Random cases per instruction.
+const CASES: usize = 4096;
+
+/// What the reference machine holds: V0 to VF, PC, and the three bytes
+/// FX33 writes at I.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Machine {
+    v: [u8; 16],
+    pc: usize,
+    bcd: Option<[u8; 3]>,
+}
+
+/// Run `opcode` on `before`. Shifts read VY when `shift_uses_vy` is set
+/// and VX otherwise.
+fn reference(opcode: u16, before: Machine, shift_uses_vy: bool) -> Machine {
+    let x = usize::from(opcode >> 8 & 0xF);
+    let y = usize::from(opcode >> 4 & 0xF);
+    let nn = u32::from(opcode & 0xFF);
+    let vx = u32::from(before.v[x]);
+    let vy = u32::from(before.v[y]);
+    let shifted = if shift_uses_vy { vy } else { vx };
+
+    // (new VX, new VF if the instruction sets a flag)
+    let (result, flag) = match (opcode >> 12, opcode & 0xF) {
+        (0x7, _) => ((vx + nn) % 256, None),
+        (0x8, 0x0) => (vy, None),
+        (0x8, 0x1) => (vx | vy, None),
+        (0x8, 0x2) => (vx & vy, None),
+        (0x8, 0x3) => (vx ^ vy, None),
+        (0x8, 0x4) => ((vx + vy) % 256, Some(u32::from(vx + vy > 255))),
+        (0x8, 0x5) => ((256 + vx - vy) % 256, Some(u32::from(vx >= vy))),
+        (0x8, 0x6) => (shifted / 2, Some(shifted % 2)),
+        (0x8, 0x7) => ((256 + vy - vx) % 256, Some(u32::from(vy >= vx))),
+        (0x8, 0xE) => ((shifted * 2) % 256, Some(shifted / 128)),
+        (0xF, _) if nn == 0x33 => {
+            let digits = [vx / 100, vx / 10 % 10, vx % 10].map(|d| d as u8);
+            return Machine {
+                pc: before.pc + 2,
+                bcd: Some(digits),
+                ..before
+            };
+        }
+        _ => unreachable!("no reference for {opcode:04X}"),
+    };
+
+    let mut after = before;
+    after.v[x] = result as u8;
+    if let Some(flag) = flag {
+        after.v[0xF] = flag as u8;
+    }
+    after.pc += 2;
+    after
+}
+
+/// Where FX33 writes in the machines under test.
+const BCD_ADDR: usize = 0x300;
+
+/// Run `opcode` on a `Chip8` set up like `before`.
+fn exec(opcode: u16, before: Machine, shift_uses_vy: bool) -> Machine {
+    let mut chip = Chip8::new();
+    chip.quirks.shift_uses_vy = shift_uses_vy;
+    chip.v = before.v;
+    chip.pc = before.pc;
+    chip.i = BCD_ADDR;
+    chip.try_exec(decode(opcode)).unwrap();
+    assert_eq!(chip.i, BCD_ADDR, "{opcode:04X} moved I");
+    let bcd = before
+        .bcd
+        .map(|_| chip.memory[BCD_ADDR..BCD_ADDR + 3].try_into().unwrap());
+    Machine {
+        v: chip.v,
+        pc: chip.pc,
+        bcd,
+    }
+}
+
+/// A register value, favouring the ones at the edges of carries and
+/// borrows.
+fn register(rng: &mut Rng) -> u8 {
+    const EDGES: [u8; 6] = [0x00, 0x01, 0x7F, 0x80, 0xFE, 0xFF];
+    match rng.next_u8() % 4 {
+        0 => EDGES[usize::from(rng.next_u8()) % EDGES.len()],
+        _ => rng.next_u8(),
+    }
+}
+
+/// Check every opcode `template` | XY00 | NN against the reference on
+/// random registers, for both shift quirks. `random_nn` fills the low byte.
+fn check(seed: u64, template: u16, random_nn: bool) {
+    let mut rng = Rng::new(seed);
+    for case in 0..CASES {
+        let x = u16::from(rng.next_u8() & 0xF);
+        let y = u16::from(rng.next_u8() & 0xF);
+        let mut opcode = template | x << 8;
+        if random_nn {
+            opcode |= u16::from(rng.next_u8());
+        } else if template & 0xF000 == 0x8000 {
+            opcode |= y << 4;
+        }
+        let before = Machine {
+            v: std::array::from_fn(|_| register(&mut rng)),
+            pc: 0x200 + 2 * (rng.next_u64() % 0x600) as usize,
+            bcd: (template & 0xF0FF == 0xF033).then_some([0; 3]),
+        };
+        let shift_uses_vy = rng.next_u8() & 1 == 1;
+        assert_eq!(
+            exec(opcode, before, shift_uses_vy),
+            reference(opcode, before, shift_uses_vy),
+            "case {case}: {opcode:04X} with shift_uses_vy {shift_uses_vy} from {before:?}"
+        );
+    }
+}
+
+#[test]
+fn add_nn() {
+    check(1, 0x7000, true);
+}
+
+#[test]
+fn logic() {
+    for (seed, template) in [(2, 0x8000), (3, 0x8001), (4, 0x8002), (5, 0x8003)] {
+        check(seed, template, false);
+    }
+}
+
+#[test]
+fn arithmetic() {
+    for (seed, template) in [(6, 0x8004), (7, 0x8005), (8, 0x8007)] {
+        check(seed, template, false);
+    }
+}
+
+#[test]
+fn shifts() {
+    check(9, 0x8006, false);
+    check(10, 0x800E, false);
+}
+
+#[test]
+fn bcd() {
+    check(11, 0xF033, false);
+}
+
+#[test]
+fn flag_register_as_destination() {
+    // ADD VF, V1 with a carry: the flag overwrites the sum.
+    let before = Machine {
+        v: std::array::from_fn(|r| if r == 1 { 0x02 } else { 0xFF }),
+        pc: 0x200,
+        bcd: None,
+    };
+    let after = exec(0x8F14, before, true);
+    assert_eq!(after, reference(0x8F14, before, true));
+    assert_eq!(after.v[0xF], 1);
+}